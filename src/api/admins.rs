@@ -0,0 +1,55 @@
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use serde::{Deserialize, Serialize};
+
+use crate::api::SuperadminToken;
+use crate::backend::config::AppConfig;
+use crate::backend::error::BackendError;
+use crate::backend::state::{AppState, RecoverLock};
+use crate::backend::validation::Validator;
+
+#[derive(Deserialize)]
+pub struct NewAdmin {
+    pub username: String,
+    pub password: String,
+    /// Organization of the admin, omitted for the default tenant
+    pub tenant: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AdminSummary {
+    pub username: String,
+    pub tenant: Option<String>,
+}
+
+/// Lists all admin accounts with their tenant
+#[get("/v1/admins")]
+pub fn list_admins(_token: SuperadminToken, state: &State<AppState>) -> Json<Vec<AdminSummary>> {
+    let storage = state.storage.read_unpoisoned();
+    let mut admins: Vec<AdminSummary> = storage.admins.values()
+        .map(|acc| AdminSummary { username: acc.username.clone(), tenant: acc.tenant.clone() })
+        .collect();
+    admins.sort_by(|a, b| (&a.tenant, &a.username).cmp(&(&b.tenant, &b.username)));
+    Json(admins)
+}
+
+/// Creates an admin account. A new tenant comes into existence with its first admin.
+#[post("/v1/admins", data = "<admin>")]
+pub fn create_admin(_token: SuperadminToken, state: &State<AppState>, config: &State<AppConfig>, admin: Json<NewAdmin>) -> Result<(Status, Json<AdminSummary>), BackendError> {
+    let NewAdmin { username, password, tenant } = admin.into_inner();
+    let username = username.trim().to_string();
+    let tenant = tenant.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    let mut v = Validator::new(&config.form_limits);
+    v.name("username", &username);
+    if let Some(tenant) = &tenant { v.name("tenant", tenant); }
+    v.password("password", &password);
+    v.finish()?;
+
+    let mut storage = state.storage.write_unpoisoned();
+    if storage.admins.contains_key(&username) {
+        return Err(BackendError::conflict(format!("The admin {} already exists.", username)));
+    }
+    storage.add_admin(username.clone(), &password, tenant.clone()).map_err(BackendError::bad_request)?;
+    Ok((Status::Created, Json(AdminSummary { username, tenant })))
+}
@@ -0,0 +1,16 @@
+use chrono::Utc;
+use rocket::serde::json::Json;
+use rocket::State;
+use uuid::Uuid;
+
+use crate::api::ReadToken;
+use crate::backend::completion::ParticipantCompletion;
+use crate::backend::error::BackendError;
+use crate::backend::state::{AppState, RecoverLock};
+
+/// Which open slots each registered participant has chosen preferences for, and whether they
+/// were already notified about unfinished ones. Participants are only identified by their id.
+#[get("/v1/events/<event_id>/completion")]
+pub fn event_completion(_token: ReadToken, state: &State<AppState>, event_id: Uuid) -> Result<Json<Vec<ParticipantCompletion>>, BackendError> {
+    state.storage.read_unpoisoned().completion_states(event_id, Utc::now()).map(Json)
+}
@@ -0,0 +1,116 @@
+use rocket::http::{ContentType, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::State;
+
+use crate::backend::config::{default_api_requests_per_minute, ApiScope, AppConfig};
+use crate::backend::error::ProblemDetails;
+use crate::backend::state::AppState;
+
+pub mod admins;
+pub mod state;
+pub mod stats;
+pub mod research;
+pub mod solver;
+pub mod completion;
+pub mod seats;
+
+/// API token from the config that authenticated the request (`Authorization: Bearer <token>`).
+/// Each token is rate limited on its own. Without any configured token, the API is disabled.
+pub struct ApiToken {
+    pub name: String,
+    pub scope: ApiScope,
+}
+
+/// Request guard for API calls that only read statistics
+pub struct ReadToken;
+
+/// Request guard for API calls that change events
+pub struct EventWriteToken;
+
+/// Request guard for API calls that need full access
+pub struct SuperadminToken;
+
+/// Compares both strings in constant time (for equal lengths)
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ApiToken {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let config = match req.guard::<&State<AppConfig>>().await {
+            Outcome::Success(c) => c,
+            _ => return Outcome::Error((Status::InternalServerError, ())),
+        };
+        let state = match req.guard::<&State<AppState>>().await {
+            Outcome::Success(s) => s,
+            _ => return Outcome::Error((Status::InternalServerError, ())),
+        };
+        // The superadmin token of older configs is treated like a configured token named "default"
+        let legacy = config.api_token.as_deref().map(|token| ("default", token, ApiScope::Superadmin, default_api_requests_per_minute()));
+        let configured = config.api_tokens.iter().map(|t| (t.name.as_str(), t.token.as_str(), t.scope, t.requests_per_minute));
+        let tokens: Vec<_> = legacy.into_iter().chain(configured).filter(|(_, token, _, _)| !token.is_empty()).collect();
+        if tokens.is_empty() {
+            return Outcome::Error((Status::Forbidden, ()));
+        }
+        let Some(given) = req.headers().get_one("Authorization").and_then(|h| h.strip_prefix("Bearer ")).map(str::trim) else {
+            return Outcome::Error((Status::Unauthorized, ()));
+        };
+        let Some((name, _, scope, per_minute)) = tokens.into_iter().find(|(_, token, _, _)| constant_time_eq(given, token)) else {
+            return Outcome::Error((Status::Unauthorized, ()));
+        };
+        if !state.api_limiter.check(name, per_minute) {
+            return Outcome::Error((Status::TooManyRequests, ()));
+        }
+        Outcome::Success(ApiToken { name: name.to_string(), scope })
+    }
+}
+
+/// Authenticates the request and checks that the token's scope includes `required`
+async fn require_scope(req: &Request<'_>, required: ApiScope) -> Outcome<(), ()> {
+    match req.guard::<ApiToken>().await {
+        Outcome::Success(token) if token.scope >= required => Outcome::Success(()),
+        Outcome::Success(token) => {
+            eprintln!("API token {} lacks the {:?} scope for {}", token.name, required, req.uri());
+            Outcome::Error((Status::Forbidden, ()))
+        }
+        Outcome::Error(e) => Outcome::Error(e),
+        Outcome::Forward(f) => Outcome::Forward(f),
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ReadToken {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        require_scope(req, ApiScope::ReadOnly).await.map(|_| ReadToken)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for EventWriteToken {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        require_scope(req, ApiScope::EventWrite).await.map(|_| EventWriteToken)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SuperadminToken {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        require_scope(req, ApiScope::Superadmin).await.map(|_| SuperadminToken)
+    }
+}
+
+/// Answers all failed API requests with JSON problem details
+#[catch(default)]
+pub fn api_catcher(status: Status, _req: &Request<'_>) -> (Status, (ContentType, String)) {
+    let problem = ProblemDetails::new(status, status.reason_lossy().to_string());
+    (status, (ContentType::new("application", "problem+json"), problem.to_json()))
+}
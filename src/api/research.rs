@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use rand_core::{OsRng, RngCore};
+use rocket::http::ContentType;
+use rocket::State;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::api::SuperadminToken;
+use crate::backend::data::{ApplicationPriority, EventState, InvitationCategory};
+use crate::backend::error::BackendError;
+use crate::backend::state::{AppState, RecoverLock};
+use crate::gui::export::csv_line;
+
+/// Value of the `confirm` parameter acknowledging that the dataset leaves the instance
+const CONFIRMATION: &str = "anonymized";
+
+/// One application or assigned seat. Participants are only identified by a salted hash.
+#[derive(Serialize)]
+pub struct ResearchRecord {
+    pub event_id: Uuid,
+    pub event_state: EventState,
+    /// Day of the latest distribution run
+    pub distributed_on: Option<String>,
+    pub slot_id: Uuid,
+    pub session_id: Uuid,
+    pub seats: usize,
+    /// Linked people share one hash across events
+    pub participant: String,
+    pub category: InvitationCategory,
+    pub points_from_previous_rounds: usize,
+    /// None for seats assigned without an application
+    pub preference: Option<ApplicationPriority>,
+    pub calculated_points: Option<usize>,
+    pub assigned: bool,
+}
+
+/// Salted hash of an identifier. The salt is drawn per export, so hashes can't be matched
+/// against the live data or other exports.
+fn pseudonym(salt: &[u8], id: Uuid) -> String {
+    let digest = Sha256::new().chain_update(salt).chain_update(id.as_bytes()).finalize();
+    digest[..16].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Anonymized preferences, assignments and points of all events for offline fairness analysis.
+/// Names, codes, email addresses and notes are left out. Requires `confirm=anonymized`.
+#[get("/v1/research/export?<confirm>&<format>")]
+pub fn export_research_dataset(_token: SuperadminToken, state: &State<AppState>, confirm: Option<&str>, format: Option<&str>) -> Result<(ContentType, String), BackendError> {
+    if confirm != Some(CONFIRMATION) {
+        return Err(BackendError::bad_request(format!("Confirm the export of the anonymized dataset with confirm={}.", CONFIRMATION)));
+    }
+    let csv = match format.unwrap_or("json") {
+        "json" => false,
+        "csv" => true,
+        other => return Err(BackendError::bad_request(format!("Unknown format {}, use json or csv.", other))),
+    };
+
+    let storage = state.storage.read_unpoisoned();
+    let mut salt = [0u8; 32];
+    OsRng.fill_bytes(&mut salt);
+    let people: HashMap<Uuid, Uuid> = storage.invitations_codes.values()
+        .filter_map(|inv| Some((inv.participant_id?, inv.person_id?)))
+        .collect();
+
+    let mut records = Vec::new();
+    for ev in storage.events.values() {
+        let distributed_on = ev.distribution_runs.last().map(|run| run.started_at.format("%Y-%m-%d").to_string());
+        for slot in &ev.slots {
+            for sess in &slot.sessions {
+                let applications = sess.applications.iter().map(|a| (a.participant, Some(a)));
+                let unapplied_seats = sess.participants.iter().filter(|pid| !sess.applications.iter().any(|a| a.participant == **pid)).map(|pid| (*pid, None));
+                for (participant_id, application) in applications.chain(unapplied_seats) {
+                    let Some(participant) = ev.participants.get(&participant_id) else { continue; };
+                    records.push(ResearchRecord {
+                        event_id: ev.uuid,
+                        event_state: ev.state,
+                        distributed_on: distributed_on.clone(),
+                        slot_id: slot.uuid,
+                        session_id: sess.uuid,
+                        seats: sess.seats,
+                        participant: pseudonym(&salt, *people.get(&participant_id).unwrap_or(&participant_id)),
+                        category: participant.category,
+                        points_from_previous_rounds: participant.points_from_previous_rounds,
+                        preference: application.map(|a| a.priority.clone()),
+                        calculated_points: application.and_then(|a| a.calculated_points),
+                        assigned: sess.participants.contains(&participant_id),
+                    });
+                }
+            }
+        }
+    }
+
+    if !csv {
+        return Ok((ContentType::JSON, serde_json::to_string(&records).expect("records are serializable")));
+    }
+    let mut out = csv_line(&["event_id", "event_state", "distributed_on", "slot_id", "session_id", "seats", "participant", "category", "points_from_previous_rounds", "preference", "calculated_points", "assigned"]);
+    for record in records {
+        out.push_str(&csv_line(&[
+            record.event_id.to_string(),
+            record.event_state.to_string(),
+            record.distributed_on.unwrap_or_default(),
+            record.slot_id.to_string(),
+            record.session_id.to_string(),
+            record.seats.to_string(),
+            record.participant,
+            record.category.as_tag().to_string(),
+            record.points_from_previous_rounds.to_string(),
+            record.preference.map(|p| format!("{:?}", p)).unwrap_or_default(),
+            record.calculated_points.map(|p| p.to_string()).unwrap_or_default(),
+            record.assigned.to_string(),
+        ]));
+    }
+    Ok((ContentType::CSV, out))
+}
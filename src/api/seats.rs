@@ -0,0 +1,22 @@
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use uuid::Uuid;
+
+use crate::api::EventWriteToken;
+use crate::backend::config::AppConfig;
+use crate::backend::error::BackendError;
+use crate::backend::seat_batch::{SeatAdjustment, SeatBatchReport};
+use crate::backend::state::{AppState, RecoverLock};
+
+/// Changes the seat counts of several sessions at once. The batch is applied atomically: if any
+/// adjustment is invalid, nothing changes and the response (422) lists the problems per item.
+#[post("/v1/events/<event_id>/seats", data = "<adjustments>")]
+pub fn adjust_seats(_token: EventWriteToken, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, adjustments: Json<Vec<SeatAdjustment>>) -> Result<(Status, Json<SeatBatchReport>), BackendError> {
+    let report = state.storage.write_unpoisoned().adjust_seats(event_id, &adjustments, &config.form_limits)?;
+    if !report.applied {
+        return Ok((Status::UnprocessableEntity, Json(report)));
+    }
+    println!("Adjusted the seats of {} sessions of event {} via API.", report.results.len(), event_id);
+    Ok((Status::Ok, Json(report)))
+}
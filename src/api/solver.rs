@@ -0,0 +1,26 @@
+use rocket::serde::json::Json;
+use rocket::State;
+
+use crate::api::EventWriteToken;
+use crate::backend::data::DistributionRun;
+use crate::backend::error::BackendError;
+use crate::backend::solver::{AllocationProblem, ExternalAssignment};
+use crate::backend::state::{AppState, RecoverLock};
+
+/// Participants, preferences, capacities and constraints of the event for an external solver
+#[get("/v1/events/<event_id>/allocation-problem")]
+pub fn export_allocation_problem(_token: EventWriteToken, state: &State<AppState>, event_id: uuid::Uuid) -> Result<Json<AllocationProblem>, BackendError> {
+    state.storage.read_unpoisoned().allocation_problem(event_id).map(Json)
+}
+
+/// Closes the registration and stores an externally computed assignment as the distribution.
+/// Like a built-in run, the result has to be confirmed by an admin.
+#[post("/v1/events/<event_id>/assignment", data = "<body>")]
+pub fn import_external_assignment(_token: EventWriteToken, state: &State<AppState>, event_id: uuid::Uuid, body: &str) -> Result<Json<DistributionRun>, BackendError> {
+    let assignment = ExternalAssignment::parse(body).map_err(BackendError::bad_request)?;
+    let mut storage = state.storage.write_unpoisoned();
+    let run = storage.import_assignment(event_id, &assignment, "api")?;
+    storage.queue_state_push(event_id);
+    println!("Imported an external assignment for event {} via API, {} seats assigned.", event_id, run.assigned_seats);
+    Ok(Json(run))
+}
@@ -0,0 +1,58 @@
+use rocket::http::ContentType;
+use rocket::serde::json::Json;
+use rocket::State;
+use serde::Serialize;
+
+use crate::api::SuperadminToken;
+use crate::backend::config::AppConfig;
+use crate::backend::data::Storage;
+use crate::backend::error::BackendError;
+use crate::backend::state::{AppState, RecoverLock};
+use crate::backend::validation::Validator;
+
+#[derive(Serialize)]
+pub struct ImportSummary {
+    pub events: usize,
+    pub invitations: usize,
+    pub admins: usize,
+    /// Findings of the integrity check on the imported state
+    pub problems: Vec<String>,
+}
+
+/// Returns the complete storage as JSON, e.g. for backups or migrations to another host
+#[get("/v1/state/export")]
+pub fn export_state(_token: SuperadminToken, state: &State<AppState>) -> (ContentType, String) {
+    // Serialize a snapshot, so writers aren't blocked while a large export is rendered
+    let storage = state.storage.read_unpoisoned().clone();
+    let json = serde_json::to_string(&storage).expect("storage is serializable");
+    (ContentType::JSON, json)
+}
+
+/// Replaces the complete storage with the uploaded one. All sessions are ended.
+/// Events violating the configured form limits are rejected before anything is replaced.
+#[post("/v1/state/import", data = "<storage>")]
+pub fn import_state(_token: SuperadminToken, state: &State<AppState>, config: &State<AppConfig>, storage: Json<Storage>) -> Result<Json<ImportSummary>, BackendError> {
+    let mut imported = storage.into_inner();
+    imported.reindex_events();
+    let mut v = Validator::new(&config.form_limits);
+    for ev in imported.events.values() {
+        v.name(&format!("events.{}.name", ev.uuid), &ev.name);
+        for slot in &ev.slots {
+            for session in &slot.sessions {
+                v.seats(&format!("events.{}.slots.{}.sessions.{}.seats", ev.uuid, slot.uuid, session.uuid), session.seats);
+            }
+        }
+    }
+    v.finish()?;
+    let summary = ImportSummary {
+        events: imported.events.len(),
+        invitations: imported.invitations_codes.len(),
+        admins: imported.admins.len(),
+        problems: imported.check_integrity(),
+    };
+    *state.storage.write_unpoisoned() = imported;
+    state.sessions.write_unpoisoned().clear();
+    state.login_tokens.write_unpoisoned().clear();
+    println!("Imported state with {} event(s) via API.", summary.events);
+    Ok(Json(summary))
+}
@@ -0,0 +1,126 @@
+use rocket::serde::json::Json;
+use rocket::State;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::api::ReadToken;
+use crate::backend::data::{ApplicationPriority, Event, EventState, PriorityCounts, Storage};
+use crate::backend::error::BackendError;
+use crate::backend::no_preference::NoPreferenceCounts;
+use crate::backend::state::{AppState, RecoverLock};
+
+#[derive(Serialize)]
+pub struct EventStats {
+    pub event_id: Uuid,
+    pub name: String,
+    pub state: EventState,
+    pub registrations: RegistrationStats,
+    pub sessions: Vec<SessionStats>,
+    /// Outcome of the latest distribution run, None before the seats are distributed
+    pub fairness: Option<FairnessStats>,
+}
+
+#[derive(Serialize)]
+pub struct RegistrationStats {
+    pub invitations: usize,
+    pub participants: usize,
+    /// Participants who chose a preference in at least one slot
+    pub with_preferences: usize,
+    pub awaiting_verification: usize,
+    pub waiting_list: usize,
+}
+
+#[derive(Serialize)]
+pub struct SessionStats {
+    pub slot_id: Uuid,
+    pub slot: String,
+    pub session_id: Uuid,
+    pub session: String,
+    pub seats: usize,
+    pub assigned: usize,
+    /// Assigned seats per available seat
+    pub fill_rate: f64,
+    pub first_preferences: usize,
+    pub second_preferences: usize,
+    pub third_preferences: usize,
+    /// First preferences per available seat, above 1.0 the session is oversubscribed
+    pub demand: f64,
+}
+
+#[derive(Serialize)]
+pub struct FairnessStats {
+    pub assigned_by_priority: PriorityCounts,
+    /// Share of assigned seats that were the participant's first choice
+    pub first_choice_rate: f64,
+    /// Share of assigned seats that were one of the participant's three choices
+    pub top_three_rate: f64,
+    /// Participants taking part in the distribution who didn't get any seat
+    pub participants_without_seat: usize,
+    /// Participants who ranked no session of a slot, per outcome of the event's policy
+    pub without_preferences: NoPreferenceCounts,
+}
+
+fn ratio(part: usize, total: usize) -> f64 {
+    if total == 0 { 0.0 } else { part as f64 / total as f64 }
+}
+
+/// Live statistics of an event, e.g. for an infoscreen at the venue
+#[get("/v1/events/<event_id>/stats")]
+pub fn event_stats(_token: ReadToken, state: &State<AppState>, event_id: Uuid) -> Result<Json<EventStats>, BackendError> {
+    let storage = state.storage.read_unpoisoned();
+    let Some(ev) = storage.events.get(&event_id) else { return Err(BackendError::NotFound("event")); };
+    Ok(Json(collect_stats(&storage, ev)))
+}
+
+/// Counts only, safe to show to anyone allowed to see the event's figures
+pub fn collect_stats(storage: &Storage, ev: &Event) -> EventStats {
+    let event_id = ev.uuid;
+    let registrations = RegistrationStats {
+        invitations: storage.invitations_codes.values().filter(|inv| inv.event_id == event_id).count(),
+        participants: ev.participants.len(),
+        with_preferences: ev.participants.keys()
+            .filter(|pid| ev.slots.iter().flat_map(|slot| slot.sessions.iter()).flat_map(|s| s.applications.iter())
+                .any(|a| a.participant == **pid && !matches!(a.priority, ApplicationPriority::NoPreference)))
+            .count(),
+        awaiting_verification: storage.invitations_codes.values().filter(|inv| inv.event_id == event_id && inv.pending_verification.is_some()).count(),
+        waiting_list: ev.waiting_list.len(),
+    };
+
+    let mut sessions = Vec::new();
+    for slot in &ev.slots {
+        for sess in &slot.sessions {
+            let count = |priority: fn(&ApplicationPriority) -> bool| sess.applications.iter().filter(|a| priority(&a.priority)).count();
+            let first_preferences = count(|p| matches!(p, ApplicationPriority::FirstPreference));
+            sessions.push(SessionStats {
+                slot_id: slot.uuid,
+                slot: slot.name.clone(),
+                session_id: sess.uuid,
+                session: sess.name.clone(),
+                seats: sess.seats,
+                assigned: sess.participants.len(),
+                fill_rate: ratio(sess.participants.len(), sess.seats),
+                first_preferences,
+                second_preferences: count(|p| matches!(p, ApplicationPriority::SecondPreference)),
+                third_preferences: count(|p| matches!(p, ApplicationPriority::ThirdPreference)),
+                demand: ratio(first_preferences, sess.seats),
+            });
+        }
+    }
+
+    let fairness = ev.distribution_runs.last().filter(|_| ev.is_distributed()).map(|run| {
+        let counts = run.assigned_by_priority;
+        let participants_without_seat = ev.participants.values()
+            .filter(|p| !p.awaiting_verification)
+            .filter(|p| !ev.slots.iter().any(|slot| slot.sessions.iter().any(|s| s.participants.contains(&p.uuid))))
+            .count();
+        FairnessStats {
+            assigned_by_priority: counts,
+            first_choice_rate: ratio(counts.first, counts.total()),
+            top_three_rate: ratio(counts.first + counts.second + counts.third, counts.total()),
+            participants_without_seat,
+            without_preferences: run.without_preferences,
+        }
+    });
+
+    EventStats { event_id, name: ev.name.clone(), state: ev.state, registrations, sessions, fairness }
+}
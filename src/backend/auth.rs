@@ -4,10 +4,18 @@ use rocket::request::{FromRequest, Outcome, Request};
 use rocket::serde::json::Json;
 use rocket::State;
 use std::time::{Duration, SystemTime};
-use rocket::response::Redirect;
+use rocket::response::{Flash, Redirect};
 use uuid::Uuid;
 
-use crate::backend::state::AppState;
+use crate::backend::config::AppConfig;
+use crate::backend::data::Invitation;
+use crate::backend::error::BackendError;
+use crate::backend::mail::OutgoingMail;
+use crate::backend::state::{AppState, RecoverLock};
+use crate::backend::waiting_room::Admission;
+
+/// Validity of login links sent by email
+const LOGIN_LINK_TTL: Duration = Duration::from_secs(15 * 60);
 
 #[derive(FromForm)]
 pub struct LoginRequest {
@@ -20,6 +28,17 @@ pub struct UserLoginRequest {
     pub code: String,
 }
 
+#[derive(FromForm)]
+pub struct EmailLoginRequest {
+    pub email: String,
+}
+
+/// One-time token of a login link sent by email
+#[derive(Clone, Debug)]
+pub struct LoginToken {
+    pub code: String,
+    pub valid_until: SystemTime,
+}
 
 #[derive(Clone, Debug)]
 pub struct Session{
@@ -30,7 +49,8 @@ pub struct Session{
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum SessionUserType{
-    Admin,
+    /// Admins only see the events of their tenant (None is the default tenant)
+    Admin { username: String, tenant: Option<String> },
     User { code: String }
 }
 
@@ -58,7 +78,7 @@ impl<'r> FromRequest<'r> for Session {
             _ => return Outcome::Error((Status::InternalServerError, ())),
         };
 
-        let sessions = state.sessions.read().expect("sessions poisoned");
+        let sessions = state.sessions.read_unpoisoned();
         if let Some(sess) = sessions.get(&sid) {
             // validate expiry
             if sess.valid_until > SystemTime::now() {
@@ -69,71 +89,192 @@ impl<'r> FromRequest<'r> for Session {
     }
 }
 
+/// Session of a logged in admin. Routes taking this guard reject participants with 403.
+/// Co-organizers limited to some events are rejected with 403 outside the admin start page
+/// and the pages of their events.
+pub struct AdminSession {
+    pub username: String,
+    /// Tenant whose events the admin may access
+    pub tenant: Option<String>,
+    /// Events a co-organizer may access, None for all events of the tenant
+    pub events: Option<Vec<Uuid>>,
+}
+
+/// Returns true if the request targets the admin start page or `/admin/events/<id>/...` of one of the events
+fn within_events(req: &Request<'_>, events: &[Uuid]) -> bool {
+    let segments: Vec<&str> = req.uri().path().segments().collect();
+    match segments.as_slice() {
+        ["admin"] => true,
+        ["admin", "events", id, ..] => id.parse::<Uuid>().is_ok_and(|id| events.contains(&id)),
+        _ => false,
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminSession {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.guard::<Session>().await {
+            Outcome::Success(Session { user_type: SessionUserType::Admin { username, tenant }, .. }) => {
+                let state = match req.guard::<&State<AppState>>().await {
+                    Outcome::Success(s) => s,
+                    _ => return Outcome::Error((Status::InternalServerError, ())),
+                };
+                // Looked up on every request, so removing a co-organizer takes effect immediately
+                let events = state.storage.read_unpoisoned().admins.get(&username).and_then(|acc| acc.events.clone());
+                if events.as_ref().is_some_and(|events| !within_events(req, events)) {
+                    return Outcome::Error((Status::Forbidden, ()));
+                }
+                Outcome::Success(AdminSession { username, tenant, events })
+            }
+            Outcome::Success(_) => Outcome::Error((Status::Forbidden, ())),
+            Outcome::Error(e) => Outcome::Error(e),
+            Outcome::Forward(f) => Outcome::Forward(f),
+        }
+    }
+}
+
+/// Session of a participant together with their invitation. Admins are rejected with 403,
+/// sessions whose invitation code was deleted or rotated with 401.
+pub struct ParticipantSession {
+    pub code: String,
+    /// Snapshot of the invitation taken when the request arrived
+    pub invitation: Invitation,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ParticipantSession {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let code = match req.guard::<Session>().await {
+            Outcome::Success(Session { user_type: SessionUserType::User { code }, .. }) => code,
+            Outcome::Success(_) => return Outcome::Error((Status::Forbidden, ())),
+            Outcome::Error(e) => return Outcome::Error(e),
+            Outcome::Forward(f) => return Outcome::Forward(f),
+        };
+        let state = match req.guard::<&State<AppState>>().await {
+            Outcome::Success(s) => s,
+            _ => return Outcome::Error((Status::InternalServerError, ())),
+        };
+        let invitation = state.storage.read_unpoisoned().invitations_codes.get(&code).cloned();
+        match invitation {
+            Some(invitation) => Outcome::Success(ParticipantSession { code, invitation }),
+            None => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
 impl Session {
     pub fn new(user_type: SessionUserType, ttl: Duration) -> Self {
         Session { id: uuid::Uuid::new_v4(), user_type, valid_until: SystemTime::now() + ttl }
     }
 }
 
-#[post("/login/admin", data = "<form>")]
-pub fn login_admin(form: Form<LoginRequest>, jar: &CookieJar, state: &State<AppState>) -> Result<Redirect, Status> {
-    let form = form.into_inner();
-    let ok = {
-        let storage = state.storage.read().expect("storage poisoned");
-        storage.verify_admin(&form.username, &form.password)
-    };
-    if !ok {
-        return Err(Status::Unauthorized);
+/// Creates a session valid for 24 hours and sets the session cookie
+pub fn start_session(jar: &CookieJar, state: &AppState, user_type: SessionUserType) {
+    if let SessionUserType::User { code } = &user_type {
+        state.storage.write_unpoisoned().record_first_login(code);
     }
-
-    let sess = Session::new(SessionUserType::Admin, Duration::from_secs(24*60*60));
-    let sid = sess.id.clone();
+    let sess = Session::new(user_type, Duration::from_secs(24*60*60));
+    let sid = sess.id;
     {
-        let mut sessions = state.sessions.write().expect("sessions poisoned");
-        sessions.insert(sess.id.clone(), sess);
+        let mut sessions = state.sessions.write_unpoisoned();
+        sessions.insert(sess.id, sess);
     }
     let cookie = Cookie::build(Cookie::new("sid", sid.to_string()))
         .http_only(true)
         .same_site(SameSite::Lax)
         .build();
     jar.add(cookie);
+}
+
+#[post("/login/admin", data = "<form>")]
+pub fn login_admin(form: Form<LoginRequest>, jar: &CookieJar, state: &State<AppState>) -> Result<Redirect, BackendError> {
+    let form = form.into_inner();
+    let tenant = {
+        let storage = state.storage.read_unpoisoned();
+        if !storage.verify_admin(&form.username, &form.password) {
+            return Err(BackendError::Unauthorized("Invalid username or password.".to_string()));
+        }
+        storage.admins.get(&form.username).and_then(|acc| acc.tenant.clone())
+    };
+
+    start_session(jar, state, SessionUserType::Admin { username: form.username, tenant });
     Ok(Redirect::to("/admin"))
 }
 
 #[post("/login", data = "<form>")]
-pub fn login_user(form: Form<UserLoginRequest>, jar: &CookieJar, state: &State<AppState>) -> Result<Redirect, Status> {
+pub fn login_user(_admission: Admission<'_>, form: Form<UserLoginRequest>, jar: &CookieJar, state: &State<AppState>) -> Result<Redirect, BackendError> {
     let form = form.into_inner();
 
     // Validate invitation code exists
     let is_valid = {
-        let storage = state.storage.read().expect("storage poisoned");
+        let storage = state.storage.read_unpoisoned();
         storage.invitations_codes.contains_key(&form.code)
     };
 
     if !is_valid {
-        return Err(Status::Unauthorized);
+        return Err(BackendError::Unauthorized("Unknown invitation code.".to_string()));
     }
 
     // Create user session and set cookie, include invite code in session type
-    let sess = Session::new(SessionUserType::User { code: form.code.clone() }, Duration::from_secs(24*60*60));
-    let sid = sess.id.clone();
-    {
-        let mut sessions = state.sessions.write().expect("sessions poisoned");
-        sessions.insert(sess.id.clone(), sess);
-    }
-    let cookie = Cookie::build(Cookie::new("sid", sid.to_string()))
-        .http_only(true)
-        .same_site(SameSite::Lax)
-        .build();
-    jar.add(cookie);
+    start_session(jar, state, SessionUserType::User { code: form.code.clone() });
 
     Ok(Redirect::to("/event"))
 }
 
+/// Sends a short-lived login link to every invitation registered with the given address.
+/// The response doesn't reveal whether the address is known.
+#[post("/login/email", data = "<form>")]
+pub fn request_login_link(form: Form<EmailLoginRequest>, state: &State<AppState>, config: &State<AppConfig>) -> Flash<Redirect> {
+    let email = form.into_inner().email.trim().to_lowercase();
+    let done = Flash::success(Redirect::to("/"), "If this address belongs to an invitation, we've sent you a login link.");
+    if !state.login_link_limiter.check(&email, 3) {
+        return done;
+    }
+
+    let mut storage = state.storage.write_unpoisoned();
+    // Self-registrations still awaiting verification have to use their verification link
+    let invitations: Vec<Invitation> = storage.invitations_codes.values()
+        .filter(|inv| inv.pending_verification.is_none() && inv.email.as_deref().is_some_and(|e| e.eq_ignore_ascii_case(&email)))
+        .cloned()
+        .collect();
+    let mut tokens = state.login_tokens.write_unpoisoned();
+    let now = SystemTime::now();
+    tokens.retain(|_, t| t.valid_until > now);
+    for inv in invitations {
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        tokens.insert(token.clone(), LoginToken { code: inv.code.clone(), valid_until: now + LOGIN_LINK_TTL });
+        let event_name = storage.events.get(&inv.event_id).map(|ev| ev.name.clone()).unwrap_or_default();
+        let (subject, body) = config.mail_templates.render("login_link", storage.locale_of(&inv).as_deref(), &[
+            ("event", &event_name),
+            ("minutes", &(LOGIN_LINK_TTL.as_secs() / 60).to_string()),
+            ("link", &config.url(&format!("/login/link/{}", token))),
+        ]);
+        storage.queue_mail(OutgoingMail::new(Some(inv.event_id), Some(email.clone()), email.clone(), subject, body));
+    }
+    done
+}
+
+/// Logs in with a token from a login link. Each token works only once.
+#[get("/login/link/<token>")]
+pub fn login_link(_admission: Admission<'_>, token: &str, jar: &CookieJar, state: &State<AppState>) -> Flash<Redirect> {
+    let login = state.login_tokens.write_unpoisoned().remove(token);
+    match login {
+        Some(login) if login.valid_until > SystemTime::now() => {
+            start_session(jar, state, SessionUserType::User { code: login.code });
+            Flash::success(Redirect::to("/event"), "You're logged in.")
+        }
+        _ => Flash::error(Redirect::to("/"), "This login link is invalid or has expired. Please request a new one."),
+    }
+}
+
 #[post("/logout")]
 pub fn logout(jar: &CookieJar, state: &State<AppState>, session: Option<Session>) -> Redirect {
     if let Some(sess) = session {
-        let mut sessions = state.sessions.write().expect("sessions poisoned");
+        let mut sessions = state.sessions.write_unpoisoned();
         sessions.remove(&sess.id);
     }
     jar.remove(Cookie::from("sid"));
@@ -143,27 +284,17 @@ pub fn logout(jar: &CookieJar, state: &State<AppState>, session: Option<Session>
 /// Allow direct access via link: GET /invitation/<code>
 /// If the code exists, create a user session, set cookie, and redirect to /event.
 #[get("/invitation/<code>")]
-pub fn invitation_login(code: &str, jar: &CookieJar, state: &State<AppState>) -> Result<Redirect, Status> {
+pub fn invitation_login(_admission: Admission<'_>, code: &str, jar: &CookieJar, state: &State<AppState>) -> Result<Redirect, Status> {
     // Validate invitation code exists
     let is_valid = {
-        let storage = state.storage.read().expect("storage poisoned");
+        let storage = state.storage.read_unpoisoned();
         storage.invitations_codes.contains_key(code)
     };
 
     if !is_valid { return Err(Status::Unauthorized); }
 
     // Create user session and set cookie
-    let sess = Session::new(SessionUserType::User { code: code.to_string() }, Duration::from_secs(24*60*60));
-    let sid = sess.id.clone();
-    {
-        let mut sessions = state.sessions.write().expect("sessions poisoned");
-        sessions.insert(sess.id.clone(), sess);
-    }
-    let cookie = Cookie::build(Cookie::new("sid", sid.to_string()))
-        .http_only(true)
-        .same_site(SameSite::Lax)
-        .build();
-    jar.add(cookie);
+    start_session(jar, state, SessionUserType::User { code: code.to_string() });
 
     Ok(Redirect::to("/event"))
 }
\ No newline at end of file
@@ -0,0 +1,36 @@
+use rocket::data::{self, ByteUnit, Data, FromData, ToByteUnit};
+use rocket::form::{Form, FromForm};
+use rocket::http::Status;
+use rocket::request::Request;
+
+use crate::backend::error::BackendError;
+
+/// Name of the Rocket limit for [`BulkForm`] submissions
+pub const BULK_FORM_LIMIT: &str = "bulk-form";
+
+fn default_bulk_form_limit() -> ByteUnit {
+    2.mebibytes()
+}
+
+/// URL-encoded form bounded by the `bulk-form` limit instead of the tight `form` limit, for pasted
+/// lists and imports. Take it as `Result<BulkForm<T>, BackendError>` to send oversized submissions
+/// back with a message.
+pub struct BulkForm<T>(pub T);
+
+#[rocket::async_trait]
+impl<'r, T: for<'a> FromForm<'a> + Send> FromData<'r> for BulkForm<T> {
+    type Error = BackendError;
+
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let limit = req.limits().get(BULK_FORM_LIMIT).unwrap_or_else(default_bulk_form_limit);
+        let body = match data.open(limit).into_string().await {
+            Ok(body) if body.is_complete() => body.into_inner(),
+            Ok(_) => return data::Outcome::Error((Status::PayloadTooLarge, BackendError::bad_request(format!("The submission is larger than {}. Please split it into several parts.", limit)))),
+            Err(_) => return data::Outcome::Error((Status::BadRequest, BackendError::bad_request("The submission couldn't be read."))),
+        };
+        match Form::<T>::parse(&body) {
+            Ok(form) => data::Outcome::Success(BulkForm(form)),
+            Err(errors) => data::Outcome::Error((Status::UnprocessableEntity, BackendError::bad_request(format!("The submission is incomplete: {}", errors)))),
+        }
+    }
+}
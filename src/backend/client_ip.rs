@@ -0,0 +1,141 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::State;
+use serde::Deserialize;
+
+use crate::backend::config::AppConfig;
+
+/// Address range in CIDR notation (`10.0.0.0/8`, `::1/128`). A plain address is a range of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNet {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // IPv4 peers on dual-stack sockets show up as mapped IPv6 addresses
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            v4 => v4,
+        };
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => prefix_matches(&net.octets(), &ip.octets(), self.prefix),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => prefix_matches(&net.octets(), &ip.octets(), self.prefix),
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(net: &[u8], ip: &[u8], prefix: u8) -> bool {
+    let full = prefix as usize / 8;
+    let rest = prefix % 8;
+    if net[..full] != ip[..full] {
+        return false;
+    }
+    rest == 0 || (net[full] ^ ip[full]) >> (8 - rest) == 0
+}
+
+impl FromStr for IpNet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s.trim(), None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| format!("invalid address in {}", s))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse::<u8>().ok().filter(|p| *p <= max).ok_or_else(|| format!("invalid prefix length in {}", s))?,
+            None => max,
+        };
+        Ok(IpNet { addr, prefix })
+    }
+}
+
+impl TryFrom<String> for IpNet {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Address of the client that sent the request. Behind one of the configured `trusted_proxies`,
+/// it's taken from the `Forwarded` or `X-Forwarded-For` header, reading the chain from the right
+/// and stopping at the first address that isn't a trusted proxy. Headers sent by other peers are ignored.
+/// Use this guard instead of `IpAddr` wherever an address is used for rate limiting or logging.
+pub struct ClientIp(pub IpAddr);
+
+impl ClientIp {
+    pub fn resolve(remote: IpAddr, forwarded_for: &[Option<IpAddr>], trusted: &[IpNet]) -> IpAddr {
+        let mut client = remote;
+        for hop in forwarded_for.iter().rev() {
+            if !trusted.iter().any(|net| net.contains(client)) {
+                break;
+            }
+            // Obfuscated or malformed entries hide the client, so the last known hop is used
+            let Some(hop) = hop else { break; };
+            client = *hop;
+        }
+        client
+    }
+}
+
+/// Addresses of the `for` parameters of all `Forwarded` headers (RFC 7239), in order
+fn forwarded_chain(req: &Request<'_>) -> Vec<Option<IpAddr>> {
+    req.headers().get("Forwarded")
+        .flat_map(|header| header.split(','))
+        .filter_map(|element| element.split(';').find_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            key.trim().eq_ignore_ascii_case("for").then(|| parse_node(value.trim().trim_matches('"')))
+        }))
+        .collect()
+}
+
+/// Addresses of all `X-Forwarded-For` headers, in order
+fn x_forwarded_for_chain(req: &Request<'_>) -> Vec<Option<IpAddr>> {
+    req.headers().get("X-Forwarded-For")
+        .flat_map(|header| header.split(','))
+        .map(|node| parse_node(node.trim()))
+        .collect()
+}
+
+/// Parses `192.0.2.1`, `192.0.2.1:4711`, `2001:db8::1` or `[2001:db8::1]:4711`
+fn parse_node(node: &str) -> Option<IpAddr> {
+    if let Ok(ip) = node.parse() {
+        return Some(ip);
+    }
+    if let Some(rest) = node.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+    node.rsplit_once(':')?.0.parse().ok()
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientIp {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(remote) = req.remote().map(|addr| addr.ip()) else {
+            return Outcome::Forward(Status::InternalServerError);
+        };
+        let config = match req.guard::<&State<AppConfig>>().await {
+            Outcome::Success(c) => c,
+            _ => return Outcome::Error((Status::InternalServerError, ())),
+        };
+        if config.trusted_proxies.is_empty() {
+            return Outcome::Success(ClientIp(remote));
+        }
+        let mut chain = forwarded_chain(req);
+        if chain.is_empty() {
+            chain = x_forwarded_for_chain(req);
+        }
+        Outcome::Success(ClientIp(ClientIp::resolve(remote, &chain, &config.trusted_proxies)))
+    }
+}
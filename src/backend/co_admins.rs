@@ -0,0 +1,101 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::backend::data::{AdminAccount, Event, Storage};
+use crate::backend::error::BackendError;
+
+/// Validity of an onboarding link sent to a co-organizer
+pub const CO_ADMIN_INVITE_DAYS: i64 = 7;
+
+/// Invitation of a co-organizer by email. The onboarding link lets the invitee create an admin
+/// account that only sees this event, or add the event to such an account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoAdminInvite {
+    pub uuid: Uuid,
+    pub token: String,
+    pub email: String,
+    pub invited_by: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl CoAdminInvite {
+    pub fn new(email: String, invited_by: String, now: DateTime<Utc>) -> Self {
+        CoAdminInvite {
+            uuid: Uuid::new_v4(),
+            token: format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()),
+            email,
+            invited_by,
+            created_at: now,
+            expires_at: now + Duration::days(CO_ADMIN_INVITE_DAYS),
+        }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+}
+
+impl AdminAccount {
+    /// Returns true if the admin may manage the event, given it belongs to the admin's tenant
+    pub fn may_manage(&self, event_id: Uuid) -> bool {
+        self.events.as_ref().is_none_or(|events| events.contains(&event_id))
+    }
+}
+
+impl Storage {
+    /// The event and the unexpired co-organizer invitation with the token
+    pub fn co_admin_invite(&self, token: &str, now: DateTime<Utc>) -> Option<(&Event, &CoAdminInvite)> {
+        self.events.values()
+            .find_map(|ev| ev.co_admin_invites.iter().find(|invite| invite.token == token).map(|invite| (ev, invite)))
+            .filter(|(_, invite)| !invite.is_expired(now))
+    }
+
+    /// Accounts limited to the event that were added as co-organizers, sorted by name
+    pub fn co_admins(&self, event_id: Uuid) -> Vec<&AdminAccount> {
+        let mut accounts: Vec<&AdminAccount> = self.admins.values()
+            .filter(|acc| acc.events.as_ref().is_some_and(|events| events.contains(&event_id)))
+            .collect();
+        accounts.sort_by(|a, b| a.username.cmp(&b.username));
+        accounts
+    }
+
+    /// Redeems an onboarding link. An unknown username creates an account limited to the event,
+    /// a known one needs its password and gets the event added. Returns the event and the tenant
+    /// to start the admin session with.
+    pub fn accept_co_admin_invite(&mut self, token: &str, username: &str, password: &str, now: DateTime<Utc>) -> Result<(Uuid, Option<String>), BackendError> {
+        let (event_id, tenant) = self.co_admin_invite(token, now)
+            .map(|(ev, _)| (ev.uuid, ev.tenant.clone()))
+            .ok_or(BackendError::NotFound("invitation"))?;
+        match self.admins.get(username) {
+            Some(_) if !self.verify_admin(username, password) => return Err(BackendError::Unauthorized("Invalid username or password.".to_string())),
+            Some(acc) if acc.tenant != tenant => return Err(BackendError::conflict("This account belongs to another organization.")),
+            Some(_) => {
+                let acc = self.admins.get_mut(username).expect("checked above");
+                if let Some(events) = acc.events.as_mut().filter(|events| !events.contains(&event_id)) {
+                    events.push(event_id);
+                }
+            }
+            None => {
+                let mut acc = AdminAccount::new_hashed(username.to_string(), password, tenant.clone());
+                acc.events = Some(vec![event_id]);
+                self.admins.insert(username.to_string(), acc);
+            }
+        }
+        if let Some(ev) = self.events.get_mut(&event_id) {
+            ev.co_admin_invites.retain(|invite| invite.token != token);
+        }
+        Ok((event_id, tenant))
+    }
+
+    /// Takes the event away from a co-organizer. The account stays and keeps its other events.
+    pub fn remove_co_admin(&mut self, event_id: Uuid, username: &str) -> Result<(), BackendError> {
+        let events = self.admins.get_mut(username)
+            .and_then(|acc| acc.events.as_mut())
+            .filter(|events| events.contains(&event_id))
+            .ok_or(BackendError::NotFound("co-organizer"))?;
+        events.retain(|id| *id != event_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::backend::data::{ApplicationPriority, Storage};
+use crate::backend::error::BackendError;
+
+/// Participants are notified about an unfinished slot once its deadline is this close
+pub const SLOT_REMINDER_HOURS: i64 = 24;
+
+/// Which slots a registered participant has chosen preferences for
+#[derive(Debug, Clone, Serialize)]
+pub struct ParticipantCompletion {
+    pub participant_id: Uuid,
+    #[serde(skip)]
+    pub code: String,
+    /// True once every slot with sessions has a preference
+    pub complete: bool,
+    pub slots: Vec<SlotCompletion>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SlotCompletion {
+    pub slot_id: Uuid,
+    pub slot: String,
+    pub complete: bool,
+    /// Last moment preferences are accepted for the slot, with the participant's extension
+    pub deadline: Option<DateTime<Utc>>,
+    /// Set once the participant was told about the unfinished slot
+    pub notified: bool,
+}
+
+impl SlotCompletion {
+    /// Returns true if the participant should be told now that the slot isn't finished
+    pub fn needs_reminder(&self, now: DateTime<Utc>) -> bool {
+        !self.complete && !self.notified
+            && self.deadline.is_some_and(|deadline| now < deadline && now >= deadline - chrono::Duration::hours(SLOT_REMINDER_HOURS))
+    }
+}
+
+impl Storage {
+    /// Completion of every registered participant of the event. Participants awaiting email
+    /// verification and slots closed for preferences are left out.
+    pub fn completion_states(&self, event_id: Uuid, now: DateTime<Utc>) -> Result<Vec<ParticipantCompletion>, BackendError> {
+        let ev = self.events.get(&event_id).ok_or(BackendError::NotFound("event"))?;
+        let mut states: Vec<ParticipantCompletion> = self.invitations_codes.values()
+            .filter(|inv| inv.event_id == event_id)
+            .filter_map(|inv| {
+                let participant = ev.participants.get(&inv.participant_id?).filter(|p| !p.awaiting_verification)?;
+                if !ev.accepts_preferences_with(inv.deadline_extension, now) {
+                    return None;
+                }
+                let event_deadline = ev.deadline_with(inv.deadline_extension);
+                let slots: Vec<SlotCompletion> = ev.slots.iter()
+                    .filter(|slot| !slot.sessions.is_empty() && slot.accepts_preferences(now))
+                    .map(|slot| SlotCompletion {
+                        slot_id: slot.uuid,
+                        slot: slot.name.clone(),
+                        complete: slot.sessions.iter().flat_map(|s| &s.applications)
+                            .any(|a| a.participant == participant.uuid && !matches!(a.priority, ApplicationPriority::NoPreference)),
+                        deadline: [slot.preference_deadline, event_deadline].into_iter().flatten().min(),
+                        notified: participant.slot_reminders_sent.contains(&slot.uuid),
+                    })
+                    .collect();
+                Some(ParticipantCompletion { participant_id: participant.uuid, code: inv.code.clone(), complete: slots.iter().all(|s| s.complete), slots })
+            })
+            .collect();
+        states.sort_by_key(|state| state.participant_id);
+        Ok(states)
+    }
+}
@@ -0,0 +1,56 @@
+use serde::Serialize;
+
+use crate::backend::error::BackendError;
+
+/// Field of an edit form, with the value the admin submitted and the one stored in the meantime
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictField {
+    /// Name of the form field, used to submit the value again
+    pub name: &'static str,
+    pub label: &'static str,
+    pub submitted: String,
+    pub current: String,
+    pub differs: bool,
+}
+
+/// An edit based on an outdated revision of the entity. The conflict page lists the submitted
+/// and the current values side by side, so the admin can decide which to keep.
+#[derive(Debug, Clone, Serialize)]
+pub struct EditConflict {
+    /// Kind of the edited entity, e.g. "session"
+    pub entity: &'static str,
+    pub name: String,
+    /// Form action to save the submitted values over the current revision
+    pub action: String,
+    pub revision: u64,
+    /// Page to return to without saving
+    pub back: String,
+    pub fields: Vec<ConflictField>,
+}
+
+impl EditConflict {
+    pub fn new(entity: &'static str, name: &str, action: String, revision: u64, back: String) -> Self {
+        EditConflict { entity, name: name.to_string(), action, revision, back, fields: Vec::new() }
+    }
+
+    /// Adds a field, values are compared as they appear in the form
+    pub fn field(mut self, name: &'static str, label: &'static str, submitted: impl ToString, current: impl ToString) -> Self {
+        let (submitted, current) = (submitted.to_string(), current.to_string());
+        self.fields.push(ConflictField { name, label, differs: submitted.trim() != current.trim(), submitted, current });
+        self
+    }
+}
+
+/// Form value of an optional field, empty if unset
+pub fn form_value<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(ToString::to_string).unwrap_or_default()
+}
+
+/// Rejects an edit of a form rendered for another revision than the stored one. The conflict is
+/// only built if needed.
+pub fn check_revision(submitted: u64, current: u64, conflict: impl FnOnce() -> EditConflict) -> Result<(), BackendError> {
+    if submitted != current {
+        return Err(BackendError::EditConflict(Box::new(conflict())));
+    }
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use serde::Deserialize;
+
+use crate::backend::client_ip::IpNet;
+use crate::backend::mail_templates::{MailTemplates, FALLBACK_LOCALE};
+
+/// Application specific settings, read from Rocket.toml / ROCKET_* environment variables
+#[derive(Debug, Clone, Deserialize)]
+pub struct AppConfig {
+    /// Public base URL of this instance, used to build links in outgoing messages
+    #[serde(default = "default_public_url")]
+    pub public_url: String,
+    #[serde(default)]
+    pub mail: MailConfig,
+    /// Maximum number of self-service registrations per client IP and hour
+    #[serde(default = "default_registrations_per_hour")]
+    pub registrations_per_hour: usize,
+    /// Hours a self-registered participant has to confirm their email address
+    #[serde(default = "default_verification_hours")]
+    pub verification_hours: u32,
+    /// Bearer token for the superadmin API. The API is disabled if neither this nor `api_tokens` is set.
+    #[serde(default)]
+    pub api_token: Option<String>,
+    /// Further API tokens, each limited to a scope
+    #[serde(default)]
+    pub api_tokens: Vec<ApiTokenConfig>,
+    /// Operator contact (mailto: or https: URL) sent to browser push services
+    #[serde(default = "default_push_contact")]
+    pub push_contact: String,
+    /// Bounds enforced on submitted forms, API payloads and imports
+    #[serde(default)]
+    pub form_limits: FormLimits,
+    /// Member directory invitations can be imported from. The import is hidden if unset.
+    #[serde(default)]
+    pub directory: Option<DirectoryConfig>,
+    /// Reverse proxies whose `Forwarded` / `X-Forwarded-For` headers are trusted to name the client
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpNet>,
+    /// Participant requests handled at the same time before further participants are queued, 0 disables the queue
+    #[serde(default = "default_concurrent_user_requests")]
+    pub concurrent_user_requests: usize,
+    /// How often the infoscreen snapshots of published events are regenerated
+    #[serde(default = "default_infoscreen_refresh_seconds")]
+    pub infoscreen_refresh_seconds: u64,
+    /// Rejects all changes with 503 and doesn't save the state, run the scheduler or send mails,
+    /// e.g. during a migration or for a public results mirror of a copied state file
+    #[serde(default)]
+    pub read_only: bool,
+    /// Loaded from `mail.templates_dir` at launch
+    #[serde(skip)]
+    pub mail_templates: MailTemplates,
+}
+
+/// What an API token may do. Each scope includes the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApiScope {
+    /// Statistics and other reads that don't expose accounts or personal data
+    ReadOnly,
+    /// Changes to events
+    EventWrite,
+    /// Everything, including admin accounts and full state exports and imports
+    Superadmin,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiTokenConfig {
+    /// Shown in logs and used as key for the rate limit
+    pub name: String,
+    pub token: String,
+    pub scope: ApiScope,
+    #[serde(default = "default_api_requests_per_minute")]
+    pub requests_per_minute: usize,
+}
+
+/// Member directory to import invitations from, selected by `kind`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum DirectoryConfig {
+    /// HTTP endpoint returning a JSON array of members, e.g. a small bridge in front of LDAP
+    Json {
+        url: String,
+        /// Sent as bearer token
+        #[serde(default)]
+        token: Option<String>,
+    },
+}
+
+/// Validation limits for user and admin input. Missing keys keep their default.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FormLimits {
+    /// Maximum number of seats of a single session
+    pub max_seats: usize,
+    /// Maximum length of names (events, slots, sessions, participants) in characters
+    pub max_name_length: usize,
+    /// Maximum length of descriptions, notes and feedback comments in characters
+    pub max_text_length: usize,
+    /// Maximum length of an invitation code
+    pub max_code_length: usize,
+    /// Maximum number of codes accepted by one bulk import
+    pub max_bulk_invites: usize,
+    /// Minimum length of admin passwords
+    pub min_password_length: usize,
+}
+
+/// SMTP settings. Without an smtp_host, outgoing mails stay queued for the admins.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MailConfig {
+    pub smtp_host: Option<String>,
+    pub smtp_port: Option<u16>,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    /// Use STARTTLS instead of implicit TLS
+    #[serde(default)]
+    pub starttls: bool,
+    #[serde(default = "default_from")]
+    pub from: String,
+    /// Directory with `<locale>/<name>.txt` files adding locales or overriding the built-in mail templates
+    #[serde(default)]
+    pub templates_dir: Option<String>,
+    /// Locale of mails to participants who haven't chosen one
+    #[serde(default = "default_locale")]
+    pub default_locale: String,
+}
+
+fn default_public_url() -> String {
+    "http://localhost:8000".to_string()
+}
+
+fn default_registrations_per_hour() -> usize {
+    5
+}
+
+pub fn default_api_requests_per_minute() -> usize {
+    120
+}
+
+fn default_verification_hours() -> u32 {
+    48
+}
+
+fn default_push_contact() -> String {
+    "mailto:noreply@localhost".to_string()
+}
+
+fn default_from() -> String {
+    "FESD <noreply@localhost>".to_string()
+}
+
+fn default_concurrent_user_requests() -> usize {
+    32
+}
+
+fn default_infoscreen_refresh_seconds() -> u64 {
+    30
+}
+
+fn default_locale() -> String {
+    FALLBACK_LOCALE.to_string()
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        MailConfig { smtp_host: None, smtp_port: None, smtp_username: None, smtp_password: None, starttls: false, from: default_from(), templates_dir: None, default_locale: default_locale() }
+    }
+}
+
+impl Default for FormLimits {
+    fn default() -> Self {
+        FormLimits { max_seats: 10000, max_name_length: 200, max_text_length: 5000, max_code_length: 64, max_bulk_invites: 5000, min_password_length: 12 }
+    }
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig { public_url: default_public_url(), mail: MailConfig::default(), registrations_per_hour: default_registrations_per_hour(), verification_hours: default_verification_hours(), api_token: None, api_tokens: Vec::new(), push_contact: default_push_contact(), form_limits: FormLimits::default(), directory: None, trusted_proxies: Vec::new(), concurrent_user_requests: default_concurrent_user_requests(), infoscreen_refresh_seconds: default_infoscreen_refresh_seconds(), read_only: false, mail_templates: MailTemplates::default() }
+    }
+}
+
+impl AppConfig {
+    /// Builds an absolute link to the given path of this instance
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.public_url.trim_end_matches('/'), path)
+    }
+}
@@ -0,0 +1,35 @@
+use std::time::{Duration, SystemTime};
+
+use uuid::Uuid;
+
+use crate::backend::state::{AppState, RecoverLock};
+
+/// Time an admin has to complete a destructive action after opening its confirmation page
+pub const CONFIRMATION_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Issued by the confirmation page of a destructive action and required by the action itself,
+/// so a replayed or forged request alone can't destroy data. Bound to one admin and action, usable once.
+pub struct ConfirmationToken {
+    pub username: String,
+    /// Action and target, e.g. `delete-event:<uuid>`
+    pub action: String,
+    pub valid_until: SystemTime,
+}
+
+impl AppState {
+    pub fn issue_confirmation(&self, username: &str, action: &str) -> String {
+        let mut tokens = self.confirmation_tokens.write_unpoisoned();
+        let now = SystemTime::now();
+        tokens.retain(|_, t| t.valid_until > now);
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        tokens.insert(token.clone(), ConfirmationToken { username: username.to_string(), action: action.to_string(), valid_until: now + CONFIRMATION_TTL });
+        token
+    }
+
+    /// Uses up the token, returns true if it was issued to the admin for this action and is still valid
+    pub fn consume_confirmation(&self, token: &str, username: &str, action: &str) -> bool {
+        let mut tokens = self.confirmation_tokens.write_unpoisoned();
+        let Some(confirmation) = tokens.remove(token) else { return false; };
+        confirmation.username == username && confirmation.action == action && confirmation.valid_until > SystemTime::now()
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::{HashMap, HashSet};
+
+use uuid::Uuid;
+
+use crate::backend::data::{Event, Session};
+
+impl Session {
+    /// Returns true if both sessions carry the same content label, e.g. one workshop running in two slots
+    pub fn same_content(&self, other: &Session) -> bool {
+        match (&self.content, &other.content) {
+            (Some(a), Some(b)) => a.trim().eq_ignore_ascii_case(b.trim()),
+            _ => false,
+        }
+    }
+}
+
+impl Event {
+    /// Pairs of session and participant in the slot where the participant already has a seat in
+    /// a session with the same content in another slot
+    pub fn repeated_content(&self, index: usize) -> HashSet<(Uuid, Uuid)> {
+        let mut repeats = HashSet::new();
+        for session in self.slots[index].sessions.iter().filter(|s| s.content.is_some()) {
+            let elsewhere = self.slots.iter().enumerate()
+                .filter(|(i, _)| *i != index)
+                .flat_map(|(_, slot)| slot.sessions.iter())
+                .filter(|other| session.same_content(other));
+            for pid in elsewhere.flat_map(|other| other.participants.iter()) {
+                repeats.insert((session.uuid, *pid));
+            }
+        }
+        repeats
+    }
+
+    /// Marks the ranked applications of the slot for content the participant already got in
+    /// another slot. They are served after everyone else's, so the second instance of a workshop
+    /// goes to someone who hasn't attended it, and they never end up on a waitlist.
+    pub fn demote_repeated_content(&mut self, index: usize) {
+        let repeats = self.repeated_content(index);
+        if repeats.is_empty() {
+            return;
+        }
+        for session in self.slots[index].sessions.iter_mut() {
+            let mut demoted = false;
+            for application in session.applications.iter_mut().filter(|a| repeats.contains(&(session.uuid, a.participant))) {
+                application.repeat = true;
+                application.calculated_points = Some(0);
+                demoted = true;
+            }
+            // The greedy allocation serves the front of the list, keep it ordered by points
+            if demoted {
+                session.applications.sort_by(|a, b| b.cmp(a));
+            }
+        }
+    }
+
+    /// Sessions with the same name in several slots but without a shared content label. The
+    /// distribution can't tell they are the same workshop and may seat a participant in both.
+    pub fn unlabelled_repeats(&self) -> Vec<String> {
+        let mut by_name: HashMap<String, Vec<(usize, &Session)>> = HashMap::new();
+        for (i, slot) in self.slots.iter().enumerate() {
+            for session in &slot.sessions {
+                by_name.entry(session.name.trim().to_lowercase()).or_default().push((i, session));
+            }
+        }
+        let mut hints: Vec<String> = by_name.into_values()
+            .filter(|sessions| sessions.iter().any(|(i, _)| *i != sessions[0].0))
+            .filter(|sessions| sessions.iter().any(|(_, s)| !s.same_content(sessions[0].1)))
+            .map(|sessions| {
+                let slots: Vec<&str> = sessions.iter().map(|(i, _)| self.slots[*i].name.as_str()).collect();
+                format!("{} runs in {}. Give the sessions the same content label so nobody is seated in it twice.", sessions[0].1.name, slots.join(", "))
+            })
+            .collect();
+        hints.sort();
+        hints
+    }
+
+    /// Participants seated in sessions with the same content in more than one slot
+    pub fn repeated_seats(&self) -> usize {
+        let mut seen: HashSet<(Uuid, String)> = HashSet::new();
+        let mut repeated = 0;
+        for session in self.slots.iter().flat_map(|slot| slot.sessions.iter()) {
+            let Some(content) = &session.content else { continue; };
+            for pid in &session.participants {
+                if !seen.insert((*pid, content.trim().to_lowercase())) {
+                    repeated += 1;
+                }
+            }
+        }
+        repeated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::backend::data::{ApplicationPriority, Event, Invitation, InvitationCategory, Session, Slot};
+
+    fn add_participant(ev: &mut Event, name: &str, points: usize) -> Uuid {
+        let mut participant = Invitation::new(name.to_string(), ev.uuid, vec![], InvitationCategory::default()).new_participant();
+        participant.name = name.to_string();
+        participant.points_from_previous_rounds = points;
+        let pid = participant.uuid;
+        ev.participants.insert(pid, participant);
+        pid
+    }
+
+    fn workshop(seats: usize) -> Session {
+        let mut session = Session::new("Workshop".to_string(), None, seats);
+        session.content = Some("workshop".to_string());
+        session
+    }
+
+    #[test]
+    fn repeat_with_highest_points_loses_second_instance_to_first_timer() {
+        let mut ev = Event::new("Test".to_string(), None);
+        let mut morning = Slot::new("Morning".to_string(), None);
+        let mut afternoon = Slot::new("Afternoon".to_string(), None);
+        morning.sessions.push(workshop(1));
+        afternoon.sessions.push(workshop(1));
+        let (first_instance, second_instance) = (morning.sessions[0].uuid, afternoon.sessions[0].uuid);
+        ev.slots = vec![morning, afternoon];
+        let repeat = add_participant(&mut ev, "Repeat", 50);
+        let newcomer = add_participant(&mut ev, "Newcomer", 0);
+        ev.slots[0].insert_application(first_instance, repeat, ApplicationPriority::FirstPreference);
+        ev.slots[1].insert_application(second_instance, repeat, ApplicationPriority::FirstPreference);
+        ev.slots[1].insert_application(second_instance, newcomer, ApplicationPriority::FirstPreference);
+
+        ev.distribute("test");
+
+        assert_eq!(ev.slots[0].sessions[0].participants, vec![repeat]);
+        assert_eq!(ev.slots[1].sessions[0].participants, vec![newcomer]);
+    }
+}
@@ -1,5 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::cmp::*;
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use uuid::Uuid;
 
 use argon2::{Argon2, password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
@@ -7,11 +8,54 @@ use argon2::{Argon2, password_hash::{PasswordHash, PasswordHasher, PasswordVerif
 use rand_core::OsRng;
 use serde::{Serialize, Deserialize};
 
-#[derive(Serialize, Deserialize)]
+use crate::backend::error::BackendError;
+use crate::backend::event_template::EventTemplate;
+use crate::backend::exclusions::{excluded_from, Exclusion};
+use crate::backend::identity::{PendingLink, Person};
+use crate::backend::mail::OutgoingMail;
+use crate::backend::matching::AllocationMode;
+use crate::backend::no_preference::{NoPreferenceCounts, NoPreferencePolicy};
+use crate::backend::push::{PushMessage, PushSubscription};
+use crate::backend::rooms::Room;
+use crate::backend::share::ShareLink;
+use crate::backend::co_admins::CoAdminInvite;
+use crate::backend::delivery::DeadWebhook;
+use crate::backend::scoring::{AllocationConfig, CompensationRules, ScoringMethod, ScoringStrategy};
+use crate::backend::timeline::{ActivityKind, InvitationActivity};
+use crate::backend::webhook::{PendingWebhook, Webhook};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Storage{
     pub events: HashMap<Uuid, Event>,
     pub invitations_codes: HashMap<String, Invitation>,
     pub admins: HashMap<String, AdminAccount>,
+    /// Mails waiting for delivery (or manual handling by an admin) and already sent ones
+    #[serde(default)]
+    pub outbox: Vec<OutgoingMail>,
+    /// Push notifications waiting for delivery
+    #[serde(default)]
+    pub push_queue: Vec<PushMessage>,
+    /// Private VAPID key (base64url) identifying this instance to browser push services
+    #[serde(default)]
+    pub vapid_private_key: Option<String>,
+    /// Key (HMAC) email verification links are signed with
+    #[serde(default)]
+    pub link_key: Option<String>,
+    /// People who linked registrations of several events via their email address
+    #[serde(default)]
+    pub people: HashMap<Uuid, Person>,
+    /// Slot/session structures admins can create new events from
+    #[serde(default)]
+    pub event_templates: HashMap<Uuid, EventTemplate>,
+    /// Rooms of the venue, shared by all events of a tenant
+    #[serde(default)]
+    pub rooms: HashMap<Uuid, Room>,
+    /// Webhook calls about preference submissions waiting for their debounce time, by participant
+    #[serde(default)]
+    pub pending_webhooks: HashMap<Uuid, PendingWebhook>,
+    /// Webhook calls given up after the last attempt
+    #[serde(default)]
+    pub dead_webhooks: Vec<DeadWebhook>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,16 +63,22 @@ pub struct AdminAccount{
     pub username: String,
     /// PHC-format Argon2 hash string
     pub password_hash: String,
+    /// Organization the admin belongs to, None for the default tenant
+    #[serde(default)]
+    pub tenant: Option<String>,
+    /// Events a co-organizer may manage, None for all events of the tenant
+    #[serde(default)]
+    pub events: Option<Vec<Uuid>>,
 }
 
 impl AdminAccount {
-    pub fn new_hashed(username: String, password_plain: &str) -> Self {
+    pub fn new_hashed(username: String, password_plain: &str, tenant: Option<String>) -> Self {
         let salt = SaltString::generate(&mut OsRng);
         let argon2 = Argon2::default();
         let hash = argon2
             .hash_password(password_plain.as_bytes(), &salt)
             .expect("argon2 hashing failed");
-        AdminAccount { username, password_hash: hash.to_string() }
+        AdminAccount { username, password_hash: hash.to_string(), tenant, events: None }
     }
 }
 
@@ -40,6 +90,166 @@ pub struct Invitation{
     pub event_id: Uuid,
     /// Reference to an event's participant entry once the user registered for the event
     pub participant_id: Option<Uuid>,
+    /// Tags granting access to restricted sessions, copied to the participant on registration
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Category of the invitee, determines the allocation bonus of the participant
+    #[serde(default)]
+    pub category: InvitationCategory,
+    /// Contact address used for notifications, set by an admin or the participant
+    #[serde(default)]
+    pub email: Option<String>,
+    /// Set for self-registrations until the email address has been confirmed
+    #[serde(default)]
+    pub pending_verification: Option<EmailVerification>,
+    /// Browsers of the participant subscribed to push notifications
+    #[serde(default)]
+    pub push_subscriptions: Vec<PushSubscription>,
+    /// Person this registration is linked to across events, set once the email address is confirmed
+    #[serde(default)]
+    pub person_id: Option<Uuid>,
+    #[serde(default)]
+    pub pending_link: Option<PendingLink>,
+    /// Individual registration deadline granted by an admin, only effective if later than the event's
+    #[serde(default)]
+    pub deadline_extension: Option<DateTime<Utc>>,
+    /// Name suggested to the participant on registration, e.g. taken from the member directory
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Identifier of the member in the directory the invitation was imported from
+    #[serde(default)]
+    pub directory_id: Option<String>,
+    /// The invitee can only attend sessions in accessible rooms, copied to the participant
+    #[serde(default)]
+    pub needs_accessible_room: bool,
+    /// What the holder did with the invitation, shown on the participant's timeline
+    #[serde(default)]
+    pub activity: Vec<InvitationActivity>,
+    /// Bundle of the group leader who created this invitation
+    #[serde(default)]
+    pub bundle: Option<Uuid>,
+    /// Fairness points imported from an earlier event, the participant starts with them
+    #[serde(default)]
+    pub carryover_points: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailVerification{
+    /// Random token identifying the registration. The verification link carries it signed, see Storage::signed_verification
+    pub token: String,
+    /// Unverified registrations are removed after this point in time
+    pub expires_at: DateTime<Utc>,
+}
+
+impl EmailVerification {
+    pub fn new(valid_for: chrono::Duration) -> Self {
+        EmailVerification { token: format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()), expires_at: Utc::now() + valid_for }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, Hash, FromFormField)]
+pub enum InvitationCategory{
+    #[default]
+    Guest,
+    Member,
+    Speaker,
+}
+
+impl InvitationCategory {
+    /// Lowercase name, also usable as an eligibility tag
+    pub fn as_tag(&self) -> &'static str {
+        match self {
+            InvitationCategory::Guest => "guest",
+            InvitationCategory::Member => "member",
+            InvitationCategory::Speaker => "speaker",
+        }
+    }
+}
+
+/// Points added to (or, if negative, removed from) every application depending on the participant's category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryBonuses{
+    pub guest: i64,
+    pub member: i64,
+    pub speaker: i64,
+}
+
+impl Default for CategoryBonuses {
+    fn default() -> Self {
+        CategoryBonuses { guest: 0, member: 5, speaker: 10 }
+    }
+}
+
+impl CategoryBonuses {
+    pub fn bonus_for(&self, category: InvitationCategory) -> i64 {
+        match category {
+            InvitationCategory::Guest => self.guest,
+            InvitationCategory::Member => self.member,
+            InvitationCategory::Speaker => self.speaker,
+        }
+    }
+}
+
+/// Seats of a session reserved for participants of a category (e.g. 20 guests + 10 speakers).
+/// Seats not reserved are open to everyone; reserved seats left over after the distribution
+/// went through all applications are given to the remaining applicants of other categories.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CategorySeats{
+    pub guest: Option<usize>,
+    pub member: Option<usize>,
+    pub speaker: Option<usize>,
+}
+
+impl CategorySeats {
+    pub fn reserved_for(&self, category: InvitationCategory) -> usize {
+        match category {
+            InvitationCategory::Guest => self.guest,
+            InvitationCategory::Member => self.member,
+            InvitationCategory::Speaker => self.speaker,
+        }.unwrap_or(0)
+    }
+
+    pub fn total(&self) -> usize {
+        [self.guest, self.member, self.speaker].into_iter().flatten().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+}
+
+impl Invitation {
+    pub fn new(code: String, event_id: Uuid, tags: Vec<String>, category: InvitationCategory) -> Self {
+        Invitation { code, event_id, participant_id: None, tags, category, email: None, pending_verification: None, push_subscriptions: vec![], person_id: None, pending_link: None, deadline_extension: None, name: None, directory_id: None, needs_accessible_room: false, activity: vec![InvitationActivity { at: Utc::now(), kind: ActivityKind::Created }], bundle: None, carryover_points: 0 }
+    }
+
+    /// Creates a fresh participant entry carrying the attributes granted by this invitation
+    pub fn new_participant(&self) -> Participant {
+        let mut participant = Participant {
+            uuid: Uuid::new_v4(),
+            name: self.name.clone().unwrap_or_default(),
+            points_from_previous_rounds: self.carryover_points,
+            tags: vec![],
+            category: InvitationCategory::default(),
+            reminder_sent: false,
+            slot_reminders_sent: vec![],
+            awaiting_verification: false,
+            needs_accessible_room: false,
+            badge: None,
+            locale: None,
+            schedule_seen_at: None,
+        };
+        self.apply_to(&mut participant);
+        participant
+    }
+
+    /// Copies the attributes granted by this invitation to its participant
+    pub fn apply_to(&self, participant: &mut Participant) {
+        participant.tags = self.tags.clone();
+        participant.category = self.category;
+        participant.needs_accessible_room = self.needs_accessible_room;
+        participant.awaiting_verification = self.pending_verification.is_some();
+    }
 }
 
 impl Default for Storage {
@@ -49,17 +259,269 @@ impl Default for Storage {
 }
 
 impl Storage {
+    /// Builds the slot and session index of every event, after the storage was deserialized
+    pub fn reindex_events(&mut self) {
+        for ev in self.events.values_mut() {
+            ev.reindex();
+        }
+    }
+
     pub fn new() -> Self {
-        Storage { events: HashMap::new(), invitations_codes: Default::default(), admins: HashMap::new() }
+        let mut storage = Storage { events: HashMap::new(), invitations_codes: Default::default(), admins: HashMap::new(), outbox: Vec::new(), push_queue: Vec::new(), vapid_private_key: None, link_key: None, people: HashMap::new(), event_templates: HashMap::new(), rooms: HashMap::new(), pending_webhooks: HashMap::new(), dead_webhooks: Vec::new() };
+        storage.ensure_vapid_key();
+        storage.ensure_link_key();
+        storage
     }
 
-    pub fn add_admin(&mut self, username: impl Into<String>, password_plain: &str) -> Result<(), &'static str> {
+    pub fn add_admin(&mut self, username: impl Into<String>, password_plain: &str, tenant: Option<String>) -> Result<(), &'static str> {
         let username = username.into();
-        let acc = AdminAccount::new_hashed(username.clone(), password_plain);
+        let acc = AdminAccount::new_hashed(username.clone(), password_plain, tenant);
         self.admins.insert(username, acc);
         Ok(())
     }
 
+    /// Removes an invitation together with its participant and all of the participant's data
+    pub fn remove_invitation(&mut self, code: &str) -> Option<Invitation> {
+        let inv = self.invitations_codes.remove(code)?;
+        if let Some(ev) = self.events.get_mut(&inv.event_id) {
+            // If a participant was registered via this invite, remove them and their data from the event
+            if let Some(participant_id) = inv.participant_id {
+                ev.participants.remove(&participant_id);
+                ev.forget_exclusions_of(participant_id);
+                // Remove from all sessions: assigned seats and applications
+                for slot in ev.slots.iter_mut() {
+                    slot.remove_participant(participant_id);
+                }
+            }
+            ev.waiting_list.retain(|entry| entry.code != code);
+        }
+        Some(inv)
+    }
+
+    /// Invitations of the event whose participant entry was created on login but never used:
+    /// the name is still the one suggested by the invitation and there are neither applications nor seats.
+    /// Self-registrations awaiting verification are left alone.
+    pub fn unused_participants(&self, event_id: Uuid) -> Vec<&Invitation> {
+        let Some(ev) = self.events.get(&event_id) else { return vec![]; };
+        let mut unused: Vec<&Invitation> = self.invitations_codes.values()
+            .filter(|inv| inv.event_id == event_id)
+            .filter(|inv| {
+                let Some(participant) = inv.participant_id.and_then(|pid| ev.participants.get(&pid)) else { return false; };
+                !participant.awaiting_verification
+                    && participant.name.trim() == inv.name.as_deref().unwrap_or("").trim()
+                    && !ev.slots.iter().flat_map(|slot| slot.sessions.iter())
+                        .any(|s| s.participants.contains(&participant.uuid) || s.applications.iter().any(|a| a.participant == participant.uuid))
+            })
+            .collect();
+        unused.sort_by(|a, b| a.code.cmp(&b.code));
+        unused
+    }
+
+    /// Removes the unused participant entries of the event and resets their invitations, so the
+    /// codes work like new ones. Returns the number of removed participants.
+    pub fn remove_unused_participants(&mut self, event_id: Uuid) -> usize {
+        let codes: Vec<String> = self.unused_participants(event_id).into_iter().map(|inv| inv.code.clone()).collect();
+        for code in &codes {
+            let Some(inv) = self.invitations_codes.get_mut(code) else { continue; };
+            let Some(participant_id) = inv.participant_id.take() else { continue; };
+            if let Some(ev) = self.events.get_mut(&event_id) {
+                ev.participants.remove(&participant_id);
+                ev.forget_exclusions_of(participant_id);
+                for slot in ev.slots.iter_mut() {
+                    slot.remove_participant(participant_id);
+                }
+            }
+        }
+        codes.len()
+    }
+
+    /// Replaces the code of an invitation with a freshly generated one, keeping its participant.
+    /// Returns the new code, or None if the old code doesn't exist.
+    pub fn rotate_invitation_code(&mut self, old_code: &str) -> Option<String> {
+        let mut inv = self.invitations_codes.remove(old_code)?;
+        let new_code = self.generate_invitation_code();
+        inv.code = new_code.clone();
+        if let Some(ev) = self.events.get_mut(&inv.event_id) {
+            for entry in ev.waiting_list.iter_mut().filter(|entry| entry.code == old_code) {
+                entry.code = new_code.clone();
+            }
+        }
+        self.invitations_codes.insert(new_code.clone(), inv);
+        self.record_activity(&new_code, ActivityKind::CodeRotated);
+        Some(new_code)
+    }
+
+    /// Confirms the email address of a self-registration with the signed token of the verification link
+    /// and only then registers it, so unverified registrations don't take up a place. Returns the
+    /// invitation code on success.
+    pub fn verify_email(&mut self, signed: &str, now: DateTime<Utc>) -> Option<String> {
+        let token = self.verified_token(signed)?;
+        let inv = self.invitations_codes.values_mut()
+            .find(|inv| inv.pending_verification.as_ref().is_some_and(|v| v.token == token && now < v.expires_at))?;
+        inv.pending_verification = None;
+        inv.activity.push(InvitationActivity { at: now, kind: ActivityKind::EmailVerified });
+        let inv = inv.clone();
+        if let Some(p) = inv.participant_id.and_then(|pid| self.events.get_mut(&inv.event_id)?.participants.get_mut(&pid)) {
+            inv.apply_to(p);
+        }
+        self.ensure_participant(&inv.code, now);
+        Some(inv.code)
+    }
+
+    /// Removes self-registrations whose verification period has passed. Returns the number of removed registrations.
+    pub fn expire_unverified_registrations(&mut self, now: DateTime<Utc>) -> usize {
+        let expired: Vec<String> = self.invitations_codes.values()
+            .filter(|inv| inv.pending_verification.as_ref().is_some_and(|v| v.expires_at <= now))
+            .map(|inv| inv.code.clone())
+            .collect();
+        for code in &expired {
+            self.remove_invitation(code);
+        }
+        expired.len()
+    }
+
+    /// Looks for inconsistencies in the stored data, e.g. duplicate applications or seats.
+    /// Returns a human readable description of every problem found.
+    pub fn check_integrity(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for ev in self.events.values() {
+            if !ev.is_indexed() {
+                problems.push(format!("Event '{}': the slot and session index is out of date", ev.name));
+            }
+            for slot in &ev.slots {
+                let mut seated: Vec<Uuid> = Vec::new();
+                for session in &slot.sessions {
+                    let mut applicants: Vec<Uuid> = Vec::new();
+                    for application in &session.applications {
+                        if applicants.contains(&application.participant) {
+                            problems.push(format!("Event '{}', session '{}': participant {} has more than one application", ev.name, session.name, application.participant));
+                        }
+                        applicants.push(application.participant);
+                        if application.session_uuid != session.uuid {
+                            problems.push(format!("Event '{}', session '{}': application {} points to another session", ev.name, session.name, application.uuid));
+                        }
+                        if !ev.participants.contains_key(&application.participant) {
+                            problems.push(format!("Event '{}', session '{}': application of unknown participant {}", ev.name, session.name, application.participant));
+                        }
+                    }
+                    for participant_id in &session.participants {
+                        if seated.contains(participant_id) {
+                            problems.push(format!("Event '{}', slot '{}': participant {} holds more than one seat", ev.name, slot.name, participant_id));
+                        }
+                        seated.push(*participant_id);
+                    }
+                    if session.participants.len() > session.seats {
+                        problems.push(format!("Event '{}', session '{}': {} participants assigned to {} seats", ev.name, session.name, session.participants.len(), session.seats));
+                    }
+                    for participant_id in session.participants.iter().filter(|pid| !ev.participants.contains_key(pid)) {
+                        problems.push(format!("Event '{}', session '{}': seat of unknown participant {}", ev.name, session.name, participant_id));
+                    }
+                    if let Some(room) = session.room && !self.rooms.contains_key(&room) {
+                        problems.push(format!("Event '{}', session '{}': unknown room {}", ev.name, session.name, room));
+                    }
+                }
+            }
+            for exclusion in &ev.exclusions {
+                if !ev.participants.contains_key(&exclusion.first) || !ev.participants.contains_key(&exclusion.second) {
+                    problems.push(format!("Event '{}': exclusion {} refers to an unknown participant", ev.name, exclusion.uuid));
+                }
+            }
+        }
+        for inv in self.invitations_codes.values() {
+            match self.events.get(&inv.event_id) {
+                None => problems.push(format!("Invitation {}: unknown event {}", inv.code, inv.event_id)),
+                Some(ev) => if let Some(pid) = inv.participant_id && !ev.participants.contains_key(&pid) {
+                    problems.push(format!("Invitation {}: unknown participant {} in event '{}'", inv.code, pid, ev.name));
+                },
+            }
+        }
+        problems
+    }
+
+    /// Generates a new random invitation code not used by any invitation yet
+    pub fn generate_invitation_code(&self) -> String {
+        loop {
+            let code = Uuid::new_v4().simple().to_string()[..10].to_uppercase();
+            if !self.invitations_codes.contains_key(&code) {
+                return code;
+            }
+        }
+    }
+
+    /// Invitations created from the given bundle, sorted by code
+    pub fn bundle_invitations(&self, bundle_id: Uuid) -> Vec<&Invitation> {
+        let mut invitations: Vec<&Invitation> = self.invitations_codes.values().filter(|inv| inv.bundle == Some(bundle_id)).collect();
+        invitations.sort_by(|a, b| a.code.cmp(&b.code));
+        invitations
+    }
+
+    /// Warning for admins if the event has far more invitations than its smallest slot has seats,
+    /// which means many invitees will get nothing in that slot
+    pub fn capacity_warning(&self, event_id: Uuid) -> Option<String> {
+        let ev = self.events.get(&event_id)?;
+        let slot = ev.slots.iter().filter(|slot| !slot.sessions.is_empty()).min_by_key(|slot| slot.capacity())?;
+        let invitations = self.invitations_codes.values().filter(|inv| inv.event_id == event_id).count();
+        let capacity = slot.capacity();
+        (invitations as f64 > capacity as f64 * CAPACITY_WARNING_FACTOR).then(|| format!(
+            "The event now has {} invitations, but slot {} only has {} seats. If everyone registers, {} of them get no seat there.",
+            invitations, slot.name, capacity, invitations - capacity
+        ))
+    }
+
+    /// Makes sure the holder of the given invitation code is registered as participant of the event,
+    /// respecting the event's registration cap. Returns None if the invitation or event doesn't exist.
+    pub fn ensure_participant(&mut self, code: &str, now: DateTime<Utc>) -> Option<Registration> {
+        let inv = self.invitations_codes.get(code)?;
+        if let Some(pid) = inv.participant_id { return Some(Registration::Registered(pid)); }
+        let ev = self.events.get_mut(&inv.event_id)?;
+        if let Err(position) = ev.try_admit(code, now) {
+            return Some(Registration::Waiting(position));
+        }
+        self.register_participant(code).map(Registration::Registered)
+    }
+
+    /// Creates the participant entry for an invitation without checking the registration cap
+    pub fn register_participant(&mut self, code: &str) -> Option<Uuid> {
+        let inv = self.invitations_codes.get_mut(code)?;
+        if let Some(pid) = inv.participant_id { return Some(pid); }
+        let ev = self.events.get_mut(&inv.event_id)?;
+        let mut participant = inv.new_participant();
+        participant.badge = Some(ev.next_badge());
+        let pid = participant.uuid;
+        ev.participants.insert(pid, participant);
+        ev.waiting_list.retain(|entry| entry.code != code);
+        inv.participant_id = Some(pid);
+        Some(pid)
+    }
+
+    /// Returns the event if it belongs to the given tenant. Events of other tenants are reported as missing.
+    pub fn tenant_event(&self, tenant: Option<&str>, event_id: Uuid) -> Result<&Event, BackendError> {
+        self.events.get(&event_id).filter(|ev| ev.tenant.as_deref() == tenant).ok_or(BackendError::NotFound("event"))
+    }
+
+    /// Like tenant_event, but fails while the event's seats are being distributed. Use it before changing
+    /// anything belonging to the event outside the event itself, e.g. its invitations.
+    pub fn tenant_event_for_update(&self, tenant: Option<&str>, event_id: Uuid) -> Result<&Event, BackendError> {
+        let ev = self.tenant_event(tenant, event_id)?;
+        ev.ensure_unlocked()?;
+        Ok(ev)
+    }
+
+    /// Returns the event for changes, see tenant_event_for_update
+    pub fn tenant_event_mut(&mut self, tenant: Option<&str>, event_id: Uuid) -> Result<&mut Event, BackendError> {
+        let ev = self.events.get_mut(&event_id).filter(|ev| ev.tenant.as_deref() == tenant).ok_or(BackendError::NotFound("event"))?;
+        ev.ensure_unlocked()?;
+        Ok(ev)
+    }
+
+    /// Returns true if the mail belongs to an event of the tenant. Mails without event belong to the default tenant.
+    pub fn mail_visible_to(&self, mail: &OutgoingMail, tenant: Option<&str>) -> bool {
+        match mail.event_id {
+            None => tenant.is_none(),
+            Some(event_id) => self.tenant_event(tenant, event_id).is_ok(),
+        }
+    }
+
     pub fn verify_admin(&self, username: &str, password_plain: &str) -> bool {
         match self.admins.get(username) {
             None => false,
@@ -73,6 +535,31 @@ impl Storage {
     }
 }
 
+/// How participants get access to an event
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, FromFormField)]
+pub enum RegistrationMode{
+    /// Only holders of an invitation code created by an admin
+    #[default]
+    InviteOnly,
+    /// Anybody can register on the event's public registration page
+    Open,
+}
+
+/// Outcome of [`Storage::ensure_participant`]
+pub enum Registration {
+    /// The participant entry exists (possibly just created)
+    Registered(Uuid),
+    /// The event is full, the invitation is on the waiting list at this (1-based) position
+    Waiting(usize),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaitingListEntry{
+    /// Invitation code of the waiting person
+    pub code: String,
+    pub joined_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event{
     pub uuid: uuid::Uuid,
@@ -81,9 +568,252 @@ pub struct Event{
     pub slots: Vec<Slot>,
     pub participants: HashMap<uuid::Uuid, Participant>,
     pub state: EventState,
+    #[serde(default)]
+    pub category_bonuses: CategoryBonuses,
+    /// Preferences are accepted until this point in time (if set)
+    #[serde(default)]
+    pub registration_deadline: Option<DateTime<Utc>>,
+    /// Remind participants with incomplete preferences this many days before the deadline
+    #[serde(default)]
+    pub reminder_days_before_deadline: Option<u32>,
+    /// Maximum number of registered participants (unlimited if None)
+    #[serde(default)]
+    pub max_participants: Option<usize>,
+    /// Invitation holders who logged in after the registration cap was reached, in order of arrival
+    #[serde(default)]
+    pub waiting_list: Vec<WaitingListEntry>,
+    #[serde(default)]
+    pub registration_mode: RegistrationMode,
+    /// Audit log of all state transitions
+    #[serde(default)]
+    pub state_history: Vec<StateChange>,
+    /// Set once subscribers have been notified about the approaching registration deadline
+    #[serde(default)]
+    pub deadline_push_sent: bool,
+    /// Internal notes of the organizers, oldest first
+    #[serde(default)]
+    pub notes: Vec<EventNote>,
+    /// Organization owning the event, None for the default tenant
+    #[serde(default)]
+    pub tenant: Option<String>,
+    /// Every distribution run of this event, oldest first
+    #[serde(default)]
+    pub distribution_runs: Vec<DistributionRun>,
+    /// Maximum number of sessions a participant is assigned per day (unlimited if None). Only slots with a start time count.
+    #[serde(default)]
+    pub max_sessions_per_day: Option<usize>,
+    /// Secret part of the kiosk URL for walk-in registrations, kiosk mode is active while set
+    #[serde(default)]
+    pub kiosk_token: Option<String>,
+    /// Distribute the seats automatically once the registration deadline has passed
+    #[serde(default)]
+    pub auto_distribute: bool,
+    /// Review window before an automatic distribution is published, published right away if None
+    #[serde(default)]
+    pub auto_publish_after_hours: Option<u32>,
+    /// How applications are ranked during the distribution
+    #[serde(default)]
+    pub scoring: ScoringMethod,
+    /// How the seats are allocated to the ranked applications
+    #[serde(default)]
+    pub allocation: AllocationMode,
+    /// Placement of participants who ranked no session of a slot
+    #[serde(default)]
+    pub no_preference_policy: NoPreferencePolicy,
+    /// Preference points and compensation values of the ranking
+    #[serde(default)]
+    pub allocation_config: AllocationConfig,
+    /// Participants may rate their sessions once the results are visible
+    #[serde(default)]
+    pub feedback_open: bool,
+    #[serde(default)]
+    pub feedback: Vec<SessionFeedback>,
+    /// Participants see the first names of the others assigned to their sessions on the results page
+    #[serde(default)]
+    pub show_peer_names: bool,
+    /// Audit log of seats moved by admins after the distribution, oldest first
+    #[serde(default)]
+    pub seat_changes: Vec<SeatChange>,
+    /// Rosters show badge numbers instead of names. Only admins can map badges to people.
+    #[serde(default)]
+    pub anonymous_rosters: bool,
+    /// Markdown shown to participants above their preferences, e.g. instructions and contacts
+    #[serde(default)]
+    pub welcome_text: Option<String>,
+    /// Pairs of participants the distribution never seats in the same session
+    #[serde(default)]
+    pub exclusions: Vec<Exclusion>,
+    /// How compensation points accumulate from slot to slot
+    #[serde(default)]
+    pub compensation: CompensationRules,
+    /// Called after preference submissions, if the organizers opted in
+    #[serde(default)]
+    pub webhook: Option<Webhook>,
+    /// Invitations handed to group leaders, who create the individual codes themselves
+    #[serde(default)]
+    pub bundles: Vec<InviteBundle>,
+    /// Read-only links to the event's figures for stakeholders without an admin account
+    #[serde(default)]
+    pub share_links: Vec<ShareLink>,
+    /// Pending invitations of co-organizers
+    #[serde(default)]
+    pub co_admin_invites: Vec<CoAdminInvite>,
+    /// Incremented on every edit of the settings or the welcome text, edit forms carry the revision they were loaded with
+    #[serde(default)]
+    pub revision: u64,
+    #[serde(skip)]
+    index: SlotIndex,
+}
+
+/// Positions of an event's slots and sessions by UUID. It isn't stored, `Event::reindex` rebuilds it
+/// after loading and whenever slots or sessions are added or removed.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct SlotIndex {
+    slots: HashMap<Uuid, usize>,
+    sessions: HashMap<Uuid, (usize, usize)>,
+}
+
+impl SlotIndex {
+    fn of(slots: &[Slot]) -> Self {
+        let mut index = SlotIndex::default();
+        for (i, slot) in slots.iter().enumerate() {
+            index.slots.insert(slot.uuid, i);
+            for (j, session) in slot.sessions.iter().enumerate() {
+                index.sessions.insert(session.uuid, (i, j));
+            }
+        }
+        index
+    }
+}
+
+/// Seat of a participant moved to another session of the same slot or taken away by an admin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeatChange{
+    pub participant_id: Uuid,
+    pub slot_id: Uuid,
+    pub from_session: Uuid,
+    /// None if the seat was taken away without a new one
+    pub to_session: Option<Uuid>,
+    /// Username of the admin who moved the seat
+    pub changed_by: String,
+    pub at: DateTime<Utc>,
+}
+
+/// Rating of an assigned session by a participant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionFeedback{
+    pub session_id: Uuid,
+    pub participant_id: Uuid,
+    /// 1 (worst) to 5 (best)
+    pub rating: u8,
+    pub comment: Option<String>,
+    pub submitted_at: DateTime<Utc>,
+}
+
+/// Aggregated feedback of one session
+#[derive(Debug, Clone, Default)]
+pub struct FeedbackSummary{
+    pub responses: usize,
+    /// Number of ratings per score, index 0 holds the ratings of 1
+    pub counts: [usize; 5],
+    pub comments: Vec<String>,
+}
+
+impl FeedbackSummary{
+    pub fn average(&self) -> Option<f64> {
+        if self.responses == 0 {
+            return None;
+        }
+        let total: usize = self.counts.iter().enumerate().map(|(i, count)| (i + 1) * count).sum();
+        Some(total as f64 / self.responses as f64)
+    }
+}
+
+/// Number of invitations an admin handed to a group leader. The leader creates the codes for
+/// the group members through a secret link, every code remains attributed to the bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InviteBundle{
+    pub uuid: Uuid,
+    /// Group leader the bundle was handed to
+    pub leader: String,
+    /// Secret part of the leader's link, None once the admin revoked it
+    pub token: Option<String>,
+    /// Number of codes the leader may create
+    pub size: usize,
+    /// Tags and category of the created codes
+    pub tags: Vec<String>,
+    pub category: InvitationCategory,
+    /// Username of the admin who handed out the bundle
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventNote{
+    pub uuid: Uuid,
+    /// Username of the admin who wrote the note
+    pub author: String,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Minutes before the slot start at which assigned participants see the join link of online sessions
+pub const JOIN_URL_LEAD_MINUTES: i64 = 15;
+
+/// Admins are warned once the invitations exceed the seats of the smallest slot by this factor
+pub const CAPACITY_WARNING_FACTOR: f64 = 1.5;
+
+/// Audit record of a distribution run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DistributionRun{
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub algorithm: String,
+    /// Seed of the random number generator, None if the algorithm is deterministic
+    pub seed: Option<u64>,
+    pub participants: usize,
+    /// Valid applications after ranking
+    pub applications: usize,
+    /// Seats assigned by this run
+    pub assigned_seats: usize,
+    #[serde(default)]
+    pub assigned_by_priority: PriorityCounts,
+    /// Outcome for participants who ranked no session of a slot
+    #[serde(default)]
+    pub without_preferences: NoPreferenceCounts,
+    /// Username of the admin who started the run
+    pub initiated_by: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+/// Number of assigned seats per preference level
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PriorityCounts{
+    pub first: usize,
+    pub second: usize,
+    pub third: usize,
+    /// Seats that were the fourth or a later choice
+    #[serde(default)]
+    pub later: usize,
+    pub no_preference: usize,
+}
+
+impl PriorityCounts{
+    pub fn add(&mut self, priority: &ApplicationPriority) {
+        match priority {
+            ApplicationPriority::FirstPreference => self.first += 1,
+            ApplicationPriority::SecondPreference => self.second += 1,
+            ApplicationPriority::ThirdPreference => self.third += 1,
+            ApplicationPriority::LowerPreference(_) => self.later += 1,
+            ApplicationPriority::NoPreference => self.no_preference += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.first + self.second + self.third + self.later + self.no_preference
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, FromFormField)]
 pub enum EventState{
     /// The event doesn't allow registrations yet
     #[default]
@@ -92,8 +822,52 @@ pub enum EventState{
     OpenForRegistration,
     /// The registration is closed, the system is assigning the seats
     AssigningSeats,
+    /// The seats are assigned, an admin has to review and confirm the result
+    AwaitingConfirmation,
     /// The assignment is finished, users can retrieve the result
-    Finished
+    Finished,
+    /// Like Finished, additionally the results can be looked up by invitation code without logging in
+    Published,
+    /// The event is over and kept for reference only
+    Archived,
+}
+
+impl EventState {
+    /// All states that can be reached from this state
+    pub fn successors(&self) -> &'static [EventState] {
+        use EventState::*;
+        match self {
+            NotOpenedYet => &[OpenForRegistration, Archived],
+            OpenForRegistration => &[NotOpenedYet, AssigningSeats],
+            AssigningSeats => &[AwaitingConfirmation],
+            AwaitingConfirmation => &[Finished],
+            Finished => &[Published, Archived],
+            Published => &[Finished, Archived],
+            Archived => &[],
+        }
+    }
+}
+
+impl std::fmt::Display for EventState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Reported by a running distribution after each allocated slot
+#[derive(Debug, Clone, Copy)]
+pub struct DistributionStep {
+    pub slots_done: usize,
+    pub slots_total: usize,
+    /// Seats assigned so far
+    pub participants_placed: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateChange{
+    pub from: EventState,
+    pub to: EventState,
+    pub at: DateTime<Utc>,
 }
 
 impl Event{
@@ -105,19 +879,420 @@ impl Event{
             slots: vec![],
             participants: HashMap::new(),
             state: Default::default(),
+            category_bonuses: Default::default(),
+            registration_deadline: None,
+            reminder_days_before_deadline: None,
+            max_participants: None,
+            waiting_list: vec![],
+            registration_mode: RegistrationMode::default(),
+            state_history: vec![],
+            deadline_push_sent: false,
+            notes: vec![],
+            distribution_runs: vec![],
+            tenant: None,
+            max_sessions_per_day: None,
+            kiosk_token: None,
+            auto_distribute: false,
+            auto_publish_after_hours: None,
+            scoring: ScoringMethod::default(),
+            allocation: AllocationMode::default(),
+            no_preference_policy: NoPreferencePolicy::default(),
+            allocation_config: AllocationConfig::default(),
+            feedback_open: false,
+            feedback: vec![],
+            show_peer_names: false,
+            seat_changes: vec![],
+            anonymous_rosters: false,
+            welcome_text: None,
+            exclusions: vec![],
+            compensation: CompensationRules::default(),
+            webhook: None,
+            bundles: vec![],
+            share_links: vec![],
+            co_admin_invites: vec![],
+            revision: 0,
+            index: SlotIndex::default(),
+        }
+    }
+
+    /// Moves the event to the given state if the transition table allows it and records the change
+    pub fn try_transition(&mut self, to: EventState) -> Result<(), BackendError> {
+        if !self.state.successors().contains(&to) {
+            return Err(BackendError::conflict(format!("The event can't change from {} to {}.", self.state, to)));
+        }
+        self.state_history.push(StateChange { from: self.state, to, at: Utc::now() });
+        self.state = to;
+        Ok(())
+    }
+
+    /// Rebuilds the UUID index of the slots and sessions. Call it after adding, removing or reordering them.
+    pub fn reindex(&mut self) {
+        self.index = SlotIndex::of(&self.slots);
+    }
+
+    /// Returns true if the index matches the current slots and sessions
+    pub fn is_indexed(&self) -> bool {
+        self.index == SlotIndex::of(&self.slots)
+    }
+
+    /// Position of the slot, from the index if it's up to date for this slot
+    fn slot_position(&self, slot_id: Uuid) -> Option<usize> {
+        match self.index.slots.get(&slot_id) {
+            Some(&i) if self.slots.get(i).is_some_and(|s| s.uuid == slot_id) => Some(i),
+            // Slots added without reindexing, e.g. while building an event in memory
+            _ => self.slots.iter().position(|s| s.uuid == slot_id),
+        }
+    }
+
+    /// Positions of the session's slot and of the session within it, from the index if it's up to date for this session
+    fn session_position(&self, session_id: Uuid) -> Option<(usize, usize)> {
+        match self.index.sessions.get(&session_id) {
+            Some(&(i, j)) if self.slots.get(i).and_then(|slot| slot.sessions.get(j)).is_some_and(|s| s.uuid == session_id) => Some((i, j)),
+            _ => self.slots.iter().enumerate().find_map(|(i, slot)| slot.sessions.iter().position(|s| s.uuid == session_id).map(|j| (i, j))),
+        }
+    }
+
+    pub fn slot_by_id(&self, slot_id: Uuid) -> Result<&Slot, BackendError> {
+        let i = self.slot_position(slot_id).ok_or(BackendError::NotFound("slot"))?;
+        Ok(&self.slots[i])
+    }
+
+    pub fn slot_by_id_mut(&mut self, slot_id: Uuid) -> Result<&mut Slot, BackendError> {
+        let i = self.slot_position(slot_id).ok_or(BackendError::NotFound("slot"))?;
+        Ok(&mut self.slots[i])
+    }
+
+    /// Exchanges the assigned seats of two participants in different sessions of the same slot.
+    /// Either both seats move or, if any check fails, nothing changes. Both moves are recorded.
+    pub fn swap_seats(&mut self, slot_id: Uuid, first: Uuid, second: Uuid, changed_by: &str) -> Result<(), BackendError> {
+        if !matches!(self.state, EventState::AwaitingConfirmation | EventState::Finished | EventState::Published) {
+            return Err(BackendError::conflict("Seats can only be swapped after the distribution and before the event is archived."));
+        }
+        if first == second {
+            return Err(BackendError::bad_request("Choose two different participants."));
+        }
+        let (Some(first_participant), Some(second_participant)) = (self.participants.get(&first), self.participants.get(&second)) else {
+            return Err(BackendError::NotFound("participant"));
+        };
+        let slot = self.slot_by_id(slot_id)?;
+        let seat_of = |participant: &Participant| slot.sessions.iter().position(|s| s.participants.contains(&participant.uuid))
+            .ok_or_else(|| BackendError::bad_request(format!("{} has no seat in this slot.", participant.name)));
+        let first_index = seat_of(first_participant)?;
+        let second_index = seat_of(second_participant)?;
+        if first_index == second_index {
+            return Err(BackendError::bad_request("Both participants are already in the same session."));
+        }
+        for (participant, target, leaving) in [(first_participant, second_index, second), (second_participant, first_index, first)] {
+            if !slot.sessions[target].is_eligible(participant) {
+                return Err(BackendError::bad_request(format!("{} isn't eligible for {}.", participant.name, slot.sessions[target].name)));
+            }
+            if slot.sessions[target].is_avoided_by(participant.uuid) {
+                return Err(BackendError::conflict(format!("{} can't attend {}.", participant.name, slot.sessions[target].name)));
+            }
+            let staying: Vec<Uuid> = slot.sessions[target].participants.iter().copied().filter(|p| *p != leaving).collect();
+            if excluded_from(&self.exclusions, participant.uuid, &staying) {
+                return Err(BackendError::conflict(format!("{} is kept apart from a participant in {}.", participant.name, slot.sessions[target].name)));
+            }
         }
+
+        // Replace in place, so the order of the remaining seats is kept
+        let slot = self.slot_by_id_mut(slot_id)?;
+        for (index, from, to) in [(first_index, first, second), (second_index, second, first)] {
+            if let Some(seat) = slot.sessions[index].participants.iter_mut().find(|p| **p == from) {
+                *seat = to;
+            }
+        }
+        let (first_session, second_session) = (slot.sessions[first_index].uuid, slot.sessions[second_index].uuid);
+        let at = Utc::now();
+        self.seat_changes.push(SeatChange { participant_id: first, slot_id, from_session: first_session, to_session: Some(second_session), changed_by: changed_by.to_string(), at });
+        self.seat_changes.push(SeatChange { participant_id: second, slot_id, from_session: second_session, to_session: Some(first_session), changed_by: changed_by.to_string(), at });
+        Ok(())
     }
-    /// Allocates all participants in all slots
-    pub fn allocate_participants(&mut self){
-        for i in 0..self.slots.len(){
-            self.allocate_participants_in_slot(i)
+
+    /// Looks up a session in any slot of the event
+    pub fn session_by_id(&self, session_id: Uuid) -> Result<&Session, BackendError> {
+        let (i, j) = self.session_position(session_id).ok_or(BackendError::NotFound("session"))?;
+        Ok(&self.slots[i].sessions[j])
+    }
+
+    pub fn session_by_id_mut(&mut self, session_id: Uuid) -> Result<&mut Session, BackendError> {
+        let (i, j) = self.session_position(session_id).ok_or(BackendError::NotFound("session"))?;
+        Ok(&mut self.slots[i].sessions[j])
+    }
+
+    /// All session tags used in this event, sorted
+    pub fn session_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.slots.iter().flat_map(|slot| slot.sessions.iter()).flat_map(|s| s.tags.iter().cloned()).collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// Rejects changes while the seats are being distributed, the allocation works on a snapshot of the event
+    pub fn ensure_unlocked(&self) -> Result<(), BackendError> {
+        if self.state == EventState::AssigningSeats {
+            return Err(BackendError::conflict("The seats of this event are being distributed right now. Please try again in a moment."));
+        }
+        Ok(())
+    }
+
+    pub fn next_badge(&self) -> u32 {
+        self.participants.values().filter_map(|p| p.badge).max().unwrap_or(0) + 1
+    }
+
+    /// Gives badge numbers to participants registered before badges existed
+    pub fn assign_badges(&mut self) {
+        let first = self.next_badge();
+        let mut missing: Vec<&mut Participant> = self.participants.values_mut().filter(|p| p.badge.is_none()).collect();
+        // Random order, so badge numbers don't hint at names
+        missing.sort_by_key(|p| p.uuid);
+        for (badge, participant) in (first..).zip(missing) {
+            participant.badge = Some(badge);
+        }
+    }
+
+    /// How the participant appears on session rosters: the badge number for anonymous rosters, the name otherwise
+    pub fn roster_label(&self, participant: &Participant) -> String {
+        if self.anonymous_rosters {
+            participant.badge.map(|badge| format!("#{}", badge)).unwrap_or_else(|| "#?".to_string())
+        } else {
+            participant.name.clone()
+        }
+    }
+
+    /// Returns true once seats have been assigned, whether or not the result is confirmed
+    pub fn is_distributed(&self) -> bool {
+        matches!(self.state, EventState::AwaitingConfirmation | EventState::Finished | EventState::Published | EventState::Archived)
+    }
+
+    /// Returns true if participants may see their assignments
+    pub fn results_visible(&self) -> bool {
+        matches!(self.state, EventState::Finished | EventState::Published | EventState::Archived)
+    }
+
+    /// Returns true if participants may rate their sessions
+    pub fn accepts_feedback(&self) -> bool {
+        self.feedback_open && self.results_visible()
+    }
+
+    /// Stores the participant's rating of a session they were assigned to, replacing an earlier one
+    pub fn submit_feedback(&mut self, participant_id: Uuid, session_id: Uuid, rating: u8, comment: Option<String>) -> Result<(), BackendError> {
+        if !self.accepts_feedback() {
+            return Err(BackendError::conflict("Feedback is not collected for this event."));
+        }
+        if !(1..=5).contains(&rating) {
+            return Err(BackendError::bad_request("Please rate the session from 1 to 5."));
+        }
+        let session = self.session_by_id(session_id)?;
+        if !session.participants.contains(&participant_id) {
+            return Err(BackendError::Forbidden);
+        }
+        self.feedback.retain(|f| !(f.session_id == session_id && f.participant_id == participant_id));
+        self.feedback.push(SessionFeedback { session_id, participant_id, rating, comment, submitted_at: Utc::now() });
+        Ok(())
+    }
+
+    pub fn feedback_of(&self, participant_id: Uuid, session_id: Uuid) -> Option<&SessionFeedback> {
+        self.feedback.iter().find(|f| f.session_id == session_id && f.participant_id == participant_id)
+    }
+
+    pub fn feedback_summary(&self, session_id: Uuid) -> FeedbackSummary {
+        let mut summary = FeedbackSummary::default();
+        for f in self.feedback.iter().filter(|f| f.session_id == session_id) {
+            summary.responses += 1;
+            summary.counts[(f.rating.clamp(1, 5) - 1) as usize] += 1;
+            if let Some(comment) = &f.comment {
+                summary.comments.push(comment.clone());
+            }
+        }
+        summary
+    }
+
+    /// Returns true if results may be shown on the public lookup page
+    pub fn has_public_results(&self) -> bool {
+        self.state == EventState::Published
+    }
+
+    /// Returns true if people can sign up on the public registration page right now
+    pub fn allows_self_registration(&self) -> bool {
+        self.registration_mode == RegistrationMode::Open
+            && matches!(self.state, EventState::NotOpenedYet | EventState::OpenForRegistration)
+    }
+
+    /// Number of places left until the registration cap is reached, None if there is no cap.
+    /// Registrations awaiting verification don't take up a place.
+    pub fn free_places(&self) -> Option<usize> {
+        let registered = self.participants.values().filter(|p| !p.awaiting_verification).count();
+        self.max_participants.map(|max| max.saturating_sub(registered))
+    }
+
+    /// Checks whether the holder of the code may register now. Free places are given to the waiting list in order,
+    /// anybody else is appended to it. On refusal the 1-based waiting list position is returned.
+    pub fn try_admit(&mut self, code: &str, now: DateTime<Utc>) -> Result<(), usize> {
+        let Some(free) = self.free_places() else { return Ok(()); };
+        match self.waiting_list.iter().position(|entry| entry.code == code) {
+            Some(index) if index < free => {
+                self.waiting_list.remove(index);
+                Ok(())
+            }
+            Some(index) => Err(index + 1),
+            None if self.waiting_list.len() < free => Ok(()),
+            None => {
+                self.waiting_list.push(WaitingListEntry { code: code.to_string(), joined_at: now });
+                Err(self.waiting_list.len())
+            }
         }
     }
 
-    pub fn allocate_participants_in_slot(&mut self, index: usize) {
+    /// Returns true if participants may currently change their preferences
+    pub fn accepts_preferences(&self, now: DateTime<Utc>) -> bool {
+        matches!(self.state, EventState::OpenForRegistration)
+            && self.registration_deadline.is_none_or(|deadline| now < deadline)
+    }
+
+    /// Like accepts_preferences, but with the registration deadline moved to a participant's extension
+    pub fn accepts_preferences_with(&self, extension: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+        matches!(self.state, EventState::OpenForRegistration)
+            && self.deadline_with(extension).is_none_or(|deadline| now < deadline)
+    }
+
+    /// Effective registration deadline for a participant with the given extension
+    pub fn deadline_with(&self, extension: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+        self.registration_deadline.map(|deadline| extension.map_or(deadline, |ext| ext.max(deadline)))
+    }
+
+    /// Returns all slots in which the participant hasn't chosen any preference yet
+    pub fn slots_without_preferences(&self, participant_id: Uuid) -> Vec<&Slot> {
+        self.slots.iter()
+            .filter(|slot| !slot.sessions.is_empty())
+            .filter(|slot| !slot.sessions.iter().any(|sess| sess.applications.iter().any(|a| {
+                a.participant == participant_id && !matches!(a.priority, ApplicationPriority::NoPreference)
+            })))
+            .collect()
+    }
+    /// Ranks all applications, allocates the seats and records the run
+    pub fn distribute(&mut self, initiated_by: &str) -> &DistributionRun {
+        self.distribute_reporting(initiated_by, |_| {})
+    }
+
+    /// Like [`Event::distribute`], reporting the progress after each slot
+    pub fn distribute_reporting(&mut self, initiated_by: &str, mut report: impl FnMut(DistributionStep)) -> &DistributionRun {
+        let started_at = Utc::now();
+        let start = std::time::Instant::now();
+        let strategy = self.scoring.strategy(self.allocation_config);
+        let mut applications = 0;
+        let mut assigned_by_priority = PriorityCounts::default();
+        let mut without_preferences = NoPreferenceCounts::default();
+        self.seat_pinned_participants();
+        for i in 0..self.slots.len() {
+            let held_back = self.hold_back_without_preferences(i);
+            // Rank right before the slot is allocated, so compensation earned in earlier slots counts
+            let ev_clone_for_ref = self.clone();
+            for sess in self.slots[i].sessions.iter_mut() {
+                sess.rank_applications(&ev_clone_for_ref, strategy.as_ref());
+                applications += sess.applications.len();
+            }
+            self.demote_repeated_content(i);
+            // The allocation consumes the applications, the waitlists are built from this copy
+            let ranked: Vec<(Uuid, Vec<Application>)> = self.slots[i].sessions.iter().map(|s| (s.uuid, s.applications.clone())).collect();
+            match self.allocation {
+                AllocationMode::Greedy => self.allocate_participants_in_slot(i, &mut assigned_by_priority, strategy.as_ref()),
+                AllocationMode::StableMatching => self.allocate_stable_in_slot(i, &mut assigned_by_priority, strategy.as_ref()),
+                AllocationMode::OptimalAssignment => self.allocate_optimal_in_slot(i, &mut assigned_by_priority, strategy.as_ref()),
+            }
+            self.slots[i].build_waitlists(&ranked);
+            self.place_without_preferences(i, held_back, &mut without_preferences);
+            report(DistributionStep {
+                slots_done: i + 1,
+                slots_total: self.slots.len(),
+                participants_placed: self.slots[..=i].iter().map(|slot| slot.allocated_count()).sum(),
+            });
+        }
+        let assigned_seats = self.slots.iter().map(|slot| slot.allocated_count()).sum();
+        self.distribution_runs.push(DistributionRun {
+            started_at,
+            duration_ms: start.elapsed().as_millis() as u64,
+            algorithm: match self.allocation {
+                AllocationMode::Greedy => strategy.name().to_string(),
+                AllocationMode::StableMatching => format!("deferred-acceptance ({})", strategy.name()),
+                AllocationMode::OptimalAssignment => format!("min-cost-flow ({})", strategy.name()),
+            },
+            seed: strategy.seed(),
+            participants: self.participants.len(),
+            applications,
+            assigned_seats,
+            assigned_by_priority,
+            without_preferences,
+            initiated_by: initiated_by.to_string(),
+        });
+        self.distribution_runs.last().expect("run was just recorded")
+    }
+
+    /// Participants who already got the maximum number of sessions on the day of the given slot in other slots
+    pub fn participants_at_daily_limit(&self, index: usize) -> HashSet<Uuid> {
+        let (Some(max), Some(day)) = (self.max_sessions_per_day, self.slots[index].day()) else { return HashSet::new(); };
+        let mut assigned: HashMap<Uuid, usize> = HashMap::new();
+        for (i, slot) in self.slots.iter().enumerate() {
+            if i == index || slot.day() != Some(day) { continue; }
+            for pid in slot.sessions.iter().flat_map(|s| s.participants.iter()) {
+                *assigned.entry(*pid).or_default() += 1;
+            }
+        }
+        assigned.into_iter().filter(|(_, count)| *count >= max).map(|(pid, _)| pid).collect()
+    }
+
+    /// Fills the sessions of the slot in two rounds: the first keeps seats reserved for categories to
+    /// participants of that category, setting other applicants aside; the second gives the seats left
+    /// over to the applicants set aside.
+    pub fn allocate_participants_in_slot(&mut self, index: usize, counts: &mut PriorityCounts, strategy: &dyn ScoringStrategy) {
+        let at_daily_limit = self.participants_at_daily_limit(index);
+        let categories: HashMap<Uuid, InvitationCategory> = self.participants.values().map(|p| (p.uuid, p.category)).collect();
+        self.slots[index].waiting_list.clear();
+        let mut set_aside = Vec::new();
+        self.allocate_round(index, counts, strategy, &at_daily_limit, Some((&categories, &mut set_aside)));
+        if set_aside.is_empty() {
+            return;
+        }
+        let slot = &mut self.slots[index];
+        let mut waiting_for_spill = false;
+        for application in set_aside {
+            let assigned = slot.sessions.iter().any(|s| s.participants.contains(&application.participant));
+            if let Some(session) = slot.session_by_id_mut(application.session_uuid) && !assigned {
+                session.applications.push(application);
+                waiting_for_spill = true;
+            }
+        }
+        if !waiting_for_spill {
+            return;
+        }
+        if slot.is_full() {
+            slot.move_applicants_to_waiting_list();
+            return;
+        }
+        println!("Giving reserved seats left over in slot {} to other categories.", slot.name);
+        self.allocate_round(index, counts, strategy, &at_daily_limit, None);
+    }
+
+    /// Assigns the best ranked applications of the slot until none are left. With `reserved`, applicants
+    /// not fitting into a session's seats for their category are moved to the given list instead.
+    fn allocate_round(&mut self, index: usize, counts: &mut PriorityCounts, strategy: &dyn ScoringStrategy, at_daily_limit: &HashSet<Uuid>, mut reserved: Option<(&HashMap<Uuid, InvitationCategory>, &mut Vec<Application>)>) {
+        let exclusions = &self.exclusions;
+        let compensation = self.compensation;
         let slot = self.slots.get_mut(index).unwrap();
         while let Some(session_id) = slot.find_session_with_highest_ranked_application() {
-            let session = slot.sessions.iter_mut().find(|s| s.uuid == session_id).unwrap(); // We can safely unwrap here
+            if slot.is_full() {
+                println!("Slot {} reached its participant cap, remaining applicants go to the waiting list.", slot.name);
+                if let Some((_, set_aside)) = reserved.as_mut() {
+                    for application in set_aside.drain(..) {
+                        if let Some(session) = slot.session_by_id_mut(application.session_uuid) {
+                            session.applications.push(application);
+                        }
+                    }
+                }
+                slot.move_applicants_to_waiting_list();
+                break;
+            }
+            let session = slot.session_by_id_mut(session_id).unwrap(); // We can safely unwrap here
 
             if session.participants.len() >= session.seats { // Check if all seats in session are taken
                 println!("No more seats for session {}!", session.name);
@@ -128,41 +1303,32 @@ impl Event{
             // Add participant to session participants
             let application = session.applications.remove(0);
             let participant_id = application.participant;
+            if let Some((categories, set_aside)) = reserved.as_mut() {
+                let category = categories.get(&participant_id).copied().unwrap_or_default();
+                if !session.has_seat_for(category, categories) {
+                    set_aside.push(application);
+                    continue;
+                }
+            }
+            if at_daily_limit.contains(&participant_id) {
+                println!("Participant {} already has the maximum number of sessions on this day, skipping session {}.", participant_id, session.name);
+                continue;
+            }
+            if excluded_from(exclusions, participant_id, &session.participants) {
+                println!("Participant {} is kept apart from someone in session {}, skipping it.", participant_id, session.name);
+                continue;
+            }
+            counts.add(&application.priority);
 
-            session.participants.push(participant_id);
+            session.assign_participant(participant_id);
             println!("Added participant {} with {:?} points and priority {:?} to session {}.", participant_id, application.calculated_points, application.priority, session.name);
 
             // Remove participant from all other session applications
-            for session in slot.sessions.iter_mut() {
-                session.applications.retain_mut(|a| a.participant != participant_id);
-            }
+            slot.remove_applications(participant_id);
 
-            // set persons points from previous round
-            match application.priority {
-                ApplicationPriority::FirstPreference => {
-                    // participant got first preference -> no points
-                    if let Some(participant) = self.participants.get_mut(&participant_id) {
-                        participant.points_from_previous_rounds = 0;
-                    }
-                }
-                ApplicationPriority::SecondPreference => {
-                    // participant got second preference -> add 5 points
-                    if let Some(participant) = self.participants.get_mut(&participant_id) {
-                        participant.points_from_previous_rounds = 5;
-                    }
-                }
-                ApplicationPriority::ThirdPreference => {
-                    // participant got third preference -> add 10 points
-                    if let Some(participant) = self.participants.get_mut(&participant_id) {
-                        participant.points_from_previous_rounds = 10;
-                    }
-                },
-                ApplicationPriority::NoPreference => {
-                    // participant didn't want this session -> add 15 points
-                    if let Some(participant) = self.participants.get_mut(&participant_id) {
-                        participant.points_from_previous_rounds = 15;
-                    }
-                }
+            // A worse assignment improves the chances in later slots and events
+            if let Some(participant) = self.participants.get_mut(&participant_id) {
+                participant.points_from_previous_rounds = compensation.carry(participant.points_from_previous_rounds, strategy.compensation(&application.priority));
             }
         }
     }
@@ -174,7 +1340,36 @@ pub struct Slot{
     pub name: String,
     pub description: Option<String>,
     pub sessions: Vec<Session>,
+    /// Maximum number of participants allocated across all sessions, e.g. limited by the venue (unlimited if None)
+    #[serde(default)]
+    pub max_participants: Option<usize>,
+    /// Participants who couldn't be allocated because max_participants was reached, best ranked first
+    #[serde(default)]
+    pub waiting_list: Vec<Uuid>,
+    /// Preferences for this slot are accepted until this point in time, even if the event is still open
+    #[serde(default)]
+    pub preference_deadline: Option<DateTime<Utc>>,
+    /// Start of the slot's sessions, sessions of the same slot run in parallel
+    #[serde(default)]
+    pub starts_at: Option<DateTime<Utc>>,
+    /// End of the slot's sessions, used to find double-booked rooms
+    #[serde(default)]
+    pub ends_at: Option<DateTime<Utc>>,
+    /// Incremented on every edit, see Event::revision
+    #[serde(default)]
+    pub revision: u64,
+    /// Number of sessions participants rank in this slot
+    #[serde(default = "default_ranked_choices")]
+    pub ranked_choices: usize,
+    /// Participants without preferences the last distribution left without a seat here, see NoPreferencePolicy
+    #[serde(default)]
+    pub without_preferences: Vec<Uuid>,
 }
+
+fn default_ranked_choices() -> usize {
+    DEFAULT_RANKED_CHOICES
+}
+
 impl Slot{
     pub fn new(name: String, description: Option<String>) -> Self{
         Slot{
@@ -182,9 +1377,100 @@ impl Slot{
             name,
             description,
             sessions: vec![],
+            max_participants: None,
+            waiting_list: vec![],
+            preference_deadline: None,
+            starts_at: None,
+            ends_at: None,
+            revision: 0,
+            ranked_choices: DEFAULT_RANKED_CHOICES,
+            without_preferences: vec![],
         }
     }
 
+    /// Local calendar day of the slot, if it has a start time
+    pub fn day(&self) -> Option<NaiveDate> {
+        self.starts_at.map(|t| t.with_timezone(&Local).date_naive())
+    }
+
+    /// Join links of online sessions are revealed this many minutes before the slot starts.
+    /// Slots without a start time reveal them right away.
+    pub fn reveals_join_urls(&self, now: DateTime<Utc>) -> bool {
+        self.starts_at.is_none_or(|start| now >= start - chrono::Duration::minutes(JOIN_URL_LEAD_MINUTES))
+    }
+
+    /// Returns false once the slot's own preference deadline has passed
+    pub fn accepts_preferences(&self, now: DateTime<Utc>) -> bool {
+        self.preference_deadline.is_none_or(|deadline| now < deadline)
+    }
+
+    /// Number of participants allocated to any session of this slot
+    pub fn allocated_count(&self) -> usize {
+        self.sessions.iter().map(|s| s.participants.len()).sum()
+    }
+
+    /// Seats of all sessions, limited by the slot's participant cap
+    pub fn capacity(&self) -> usize {
+        let seats = self.sessions.iter().map(|s| s.seats).sum();
+        self.max_participants.map_or(seats, |max| max.min(seats))
+    }
+
+    /// Returns true if the slot's participant cap is reached
+    pub fn is_full(&self) -> bool {
+        self.max_participants.is_some_and(|max| self.allocated_count() >= max)
+    }
+
+    /// Moves all remaining applicants to the waiting list, ordered by their best ranked application
+    fn move_applicants_to_waiting_list(&mut self) {
+        let mut applications: Vec<&Application> = self.sessions.iter().flat_map(|s| s.applications.iter()).collect();
+        applications.sort_by_key(|a| Reverse(a.calculated_points));
+        let mut waiting: Vec<Uuid> = Vec::new();
+        for application in applications {
+            if !waiting.contains(&application.participant) {
+                waiting.push(application.participant);
+            }
+        }
+        self.waiting_list = waiting;
+        for session in self.sessions.iter_mut() {
+            session.applications.clear();
+        }
+    }
+
+
+    pub fn session_by_id(&self, session_id: Uuid) -> Option<&Session> {
+        self.sessions.iter().find(|s| s.uuid == session_id)
+    }
+
+    pub fn session_by_id_mut(&mut self, session_id: Uuid) -> Option<&mut Session> {
+        self.sessions.iter_mut().find(|s| s.uuid == session_id)
+    }
+
+    /// Applies the participant to the given session of this slot. Returns false if the session doesn't exist.
+    pub fn insert_application(&mut self, session_id: Uuid, participant_id: Uuid, priority: ApplicationPriority) -> bool {
+        match self.session_by_id_mut(session_id) {
+            Some(session) => {
+                session.insert_application(participant_id, priority);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Withdraws all applications of the participant in this slot, keeping assigned seats
+    pub fn remove_applications(&mut self, participant_id: Uuid) {
+        for session in self.sessions.iter_mut() {
+            session.applications.retain(|a| a.participant != participant_id);
+        }
+    }
+
+    /// Removes the participant from all sessions of this slot: assigned seats, applications and the waiting list
+    pub fn remove_participant(&mut self, participant_id: Uuid) {
+        for session in self.sessions.iter_mut() {
+            session.remove_participant(participant_id);
+        }
+        self.waiting_list.retain(|p| *p != participant_id);
+        self.without_preferences.retain(|p| *p != participant_id);
+    }
 
     /// Returns the session with the application with the highest calculated_points score across all sessions
     pub fn find_session_with_highest_ranked_application(&self) -> Option<Uuid>{
@@ -212,9 +1498,75 @@ pub struct Session{
     pub seats: usize,
     pub participants: Vec<uuid::Uuid>,
     pub applications: Vec<Application>,
+    /// If not empty, only participants holding at least one of these tags may apply
+    #[serde(default)]
+    pub eligibility_tags: Vec<String>,
+    /// Track/category labels (e.g. "workshop", "beginner") used to filter and color the program
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Label shared by sessions with the same content in different slots, e.g. a workshop held twice.
+    /// The distribution avoids seating a participant in more than one of them.
+    #[serde(default)]
+    pub content: Option<String>,
+    /// Held online (or hybrid); assigned participants get the join URL shortly before the start
+    #[serde(default)]
+    pub online: bool,
+    #[serde(default)]
+    pub join_url: Option<String>,
+    /// Extra points for every preference for this session, so hard-to-fill sessions are served first
+    #[serde(default)]
+    pub attraction_bonus: usize,
+    /// Held in a room accessible to participants with mobility needs
+    #[serde(default)]
+    pub accessible: bool,
+    #[serde(default)]
+    pub category_seats: CategorySeats,
+    /// Room from the venue's room directory
+    #[serde(default)]
+    pub room: Option<Uuid>,
+    /// Participants interested in the session without choosing it as one of their preferences
+    #[serde(default)]
+    pub interested: Vec<Uuid>,
+    /// Participants who can't attend the session at all, e.g. for accessibility reasons. The
+    /// distribution never seats them here, not even as a fallback.
+    #[serde(default)]
+    pub avoided: Vec<Uuid>,
+    /// Participants an admin reserved a seat for before the distribution, e.g. speakers or helpers
+    #[serde(default)]
+    pub pinned: Vec<Uuid>,
+    /// Conditions participants must accept to choose the session, e.g. an NDA or a safety briefing
+    #[serde(default)]
+    pub acknowledgment: Option<String>,
+    /// When participants accepted the conditions. Kept after the distribution has used up the applications.
+    #[serde(default)]
+    pub acknowledgments: HashMap<Uuid, DateTime<Utc>>,
+    /// Incremented on every edit, see Event::revision
+    #[serde(default)]
+    pub revision: u64,
+    /// Applicants who didn't get a seat here although they preferred the session over the seat
+    /// they got, best ranked first. Filled during the distribution, see Slot::build_waitlists.
+    #[serde(default)]
+    pub waitlist: Vec<Application>,
 }
 
 impl Session{
+    /// Returns true if a free seat is reserved for the category or open to everyone
+    pub fn has_seat_for(&self, category: InvitationCategory, categories: &HashMap<Uuid, InvitationCategory>) -> bool {
+        if self.category_seats.is_empty() {
+            return true;
+        }
+        let mut assigned: HashMap<InvitationCategory, usize> = HashMap::new();
+        for pid in &self.participants {
+            *assigned.entry(categories.get(pid).copied().unwrap_or_default()).or_default() += 1;
+        }
+        if assigned.get(&category).copied().unwrap_or(0) < self.category_seats.reserved_for(category) {
+            return true;
+        }
+        let open_seats = self.seats.saturating_sub(self.category_seats.total());
+        let in_open_seats: usize = assigned.iter().map(|(c, n)| n.saturating_sub(self.category_seats.reserved_for(*c))).sum();
+        in_open_seats < open_seats
+    }
+
     pub fn new(name: String, description: Option<String>, seats: usize) -> Session{
         Session{
             uuid: uuid::Uuid::new_v4(),
@@ -223,9 +1575,86 @@ impl Session{
             seats,
             participants: vec![],
             applications: vec![],
+            eligibility_tags: vec![],
+            tags: vec![],
+            content: None,
+            online: false,
+            join_url: None,
+            attraction_bonus: 0,
+            accessible: false,
+            category_seats: CategorySeats::default(),
+            room: None,
+            interested: vec![],
+            avoided: vec![],
+            pinned: vec![],
+            acknowledgment: None,
+            acknowledgments: HashMap::new(),
+            revision: 0,
+            waitlist: vec![],
+        }
+    }
+
+    /// Adds an application for the participant, replacing any previous application to this session
+    pub fn insert_application(&mut self, participant_id: Uuid, priority: ApplicationPriority) {
+        self.applications.retain(|a| a.participant != participant_id);
+        let acknowledged_at = self.acknowledgments.get(&participant_id).copied();
+        self.applications.push(Application { uuid: Uuid::new_v4(), session_uuid: self.uuid, participant: participant_id, priority, calculated_points: None, interested: false, acknowledged_at, repeat: false });
+    }
+
+    /// Returns true if the participant may be seated here without accepting conditions first
+    pub fn is_acknowledged_by(&self, participant_id: Uuid) -> bool {
+        self.acknowledgment.is_none() || self.acknowledgments.contains_key(&participant_id)
+    }
+
+    pub fn has_free_seat(&self) -> bool {
+        self.participants.len() < self.seats
+    }
+
+    /// Assigns a seat to the participant unless they already have one
+    pub fn assign_participant(&mut self, participant_id: Uuid) {
+        if !self.participants.contains(&participant_id) {
+            self.participants.push(participant_id);
         }
     }
-    pub fn rank_applications(&mut self, event: &Event){
+
+    /// Returns true if the participant marked the session as one they can't attend
+    pub fn is_avoided_by(&self, participant_id: Uuid) -> bool {
+        self.avoided.contains(&participant_id)
+    }
+
+    /// Returns true if an admin pinned the participant to this session
+    pub fn is_pinned(&self, participant_id: Uuid) -> bool {
+        self.pinned.contains(&participant_id)
+    }
+
+    /// Removes the participant's seat, application, interest, avoid mark and pin from this session
+    pub fn remove_participant(&mut self, participant_id: Uuid) {
+        self.participants.retain(|p| *p != participant_id);
+        self.applications.retain(|a| a.participant != participant_id);
+        self.interested.retain(|p| *p != participant_id);
+        self.avoided.retain(|p| *p != participant_id);
+        self.pinned.retain(|p| *p != participant_id);
+        self.waitlist.retain(|a| a.participant != participant_id);
+    }
+
+    /// Returns true if the participant may see and apply to this session
+    pub fn is_eligible(&self, participant: &Participant) -> bool {
+        Session::allows(&self.eligibility_tags, self.accessible, participant)
+    }
+
+    /// Tags restrict sessions to some participants, inaccessible rooms are ruled out for participants needing access
+    fn allows(eligibility_tags: &[String], accessible: bool, participant: &Participant) -> bool {
+        (accessible || !participant.needs_accessible_room)
+            && (eligibility_tags.is_empty() || eligibility_tags.iter().any(|t| participant.tags.contains(t) || t == participant.category.as_tag()))
+    }
+
+    pub fn rank_applications(&mut self, event: &Event, strategy: &dyn ScoringStrategy){
+        let eligibility_tags = &self.eligibility_tags;
+        let accessible = self.accessible;
+        let session_name = &self.name;
+        let attraction_bonus = self.attraction_bonus as i64;
+        let interested = &self.interested;
+        let avoided = &self.avoided;
         // remove invalid applications and calculate points for each application
         self.applications.retain_mut(|application|{
             match event.participants.get(&application.participant) {
@@ -233,8 +1662,23 @@ impl Session{
                     eprintln!("Participant id {} from application not found in event {}. Removing application. ", application.participant, event.name);
                     false
                 }
+                Some(participant) if participant.awaiting_verification => {
+                    eprintln!("Participant {} hasn't verified their email address. Removing application.", participant.uuid);
+                    false
+                }
+                Some(participant) if !Session::allows(eligibility_tags, accessible, participant) => {
+                    eprintln!("Participant {} is not eligible for session {}. Removing application.", participant.uuid, session_name);
+                    false
+                }
+                Some(participant) if avoided.contains(&participant.uuid) => {
+                    eprintln!("Participant {} can't attend session {}. Removing application.", participant.uuid, session_name);
+                    false
+                }
                 Some(participant) => {
-                    application.calculate_points(participant);
+                    application.interested = matches!(application.priority, ApplicationPriority::NoPreference) && interested.contains(&participant.uuid);
+                    // The attraction bonus only rewards actual preferences, nobody is pushed into an unwanted session
+                    let session_bonus = if matches!(application.priority, ApplicationPriority::NoPreference) { 0 } else { attraction_bonus };
+                    application.calculate_points(strategy, participant, event.category_bonuses.bonus_for(participant.category) + session_bonus);
                     true
                 }
             }
@@ -244,14 +1688,56 @@ impl Session{
     }
 }
 
+/// Ranked preferences per slot unless an admin asks for another number
+pub const DEFAULT_RANKED_CHOICES: usize = 3;
+/// Most ranked preferences a slot can ask for
+pub const MAX_RANKED_CHOICES: usize = 10;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ApplicationPriority{
     FirstPreference,
     SecondPreference,
     ThirdPreference,
+    /// Fourth or later choice, holds the rank counting from 1
+    LowerPreference(u8),
     NoPreference
 }
 
+impl ApplicationPriority{
+    /// Priority of the choice with the given rank, counting from 1
+    pub fn from_rank(rank: usize) -> Self {
+        match rank {
+            1 => ApplicationPriority::FirstPreference,
+            2 => ApplicationPriority::SecondPreference,
+            3 => ApplicationPriority::ThirdPreference,
+            rank => ApplicationPriority::LowerPreference(rank.min(MAX_RANKED_CHOICES) as u8),
+        }
+    }
+
+    /// Rank of the choice counting from 1, None for sessions without preference
+    pub fn rank(&self) -> Option<usize> {
+        match self {
+            ApplicationPriority::FirstPreference => Some(1),
+            ApplicationPriority::SecondPreference => Some(2),
+            ApplicationPriority::ThirdPreference => Some(3),
+            ApplicationPriority::LowerPreference(rank) => Some(*rank as usize),
+            ApplicationPriority::NoPreference => None,
+        }
+    }
+}
+
+/// "1st", "2nd", "3rd", "4th" and so on
+pub fn ordinal(rank: usize) -> String {
+    let suffix = match (rank % 10, rank % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", rank, suffix)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Application{
     pub uuid: uuid::Uuid,
@@ -259,13 +1745,24 @@ pub struct Application{
     pub participant: uuid::Uuid,
     pub priority: ApplicationPriority,
     pub calculated_points: Option<usize>,
+    /// Set on applications without preference if the participant marked the session as "maybe"
+    #[serde(default)]
+    pub interested: bool,
+    /// When the participant accepted the session's conditions, if it has any
+    #[serde(default)]
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    /// Set during the distribution if the participant already got a session with the same content
+    /// in another slot, the application is served last then
+    #[serde(default)]
+    pub repeat: bool,
 }
 
 impl Ord for Application{
     fn cmp(&self, other: &Self) -> Ordering {
-        self.calculated_points.cmp(&other.calculated_points).then(
-            self.uuid.cmp(&other.uuid) // We use the uuid to induce randomness for applications with same number of points
-        )
+        self.calculated_points.cmp(&other.calculated_points)
+            // Among equal points, sessions marked as "maybe" go first, e.g. when filling up seats without preference
+            .then(self.interested.cmp(&other.interested))
+            .then(self.uuid.cmp(&other.uuid)) // We use the uuid to induce randomness for applications with same number of points
     }
 }
 
@@ -277,7 +1774,7 @@ impl PartialOrd for Application{
 
 impl PartialEq for Application{
     fn eq(&self, other: &Self) -> bool {
-        self.calculated_points.unwrap_or(0) == other.calculated_points.unwrap_or(0) && self.uuid == other.uuid
+        self.calculated_points.unwrap_or(0) == other.calculated_points.unwrap_or(0) && self.interested == other.interested && self.uuid == other.uuid
     }
 }
 
@@ -286,26 +1783,8 @@ impl Eq for Application{
 }
 
 impl Application {
-    pub fn calculate_points(&mut self, participant: &Participant){
-        let mut points = 0;
-        if participant.points_from_previous_rounds != 0{
-            points += participant.points_from_previous_rounds;
-        }
-        points += match self.priority{
-            ApplicationPriority::FirstPreference => {
-                15
-            }
-            ApplicationPriority::SecondPreference => {
-                10
-            }
-            ApplicationPriority::ThirdPreference => {
-                5
-            },
-            ApplicationPriority::NoPreference => {
-                0
-            }
-        };
-        self.calculated_points = Some(points);
+    pub fn calculate_points(&mut self, strategy: &dyn ScoringStrategy, participant: &Participant, bonus: i64){
+        self.calculated_points = Some(strategy.points(self, participant, bonus));
     }
 }
 
@@ -314,5 +1793,97 @@ pub struct Participant {
     pub uuid: uuid::Uuid,
     pub name: String,
     pub points_from_previous_rounds: usize,
+    /// Tags taken over from the invitation, matched against session eligibility tags
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub category: InvitationCategory,
+    /// Set once a reminder about incomplete preferences has been queued
+    #[serde(default)]
+    pub reminder_sent: bool,
+    /// Slots the participant was told are about to close without preferences, each is only notified once
+    #[serde(default)]
+    pub slot_reminders_sent: Vec<Uuid>,
+    /// Self-registered participants don't take part in the distribution until their email address is verified
+    #[serde(default)]
+    pub awaiting_verification: bool,
+    /// Only sessions in accessible rooms are offered and allocated to the participant
+    #[serde(default)]
+    pub needs_accessible_room: bool,
+    /// Number shown on rosters instead of the name if the event uses anonymous rosters
+    #[serde(default)]
+    pub badge: Option<u32>,
+    /// Language of mails to the participant, e.g. `de`. The instance's default locale is used if unset.
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// When the participant dismissed the notice about changes to their seats
+    #[serde(default)]
+    pub schedule_seen_at: Option<DateTime<Utc>>,
+}
+
+impl Participant {
+    /// First word of the participant's name, shown to fellow attendees if the event allows it
+    pub fn first_name(&self) -> Option<&str> {
+        self.name.split_whitespace().next()
+    }
+}
+
+/// Splits a comma separated list into normalized (trimmed, lowercase) tags
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split(',') {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event_with_slots(names: &[&str]) -> Event {
+        let mut ev = Event::new("Test".to_string(), None);
+        for name in names {
+            let mut slot = Slot::new(name.to_string(), None);
+            slot.sessions.push(Session::new(format!("{} session", name), None, 1));
+            ev.slots.push(slot);
+        }
+        ev.reindex();
+        ev
+    }
+
+    #[test]
+    fn lookups_use_the_index_and_survive_changes_without_reindexing() {
+        let mut ev = event_with_slots(&["Morning", "Afternoon", "Evening"]);
+        assert!(ev.is_indexed());
+        let evening = ev.slots[2].uuid;
+        let evening_session = ev.slots[2].sessions[0].uuid;
+        assert_eq!(ev.slot_by_id(evening).unwrap().name, "Evening");
+
+        // Removing a slot shifts the positions behind it
+        ev.slots.remove(0);
+        assert!(!ev.is_indexed());
+        assert_eq!(ev.slot_by_id(evening).unwrap().name, "Evening");
+        assert_eq!(ev.session_by_id(evening_session).unwrap().name, "Evening session");
+
+        ev.reindex();
+        assert!(ev.is_indexed());
+        assert_eq!(ev.session_by_id_mut(evening_session).unwrap().name, "Evening session");
+        assert!(ev.slot_by_id(Uuid::new_v4()).is_err());
+    }
+
+    #[test]
+    fn integrity_check_reports_a_stale_index() {
+        let mut storage = Storage::new();
+        let mut ev = event_with_slots(&["Morning"]);
+        ev.slots[0].sessions.push(Session::new("Added later".to_string(), None, 1));
+        storage.events.insert(ev.uuid, ev);
+        assert_eq!(storage.check_integrity().len(), 1);
+        storage.reindex_events();
+        assert!(storage.check_integrity().is_empty());
+    }
+}
@@ -0,0 +1,56 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::backend::data::Storage;
+use crate::backend::error::BackendError;
+use crate::backend::webhook::WEBHOOK_DEBOUNCE_SECONDS;
+
+/// Failed mails and webhook calls are tried this often before they become dead letters
+pub const MAX_DELIVERY_ATTEMPTS: u32 = 8;
+/// Wait after the first failure, doubled after every further one
+const FIRST_RETRY_MINUTES: i64 = 1;
+const MAX_RETRY_MINUTES: i64 = 6 * 60;
+
+/// Time to wait before the next try after the given number of failed attempts
+pub fn retry_delay(attempts: u32) -> Duration {
+    let factor = 1i64 << attempts.saturating_sub(1).min(16);
+    Duration::minutes((FIRST_RETRY_MINUTES * factor).min(MAX_RETRY_MINUTES))
+}
+
+/// Webhook call given up after the last attempt, kept until an admin sends it again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadWebhook {
+    pub uuid: Uuid,
+    pub event_id: Uuid,
+    pub participant_id: Uuid,
+    pub attempts: u32,
+    pub last_error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+impl Storage {
+    /// Puts a dead-lettered mail back into the queue for immediate delivery
+    pub fn resend_mail(&mut self, mail_id: Uuid, tenant: Option<&str>) -> Result<(), BackendError> {
+        let index = self.outbox.iter().position(|m| m.uuid == mail_id && self.mail_visible_to(m, tenant)).ok_or(BackendError::NotFound("mail"))?;
+        let mail = &mut self.outbox[index];
+        if !mail.is_pending() {
+            return Err(BackendError::conflict("The mail has already been sent."));
+        }
+        mail.attempts = 0;
+        mail.next_attempt_at = None;
+        mail.dead_lettered_at = None;
+        Ok(())
+    }
+
+    /// Queues a given-up webhook call again. The call carries the participant's current preferences.
+    pub fn resend_webhook(&mut self, dead_id: Uuid, tenant: Option<&str>, now: DateTime<Utc>) -> Result<(), BackendError> {
+        let index = self.dead_webhooks.iter().position(|d| d.uuid == dead_id && self.tenant_event(tenant, d.event_id).is_ok()).ok_or(BackendError::NotFound("webhook call"))?;
+        if self.events.get(&self.dead_webhooks[index].event_id).is_none_or(|ev| ev.webhook.is_none()) {
+            return Err(BackendError::conflict("The event has no webhook anymore."));
+        }
+        let dead = self.dead_webhooks.remove(index);
+        self.queue_preference_webhook(dead.event_id, dead.participant_id, now - Duration::seconds(WEBHOOK_DEBOUNCE_SECONDS));
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::Path;
+
+use lettre::message::Mailbox;
+use lettre::{AsyncSmtpTransport, Tokio1Executor};
+use serde::Serialize;
+
+use crate::backend::config::AppConfig;
+use crate::backend::data::Storage;
+use crate::gui::templates::missing_templates;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    /// The instance works, but not as configured or as probably intended
+    Warning,
+    /// The instance would fail to start or serve requests
+    Error,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub messages: Vec<String>,
+}
+
+impl Check {
+    fn new(name: &'static str) -> Self {
+        Check { name, status: CheckStatus::Ok, messages: Vec::new() }
+    }
+
+    fn warn(&mut self, message: impl Into<String>) {
+        if self.status == CheckStatus::Ok {
+            self.status = CheckStatus::Warning;
+        }
+        self.messages.push(message.into());
+    }
+
+    fn fail(&mut self, message: impl Into<String>) {
+        self.status = CheckStatus::Error;
+        self.messages.push(message.into());
+    }
+}
+
+/// Result of `--check`, printed as JSON for deployment pipelines
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// False if any check failed, warnings don't count
+    pub ok: bool,
+    pub checks: Vec<Check>,
+}
+
+/// Checks the state file, templates, static files and the mail and webhook settings without
+/// starting the server or changing anything
+pub fn run(state_path: &Path, template_dir: &Path, static_dir: &Path, config: &AppConfig) -> Report {
+    let mut state = Check::new("state");
+    let mut integrity = Check::new("integrity");
+    let mut webhooks = Check::new("webhooks");
+    match load_state(state_path) {
+        Ok(None) => state.warn(format!("{} doesn't exist, a new state would be created.", state_path.display())),
+        Ok(Some(storage)) => {
+            state.messages.push(format!("{} event(s), {} invitation(s), {} admin(s)", storage.events.len(), storage.invitations_codes.len(), storage.admins.len()));
+            if storage.admins.is_empty() {
+                state.warn("There is no admin account, initial credentials would be generated.");
+            }
+            for problem in storage.check_integrity() {
+                integrity.fail(problem);
+            }
+            for ev in storage.events.values() {
+                let Some(webhook) = &ev.webhook else { continue; };
+                if !(webhook.url.starts_with("https://") || webhook.url.starts_with("http://")) || reqwest::Url::parse(&webhook.url).is_err() {
+                    webhooks.fail(format!("Event '{}': webhook URL '{}' is invalid", ev.name, webhook.url));
+                } else if webhook.url.starts_with("http://") {
+                    webhooks.warn(format!("Event '{}': webhook URL '{}' isn't encrypted", ev.name, webhook.url));
+                }
+            }
+        }
+        Err(e) => state.fail(e),
+    }
+
+    let mut templates = Check::new("templates");
+    if !template_dir.is_dir() {
+        templates.fail(format!("Template directory {} doesn't exist", template_dir.display()));
+    } else {
+        let missing = missing_templates(template_dir);
+        if !missing.is_empty() {
+            templates.fail(format!("Missing templates in {}: {}", template_dir.display(), missing.join(", ")));
+        }
+    }
+    if let Some(dir) = &config.mail.templates_dir && !Path::new(dir).is_dir() {
+        templates.warn(format!("Mail template directory {} doesn't exist, the built-in mails are used", dir));
+    }
+
+    let mut static_files = Check::new("static");
+    if !static_dir.is_dir() {
+        static_files.fail(format!("Static directory {} doesn't exist", static_dir.display()));
+    } else if !static_dir.join("css/bootstrap.css").is_file() {
+        static_files.fail(format!("{} is missing css/bootstrap.css", static_dir.display()));
+    }
+
+    let checks = vec![state, integrity, templates, static_files, check_mail(config), webhooks];
+    Report { ok: checks.iter().all(|c| c.status != CheckStatus::Error), checks }
+}
+
+/// Reads and parses the state file, None if it doesn't exist
+fn load_state(path: &Path) -> Result<Option<Storage>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut storage: Storage = serde_json::from_str(&data).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    storage.reindex_events();
+    Ok(Some(storage))
+}
+
+fn check_mail(config: &AppConfig) -> Check {
+    let mut mail = Check::new("mail");
+    let settings = &config.mail;
+    if let Err(e) = settings.from.parse::<Mailbox>() {
+        mail.fail(format!("Invalid sender address '{}': {}", settings.from, e));
+    }
+    let Some(host) = &settings.smtp_host else {
+        mail.warn("No SMTP server configured, outgoing mails will be queued for the admins.");
+        return mail;
+    };
+    let transport = if settings.starttls {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+    };
+    if let Err(e) = transport {
+        mail.fail(format!("Can't set up the SMTP transport for {}: {}", host, e));
+    }
+    if settings.smtp_username.is_some() != settings.smtp_password.is_some() {
+        mail.warn("Only one of smtp_username and smtp_password is set, mails are sent without authentication.");
+    }
+    mail
+}
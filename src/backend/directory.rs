@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::backend::config::DirectoryConfig;
+use crate::backend::data::{Invitation, InvitationCategory, Storage};
+
+/// Member as listed by a member directory
+#[derive(Debug, Clone, Deserialize)]
+pub struct DirectoryMember {
+    /// Stable identifier of the member in the directory
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+/// Source of members that can be invited to events
+#[rocket::async_trait]
+pub trait MemberDirectory: Send + Sync {
+    /// Lists the members, optionally restricted to a group of the directory
+    async fn members(&self, group: Option<&str>) -> Result<Vec<DirectoryMember>, String>;
+}
+
+/// Builds the directory configured by the operator
+pub fn from_config(config: &DirectoryConfig) -> Box<dyn MemberDirectory> {
+    match config {
+        DirectoryConfig::Json { url, token } => Box::new(JsonDirectory::new(url.clone(), token.clone())),
+    }
+}
+
+/// Directory behind an HTTP endpoint returning a JSON array of members
+/// (`[{"id": "...", "name": "...", "email": "..."}]`). The group is passed as `group` query parameter.
+pub struct JsonDirectory {
+    client: reqwest::Client,
+    url: String,
+    /// Bearer token sent with the request
+    token: Option<String>,
+}
+
+impl JsonDirectory {
+    pub fn new(url: String, token: Option<String>) -> Self {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(30)).build().expect("failed to build HTTP client");
+        JsonDirectory { client, url, token }
+    }
+}
+
+#[rocket::async_trait]
+impl MemberDirectory for JsonDirectory {
+    async fn members(&self, group: Option<&str>) -> Result<Vec<DirectoryMember>, String> {
+        let mut url = reqwest::Url::parse(&self.url).map_err(|e| format!("invalid directory URL: {}", e))?;
+        if let Some(group) = group {
+            url.query_pairs_mut().append_pair("group", group);
+        }
+        let mut request = self.client.get(url).header("Accept", "application/json");
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("directory answered with {}", status));
+        }
+        let body = response.bytes().await.map_err(|e| e.to_string())?;
+        serde_json::from_slice(&body).map_err(|e| format!("unexpected directory response: {}", e))
+    }
+}
+
+/// Outcome of a directory import
+pub struct ImportSummary {
+    pub added: usize,
+    /// Members that already have an invitation for the event
+    pub skipped: usize,
+}
+
+impl Storage {
+    /// Creates invitations for directory members who don't have one for the event yet. Existing
+    /// invitations are matched by directory id or, for invitations added by hand, by email address.
+    pub fn import_members(&mut self, event_id: Uuid, members: Vec<DirectoryMember>, tags: &[String], category: InvitationCategory) -> ImportSummary {
+        let mut summary = ImportSummary { added: 0, skipped: 0 };
+        for member in members {
+            let email = member.email.map(|e| e.trim().to_string()).filter(|e| !e.is_empty());
+            let known = self.invitations_codes.values().any(|inv| inv.event_id == event_id && (
+                inv.directory_id.as_deref() == Some(member.id.as_str())
+                    || email.as_deref().is_some_and(|email| inv.email.as_deref().is_some_and(|e| e.eq_ignore_ascii_case(email)))
+            ));
+            if known {
+                summary.skipped += 1;
+                continue;
+            }
+            let code = self.generate_invitation_code();
+            let mut inv = Invitation::new(code.clone(), event_id, tags.to_vec(), category);
+            inv.email = email;
+            inv.name = member.name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+            inv.directory_id = Some(member.id);
+            self.invitations_codes.insert(code, inv);
+            summary.added += 1;
+        }
+        summary
+    }
+}
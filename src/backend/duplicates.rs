@@ -0,0 +1,178 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::backend::data::Storage;
+use crate::backend::error::BackendError;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateCandidate {
+    pub participant_id: Uuid,
+    pub name: String,
+    pub code: String,
+    pub email: Option<String>,
+}
+
+/// Participants of an event who are probably the same person registered with different codes
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateGroup {
+    pub participants: Vec<DuplicateCandidate>,
+    /// Why the entries are grouped, e.g. "same name"
+    pub reasons: Vec<&'static str>,
+}
+
+/// Lowercase words of the name in alphabetical order, so "Doe, John" matches "john doe"
+fn normalize_name(name: &str) -> String {
+    let cleaned: String = name.chars().map(|c| if c.is_alphanumeric() { c } else { ' ' }).collect::<String>().to_lowercase();
+    let mut words: Vec<&str> = cleaned.split_whitespace().collect();
+    words.sort_unstable();
+    words.join(" ")
+}
+
+/// Number of single character edits turning one string into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            current[j + 1] = (previous[j] + usize::from(ca != *cb)).min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Typos allowed between two normalized names, none for short names
+fn similar_names(a: &str, b: &str) -> bool {
+    let allowed = match a.chars().count().min(b.chars().count()) {
+        0..=5 => 0,
+        6..=11 => 1,
+        _ => 2,
+    };
+    allowed > 0 && edit_distance(a, b) <= allowed
+}
+
+fn find(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    parents[i] = root;
+    root
+}
+
+impl Storage {
+    /// Groups the registered participants of the event sharing a name, a similar name or an email
+    /// address. Participants without a name are only matched by email address.
+    pub fn likely_duplicates(&self, event_id: Uuid) -> Vec<DuplicateGroup> {
+        let Some(ev) = self.events.get(&event_id) else { return vec![]; };
+        let mut candidates: Vec<DuplicateCandidate> = self.invitations_codes.values()
+            .filter(|inv| inv.event_id == event_id)
+            .filter_map(|inv| {
+                let participant = ev.participants.get(&inv.participant_id?)?;
+                let email = inv.email.clone().or_else(|| inv.person_id.and_then(|id| self.people.get(&id)).map(|p| p.email.clone()));
+                Some(DuplicateCandidate { participant_id: participant.uuid, name: participant.name.clone(), code: inv.code.clone(), email })
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.name.cmp(&b.name).then(a.code.cmp(&b.code)));
+        let names: Vec<String> = candidates.iter().map(|c| normalize_name(&c.name)).collect();
+        let emails: Vec<Option<String>> = candidates.iter().map(|c| c.email.as_ref().map(|e| e.trim().to_lowercase())).collect();
+
+        let mut parents: Vec<usize> = (0..candidates.len()).collect();
+        let mut reasons: Vec<Vec<&'static str>> = vec![Vec::new(); candidates.len()];
+        for i in 0..candidates.len() {
+            for j in i + 1..candidates.len() {
+                if candidates[i].participant_id == candidates[j].participant_id {
+                    continue;
+                }
+                let reason = if emails[i].is_some() && emails[i] == emails[j] {
+                    "same email address"
+                } else if names[i].is_empty() || names[j].is_empty() {
+                    continue;
+                } else if names[i] == names[j] {
+                    "same name"
+                } else if similar_names(&names[i], &names[j]) {
+                    "similar names"
+                } else {
+                    continue;
+                };
+                let (a, b) = (find(&mut parents, i), find(&mut parents, j));
+                parents[b] = a;
+                let merged = std::mem::take(&mut reasons[b]);
+                reasons[a].extend(merged);
+                reasons[a].push(reason);
+            }
+        }
+
+        let mut groups: Vec<DuplicateGroup> = Vec::new();
+        let mut roots: Vec<usize> = Vec::new();
+        for (i, candidate) in candidates.into_iter().enumerate() {
+            let root = find(&mut parents, i);
+            match roots.iter().position(|r| *r == root) {
+                Some(group) => groups[group].participants.push(candidate),
+                None => {
+                    roots.push(root);
+                    groups.push(DuplicateGroup { participants: vec![candidate], reasons: Vec::new() });
+                }
+            }
+        }
+        for (group, root) in groups.iter_mut().zip(&roots) {
+            let mut group_reasons = reasons[*root].clone();
+            group_reasons.sort_unstable();
+            group_reasons.dedup();
+            group.reasons = group_reasons;
+        }
+        groups.retain(|g| g.participants.len() > 1);
+        groups
+    }
+
+    /// Merges the duplicate into the kept participant: preferences for slots the kept participant
+    /// left empty are taken over, all codes of the duplicate log in as the kept participant.
+    /// Only possible before the distribution.
+    pub fn merge_participants(&mut self, event_id: Uuid, keep: Uuid, duplicate: Uuid) -> Result<(), BackendError> {
+        let ev = self.events.get_mut(&event_id).ok_or(BackendError::NotFound("event"))?;
+        ev.ensure_unlocked()?;
+        if ev.is_distributed() {
+            return Err(BackendError::conflict("Participants can only be merged before the distribution."));
+        }
+        if keep == duplicate {
+            return Err(BackendError::bad_request("Choose two different participants."));
+        }
+        if !ev.participants.contains_key(&keep) || !ev.participants.contains_key(&duplicate) {
+            return Err(BackendError::NotFound("participant"));
+        }
+        for slot in ev.slots.iter_mut() {
+            let kept_preferences = slot.sessions.iter().any(|s| s.applications.iter().any(|a| a.participant == keep));
+            let slot_pinned_keep = slot.sessions.iter().any(|s| s.is_pinned(keep));
+            for session in slot.sessions.iter_mut() {
+                let accepted = session.acknowledgments.remove(&duplicate);
+                if !kept_preferences {
+                    for application in session.applications.iter_mut().filter(|a| a.participant == duplicate) {
+                        application.participant = keep;
+                    }
+                    if let Some(accepted) = accepted {
+                        session.acknowledgments.entry(keep).or_insert(accepted);
+                    }
+                    if session.interested.contains(&duplicate) && !session.interested.contains(&keep) {
+                        session.interested.push(keep);
+                    }
+                }
+                // Sessions either entry can't attend stay ruled out
+                if session.is_avoided_by(duplicate) && !session.is_avoided_by(keep) {
+                    session.avoided.push(keep);
+                }
+                if session.is_pinned(duplicate) && !slot_pinned_keep {
+                    session.pinned.push(keep);
+                }
+                session.remove_participant(duplicate);
+            }
+            slot.waiting_list.retain(|p| *p != duplicate);
+        }
+        ev.forget_exclusions_of(duplicate);
+        ev.participants.remove(&duplicate);
+        for inv in self.invitations_codes.values_mut().filter(|inv| inv.event_id == event_id && inv.participant_id == Some(duplicate)) {
+            inv.participant_id = Some(keep);
+        }
+        Ok(())
+    }
+}
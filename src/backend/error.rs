@@ -0,0 +1,163 @@
+use rocket::http::uri::Absolute;
+use rocket::http::{ContentType, Method, Status};
+use rocket::request::{FlashMessage, Request};
+use rocket::response::{self, Flash, Redirect, Responder};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+use crate::backend::concurrency::EditConflict;
+use crate::backend::validation::FieldError;
+
+/// Reasons a request can fail. Form submissions are redirected back with the message as flash,
+/// API clients receive JSON problem details and everything else ends up in Rocket's catchers.
+#[derive(Debug, Clone)]
+pub enum BackendError {
+    /// The named entity (event, slot, ...) doesn't exist
+    NotFound(&'static str),
+    /// The current session lacks the required role
+    Forbidden,
+    Unauthorized(String),
+    /// The submitted data is invalid
+    BadRequest(String),
+    /// One or more fields violate the configured form limits
+    Invalid(Vec<FieldError>),
+    /// The request is valid, but not allowed in the current state of the event
+    Conflict(String),
+    /// The instance runs in read-only mode
+    ReadOnly,
+    /// The edited entity was changed by someone else since the form was loaded
+    EditConflict(Box<EditConflict>),
+}
+
+impl BackendError {
+    pub fn status(&self) -> Status {
+        match self {
+            BackendError::NotFound(_) => Status::NotFound,
+            BackendError::Forbidden => Status::Forbidden,
+            BackendError::Unauthorized(_) => Status::Unauthorized,
+            BackendError::BadRequest(_) => Status::BadRequest,
+            BackendError::Invalid(_) => Status::UnprocessableEntity,
+            BackendError::Conflict(_) | BackendError::EditConflict(_) => Status::Conflict,
+            BackendError::ReadOnly => Status::ServiceUnavailable,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            BackendError::NotFound(what) => format!("The {} doesn't exist (anymore).", what),
+            BackendError::Forbidden => "You are not allowed to do this.".to_string(),
+            BackendError::ReadOnly => "This instance is read-only. Changes can't be saved right now.".to_string(),
+            BackendError::EditConflict(conflict) => format!("The {} was changed by someone else while you were editing it. Your changes weren't saved.", conflict.entity),
+            BackendError::Unauthorized(msg) | BackendError::BadRequest(msg) | BackendError::Conflict(msg) => msg.clone(),
+            BackendError::Invalid(errors) => errors.iter().map(|e| e.message.as_str()).collect::<Vec<_>>().join(" "),
+        }
+    }
+
+    pub fn bad_request(msg: impl Into<String>) -> Self {
+        BackendError::BadRequest(msg.into())
+    }
+
+    pub fn conflict(msg: impl Into<String>) -> Self {
+        BackendError::Conflict(msg.into())
+    }
+}
+
+impl std::fmt::Display for BackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+/// Problem details as described in RFC 9457
+#[derive(Serialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    title: &'static str,
+    status: u16,
+    detail: String,
+    /// Rejected fields of a validation error
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+}
+
+impl ProblemDetails {
+    pub fn new(status: Status, detail: String) -> Self {
+        ProblemDetails { kind: "about:blank", title: status.reason_lossy(), status: status.code, detail, errors: Vec::new() }
+    }
+
+    pub fn with_errors(mut self, errors: Vec<FieldError>) -> Self {
+        self.errors = errors;
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("problem details are serializable")
+    }
+}
+
+/// Returns the path of the referring page if it points to this instance
+fn local_referer(req: &Request<'_>) -> Option<String> {
+    let referer = req.headers().get_one("Referer")?;
+    let uri = Absolute::parse(referer).ok()?;
+    let host = req.host()?;
+    if uri.authority().map(|a| a.host()) != Some(host.domain().as_str()) {
+        return None;
+    }
+    Some(match uri.query() {
+        Some(query) => format!("{}?{}", uri.path(), query),
+        None => uri.path().to_string(),
+    })
+}
+
+/// Sends form submissions over the configured size limit back to their page with a message
+#[catch(413)]
+pub fn payload_too_large(req: &Request<'_>) -> Result<Flash<Redirect>, (Status, &'static str)> {
+    match local_referer(req).filter(|_| req.method() == Method::Post) {
+        Some(back) => Ok(Flash::error(Redirect::to(back), "The submission is too large. Please shorten it or split it into several parts.")),
+        None => Err((Status::PayloadTooLarge, "The submission is too large.")),
+    }
+}
+
+impl<'r> Responder<'r, 'static> for BackendError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let status = self.status();
+        let wants_json = req.uri().path().starts_with("/api/") || req.accept().is_some_and(|a| a.preferred().is_json());
+        if wants_json {
+            let errors = match &self { BackendError::Invalid(errors) => errors.clone(), _ => Vec::new() };
+            let body = ProblemDetails::new(status, self.message()).with_errors(errors).to_json();
+            return response::Response::build_from((ContentType::new("application", "problem+json"), body).respond_to(req)?)
+                .status(status)
+                .ok();
+        }
+        // Let the admin compare both versions instead of losing the edit
+        if let BackendError::EditConflict(conflict) = self {
+            return (status, Template::render("admin/edit_conflict", conflict.as_ref())).respond_to(req);
+        }
+        // Send form submissions back to the page they came from
+        if let Some(back) = local_referer(req).filter(|_| req.method() == Method::Post) {
+            return Flash::error(Redirect::to(back), self.message()).respond_to(req);
+        }
+        Err(status)
+    }
+}
+
+/// Flash message prepared for templates
+#[derive(Debug, Clone, Serialize)]
+pub struct FlashView {
+    /// Bootstrap alert class, e.g. "danger"
+    pub class: String,
+    pub message: String,
+}
+
+impl FlashView {
+    pub fn from_flash(flash: Option<FlashMessage<'_>>) -> Option<FlashView> {
+        flash.map(|f| FlashView {
+            class: match f.kind() {
+                "error" => "danger".to_string(),
+                kind => kind.to_string(),
+            },
+            message: f.message().to_string(),
+        })
+    }
+}
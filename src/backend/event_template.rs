@@ -0,0 +1,102 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::backend::data::{Event, Session, Slot, Storage};
+use crate::backend::error::BackendError;
+
+/// Reusable slot/session structure of an event, e.g. "autumn retreat: 3 slots × 6 workshops".
+/// Dates, participants and applications aren't part of a template.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventTemplate {
+    pub uuid: Uuid,
+    pub name: String,
+    /// Tenant whose admins may use the template, None for the default tenant
+    pub tenant: Option<String>,
+    /// Username of the admin who saved the template
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub slots: Vec<TemplateSlot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateSlot {
+    pub name: String,
+    pub description: Option<String>,
+    pub max_participants: Option<usize>,
+    pub sessions: Vec<TemplateSession>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateSession {
+    pub name: String,
+    pub description: Option<String>,
+    pub seats: usize,
+    pub eligibility_tags: Vec<String>,
+    pub tags: Vec<String>,
+    pub online: bool,
+    pub join_url: Option<String>,
+    pub attraction_bonus: usize,
+    #[serde(default)]
+    pub accessible: bool,
+    #[serde(default)]
+    pub content: Option<String>,
+}
+
+impl EventTemplate {
+    /// Captures the structure of the event
+    pub fn from_event(name: String, event: &Event, created_by: String) -> Self {
+        let slots = event.slots.iter().map(|slot| TemplateSlot {
+            name: slot.name.clone(),
+            description: slot.description.clone(),
+            max_participants: slot.max_participants,
+            sessions: slot.sessions.iter().map(|session| TemplateSession {
+                name: session.name.clone(),
+                description: session.description.clone(),
+                seats: session.seats,
+                eligibility_tags: session.eligibility_tags.clone(),
+                tags: session.tags.clone(),
+                online: session.online,
+                join_url: session.join_url.clone(),
+                attraction_bonus: session.attraction_bonus,
+                accessible: session.accessible,
+                content: session.content.clone(),
+            }).collect(),
+        }).collect();
+        EventTemplate { uuid: Uuid::new_v4(), name, tenant: event.tenant.clone(), created_by, created_at: Utc::now(), slots }
+    }
+
+    /// Creates a new event with fresh slots and sessions following this template
+    pub fn instantiate(&self, name: String, description: Option<String>) -> Event {
+        let mut event = Event::new(name, description);
+        event.tenant = self.tenant.clone();
+        event.slots = self.slots.iter().map(|template_slot| {
+            let mut slot = Slot::new(template_slot.name.clone(), template_slot.description.clone());
+            slot.max_participants = template_slot.max_participants;
+            slot.sessions = template_slot.sessions.iter().map(|template_session| {
+                let mut session = Session::new(template_session.name.clone(), template_session.description.clone(), template_session.seats);
+                session.eligibility_tags = template_session.eligibility_tags.clone();
+                session.tags = template_session.tags.clone();
+                session.online = template_session.online;
+                session.join_url = template_session.join_url.clone();
+                session.attraction_bonus = template_session.attraction_bonus;
+                session.accessible = template_session.accessible;
+                session.content = template_session.content.clone();
+                session
+            }).collect();
+            slot
+        }).collect();
+        event.reindex();
+        event
+    }
+
+    pub fn session_count(&self) -> usize {
+        self.slots.iter().map(|slot| slot.sessions.len()).sum()
+    }
+}
+
+impl Storage {
+    pub fn tenant_template(&self, tenant: Option<&str>, template_id: Uuid) -> Result<&EventTemplate, BackendError> {
+        self.event_templates.get(&template_id).filter(|t| t.tenant.as_deref() == tenant).ok_or(BackendError::NotFound("template"))
+    }
+}
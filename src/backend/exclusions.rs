@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::backend::data::Event;
+use crate::backend::error::BackendError;
+
+/// Two participants who must never share a session, e.g. for safeguarding reasons. Exclusions are
+/// confidential: only admins see them, participant pages and exports never mention them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exclusion {
+    pub uuid: Uuid,
+    pub first: Uuid,
+    pub second: Uuid,
+    /// Username of the admin who added the exclusion
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Exclusion {
+    fn involves(&self, a: Uuid, b: Uuid) -> bool {
+        (self.first == a && self.second == b) || (self.first == b && self.second == a)
+    }
+
+    fn partner_of(&self, participant: Uuid) -> Option<Uuid> {
+        if self.first == participant {
+            Some(self.second)
+        } else if self.second == participant {
+            Some(self.first)
+        } else {
+            None
+        }
+    }
+}
+
+/// True if one of `seated` is excluded from sharing a session with the participant
+pub fn excluded_from(exclusions: &[Exclusion], participant: Uuid, seated: &[Uuid]) -> bool {
+    exclusions.iter().filter_map(|e| e.partner_of(participant)).any(|partner| seated.contains(&partner))
+}
+
+impl Event {
+    pub fn add_exclusion(&mut self, first: Uuid, second: Uuid, created_by: &str) -> Result<(), BackendError> {
+        if first == second {
+            return Err(BackendError::bad_request("Choose two different participants."));
+        }
+        if !self.participants.contains_key(&first) || !self.participants.contains_key(&second) {
+            return Err(BackendError::NotFound("participant"));
+        }
+        if self.exclusions.iter().any(|e| e.involves(first, second)) {
+            return Err(BackendError::conflict("These participants are already kept apart."));
+        }
+        self.exclusions.push(Exclusion { uuid: Uuid::new_v4(), first, second, created_by: created_by.to_string(), created_at: Utc::now() });
+        Ok(())
+    }
+
+    pub fn remove_exclusion(&mut self, exclusion_id: Uuid) -> Result<(), BackendError> {
+        let Some(index) = self.exclusions.iter().position(|e| e.uuid == exclusion_id) else { return Err(BackendError::NotFound("exclusion")); };
+        self.exclusions.remove(index);
+        Ok(())
+    }
+
+    /// Drops the exclusions of a participant who is removed from the event
+    pub fn forget_exclusions_of(&mut self, participant: Uuid) {
+        self.exclusions.retain(|e| e.partner_of(participant).is_none());
+    }
+
+    /// Sessions in which excluded participants currently share a seat, e.g. after adding an
+    /// exclusion to a distributed event: slot, session and both names
+    pub fn exclusion_violations(&self) -> Vec<String> {
+        let name = |id: Uuid| self.participants.get(&id).map(|p| p.name.clone()).unwrap_or_default();
+        let mut violations = Vec::new();
+        for slot in &self.slots {
+            for sess in &slot.sessions {
+                for e in self.exclusions.iter().filter(|e| sess.participants.contains(&e.first) && sess.participants.contains(&e.second)) {
+                    violations.push(format!("{} and {} are both seated in {} ({}).", name(e.first), name(e.second), sess.name, slot.name));
+                }
+            }
+        }
+        violations
+    }
+}
@@ -0,0 +1,97 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Capacity of edges that shouldn't limit the flow
+pub const UNLIMITED: i64 = i64::MAX / 4;
+
+#[derive(Debug, Clone)]
+struct Edge {
+    to: usize,
+    capacity: i64,
+    cost: i64,
+}
+
+/// Directed graph for min-cost max-flow problems. Every edge is stored next to its residual
+/// edge, so the residual of edge `e` is `e ^ 1`.
+#[derive(Debug, Default)]
+pub struct FlowGraph {
+    edges: Vec<Edge>,
+    adjacency: Vec<Vec<usize>>,
+}
+
+impl FlowGraph {
+    pub fn add_node(&mut self) -> usize {
+        self.adjacency.push(Vec::new());
+        self.adjacency.len() - 1
+    }
+
+    /// Adds an edge with a non-negative cost per unit of flow and returns its index
+    pub fn add_edge(&mut self, from: usize, to: usize, capacity: i64, cost: i64) -> usize {
+        let index = self.edges.len();
+        self.edges.push(Edge { to, capacity, cost });
+        self.edges.push(Edge { to: from, capacity: 0, cost: -cost });
+        self.adjacency[from].push(index);
+        self.adjacency[to].push(index + 1);
+        index
+    }
+
+    /// Flow sent through the edge
+    pub fn flow(&self, edge: usize) -> i64 {
+        self.edges[edge ^ 1].capacity
+    }
+
+    /// Sends as much flow as possible from source to sink at the lowest total cost, by successive
+    /// shortest paths (Dijkstra with potentials). Returns the flow sent.
+    pub fn min_cost_max_flow(&mut self, source: usize, sink: usize) -> i64 {
+        let nodes = self.adjacency.len();
+        let mut potential = vec![0i64; nodes];
+        let mut total = 0;
+        loop {
+            let mut distance = vec![i64::MAX; nodes];
+            let mut via = vec![usize::MAX; nodes];
+            let mut queue = BinaryHeap::new();
+            distance[source] = 0;
+            queue.push(Reverse((0, source)));
+            while let Some(Reverse((d, node))) = queue.pop() {
+                if d > distance[node] {
+                    continue;
+                }
+                for &e in &self.adjacency[node] {
+                    let edge = &self.edges[e];
+                    if edge.capacity <= 0 {
+                        continue;
+                    }
+                    let next = d + edge.cost + potential[node] - potential[edge.to];
+                    if next < distance[edge.to] {
+                        distance[edge.to] = next;
+                        via[edge.to] = e;
+                        queue.push(Reverse((next, edge.to)));
+                    }
+                }
+            }
+            if distance[sink] == i64::MAX {
+                return total;
+            }
+            for (p, d) in potential.iter_mut().zip(&distance) {
+                if *d < i64::MAX {
+                    *p += d;
+                }
+            }
+            let mut amount = i64::MAX;
+            let mut node = sink;
+            while node != source {
+                let e = via[node];
+                amount = amount.min(self.edges[e].capacity);
+                node = self.edges[e ^ 1].to;
+            }
+            let mut node = sink;
+            while node != source {
+                let e = via[node];
+                self.edges[e].capacity -= amount;
+                self.edges[e ^ 1].capacity += amount;
+                node = self.edges[e ^ 1].to;
+            }
+            total += amount;
+        }
+    }
+}
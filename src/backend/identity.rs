@@ -0,0 +1,287 @@
+use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::backend::data::{ApplicationPriority, DistributionRun, Event, EventState, Invitation, StateChange, Storage};
+use crate::backend::error::BackendError;
+
+/// Validity of the confirmation link sent when linking a registration to an email address
+pub const LINK_CONFIRMATION_HOURS: i64 = 48;
+
+/// A person known by a verified email address. Participants of different events can be linked to
+/// the same person, whose fairness points then carry over from one event to the next.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Person {
+    pub uuid: Uuid,
+    /// Verified address in lowercase
+    pub email: String,
+    /// Points earned in the last distribution the person took part in
+    pub points: usize,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Link request of an invitation that waits for the email address to be confirmed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingLink {
+    pub email: String,
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Participation of a linked person in another event
+#[derive(Debug, Clone, Serialize)]
+pub struct Participation {
+    pub event_id: Uuid,
+    pub event_name: String,
+    pub state: EventState,
+    pub assigned_sessions: usize,
+}
+
+/// Outcome of importing the fairness points of a finished event into another one
+#[derive(Debug, Clone, Default)]
+pub struct CarryoverImport {
+    /// Invitations of the target event that received points
+    pub matched: usize,
+    /// Participants of the finished event with points but no invitation in the target event
+    pub unmatched: usize,
+}
+
+/// Preferences a linked person chose in an earlier event, mapped to the sessions of the same name
+/// in the current event. Participants review them before saving.
+#[derive(Debug, Clone)]
+pub struct ImportedPreferences {
+    pub event_name: String,
+    /// Sessions in order of preference, by slot of the current event
+    pub picks: HashMap<Uuid, Vec<Uuid>>,
+    /// Earlier preferences without an open session of the same name
+    pub unmatched: usize,
+}
+
+impl Storage {
+    /// Starts linking the invitation to the given address and returns the confirmation token
+    pub fn request_person_link(&mut self, code: &str, email: &str, now: DateTime<Utc>) -> Option<String> {
+        let inv = self.invitations_codes.get_mut(code)?;
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        inv.pending_link = Some(PendingLink { email: email.to_lowercase(), token: token.clone(), expires_at: now + chrono::Duration::hours(LINK_CONFIRMATION_HOURS) });
+        Some(token)
+    }
+
+    /// Links the invitation holding the token to the person with the confirmed address, creating the
+    /// person on first use. Returns the invitation code.
+    pub fn confirm_person_link(&mut self, token: &str, now: DateTime<Utc>) -> Result<String, BackendError> {
+        let Some(inv) = self.invitations_codes.values().find(|inv| inv.pending_link.as_ref().is_some_and(|link| link.token == token)) else {
+            return Err(BackendError::NotFound("confirmation link"));
+        };
+        let code = inv.code.clone();
+        let link = inv.pending_link.clone().expect("found by its pending link");
+        if link.expires_at < now {
+            return Err(BackendError::bad_request("This confirmation link has expired. Please request a new one."));
+        }
+        let person_id = match self.people.values().find(|p| p.email == link.email) {
+            Some(person) => person.uuid,
+            None => {
+                let person = Person { uuid: Uuid::new_v4(), email: link.email.clone(), points: 0, created_at: now };
+                let id = person.uuid;
+                self.people.insert(id, person);
+                id
+            }
+        };
+        let inv = self.invitations_codes.get_mut(&code).expect("exists");
+        inv.pending_link = None;
+        inv.person_id = Some(person_id);
+        Ok(code)
+    }
+
+    pub fn unlink_person(&mut self, code: &str) {
+        if let Some(inv) = self.invitations_codes.get_mut(code) {
+            inv.person_id = None;
+            inv.pending_link = None;
+        }
+    }
+
+    /// Maps the event's participants to the people they are linked to
+    pub fn linked_people(&self, event_id: Uuid) -> HashMap<Uuid, Uuid> {
+        self.invitations_codes.values()
+            .filter(|inv| inv.event_id == event_id)
+            .filter_map(|inv| Some((inv.participant_id?, inv.person_id?)))
+            .collect()
+    }
+
+    /// Ways to recognize the holder of the invitation in another event: the linked person, the
+    /// member directory entry and the email address
+    fn identity_keys(&self, inv: &Invitation) -> Vec<String> {
+        let mut keys = Vec::new();
+        if let Some(person_id) = inv.person_id {
+            keys.push(format!("person:{}", person_id));
+        }
+        if let Some(directory_id) = &inv.directory_id {
+            keys.push(format!("directory:{}", directory_id));
+        }
+        let email = inv.email.clone().or_else(|| inv.person_id.and_then(|id| self.people.get(&id)).map(|p| p.email.clone()));
+        if let Some(email) = email.map(|e| e.trim().to_lowercase()).filter(|e| !e.is_empty()) {
+            keys.push(format!("email:{}", email));
+        }
+        keys
+    }
+
+    /// Gives the invitees of the target event the fairness points they ended the finished source
+    /// event with. Invitees are matched by linked person, directory entry or email address. Points
+    /// only ever rise, so importing twice or from several events keeps the highest value.
+    pub fn import_carryover_points(&mut self, source_id: Uuid, target_id: Uuid) -> Result<CarryoverImport, BackendError> {
+        if source_id == target_id {
+            return Err(BackendError::bad_request("Choose another event to import the points from."));
+        }
+        let source = self.events.get(&source_id).ok_or(BackendError::NotFound("event"))?;
+        if !source.results_visible() {
+            return Err(BackendError::conflict("Points can only be imported from an event with published results."));
+        }
+        let target = self.events.get(&target_id).ok_or(BackendError::NotFound("event"))?;
+        target.ensure_unlocked()?;
+        if target.is_distributed() {
+            return Err(BackendError::conflict("Points can only be imported before the distribution."));
+        }
+
+        // Points of the source participants by identity, and the identities of each of them
+        let mut earned: HashMap<String, usize> = HashMap::new();
+        let mut sources: Vec<Vec<String>> = Vec::new();
+        for inv in self.invitations_codes.values().filter(|inv| inv.event_id == source_id) {
+            let Some(points) = inv.participant_id.and_then(|pid| source.participants.get(&pid)).map(|p| p.points_from_previous_rounds).filter(|p| *p > 0) else { continue; };
+            let keys = self.identity_keys(inv);
+            for key in &keys {
+                let entry = earned.entry(key.clone()).or_default();
+                *entry = (*entry).max(points);
+            }
+            sources.push(keys);
+        }
+
+        let mut found: HashSet<String> = HashSet::new();
+        let mut matched = 0;
+        let target_codes: Vec<String> = self.invitations_codes.values().filter(|inv| inv.event_id == target_id).map(|inv| inv.code.clone()).collect();
+        for code in target_codes {
+            let keys = self.identity_keys(&self.invitations_codes[&code]);
+            let Some(points) = keys.iter().filter_map(|key| earned.get(key).copied()).max() else { continue; };
+            found.extend(keys);
+            matched += 1;
+            let inv = self.invitations_codes.get_mut(&code).expect("exists");
+            inv.carryover_points = inv.carryover_points.max(points);
+            let participant_id = inv.participant_id;
+            let target = self.events.get_mut(&target_id).expect("checked above");
+            if let Some(participant) = participant_id.and_then(|pid| target.participants.get_mut(&pid)) {
+                participant.points_from_previous_rounds = participant.points_from_previous_rounds.max(points);
+            }
+        }
+        let unmatched = sources.iter().filter(|keys| !keys.iter().any(|key| found.contains(key))).count();
+        Ok(CarryoverImport { matched, unmatched })
+    }
+
+    /// Starts the event's participants with the points their person earned in earlier events
+    pub fn carry_person_points(&self, ev: &mut Event) {
+        for (participant_id, person_id) in self.linked_people(ev.uuid) {
+            let (Some(participant), Some(person)) = (ev.participants.get_mut(&participant_id), self.people.get(&person_id)) else { continue; };
+            participant.points_from_previous_rounds = participant.points_from_previous_rounds.max(person.points);
+        }
+    }
+
+    /// Closes the registration of the event, distributes its seats with the points carried over from
+    /// linked people and stores the resulting points with those people again.
+    pub fn distribute_event(&mut self, event_id: Uuid, initiated_by: &str) -> Result<DistributionRun, BackendError> {
+        let mut ev = self.begin_distribution(event_id)?;
+        let run = ev.distribute(initiated_by).clone();
+        self.finish_distribution(ev)?;
+        Ok(run)
+    }
+
+    /// Closes the registration of the event and returns a copy to distribute the seats on, so the
+    /// distribution can run without holding the storage lock. The stored event stays locked
+    /// against changes until [`Storage::finish_distribution`] stores the result.
+    pub fn begin_distribution(&mut self, event_id: Uuid) -> Result<Event, BackendError> {
+        let stored = self.events.get_mut(&event_id).ok_or(BackendError::NotFound("event"))?;
+        stored.try_transition(EventState::AssigningSeats)?;
+        let mut ev = stored.clone();
+        self.carry_person_points(&mut ev);
+        Ok(ev)
+    }
+
+    /// Stores a distributed event and the resulting points with the linked people
+    pub fn finish_distribution(&mut self, mut ev: Event) -> Result<(), BackendError> {
+        ev.try_transition(EventState::AwaitingConfirmation)?;
+        for (participant_id, person_id) in self.linked_people(ev.uuid) {
+            let (Some(participant), Some(person)) = (ev.participants.get(&participant_id), self.people.get_mut(&person_id)) else { continue; };
+            person.points = participant.points_from_previous_rounds;
+        }
+        self.events.insert(ev.uuid, ev);
+        Ok(())
+    }
+
+    /// Reopens the registration of an event whose distribution didn't complete
+    pub fn abort_distribution(&mut self, event_id: Uuid) {
+        let Some(ev) = self.events.get_mut(&event_id).filter(|ev| ev.state == EventState::AssigningSeats) else { return; };
+        ev.state_history.push(StateChange { from: ev.state, to: EventState::OpenForRegistration, at: Utc::now() });
+        ev.state = EventState::OpenForRegistration;
+    }
+
+    /// Other events the person took part in, limited to the given tenant
+    pub fn participation_history(&self, person_id: Uuid, tenant: Option<&str>, except: Uuid) -> Vec<Participation> {
+        let mut history: Vec<Participation> = self.invitations_codes.values()
+            .filter(|inv| inv.person_id == Some(person_id) && inv.event_id != except)
+            .filter_map(|inv| {
+                let ev = self.events.get(&inv.event_id).filter(|ev| ev.tenant.as_deref() == tenant)?;
+                let participant_id = inv.participant_id?;
+                let assigned_sessions = ev.slots.iter().flat_map(|slot| &slot.sessions).filter(|s| s.participants.contains(&participant_id)).count();
+                Some(Participation { event_id: ev.uuid, event_name: ev.name.clone(), state: ev.state, assigned_sessions })
+            })
+            .collect();
+        history.sort_by(|a, b| a.event_name.cmp(&b.event_name));
+        history
+    }
+
+    /// The latest other event of the tenant in which the person linked to the invitation chose
+    /// preferences, with the participant there. Events are ordered by their last state change.
+    pub fn previous_linked_event(&self, code: &str) -> Option<(&Event, Uuid)> {
+        let inv = self.invitations_codes.get(code)?;
+        let person_id = inv.person_id?;
+        let tenant = self.events.get(&inv.event_id)?.tenant.as_deref();
+        self.invitations_codes.values()
+            .filter(|other| other.person_id == Some(person_id) && other.event_id != inv.event_id)
+            .filter_map(|other| {
+                let ev = self.events.get(&other.event_id).filter(|ev| ev.tenant.as_deref() == tenant)?;
+                let pid = other.participant_id?;
+                let chose = ev.slots.iter().flat_map(|slot| &slot.sessions).flat_map(|s| &s.applications)
+                    .any(|a| a.participant == pid && !matches!(a.priority, ApplicationPriority::NoPreference));
+                chose.then_some((ev, pid))
+            })
+            .max_by_key(|(ev, _)| ev.state_history.last().map(|change| change.at))
+    }
+
+    /// Preferences of the previous linked event carried over to the invitation's event. Sessions
+    /// are matched by name; closed slots and sessions the participant isn't eligible for are skipped.
+    pub fn import_preferences(&self, code: &str, now: DateTime<Utc>) -> Option<ImportedPreferences> {
+        let inv = self.invitations_codes.get(code)?;
+        let ev = self.events.get(&inv.event_id)?;
+        let participant = ev.participants.get(&inv.participant_id?)?;
+        let (previous, previous_pid) = self.previous_linked_event(code)?;
+        let mut earlier: Vec<(&ApplicationPriority, &str)> = previous.slots.iter().flat_map(|slot| &slot.sessions)
+            .flat_map(|s| s.applications.iter().filter(|a| a.participant == previous_pid && !matches!(a.priority, ApplicationPriority::NoPreference)).map(|a| (&a.priority, s.name.as_str())))
+            .collect();
+        earlier.sort_by_key(|(priority, _)| priority.rank());
+        let mut imported = ImportedPreferences { event_name: previous.name.clone(), picks: HashMap::new(), unmatched: 0 };
+        for (_, name) in earlier {
+            let found = ev.slots.iter().filter(|slot| slot.accepts_preferences(now)).find_map(|slot| {
+                let sess = slot.sessions.iter().find(|s| s.name.trim().eq_ignore_ascii_case(name.trim()) && s.is_eligible(participant))?;
+                Some((slot, sess.uuid))
+            });
+            let Some((slot, session_id)) = found else {
+                imported.unmatched += 1;
+                continue;
+            };
+            let picks = imported.picks.entry(slot.uuid).or_default();
+            if picks.len() < slot.ranked_choices && !picks.contains(&session_id) {
+                picks.push(session_id);
+            } else {
+                imported.unmatched += 1;
+            }
+        }
+        Some(imported)
+    }
+}
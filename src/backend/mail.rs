@@ -0,0 +1,217 @@
+use chrono::{DateTime, Utc};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::backend::config::{AppConfig, MailConfig};
+use crate::backend::data::{Invitation, Storage};
+use crate::backend::delivery::{retry_delay, MAX_DELIVERY_ATTEMPTS};
+use crate::backend::error::BackendError;
+use crate::backend::state::{RecoverLock, Shared};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutgoingMail {
+    pub uuid: Uuid,
+    pub event_id: Option<Uuid>,
+    /// Email address of the recipient. If unknown, the mail has to be delivered manually by an admin.
+    pub recipient: Option<String>,
+    /// Human readable description of the recipient (name or invitation code)
+    pub recipient_name: String,
+    pub subject: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Failed delivery attempts so far
+    #[serde(default)]
+    pub attempts: u32,
+    /// Earliest time of the next attempt after a failure
+    #[serde(default)]
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    /// Set once delivery was given up, the mail waits for an admin to send it again
+    #[serde(default)]
+    pub dead_lettered_at: Option<DateTime<Utc>>,
+}
+
+impl OutgoingMail {
+    pub fn new(event_id: Option<Uuid>, recipient: Option<String>, recipient_name: String, subject: String, body: String) -> Self {
+        OutgoingMail { uuid: Uuid::new_v4(), event_id, recipient, recipient_name, subject, body, created_at: Utc::now(), sent_at: None, last_error: None, attempts: 0, next_attempt_at: None, dead_lettered_at: None }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.sent_at.is_none()
+    }
+
+    /// Returns true if the mail should be delivered now: pending, not given up and past its retry delay
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.is_pending() && self.dead_lettered_at.is_none() && self.next_attempt_at.is_none_or(|t| t <= now)
+    }
+}
+
+/// Replaces the placeholders {name}, {event}, {code} and {link} (personal login link) in a text
+/// written by an admin. Unknown placeholders are kept as they are.
+pub fn fill_placeholders(text: &str, name: &str, event: &str, code: &str, link: &str) -> String {
+    text.replace("{name}", name).replace("{event}", event).replace("{code}", code).replace("{link}", link)
+}
+
+/// Recipients of a mail written by an admin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailAudience {
+    /// Everyone registered for the event
+    Registered,
+    /// Registered participants without a seat in any session
+    Unassigned,
+    /// Participants assigned to the session
+    Session(Uuid),
+}
+
+impl MailAudience {
+    /// Parses the value of the audience select: "registered", "unassigned" or a session id
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "registered" => Some(MailAudience::Registered),
+            "unassigned" => Some(MailAudience::Unassigned),
+            other => Uuid::parse_str(other).ok().map(MailAudience::Session),
+        }
+    }
+}
+
+impl Storage {
+    pub fn queue_mail(&mut self, mail: OutgoingMail) {
+        self.outbox.push(mail);
+    }
+
+    /// Mail locale chosen by the participant of the invitation
+    pub fn locale_of(&self, inv: &Invitation) -> Option<String> {
+        self.events.get(&inv.event_id)?.participants.get(&inv.participant_id?)?.locale.clone()
+    }
+
+    /// Renders the mail for every participant of the audience, sorted by name. Participants whose
+    /// email address isn't verified yet are skipped. Nothing is queued.
+    pub fn compose_participant_mails(&self, event_id: Uuid, audience: MailAudience, subject: &str, body: &str, config: &AppConfig) -> Result<Vec<OutgoingMail>, BackendError> {
+        let ev = self.events.get(&event_id).ok_or(BackendError::NotFound("event"))?;
+        if let MailAudience::Session(session_id) = audience {
+            ev.session_by_id(session_id)?;
+        }
+        let mut mails: Vec<OutgoingMail> = self.invitations_codes.values()
+            .filter(|inv| inv.event_id == event_id)
+            .filter_map(|inv| {
+                let participant = ev.participants.get(&inv.participant_id?)?;
+                if participant.awaiting_verification { return None; }
+                let mut sessions = ev.slots.iter().flat_map(|slot| slot.sessions.iter());
+                let included = match audience {
+                    MailAudience::Registered => true,
+                    MailAudience::Unassigned => !sessions.any(|s| s.participants.contains(&participant.uuid)),
+                    MailAudience::Session(session_id) => sessions.any(|s| s.uuid == session_id && s.participants.contains(&participant.uuid)),
+                };
+                if !included { return None; }
+                let name = if participant.name.trim().is_empty() { inv.code.clone() } else { participant.name.clone() };
+                let link = config.url(&format!("/invitation/{}", inv.code));
+                Some(OutgoingMail::new(
+                    Some(event_id),
+                    inv.email.clone(),
+                    name.clone(),
+                    fill_placeholders(subject, &name, &ev.name, &inv.code, &link),
+                    fill_placeholders(body, &name, &ev.name, &inv.code, &link),
+                ))
+            })
+            .collect();
+        mails.sort_by(|a, b| a.recipient_name.cmp(&b.recipient_name));
+        Ok(mails)
+    }
+}
+
+/// Sends queued mails via SMTP if configured
+#[derive(Clone)]
+pub struct Mailer {
+    transport: Option<AsyncSmtpTransport<Tokio1Executor>>,
+    from: Option<Mailbox>,
+}
+
+impl Mailer {
+    pub fn from_config(config: &MailConfig) -> Self {
+        let from = match config.from.parse::<Mailbox>() {
+            Ok(from) => Some(from),
+            Err(e) => {
+                eprintln!("Invalid mail sender address '{}': {}", config.from, e);
+                None
+            }
+        };
+        let transport = config.smtp_host.as_ref().and_then(|host| {
+            let builder = if config.starttls {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+            } else {
+                AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+            };
+            match builder {
+                Ok(mut builder) => {
+                    if let Some(port) = config.smtp_port { builder = builder.port(port); }
+                    if let (Some(user), Some(pass)) = (&config.smtp_username, &config.smtp_password) {
+                        builder = builder.credentials(Credentials::new(user.clone(), pass.clone()));
+                    }
+                    Some(builder.build())
+                }
+                Err(e) => {
+                    eprintln!("Failed to set up SMTP transport for {}: {}", host, e);
+                    None
+                }
+            }
+        });
+        Mailer { transport, from }
+    }
+
+    /// Returns true if mails can be delivered automatically
+    pub fn is_configured(&self) -> bool {
+        self.transport.is_some() && self.from.is_some()
+    }
+
+    pub async fn send(&self, mail: &OutgoingMail) -> Result<(), String> {
+        let (Some(transport), Some(from)) = (&self.transport, &self.from) else { return Err("mail delivery is not configured".to_string()); };
+        let Some(recipient) = &mail.recipient else { return Err("no recipient address".to_string()); };
+        let to: Mailbox = recipient.parse().map_err(|e| format!("invalid recipient address: {}", e))?;
+        let message = Message::builder()
+            .from(from.clone())
+            .to(to)
+            .subject(mail.subject.clone())
+            .body(mail.body.clone())
+            .map_err(|e| e.to_string())?;
+        transport.send(message).await.map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    /// Delivers all due mails having a recipient address. Mails without address stay queued for the admins.
+    /// Failed mails are retried with growing delays and given up after the last attempt.
+    pub async fn deliver_pending(&self, storage: &Shared<Storage>) {
+        if !self.is_configured() { return; }
+        // Copy pending mails out of the lock, as sending happens across await points
+        let pending: Vec<OutgoingMail> = {
+            let guard = storage.read_unpoisoned();
+            let now = Utc::now();
+            guard.outbox.iter().filter(|m| m.is_due(now) && m.recipient.is_some()).cloned().collect()
+        };
+        for mail in pending {
+            let result = self.send(&mail).await;
+            let mut guard = storage.write_unpoisoned();
+            if let Some(stored) = guard.outbox.iter_mut().find(|m| m.uuid == mail.uuid) {
+                match result {
+                    Ok(()) => {
+                        stored.sent_at = Some(Utc::now());
+                        stored.last_error = None;
+                    }
+                    Err(e) => {
+                        stored.attempts += 1;
+                        if stored.attempts >= MAX_DELIVERY_ATTEMPTS {
+                            eprintln!("Giving up on mail {} to {} after {} attempts: {}", mail.uuid, mail.recipient_name, stored.attempts, e);
+                            stored.dead_lettered_at = Some(Utc::now());
+                        } else {
+                            eprintln!("Failed to send mail {} to {}, retrying later: {}", mail.uuid, mail.recipient_name, e);
+                            stored.next_attempt_at = Some(Utc::now() + retry_delay(stored.attempts));
+                        }
+                        stored.last_error = Some(e);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use rocket::request::{FromRequest, Outcome, Request};
+
+use crate::backend::config::MailConfig;
+
+/// Locale of the built-in templates, the last step of every fallback chain
+pub const FALLBACK_LOCALE: &str = "en";
+
+/// Templates shipped with the application: locale, name and content
+const BUILT_IN: &[(&str, &str, &str)] = &[
+    ("en", "login_link", include_str!("../../templates/mail/en/login_link.txt")),
+    ("en", "registration_access", include_str!("../../templates/mail/en/registration_access.txt")),
+    ("en", "registration_verification", include_str!("../../templates/mail/en/registration_verification.txt")),
+    ("en", "link_confirmation", include_str!("../../templates/mail/en/link_confirmation.txt")),
+    ("en", "reminder", include_str!("../../templates/mail/en/reminder.txt")),
+    ("en", "slot_reminder", include_str!("../../templates/mail/en/slot_reminder.txt")),
+    ("en", "results", include_str!("../../templates/mail/en/results.txt")),
+    ("en", "results_no_seats", include_str!("../../templates/mail/en/results_no_seats.txt")),
+    ("en", "co_admin_invite", include_str!("../../templates/mail/en/co_admin_invite.txt")),
+    ("de", "login_link", include_str!("../../templates/mail/de/login_link.txt")),
+    ("de", "registration_access", include_str!("../../templates/mail/de/registration_access.txt")),
+    ("de", "registration_verification", include_str!("../../templates/mail/de/registration_verification.txt")),
+    ("de", "link_confirmation", include_str!("../../templates/mail/de/link_confirmation.txt")),
+    ("de", "reminder", include_str!("../../templates/mail/de/reminder.txt")),
+    ("de", "slot_reminder", include_str!("../../templates/mail/de/slot_reminder.txt")),
+    ("de", "results", include_str!("../../templates/mail/de/results.txt")),
+    ("de", "results_no_seats", include_str!("../../templates/mail/de/results_no_seats.txt")),
+    ("de", "co_admin_invite", include_str!("../../templates/mail/de/co_admin_invite.txt")),
+];
+
+/// Subject and body of a mail with `{placeholder}`s
+#[derive(Debug, Clone)]
+pub struct MailTemplate {
+    pub subject: String,
+    pub body: String,
+}
+
+impl MailTemplate {
+    /// Parses a template file: a `Subject: ...` line, an empty line and the body
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.replace("\r\n", "\n");
+        let (first, body) = text.split_once('\n').unwrap_or((text.as_str(), ""));
+        let subject = first.strip_prefix("Subject:").ok_or("the first line must start with \"Subject:\"")?.trim();
+        Ok(MailTemplate { subject: subject.to_string(), body: body.trim_start_matches('\n').to_string() })
+    }
+
+    /// Returns subject and body with the placeholders replaced. Unknown placeholders are kept.
+    pub fn render(&self, vars: &[(&str, &str)]) -> (String, String) {
+        let fill = |text: &str| vars.iter().fold(text.to_string(), |text, (key, value)| text.replace(&format!("{{{}}}", key), value));
+        (fill(&self.subject), fill(&self.body))
+    }
+}
+
+/// Mail templates per locale. A participant's locale is tried first, then its language without
+/// region (`de-at` → `de`), the configured default locale and finally the built-in English templates.
+#[derive(Debug, Clone)]
+pub struct MailTemplates {
+    templates: HashMap<String, HashMap<String, MailTemplate>>,
+    default_locale: String,
+}
+
+impl Default for MailTemplates {
+    fn default() -> Self {
+        let mut templates = MailTemplates { templates: HashMap::new(), default_locale: FALLBACK_LOCALE.to_string() };
+        for (locale, name, text) in BUILT_IN {
+            templates.insert(locale, name, MailTemplate::parse(text).expect("built-in mail templates are valid"));
+        }
+        templates
+    }
+}
+
+/// Lowercase with `-` as separator, e.g. `de_AT` → `de-at`
+pub fn normalize_locale(locale: &str) -> String {
+    locale.trim().replace('_', "-").to_lowercase()
+}
+
+impl MailTemplates {
+    /// Built-in templates, extended and overridden by `<templates_dir>/<locale>/<name>.txt`
+    pub fn load(config: &MailConfig) -> Self {
+        let mut templates = MailTemplates { default_locale: normalize_locale(&config.default_locale), ..Default::default() };
+        let Some(dir) = &config.templates_dir else { return templates; };
+        let locales = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("Failed to read mail templates from {}: {}", dir, e);
+                return templates;
+            }
+        };
+        for locale_dir in locales.flatten().filter(|entry| entry.path().is_dir()) {
+            let locale = locale_dir.file_name().to_string_lossy().to_string();
+            let Ok(files) = std::fs::read_dir(locale_dir.path()) else { continue; };
+            for file in files.flatten() {
+                let path = file.path();
+                if path.extension().is_none_or(|ext| ext != "txt") { continue; }
+                let Some(name) = path.file_stem().map(|stem| stem.to_string_lossy().to_string()) else { continue; };
+                match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|text| MailTemplate::parse(&text)) {
+                    Ok(template) => templates.insert(&locale, &name, template),
+                    Err(e) => eprintln!("Ignoring mail template {}: {}", path.display(), e),
+                }
+            }
+        }
+        if !templates.templates.contains_key(&templates.default_locale) {
+            eprintln!("No mail templates for the default locale {}, using {}.", templates.default_locale, FALLBACK_LOCALE);
+        }
+        templates
+    }
+
+    fn insert(&mut self, locale: &str, name: &str, template: MailTemplate) {
+        self.templates.entry(normalize_locale(locale)).or_default().insert(name.to_string(), template);
+    }
+
+    /// Locales having at least one template, sorted
+    pub fn locales(&self) -> Vec<String> {
+        let mut locales: Vec<String> = self.templates.keys().cloned().collect();
+        locales.sort();
+        locales
+    }
+
+    pub fn supports(&self, locale: &str) -> bool {
+        self.templates.contains_key(&normalize_locale(locale))
+    }
+
+    /// Locales tried in order for a participant with the given locale
+    pub fn fallback_chain(&self, locale: Option<&str>) -> Vec<String> {
+        let mut candidates = Vec::new();
+        if let Some(locale) = locale.map(normalize_locale) {
+            candidates.push(locale.split('-').next().unwrap_or_default().to_string());
+            candidates.insert(0, locale);
+        }
+        candidates.extend([self.default_locale.clone(), FALLBACK_LOCALE.to_string()]);
+        let mut chain: Vec<String> = Vec::new();
+        for locale in candidates {
+            if !chain.contains(&locale) {
+                chain.push(locale);
+            }
+        }
+        chain
+    }
+
+    /// Subject and body of the named template in the best matching locale
+    pub fn render(&self, name: &str, locale: Option<&str>, vars: &[(&str, &str)]) -> (String, String) {
+        let template = self.fallback_chain(locale).iter()
+            .find_map(|locale| self.templates.get(locale)?.get(name))
+            .unwrap_or_else(|| panic!("no built-in mail template {}", name));
+        template.render(vars)
+    }
+
+    /// First locale of an `Accept-Language` header there are templates for
+    pub fn negotiate(&self, accept_language: &str) -> Option<String> {
+        let mut ranges: Vec<(f32, String)> = accept_language.split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let locale = normalize_locale(parts.next()?);
+                let quality = parts.find_map(|p| p.trim().strip_prefix("q=")?.parse::<f32>().ok()).unwrap_or(1.0);
+                (!locale.is_empty() && locale != "*").then_some((quality, locale))
+            })
+            .collect();
+        ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+        ranges.into_iter().find_map(|(_, locale)| {
+            if self.supports(&locale) {
+                return Some(locale);
+            }
+            let language = locale.split('-').next()?;
+            self.supports(language).then(|| language.to_string())
+        })
+    }
+}
+
+/// Raw `Accept-Language` header of the request, if sent
+pub struct AcceptLanguage(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AcceptLanguage {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(AcceptLanguage(req.headers().get_one("Accept-Language").map(str::to_string)))
+    }
+}
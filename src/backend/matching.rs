@@ -0,0 +1,401 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::backend::data::{Application, ApplicationPriority, Event, InvitationCategory, PriorityCounts, Session, Slot, MAX_RANKED_CHOICES};
+use crate::backend::exclusions::{excluded_from, Exclusion};
+use crate::backend::flow::{FlowGraph, UNLIMITED};
+use crate::backend::scoring::ScoringStrategy;
+
+/// How the seats of a slot are allocated once the applications are ranked, selected per event
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, FromFormField)]
+pub enum AllocationMode {
+    /// The best ranked application of the whole slot is served next
+    #[default]
+    Greedy,
+    /// Participants propose to their sessions in the order of their preferences and every session
+    /// keeps the best ranked proposals (deferred acceptance after Gale and Shapley)
+    StableMatching,
+    /// Minimizes the total dissatisfaction of the slot with a min-cost max-flow: as many seats as
+    /// possible, then preference levels as good as possible, points decide between applicants
+    OptimalAssignment,
+}
+
+const CATEGORIES: [InvitationCategory; 3] = [InvitationCategory::Guest, InvitationCategory::Member, InvitationCategory::Speaker];
+
+/// Order in which a participant proposes: their preferences, then sessions marked as "maybe",
+/// then the remaining sessions by points
+fn proposal_order(application: &Application) -> (u8, Reverse<&Application>) {
+    (dissatisfaction(application), Reverse(application))
+}
+
+/// Dissatisfaction of a seat the participant didn't choose, see [`dissatisfaction`]
+pub const UNRANKED_DISSATISFACTION: u8 = MAX_RANKED_CHOICES as u8 + 1;
+
+/// How far the application is from the participant's first preference. Content the participant
+/// already got in another slot comes after everything else.
+pub fn dissatisfaction(application: &Application) -> u8 {
+    if application.repeat {
+        return UNRANKED_DISSATISFACTION + 1;
+    }
+    match application.priority {
+        ApplicationPriority::FirstPreference => 0,
+        ApplicationPriority::SecondPreference => 1,
+        ApplicationPriority::ThirdPreference => 2,
+        ApplicationPriority::LowerPreference(rank) => rank - 1,
+        ApplicationPriority::NoPreference if application.interested => UNRANKED_DISSATISFACTION - 1,
+        ApplicationPriority::NoPreference => UNRANKED_DISSATISFACTION,
+    }
+}
+
+/// Applicants of the slot without a seat, ordered by their best ranked application
+fn unplaced_applicants(slot: &Slot, placed: &HashSet<Uuid>) -> Vec<Uuid> {
+    let mut applications: Vec<&Application> = slot.sessions.iter().flat_map(|s| &s.applications).filter(|a| !placed.contains(&a.participant)).collect();
+    applications.sort_by_key(|a| Reverse(a.calculated_points));
+    let mut waiting = Vec::new();
+    for application in applications {
+        if !waiting.contains(&application.participant) {
+            waiting.push(application.participant);
+        }
+    }
+    waiting
+}
+
+impl Session {
+    /// Splits the proposals into the ones the session keeps and the ones it turns away. Reserved seats
+    /// go to the best ranked applicants of their category, the remaining seats to the best ranked of
+    /// everyone else. Applicants kept apart from someone already chosen are turned away.
+    fn choose(&self, mut proposals: Vec<Application>, categories: &HashMap<Uuid, InvitationCategory>, exclusions: &[Exclusion]) -> (Vec<Application>, Vec<Application>) {
+        proposals.sort_by(|a, b| b.cmp(a));
+        let capacity = self.seats.saturating_sub(self.participants.len());
+        let category_of = |pid: &Uuid| categories.get(pid).copied().unwrap_or_default();
+        let mut seated = self.participants.clone();
+        let mut kept = Vec::new();
+        let mut rest = Vec::new();
+        for application in proposals {
+            let category = category_of(&application.participant);
+            let in_category = seated.iter().filter(|pid| category_of(pid) == category).count();
+            if kept.len() < capacity && in_category < self.category_seats.reserved_for(category) && !excluded_from(exclusions, application.participant, &seated) {
+                seated.push(application.participant);
+                kept.push(application);
+            } else {
+                rest.push(application);
+            }
+        }
+        let mut rejected = Vec::new();
+        for application in rest {
+            if kept.len() < capacity && !excluded_from(exclusions, application.participant, &seated) {
+                seated.push(application.participant);
+                kept.push(application);
+            } else {
+                rejected.push(application);
+            }
+        }
+        (kept, rejected)
+    }
+}
+
+impl Event {
+    /// Allocates the seats of the slot by deferred acceptance. Unlike the greedy allocation, nobody ends
+    /// up in a worse session while an applicant the session ranked lower got a seat there. If the slot's
+    /// participant cap is reached, the best ranked placements are kept and everyone else waits.
+    pub fn allocate_stable_in_slot(&mut self, index: usize, counts: &mut PriorityCounts, strategy: &dyn ScoringStrategy) {
+        let at_daily_limit = self.participants_at_daily_limit(index);
+        let categories: HashMap<Uuid, InvitationCategory> = self.participants.values().map(|p| (p.uuid, p.category)).collect();
+        let slot = &self.slots[index];
+        let seated: HashSet<Uuid> = slot.sessions.iter().flat_map(|s| s.participants.iter().copied()).collect();
+
+        let mut proposals: HashMap<Uuid, VecDeque<&Application>> = HashMap::new();
+        for application in slot.sessions.iter().flat_map(|s| &s.applications) {
+            if !at_daily_limit.contains(&application.participant) && !seated.contains(&application.participant) {
+                proposals.entry(application.participant).or_default().push_back(application);
+            }
+        }
+        for list in proposals.values_mut() {
+            list.make_contiguous().sort_by(|a, b| proposal_order(a).cmp(&proposal_order(b)));
+        }
+        let mut free: Vec<Uuid> = proposals.keys().copied().collect();
+        free.sort();
+        let mut free = VecDeque::from(free);
+        let mut held: HashMap<Uuid, Vec<Application>> = HashMap::new();
+        while let Some(participant_id) = free.pop_front() {
+            let Some(application) = proposals.get_mut(&participant_id).and_then(|list| list.pop_front()) else { continue; };
+            let Some(session) = slot.session_by_id(application.session_uuid) else { continue; };
+            let mut candidates = held.remove(&session.uuid).unwrap_or_default();
+            candidates.push(application.clone());
+            let (kept, rejected) = session.choose(candidates, &categories, &self.exclusions);
+            free.extend(rejected.into_iter().map(|a| a.participant));
+            held.insert(session.uuid, kept);
+        }
+
+        let mut placed: Vec<Application> = held.into_values().flatten().collect();
+        placed.sort_by(|a, b| b.cmp(a));
+        let cap = slot.max_participants.map_or(placed.len(), |max| max.saturating_sub(slot.allocated_count()));
+        let overflow = placed.split_off(cap.min(placed.len()));
+        let waiting = if overflow.is_empty() {
+            Vec::new()
+        } else {
+            println!("Slot {} reached its participant cap, remaining applicants go to the waiting list.", slot.name);
+            let placed_ids: HashSet<Uuid> = placed.iter().map(|a| a.participant).chain(seated).collect();
+            unplaced_applicants(slot, &placed_ids)
+        };
+        self.seat_placed(index, placed, waiting, counts, strategy);
+    }
+
+    /// Allocates the seats of the slot with the fewest and mildest disappointments overall: as many
+    /// applicants as possible get a seat, then as many as possible get a better preference level,
+    /// and among equal levels the better ranked applicant wins. Reserved seats go to their category
+    /// first. Exclusions can't be expressed in the flow, so the lower ranked of two participants kept
+    /// apart loses the seat afterwards.
+    pub fn allocate_optimal_in_slot(&mut self, index: usize, counts: &mut PriorityCounts, strategy: &dyn ScoringStrategy) {
+        let at_daily_limit = self.participants_at_daily_limit(index);
+        let categories: HashMap<Uuid, InvitationCategory> = self.participants.values().map(|p| (p.uuid, p.category)).collect();
+        let category_of = |pid: &Uuid| categories.get(pid).copied().unwrap_or_default();
+        let slot = &self.slots[index];
+        let seated: HashSet<Uuid> = slot.sessions.iter().flat_map(|s| s.participants.iter().copied()).collect();
+        let applications: Vec<&Application> = slot.sessions.iter().flat_map(|s| &s.applications)
+            .filter(|a| !at_daily_limit.contains(&a.participant) && !seated.contains(&a.participant))
+            .collect();
+        let max_points = applications.iter().filter_map(|a| a.calculated_points).max().unwrap_or(0) as i64;
+        // Preference levels outweigh points, reserved seats outweigh both
+        let cost = |a: &Application| dissatisfaction(a) as i64 * (max_points + 1) + max_points - a.calculated_points.unwrap_or(0) as i64;
+        let spill_cost = 5 * (max_points + 1);
+
+        let mut graph = FlowGraph::default();
+        let source = graph.add_node();
+        let sink = graph.add_node();
+        let hub = graph.add_node();
+        let remaining = slot.max_participants.map_or(UNLIMITED, |max| max.saturating_sub(slot.allocated_count()) as i64);
+        graph.add_edge(source, hub, remaining, 0);
+        // Applicants enter a session through the node of their category's reserved seats, if it has any
+        let mut entries: HashMap<(Uuid, Option<InvitationCategory>), usize> = HashMap::new();
+        for session in &slot.sessions {
+            let mut in_category: HashMap<InvitationCategory, usize> = HashMap::new();
+            for pid in &session.participants {
+                *in_category.entry(category_of(pid)).or_default() += 1;
+            }
+            let free = session.seats.saturating_sub(session.participants.len());
+            let mut reserved_free = 0;
+            let open = graph.add_node();
+            entries.insert((session.uuid, None), open);
+            for category in CATEGORIES {
+                let reserved = session.category_seats.reserved_for(category).saturating_sub(in_category.get(&category).copied().unwrap_or(0)).min(free - reserved_free);
+                if reserved == 0 {
+                    continue;
+                }
+                reserved_free += reserved;
+                let node = graph.add_node();
+                graph.add_edge(node, sink, reserved as i64, 0);
+                graph.add_edge(node, open, UNLIMITED, 0);
+                graph.add_edge(open, node, UNLIMITED, spill_cost);
+                entries.insert((session.uuid, Some(category)), node);
+            }
+            graph.add_edge(open, sink, (free - reserved_free) as i64, 0);
+        }
+        let mut participant_nodes: HashMap<Uuid, usize> = HashMap::new();
+        let mut application_edges = Vec::new();
+        for application in &applications {
+            let node = *participant_nodes.entry(application.participant).or_insert_with(|| {
+                let node = graph.add_node();
+                graph.add_edge(hub, node, 1, 0);
+                node
+            });
+            let category = category_of(&application.participant);
+            let Some(entry) = entries.get(&(application.session_uuid, Some(category))).or_else(|| entries.get(&(application.session_uuid, None))) else { continue; };
+            application_edges.push((graph.add_edge(node, *entry, 1, cost(application)), *application));
+        }
+        let flow = graph.min_cost_max_flow(source, sink);
+
+        let mut placed: Vec<Application> = application_edges.into_iter().filter(|(edge, _)| graph.flow(*edge) > 0).map(|(_, a)| a.clone()).collect();
+        placed.sort_by(|a, b| b.cmp(a));
+        let mut kept: Vec<Application> = Vec::new();
+        for application in placed {
+            let mut sharing: Vec<Uuid> = kept.iter().filter(|k| k.session_uuid == application.session_uuid).map(|k| k.participant).collect();
+            if let Some(session) = slot.session_by_id(application.session_uuid) {
+                sharing.extend(&session.participants);
+            }
+            if excluded_from(&self.exclusions, application.participant, &sharing) {
+                println!("Participant {} is kept apart from someone in session {}, leaving them without a seat.", application.participant, application.session_uuid);
+                continue;
+            }
+            kept.push(application);
+        }
+        let waiting = if flow == remaining && slot.max_participants.is_some() {
+            let placed_ids: HashSet<Uuid> = kept.iter().map(|a| a.participant).chain(seated).collect();
+            let waiting = unplaced_applicants(slot, &placed_ids);
+            if !waiting.is_empty() {
+                println!("Slot {} reached its participant cap, remaining applicants go to the waiting list.", slot.name);
+            }
+            waiting
+        } else {
+            Vec::new()
+        };
+        self.seat_placed(index, kept, waiting, counts, strategy);
+    }
+
+    /// Seats the placed applications, puts the given participants on the slot's waiting list and
+    /// drops all other applications of the slot
+    fn seat_placed(&mut self, index: usize, placed: Vec<Application>, waiting: Vec<Uuid>, counts: &mut PriorityCounts, strategy: &dyn ScoringStrategy) {
+        let compensation = self.compensation;
+        let slot = &mut self.slots[index];
+        slot.waiting_list = waiting;
+        for session in slot.sessions.iter_mut() {
+            session.applications.clear();
+        }
+        for application in placed {
+            let Some(session) = slot.session_by_id_mut(application.session_uuid) else { continue; };
+            session.assign_participant(application.participant);
+            counts.add(&application.priority);
+            println!("Added participant {} with {:?} points and priority {:?} to session {}.", application.participant, application.calculated_points, application.priority, session.name);
+            // A worse assignment improves the chances in later slots and events
+            if let Some(participant) = self.participants.get_mut(&application.participant) {
+                participant.points_from_previous_rounds = compensation.carry(participant.points_from_previous_rounds, strategy.compensation(&application.priority));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::backend::data::{Invitation, InvitationCategory, Session, Slot};
+
+    const RANKS: [ApplicationPriority; 3] = [ApplicationPriority::FirstPreference, ApplicationPriority::SecondPreference, ApplicationPriority::ThirdPreference];
+
+    /// Deterministic pseudo-random numbers, so failing instances can be reproduced
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((self.0 >> 33) % n as u64) as usize
+        }
+    }
+
+    /// One slot with the given seats per session. Every participant ranks sessions by index in
+    /// the given order and carries the given points.
+    fn single_slot_event(seats: &[usize], rankings: &[Vec<usize>], points: &[usize]) -> (Event, Vec<Uuid>, Vec<Uuid>) {
+        let mut ev = Event::new("Test".to_string(), None);
+        let mut slot = Slot::new("Slot".to_string(), None);
+        for (i, seats) in seats.iter().enumerate() {
+            slot.sessions.push(Session::new(format!("Session {}", i), None, *seats));
+        }
+        let sessions: Vec<Uuid> = slot.sessions.iter().map(|s| s.uuid).collect();
+        let mut participants = Vec::new();
+        for (ranking, points) in rankings.iter().zip(points) {
+            let mut participant = Invitation::new("CODE".to_string(), ev.uuid, vec![], InvitationCategory::default()).new_participant();
+            participant.points_from_previous_rounds = *points;
+            for (rank, session) in ranking.iter().enumerate() {
+                slot.insert_application(sessions[*session], participant.uuid, RANKS[rank].clone());
+            }
+            participants.push(participant.uuid);
+            ev.participants.insert(participant.uuid, participant);
+        }
+        ev.slots = vec![slot];
+        (ev, participants, sessions)
+    }
+
+    /// Seated participants and the sum of their ranks of the seat, counting from 0 for first choices
+    fn outcome(ev: &Event, participants: &[Uuid], sessions: &[Uuid], rankings: &[Vec<usize>]) -> (usize, usize) {
+        let mut seated = 0;
+        let mut dissatisfaction = 0;
+        for (pid, ranking) in participants.iter().zip(rankings) {
+            let Some(session) = ev.slots[0].sessions.iter().find(|s| s.participants.contains(pid)) else { continue; };
+            let index = sessions.iter().position(|id| *id == session.uuid).unwrap();
+            seated += 1;
+            dissatisfaction += ranking.iter().position(|s| *s == index).unwrap();
+        }
+        (seated, dissatisfaction)
+    }
+
+    fn distribute(ev: &Event, mode: AllocationMode) -> Event {
+        let mut ev = ev.clone();
+        ev.allocation = mode;
+        ev.distribute("test");
+        ev
+    }
+
+    fn random_instance(rng: &mut Lcg) -> (Vec<usize>, Vec<Vec<usize>>, Vec<usize>) {
+        let seats: Vec<usize> = (0..3 + rng.below(2)).map(|_| 1 + rng.below(3)).collect();
+        let rankings = (0..4 + rng.below(6))
+            .map(|_| {
+                let mut sessions: Vec<usize> = (0..seats.len()).collect();
+                for i in (1..sessions.len()).rev() {
+                    sessions.swap(i, rng.below(i + 1));
+                }
+                sessions.truncate(1 + rng.below(3));
+                sessions
+            })
+            .collect::<Vec<_>>();
+        let points = rankings.iter().map(|_| rng.below(20)).collect();
+        (seats, rankings, points)
+    }
+
+    #[test]
+    fn optimal_assignment_is_never_worse_than_greedy() {
+        let mut rng = Lcg(1);
+        for _ in 0..200 {
+            let (seats, rankings, points) = random_instance(&mut rng);
+            let (ev, participants, sessions) = single_slot_event(&seats, &rankings, &points);
+            let (greedy_seated, greedy_dissatisfaction) = outcome(&distribute(&ev, AllocationMode::Greedy), &participants, &sessions, &rankings);
+            let (optimal_seated, optimal_dissatisfaction) = outcome(&distribute(&ev, AllocationMode::OptimalAssignment), &participants, &sessions, &rankings);
+            assert!(optimal_seated >= greedy_seated, "{:?} {:?}", seats, rankings);
+            if optimal_seated == greedy_seated {
+                assert!(optimal_dissatisfaction <= greedy_dissatisfaction, "{:?} {:?}", seats, rankings);
+            }
+        }
+    }
+
+    #[test]
+    fn optimal_assignment_never_exceeds_capacity() {
+        let mut rng = Lcg(2);
+        for _ in 0..200 {
+            let (seats, rankings, points) = random_instance(&mut rng);
+            let (mut ev, _, _) = single_slot_event(&seats, &rankings, &points);
+            let cap = 1 + rng.below(seats.iter().sum::<usize>());
+            ev.slots[0].max_participants = Some(cap);
+            let ev = distribute(&ev, AllocationMode::OptimalAssignment);
+            let slot = &ev.slots[0];
+            assert!(slot.sessions.iter().all(|s| s.participants.len() <= s.seats));
+            assert!(slot.allocated_count() <= cap);
+        }
+    }
+
+    #[test]
+    fn optimal_assignment_never_seats_excluded_pairs_together() {
+        let mut rng = Lcg(3);
+        for _ in 0..200 {
+            let (seats, rankings, points) = random_instance(&mut rng);
+            let (mut ev, participants, _) = single_slot_event(&seats, &rankings, &points);
+            for _ in 0..2 {
+                let (first, second) = (participants[rng.below(participants.len())], participants[rng.below(participants.len())]);
+                let _ = ev.add_exclusion(first, second, "test");
+            }
+            let ev = distribute(&ev, AllocationMode::OptimalAssignment);
+            assert!(ev.exclusion_violations().is_empty(), "{:?}", ev.exclusion_violations());
+        }
+    }
+
+    #[test]
+    fn optimal_assignment_matches_brute_force_on_three_by_three() {
+        let permutations = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+        for a in permutations {
+            for b in permutations {
+                for c in permutations {
+                    let rankings = vec![a.to_vec(), b.to_vec(), c.to_vec()];
+                    let (ev, participants, sessions) = single_slot_event(&[1, 1, 1], &rankings, &[0, 7, 3]);
+                    let best = permutations.iter()
+                        .map(|seating| rankings.iter().zip(seating).map(|(ranking, session)| ranking.iter().position(|s| s == session).unwrap()).sum::<usize>())
+                        .min()
+                        .unwrap();
+                    let result = outcome(&distribute(&ev, AllocationMode::OptimalAssignment), &participants, &sessions, &rankings);
+                    assert_eq!(result, (3, best), "{:?}", rankings);
+                }
+            }
+        }
+    }
+}
@@ -1,3 +1,46 @@
 pub mod auth;
 pub mod state;
-pub mod data;
\ No newline at end of file
+pub mod data;
+pub mod config;
+pub mod mail;
+pub mod mail_templates;
+pub mod scheduler;
+pub mod rate_limit;
+pub mod error;
+pub mod push;
+pub mod validation;
+pub mod identity;
+pub mod scoring;
+pub mod directory;
+pub mod event_template;
+pub mod progress;
+pub mod client_ip;
+pub mod body_limit;
+pub mod personal_data;
+pub mod quality;
+pub mod confirmation;
+pub mod timeline;
+pub mod rooms;
+pub mod snapshot;
+pub mod waiting_room;
+pub mod exclusions;
+pub mod webhook;
+pub mod solver;
+pub mod splitting;
+pub mod completion;
+pub mod read_only;
+pub mod matching;
+pub mod flow;
+pub mod diagnostics;
+pub mod concurrency;
+pub mod seat_batch;
+pub mod waitlist;
+pub mod duplicates;
+pub mod share;
+pub mod co_admins;
+pub mod schedule_changes;
+pub mod delivery;
+pub mod no_preference;
+pub mod pins;
+pub mod content;
+pub mod verification;
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::backend::data::{Event, InvitationCategory};
+use crate::backend::exclusions::excluded_from;
+
+/// What the distribution does with participants who ranked no session of a slot, selected per event
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, FromFormField)]
+pub enum NoPreferencePolicy {
+    /// After everyone with preferences is placed, they get a seat in the emptiest session they may attend
+    #[default]
+    FillEmptiest,
+    /// They get no seat, admins can still place them by hand
+    LeaveUnassigned,
+    /// They don't take part in the slot and don't count as unassigned
+    ExcludeFromSlot,
+}
+
+/// Participants without preferences per outcome, summed over all slots of a distribution run
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct NoPreferenceCounts {
+    /// Placed into the emptiest session
+    pub filled: usize,
+    /// Left without a seat, by policy or because no session had room for them
+    pub unassigned: usize,
+    /// Kept out of the slot
+    pub excluded: usize,
+}
+
+impl Event {
+    /// Takes the participants who ranked no session of the slot out of its allocation, withdrawing
+    /// their unranked applications. Participants awaiting verification or already seated are left alone,
+    /// so are entries created by merely opening the event page, without a name or any preference.
+    pub fn hold_back_without_preferences(&mut self, index: usize) -> Vec<Uuid> {
+        let slot = &self.slots[index];
+        let has_preferences = |pid: Uuid| self.slots.iter().flat_map(|slot| slot.sessions.iter()).flat_map(|s| s.applications.iter())
+            .any(|a| a.participant == pid && a.priority.rank().is_some());
+        let mut held_back: Vec<Uuid> = self.participants.values()
+            .filter(|p| !p.awaiting_verification)
+            .filter(|p| !p.name.trim().is_empty() || has_preferences(p.uuid))
+            .map(|p| p.uuid)
+            .filter(|pid| !slot.sessions.iter().any(|s| s.participants.contains(pid)))
+            .filter(|pid| !slot.sessions.iter().flat_map(|s| s.applications.iter()).any(|a| a.participant == *pid && a.priority.rank().is_some()))
+            .collect();
+        held_back.sort();
+        let slot = &mut self.slots[index];
+        slot.without_preferences.clear();
+        for pid in &held_back {
+            slot.remove_applications(*pid);
+        }
+        held_back
+    }
+
+    /// Applies the event's policy to the participants held back from the slot. Filled seats are
+    /// neither compensated nor counted as a preference level of the run.
+    pub fn place_without_preferences(&mut self, index: usize, mut participants: Vec<Uuid>, counts: &mut NoPreferenceCounts) {
+        match self.no_preference_policy {
+            NoPreferencePolicy::ExcludeFromSlot => {
+                counts.excluded += participants.len();
+                return;
+            }
+            NoPreferencePolicy::LeaveUnassigned => {
+                counts.unassigned += participants.len();
+                self.slots[index].without_preferences = participants;
+                return;
+            }
+            NoPreferencePolicy::FillEmptiest => {}
+        }
+        // Participants who lost out in earlier rounds choose first
+        participants.sort_by_key(|pid| (std::cmp::Reverse(self.participants.get(pid).map_or(0, |p| p.points_from_previous_rounds)), *pid));
+        let at_daily_limit = self.participants_at_daily_limit(index);
+        let categories: HashMap<Uuid, InvitationCategory> = self.participants.values().map(|p| (p.uuid, p.category)).collect();
+        let repeats = self.repeated_content(index);
+        let exclusions = &self.exclusions;
+        let slot = &mut self.slots[index];
+        let mut left_over = Vec::new();
+        for pid in participants {
+            let Some(participant) = self.participants.get(&pid) else { continue; };
+            if slot.is_full() || at_daily_limit.contains(&pid) {
+                left_over.push(pid);
+                continue;
+            }
+            let emptiest = slot.sessions.iter_mut()
+                .filter(|s| s.has_free_seat() && s.is_eligible(participant) && !s.is_avoided_by(pid) && s.is_acknowledged_by(pid))
+                .filter(|s| s.has_seat_for(participant.category, &categories) && !excluded_from(exclusions, pid, &s.participants))
+                // Content the participant already got elsewhere only if nothing else is left
+                .min_by(|a, b| repeats.contains(&(a.uuid, pid)).cmp(&repeats.contains(&(b.uuid, pid)))
+                    .then((a.participants.len() * b.seats).cmp(&(b.participants.len() * a.seats))));
+            match emptiest {
+                Some(session) => {
+                    session.assign_participant(pid);
+                    println!("Added participant {} without preferences to session {}.", pid, session.name);
+                    counts.filled += 1;
+                }
+                None => left_over.push(pid),
+            }
+        }
+        if !left_over.is_empty() {
+            println!("{} participant(s) without preferences found no free seat in slot {}.", left_over.len(), slot.name);
+        }
+        counts.unassigned += left_over.len();
+        slot.without_preferences = left_over;
+    }
+}
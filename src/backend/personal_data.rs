@@ -0,0 +1,170 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::backend::data::{ApplicationPriority, InvitationCategory, Storage};
+use crate::backend::error::BackendError;
+use crate::backend::timeline::InvitationActivity;
+use crate::gui::export::csv_line;
+
+/// Everything stored about the holder of an invitation code, for answering access requests
+#[derive(Serialize)]
+pub struct PersonalData {
+    pub exported_at: DateTime<Utc>,
+    pub code: String,
+    pub event_id: Uuid,
+    pub event_name: String,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    /// Address linking the registration to other events, if confirmed
+    pub linked_email: Option<String>,
+    pub category: InvitationCategory,
+    pub tags: Vec<String>,
+    pub needs_accessible_room: bool,
+    pub locale: Option<String>,
+    pub points_from_previous_rounds: Option<usize>,
+    pub deadline_extension: Option<DateTime<Utc>>,
+    pub push_subscriptions: usize,
+    /// Position on the event's waiting list
+    pub waiting_since: Option<DateTime<Utc>>,
+    pub preferences: Vec<PersonalPreference>,
+    pub assignments: Vec<PersonalAssignment>,
+    /// Slots whose waiting list includes the participant
+    pub slot_waiting_lists: Vec<String>,
+    pub seat_changes: Vec<PersonalSeatChange>,
+    pub feedback: Vec<PersonalFeedback>,
+    pub activity: Vec<InvitationActivity>,
+}
+
+#[derive(Serialize)]
+pub struct PersonalPreference {
+    pub slot: String,
+    pub session: String,
+    pub priority: ApplicationPriority,
+    pub calculated_points: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct PersonalAssignment {
+    pub slot: String,
+    pub session: String,
+}
+
+/// Seat moved by an admin. The admin isn't named, that's not the participant's data.
+#[derive(Serialize)]
+pub struct PersonalSeatChange {
+    pub slot: String,
+    pub from: String,
+    /// None if the seat was taken away
+    pub to: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct PersonalFeedback {
+    pub session: String,
+    pub rating: u8,
+    pub comment: Option<String>,
+    pub submitted_at: DateTime<Utc>,
+}
+
+impl Storage {
+    /// Collects the data stored about the holder of the invitation code
+    pub fn personal_data(&self, code: &str) -> Result<PersonalData, BackendError> {
+        let inv = self.invitations_codes.get(code).ok_or(BackendError::NotFound("invitation"))?;
+        let ev = self.events.get(&inv.event_id).ok_or(BackendError::NotFound("event"))?;
+        let participant = inv.participant_id.and_then(|pid| ev.participants.get(&pid));
+        let mut data = PersonalData {
+            exported_at: Utc::now(),
+            code: code.to_string(),
+            event_id: ev.uuid,
+            event_name: ev.name.clone(),
+            name: participant.map(|p| p.name.clone()).or_else(|| inv.name.clone()),
+            email: inv.email.clone(),
+            linked_email: inv.person_id.and_then(|id| self.people.get(&id)).map(|person| person.email.clone()),
+            category: inv.category,
+            tags: participant.map(|p| p.tags.clone()).unwrap_or_else(|| inv.tags.clone()),
+            needs_accessible_room: participant.map(|p| p.needs_accessible_room).unwrap_or(inv.needs_accessible_room),
+            locale: participant.and_then(|p| p.locale.clone()),
+            points_from_previous_rounds: participant.map(|p| p.points_from_previous_rounds),
+            deadline_extension: inv.deadline_extension,
+            push_subscriptions: inv.push_subscriptions.len(),
+            waiting_since: ev.waiting_list.iter().find(|entry| entry.code == code).map(|entry| entry.joined_at),
+            preferences: Vec::new(),
+            assignments: Vec::new(),
+            slot_waiting_lists: Vec::new(),
+            seat_changes: Vec::new(),
+            feedback: Vec::new(),
+            activity: inv.activity.clone(),
+        };
+        let Some(pid) = inv.participant_id else { return Ok(data); };
+        let session_name = |id: Uuid| ev.session_by_id(id).map(|s| s.name.clone()).unwrap_or_default();
+        for slot in &ev.slots {
+            if slot.waiting_list.contains(&pid) {
+                data.slot_waiting_lists.push(slot.name.clone());
+            }
+            for sess in &slot.sessions {
+                for application in sess.applications.iter().filter(|a| a.participant == pid) {
+                    data.preferences.push(PersonalPreference { slot: slot.name.clone(), session: sess.name.clone(), priority: application.priority.clone(), calculated_points: application.calculated_points });
+                }
+                if sess.participants.contains(&pid) {
+                    data.assignments.push(PersonalAssignment { slot: slot.name.clone(), session: sess.name.clone() });
+                }
+            }
+        }
+        data.seat_changes = ev.seat_changes.iter().filter(|change| change.participant_id == pid)
+            .map(|change| PersonalSeatChange {
+                slot: ev.slot_by_id(change.slot_id).map(|s| s.name.clone()).unwrap_or_default(),
+                from: session_name(change.from_session),
+                to: change.to_session.map(session_name),
+                at: change.at,
+            })
+            .collect();
+        data.feedback = ev.feedback.iter().filter(|f| f.participant_id == pid)
+            .map(|f| PersonalFeedback { session: session_name(f.session_id), rating: f.rating, comment: f.comment.clone(), submitted_at: f.submitted_at })
+            .collect();
+        Ok(data)
+    }
+}
+
+impl PersonalData {
+    /// One line per stored value: section, item and value
+    pub fn to_csv(&self) -> String {
+        let mut out = csv_line(&["section", "item", "value"]);
+        let mut row = |section: &str, item: &str, value: String| out.push_str(&csv_line(&[section, item, value.as_str()]));
+        row("export", "exported_at", self.exported_at.to_rfc3339());
+        row("profile", "code", self.code.clone());
+        row("profile", "event", self.event_name.clone());
+        row("profile", "name", self.name.clone().unwrap_or_default());
+        row("profile", "email", self.email.clone().unwrap_or_default());
+        row("profile", "linked_email", self.linked_email.clone().unwrap_or_default());
+        row("profile", "category", self.category.as_tag().to_string());
+        row("profile", "tags", self.tags.join(", "));
+        row("profile", "needs_accessible_room", self.needs_accessible_room.to_string());
+        row("profile", "locale", self.locale.clone().unwrap_or_default());
+        row("profile", "points_from_previous_rounds", self.points_from_previous_rounds.map(|p| p.to_string()).unwrap_or_default());
+        row("profile", "deadline_extension", self.deadline_extension.map(|d| d.to_rfc3339()).unwrap_or_default());
+        row("profile", "push_subscriptions", self.push_subscriptions.to_string());
+        row("profile", "waiting_since", self.waiting_since.map(|d| d.to_rfc3339()).unwrap_or_default());
+        for p in &self.preferences {
+            let points = p.calculated_points.map(|points| format!(" ({} points)", points)).unwrap_or_default();
+            row("preference", &format!("{} / {}", p.slot, p.session), format!("{:?}{}", p.priority, points));
+        }
+        for a in &self.assignments {
+            row("assignment", &a.slot, a.session.clone());
+        }
+        for slot in &self.slot_waiting_lists {
+            row("slot_waiting_list", slot, "waiting".to_string());
+        }
+        for change in &self.seat_changes {
+            row("seat_change", &change.slot, format!("{} -> {} at {}", change.from, change.to.as_deref().unwrap_or("no seat"), change.at.to_rfc3339()));
+        }
+        for f in &self.feedback {
+            row("feedback", &f.session, format!("{}/5 {}", f.rating, f.comment.as_deref().unwrap_or_default()).trim().to_string());
+        }
+        for a in &self.activity {
+            row("activity", &a.at.to_rfc3339(), serde_json::to_string(&a.kind).expect("activity is serializable"));
+        }
+        out
+    }
+}
@@ -0,0 +1,59 @@
+use uuid::Uuid;
+
+use crate::backend::data::{Event, EventState};
+use crate::backend::error::BackendError;
+
+impl Event {
+    /// Reserves a seat in the session for the participant, e.g. a speaker or helper. The distribution
+    /// seats pinned participants first and ignores their applications in the slot. A pin in another
+    /// session of the same slot is moved.
+    pub fn pin_participant(&mut self, slot_id: Uuid, session_id: Uuid, participant_id: Uuid) -> Result<(), BackendError> {
+        if self.is_distributed() || self.state == EventState::AssigningSeats {
+            return Err(BackendError::conflict("Seats can only be pinned before the distribution."));
+        }
+        if !self.participants.contains_key(&participant_id) {
+            return Err(BackendError::NotFound("participant"));
+        }
+        let slot = self.slot_by_id_mut(slot_id)?;
+        let session = slot.session_by_id(session_id).ok_or(BackendError::NotFound("session"))?;
+        if session.is_pinned(participant_id) {
+            return Err(BackendError::conflict("The participant is already pinned to this session."));
+        }
+        if session.pinned.len() >= session.seats {
+            return Err(BackendError::conflict(format!("All {} seats of {} are pinned already.", session.seats, session.name)));
+        }
+        for session in slot.sessions.iter_mut() {
+            session.pinned.retain(|p| *p != participant_id);
+        }
+        slot.session_by_id_mut(session_id).expect("checked above").pinned.push(participant_id);
+        Ok(())
+    }
+
+    pub fn unpin_participant(&mut self, slot_id: Uuid, participant_id: Uuid) -> Result<(), BackendError> {
+        let slot = self.slot_by_id_mut(slot_id)?;
+        let session = slot.sessions.iter_mut().find(|s| s.is_pinned(participant_id)).ok_or(BackendError::NotFound("pinned seat"))?;
+        session.pinned.retain(|p| *p != participant_id);
+        Ok(())
+    }
+
+    /// Seats the pinned participants of all slots before the allocation and withdraws their
+    /// applications in those slots, so the remaining seats go to everyone else. Seating them up front
+    /// lets the daily session limit count pinned seats of later slots, too. Like everywhere in the
+    /// distribution, participants awaiting verification are left out.
+    pub fn seat_pinned_participants(&mut self) {
+        let participants = &self.participants;
+        for slot in self.slots.iter_mut() {
+            let mut seated = Vec::new();
+            for session in slot.sessions.iter_mut() {
+                let pinned: Vec<Uuid> = session.pinned.iter().copied().filter(|pid| participants.get(pid).is_some_and(|p| !p.awaiting_verification)).collect();
+                for pid in pinned {
+                    session.assign_participant(pid);
+                    seated.push(pid);
+                }
+            }
+            for pid in seated {
+                slot.remove_applications(pid);
+            }
+        }
+    }
+}
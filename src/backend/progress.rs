@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::backend::data::{DistributionStep, Event, Storage};
+use crate::backend::state::{RecoverLock, Shared};
+
+/// State of a distribution running in the background, shown as progress bar to admins
+#[derive(Debug, Clone, Serialize)]
+pub struct DistributionProgress {
+    pub slots_done: usize,
+    pub slots_total: usize,
+    pub participants_placed: usize,
+    pub percent: usize,
+    /// Estimated remaining time, once the first slot is done
+    pub eta_seconds: Option<u64>,
+    pub finished: bool,
+    /// Why the distribution didn't complete
+    pub error: Option<String>,
+    #[serde(skip)]
+    started: Instant,
+}
+
+impl DistributionProgress {
+    pub fn new(slots_total: usize) -> Self {
+        DistributionProgress { slots_done: 0, slots_total, participants_placed: 0, percent: 0, eta_seconds: None, finished: false, error: None, started: Instant::now() }
+    }
+
+    fn update(&mut self, step: DistributionStep) {
+        self.slots_done = step.slots_done;
+        self.slots_total = step.slots_total;
+        self.participants_placed = step.participants_placed;
+        self.percent = (step.slots_done * 100).checked_div(step.slots_total).unwrap_or(100);
+        let per_slot = self.started.elapsed().as_secs_f64() / step.slots_done.max(1) as f64;
+        self.eta_seconds = Some((per_slot * step.slots_total.saturating_sub(step.slots_done) as f64).ceil() as u64);
+    }
+
+    fn finish(&mut self, error: Option<String>) {
+        self.finished = true;
+        self.eta_seconds = Some(0);
+        if error.is_none() {
+            self.percent = 100;
+        }
+        self.error = error;
+    }
+}
+
+/// Distributes the seats of an event prepared with [`Storage::begin_distribution`] on a blocking
+/// thread, updating its entry in `progress` after each slot. The result is stored once done; if the
+/// distribution fails, the registration is reopened.
+pub fn spawn_distribution(storage: Shared<Storage>, progress: Shared<HashMap<Uuid, DistributionProgress>>, mut ev: Event, initiated_by: String) {
+    let event_id = ev.uuid;
+    progress.write_unpoisoned().insert(event_id, DistributionProgress::new(ev.slots.len()));
+    let reporter = progress.clone();
+    let distribution = tokio::task::spawn_blocking(move || {
+        let run = ev.distribute_reporting(&initiated_by, |step| {
+            if let Some(entry) = reporter.write_unpoisoned().get_mut(&event_id) {
+                entry.update(step);
+            }
+        });
+        println!("Distribution of event {} by {} took {} ms, {} seats assigned.", event_id, initiated_by, run.duration_ms, run.assigned_seats);
+        ev
+    });
+    tokio::spawn(async move {
+        let result = distribution.await;
+        let error = {
+            let mut storage = storage.write_unpoisoned();
+            match result {
+                Ok(ev) => storage.finish_distribution(ev).err().map(|e| e.to_string()),
+                Err(e) => {
+                    eprintln!("Distribution of event {} failed: {}", event_id, e);
+                    storage.abort_distribution(event_id);
+                    Some("The distribution failed, the registration was reopened.".to_string())
+                }
+            }
+        };
+        if let Some(entry) = progress.write_unpoisoned().get_mut(&event_id) {
+            entry.finish(error);
+        }
+    });
+}
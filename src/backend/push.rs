@@ -0,0 +1,244 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes128Gcm, Nonce};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hkdf::Hkdf;
+use p256::ecdh::EphemeralSecret;
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::{PublicKey, SecretKey};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::backend::data::{EventState, Storage};
+use crate::backend::state::{RecoverLock, Shared};
+
+/// Browsers kept per invitation, older subscriptions are dropped
+const MAX_SUBSCRIPTIONS_PER_INVITATION: usize = 5;
+/// Record size announced in the aes128gcm header (RFC 8188)
+const RECORD_SIZE: u32 = 4096;
+
+/// Push subscription of a participant's browser (see the Push API's PushSubscription)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PushSubscription {
+    pub endpoint: String,
+    /// Public key of the browser, base64url encoded
+    pub p256dh: String,
+    /// Authentication secret, base64url encoded
+    pub auth: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushMessage {
+    pub uuid: Uuid,
+    pub subscription: PushSubscription,
+    pub title: String,
+    pub body: String,
+    /// Page opened when the notification is clicked
+    pub url: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Payload read by the service worker
+#[derive(Serialize)]
+struct PushPayload<'a> {
+    title: &'a str,
+    body: &'a str,
+    url: &'a str,
+}
+
+impl Storage {
+    /// Generates the VAPID key pair identifying this instance to push services, if there is none yet
+    pub fn ensure_vapid_key(&mut self) {
+        if self.vapid_private_key.is_none() {
+            let key = SecretKey::random(&mut OsRng);
+            self.vapid_private_key = Some(URL_SAFE_NO_PAD.encode(key.to_bytes()));
+        }
+    }
+
+    fn vapid_signing_key(&self) -> Option<SigningKey> {
+        let bytes = URL_SAFE_NO_PAD.decode(self.vapid_private_key.as_ref()?).ok()?;
+        SigningKey::from_slice(&bytes).ok()
+    }
+
+    /// Public VAPID key in the form expected by `PushManager.subscribe`
+    pub fn vapid_public_key(&self) -> Option<String> {
+        let key = self.vapid_signing_key()?;
+        Some(URL_SAFE_NO_PAD.encode(key.verifying_key().to_encoded_point(false).as_bytes()))
+    }
+
+    pub fn add_push_subscription(&mut self, code: &str, subscription: PushSubscription) -> bool {
+        let Some(inv) = self.invitations_codes.get_mut(code) else { return false; };
+        inv.push_subscriptions.retain(|s| s.endpoint != subscription.endpoint);
+        inv.push_subscriptions.push(subscription);
+        let excess = inv.push_subscriptions.len().saturating_sub(MAX_SUBSCRIPTIONS_PER_INVITATION);
+        inv.push_subscriptions.drain(..excess);
+        true
+    }
+
+    pub fn remove_push_subscription(&mut self, endpoint: &str) {
+        for inv in self.invitations_codes.values_mut() {
+            inv.push_subscriptions.retain(|s| s.endpoint != endpoint);
+        }
+    }
+
+    /// Queues a notification for every subscribed browser of the event's participants
+    pub fn queue_push_for_event(&mut self, event_id: Uuid, title: &str, body: &str) -> usize {
+        let messages: Vec<PushMessage> = self.invitations_codes.values()
+            .filter(|inv| inv.event_id == event_id)
+            .flat_map(|inv| inv.push_subscriptions.iter().map(move |sub| PushMessage {
+                uuid: Uuid::new_v4(),
+                subscription: sub.clone(),
+                title: title.to_string(),
+                body: body.to_string(),
+                url: format!("/invitation/{}", inv.code),
+                created_at: Utc::now(),
+            }))
+            .collect();
+        let count = messages.len();
+        self.push_queue.extend(messages);
+        count
+    }
+
+    /// Queues a notification for every subscribed browser of the invitation's holder
+    pub fn queue_push_for_invitation(&mut self, code: &str, title: &str, body: &str) -> usize {
+        let Some(inv) = self.invitations_codes.get(code) else { return 0; };
+        let messages: Vec<PushMessage> = inv.push_subscriptions.iter().map(|sub| PushMessage {
+            uuid: Uuid::new_v4(),
+            subscription: sub.clone(),
+            title: title.to_string(),
+            body: body.to_string(),
+            url: format!("/invitation/{}", inv.code),
+            created_at: Utc::now(),
+        }).collect();
+        let count = messages.len();
+        self.push_queue.extend(messages);
+        count
+    }
+
+    /// Notifies participants about state changes they care about (registration opened, results published)
+    pub fn queue_state_push(&mut self, event_id: Uuid) {
+        let Some(ev) = self.events.get(&event_id) else { return; };
+        let name = ev.name.clone();
+        match ev.state {
+            EventState::OpenForRegistration => {
+                self.queue_push_for_event(event_id, &name, "Registration is open. Choose your sessions now!");
+            }
+            EventState::Published => {
+                self.queue_push_for_event(event_id, &name, "The results are out. See which sessions you got!");
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Encrypts the payload for the subscription as described in RFC 8291 (aes128gcm content encoding)
+fn encrypt_payload(subscription: &PushSubscription, payload: &[u8]) -> Result<Vec<u8>, String> {
+    let ua_public_bytes = URL_SAFE_NO_PAD.decode(&subscription.p256dh).map_err(|e| format!("invalid p256dh: {}", e))?;
+    let ua_public = PublicKey::from_sec1_bytes(&ua_public_bytes).map_err(|e| format!("invalid p256dh: {}", e))?;
+    let auth_secret = URL_SAFE_NO_PAD.decode(&subscription.auth).map_err(|e| format!("invalid auth secret: {}", e))?;
+
+    let as_secret = EphemeralSecret::random(&mut OsRng);
+    let as_public = as_secret.public_key().to_encoded_point(false);
+    let shared = as_secret.diffie_hellman(&ua_public);
+
+    let mut key_info = b"WebPush: info\0".to_vec();
+    key_info.extend_from_slice(&ua_public_bytes);
+    key_info.extend_from_slice(as_public.as_bytes());
+    let mut ikm = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&auth_secret), shared.raw_secret_bytes())
+        .expand(&key_info, &mut ikm)
+        .map_err(|e| e.to_string())?;
+
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let hkdf = Hkdf::<Sha256>::new(Some(&salt), &ikm);
+    let mut cek = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    hkdf.expand(b"Content-Encoding: aes128gcm\0", &mut cek).map_err(|e| e.to_string())?;
+    hkdf.expand(b"Content-Encoding: nonce\0", &mut nonce).map_err(|e| e.to_string())?;
+
+    // A single record, terminated by the padding delimiter
+    let mut plaintext = payload.to_vec();
+    plaintext.push(2);
+    let cipher = Aes128Gcm::new_from_slice(&cek).map_err(|e| e.to_string())?;
+    let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), plaintext.as_ref()).map_err(|e| e.to_string())?;
+
+    let mut body = salt.to_vec();
+    body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+    body.push(as_public.as_bytes().len() as u8);
+    body.extend_from_slice(as_public.as_bytes());
+    body.extend_from_slice(&ciphertext);
+    Ok(body)
+}
+
+/// Builds the VAPID Authorization header (RFC 8292) for the push service behind the endpoint
+fn vapid_authorization(key: &SigningKey, endpoint: &str, contact: &str) -> Result<String, String> {
+    let url = reqwest::Url::parse(endpoint).map_err(|e| format!("invalid endpoint: {}", e))?;
+    let claims = serde_json::json!({
+        "aud": url.origin().ascii_serialization(),
+        "exp": (Utc::now() + chrono::Duration::hours(12)).timestamp(),
+        "sub": contact,
+    });
+    let unsigned = format!("{}.{}", URL_SAFE_NO_PAD.encode(r#"{"typ":"JWT","alg":"ES256"}"#), URL_SAFE_NO_PAD.encode(claims.to_string()));
+    let signature: Signature = key.sign(unsigned.as_bytes());
+    let public = key.verifying_key().to_encoded_point(false);
+    Ok(format!("vapid t={}.{}, k={}", unsigned, URL_SAFE_NO_PAD.encode(signature.to_bytes()), URL_SAFE_NO_PAD.encode(public.as_bytes())))
+}
+
+/// Delivers queued push notifications to the browsers' push services
+#[derive(Clone)]
+pub struct PushSender {
+    client: reqwest::Client,
+    /// Contact of the operator (mailto: or https: URL), sent to the push services
+    contact: String,
+}
+
+impl PushSender {
+    pub fn new(contact: String) -> Self {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(30)).build().expect("failed to build HTTP client");
+        PushSender { client, contact }
+    }
+
+    async fn send(&self, key: &SigningKey, message: &PushMessage) -> Result<(), (Option<u16>, String)> {
+        let payload = serde_json::to_vec(&PushPayload { title: &message.title, body: &message.body, url: &message.url }).map_err(|e| (None, e.to_string()))?;
+        let body = encrypt_payload(&message.subscription, &payload).map_err(|e| (None, e))?;
+        let authorization = vapid_authorization(key, &message.subscription.endpoint, &self.contact).map_err(|e| (None, e))?;
+        let response = self.client.post(&message.subscription.endpoint)
+            .header("Authorization", authorization)
+            .header("Content-Encoding", "aes128gcm")
+            .header("Content-Type", "application/octet-stream")
+            .header("TTL", "86400")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| (None, e.to_string()))?;
+        let status = response.status();
+        if status.is_success() { Ok(()) } else { Err((Some(status.as_u16()), status.to_string())) }
+    }
+
+    /// Sends all queued notifications. Delivery is best effort: failed messages are dropped,
+    /// subscriptions the push service reports as gone are removed.
+    pub async fn deliver_pending(&self, storage: &Shared<Storage>) {
+        let (key, messages) = {
+            let mut guard = storage.write_unpoisoned();
+            if guard.push_queue.is_empty() { return; }
+            let Some(key) = guard.vapid_signing_key() else { return; };
+            (key, std::mem::take(&mut guard.push_queue))
+        };
+        for message in messages {
+            match self.send(&key, &message).await {
+                Ok(()) => {}
+                Err((Some(404 | 410), _)) => {
+                    storage.write_unpoisoned().remove_push_subscription(&message.subscription.endpoint);
+                }
+                Err((_, e)) => eprintln!("Failed to send push notification {}: {}", message.uuid, e),
+            }
+        }
+    }
+}
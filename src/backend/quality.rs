@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::backend::data::{ApplicationPriority, Event, MAX_RANKED_CHOICES};
+
+/// Sessions filled below this share of their seats are reported
+const LOW_FILL_RATE: f64 = 0.25;
+
+/// How well a distribution met the participants' preferences, checked before the result is published
+#[derive(Debug, Clone, Serialize)]
+pub struct DistributionQuality {
+    /// 0 (poor) to 100 (everyone got their first choice, no one left out, evenly filled sessions)
+    pub score: u32,
+    /// Average satisfaction per slot a participant applied for, in percent
+    pub satisfaction: u32,
+    /// Slots participants applied for without getting a seat, summed over all participants
+    pub unassigned: usize,
+    /// Standard deviation of the session fill rates, in percentage points
+    pub fill_rate_spread: u32,
+    pub warnings: Vec<String>,
+}
+
+/// Share of the full satisfaction a seat of the given priority gives
+fn satisfaction(priority: &ApplicationPriority) -> f64 {
+    match priority {
+        ApplicationPriority::FirstPreference => 1.0,
+        ApplicationPriority::SecondPreference => 0.6,
+        ApplicationPriority::ThirdPreference => 0.3,
+        // Between a third choice and no preference, falling with the rank
+        ApplicationPriority::LowerPreference(rank) => 0.2 + 0.1 * MAX_RANKED_CHOICES.saturating_sub(*rank as usize) as f64 / (MAX_RANKED_CHOICES - 3) as f64,
+        ApplicationPriority::NoPreference => 0.2,
+    }
+}
+
+impl Event {
+    /// Rates the current assignment. Participants awaiting verification don't take part and are left out.
+    pub fn distribution_quality(&self) -> DistributionQuality {
+        let mut warnings = Vec::new();
+        let mut satisfaction_sum = 0.0;
+        let mut wishes = 0usize;
+        let mut unassigned = 0usize;
+        let mut fill_rates = Vec::new();
+        for slot in &self.slots {
+            let applicants: HashSet<Uuid> = slot.sessions.iter()
+                .flat_map(|s| s.applications.iter().map(|a| a.participant))
+                .filter(|pid| self.participants.get(pid).is_some_and(|p| !p.awaiting_verification))
+                .collect();
+            let mut without_seat = 0;
+            // Participants without preferences the policy meant to seat count like applicants
+            let unplaced = slot.without_preferences.iter()
+                .filter(|pid| !applicants.contains(pid) && !slot.sessions.iter().any(|s| s.participants.contains(pid)))
+                .filter(|pid| self.participants.get(pid).is_some_and(|p| !p.awaiting_verification))
+                .count();
+            if unplaced > 0 {
+                warnings.push(format!("{} participant(s) without preferences received no seat in slot {}.", unplaced, slot.name));
+            }
+            wishes += unplaced;
+            unassigned += unplaced;
+            for pid in &applicants {
+                wishes += 1;
+                let Some(sess) = slot.sessions.iter().find(|s| s.participants.contains(pid)) else {
+                    without_seat += 1;
+                    continue;
+                };
+                // A seat in a session the participant didn't apply for counts like one without preference
+                satisfaction_sum += sess.applications.iter().find(|a| a.participant == *pid)
+                    .map_or(satisfaction(&ApplicationPriority::NoPreference), |a| satisfaction(&a.priority));
+            }
+            if without_seat > 0 {
+                warnings.push(format!("{} participant(s) received no seat in slot {}.", without_seat, slot.name));
+            }
+            unassigned += without_seat;
+            for sess in slot.sessions.iter().filter(|s| s.seats > 0) {
+                let fill_rate = sess.participants.len() as f64 / sess.seats as f64;
+                if fill_rate < LOW_FILL_RATE {
+                    warnings.push(format!("Session {} in slot {} is only {}% full.", sess.name, slot.name, (fill_rate * 100.0).round()));
+                }
+                fill_rates.push(fill_rate.min(1.0));
+            }
+        }
+        let repeated = self.repeated_seats();
+        if repeated > 0 {
+            warnings.push(format!("{} seat(s) repeat content the participant already attends in another slot.", repeated));
+        }
+        let satisfaction = if wishes == 0 { 1.0 } else { satisfaction_sum / wishes as f64 };
+        let assigned_share = if wishes == 0 { 1.0 } else { 1.0 - unassigned as f64 / wishes as f64 };
+        let spread = if fill_rates.is_empty() {
+            0.0
+        } else {
+            let mean = fill_rates.iter().sum::<f64>() / fill_rates.len() as f64;
+            (fill_rates.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / fill_rates.len() as f64).sqrt()
+        };
+        // Fill rates lie between 0 and 1, so their standard deviation is at most 0.5
+        let evenness = 1.0 - (spread * 2.0).min(1.0);
+        let score = 0.6 * satisfaction + 0.3 * assigned_share + 0.1 * evenness;
+        DistributionQuality {
+            score: (score * 100.0).round() as u32,
+            satisfaction: (satisfaction * 100.0).round() as u32,
+            unassigned,
+            fill_rate_spread: (spread * 100.0).round() as u32,
+            warnings,
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Sliding window rate limiter keyed by an arbitrary string (e.g. client IP)
+pub struct RateLimiter {
+    window: Duration,
+    hits: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    pub fn new(window: Duration) -> Self {
+        RateLimiter { window, hits: Mutex::new(HashMap::new()) }
+    }
+
+    /// Records a hit for the key and returns true if it stays within `max` hits per window
+    pub fn check(&self, key: &str, max: usize) -> bool {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap_or_else(|e| e.into_inner());
+        // Forget keys without recent hits so the map doesn't grow unbounded
+        hits.retain(|_, times| times.back().is_some_and(|t| now.duration_since(*t) < self.window));
+        let times = hits.entry(key.to_string()).or_default();
+        while times.front().is_some_and(|t| now.duration_since(*t) >= self.window) {
+            times.pop_front();
+        }
+        if times.len() >= max {
+            return false;
+        }
+        times.push_back(now);
+        true
+    }
+}
@@ -0,0 +1,65 @@
+use std::io::Cursor;
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::Method;
+use rocket::{Data, Request, Response, State};
+use rocket_dyn_templates::{context, Template};
+
+use crate::backend::config::AppConfig;
+use crate::backend::error::BackendError;
+
+/// Submissions still accepted in read-only mode, they only touch the in-memory login sessions
+const ALLOWED_PATHS: &[&str] = &["/login", "/login/admin", "/logout"];
+
+const BANNER: &str = r#"<div class="alert alert-warning rounded-0 mb-0 text-center" role="alert">This instance is read-only. Changes can't be saved right now.</div>"#;
+
+/// Turns every mutating request away with 503 and shows a banner on all pages. Attached if the
+/// instance runs with `read_only = true`, e.g. during a migration or as a public results mirror
+/// of a copied state file.
+pub struct ReadOnlyMode;
+
+#[rocket::async_trait]
+impl Fairing for ReadOnlyMode {
+    fn info(&self) -> Info {
+        Info { name: "read-only mode", kind: Kind::Request | Kind::Response }
+    }
+
+    /// Sends submissions to the read-only routes instead of their handlers
+    async fn on_request(&self, req: &mut Request<'_>, _: &mut Data<'_>) {
+        if matches!(req.method(), Method::Get | Method::Head | Method::Options) || ALLOWED_PATHS.contains(&req.uri().path().as_str()) {
+            return;
+        }
+        let target = if req.uri().path().starts_with("/api/") { "/api/read-only" } else { "/read-only" };
+        req.set_method(Method::Get);
+        req.set_uri(Origin::parse(target).expect("valid path"));
+    }
+
+    async fn on_response<'r>(&self, _req: &'r Request<'_>, res: &mut Response<'r>) {
+        if !res.content_type().is_some_and(|ct| ct.is_html()) {
+            return;
+        }
+        let Ok(body) = res.body_mut().to_string().await else { return; };
+        let html = body.replacen("<body>", &format!("<body>{}", BANNER), 1);
+        res.set_sized_body(html.len(), Cursor::new(html));
+    }
+}
+
+#[derive(Responder)]
+#[response(status = 503)]
+pub struct ReadOnlyPage(Template);
+
+/// Answer to form submissions in read-only mode
+#[get("/read-only")]
+pub fn read_only_page(config: &State<AppConfig>) -> Result<ReadOnlyPage, BackendError> {
+    if !config.read_only {
+        return Err(BackendError::NotFound("page"));
+    }
+    Ok(ReadOnlyPage(Template::render("read_only", context! {})))
+}
+
+/// Answer to API writes in read-only mode, mounted at `/api`
+#[get("/read-only")]
+pub fn api_read_only(config: &State<AppConfig>) -> Result<(), BackendError> {
+    Err(if config.read_only { BackendError::ReadOnly } else { BackendError::NotFound("page") })
+}
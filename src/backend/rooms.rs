@@ -0,0 +1,130 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::backend::data::{EventState, Storage};
+use crate::backend::error::BackendError;
+
+/// Room of the venue sessions can be held in, shared by all events of a tenant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Room {
+    pub uuid: Uuid,
+    pub name: String,
+    /// Tenant whose admins may use the room, None for the default tenant
+    pub tenant: Option<String>,
+    /// Seats available, sessions in this room can't offer more
+    pub capacity: usize,
+    /// Reachable without steps, sessions in this room count as accessible
+    pub accessible: bool,
+    /// E.g. "projector", "whiteboard"
+    pub equipment: Vec<String>,
+}
+
+impl Room {
+    pub fn new(name: String, tenant: Option<String>, capacity: usize) -> Self {
+        Room { uuid: Uuid::new_v4(), name, tenant, capacity, accessible: false, equipment: vec![] }
+    }
+}
+
+/// Slots without an end time are assumed to take this long when looking for double bookings
+pub const ASSUMED_SLOT_MINUTES: i64 = 60;
+
+/// Session in a room at a known time
+#[derive(Debug, Clone, Serialize)]
+pub struct TimedBooking {
+    pub event_id: Uuid,
+    pub event_name: String,
+    pub slot_name: String,
+    pub session_name: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+/// Two sessions using the same room at overlapping times
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomClash {
+    pub room_name: String,
+    pub first: TimedBooking,
+    pub second: TimedBooking,
+}
+
+/// Session held in a room, as (event name, slot name, session name, seats)
+pub type RoomBooking = (String, String, String, usize);
+
+impl Storage {
+    pub fn tenant_room(&self, tenant: Option<&str>, room_id: Uuid) -> Result<&Room, BackendError> {
+        self.rooms.get(&room_id).filter(|r| r.tenant.as_deref() == tenant).ok_or(BackendError::NotFound("room"))
+    }
+
+    /// Rooms of the tenant sorted by name
+    pub fn tenant_rooms(&self, tenant: Option<&str>) -> Vec<&Room> {
+        let mut rooms: Vec<&Room> = self.rooms.values().filter(|r| r.tenant.as_deref() == tenant).collect();
+        rooms.sort_by(|a, b| a.name.cmp(&b.name));
+        rooms
+    }
+
+    /// All sessions of all events held in the room
+    pub fn room_bookings(&self, room_id: Uuid) -> Vec<RoomBooking> {
+        let mut bookings = Vec::new();
+        for ev in self.events.values() {
+            for slot in &ev.slots {
+                for session in slot.sessions.iter().filter(|s| s.room == Some(room_id)) {
+                    bookings.push((ev.name.clone(), slot.name.clone(), session.name.clone(), session.seats));
+                }
+            }
+        }
+        bookings
+    }
+
+    /// Sessions double-booking a room of the tenant, across all its events. Sessions of slots
+    /// without a start time can't be checked. Archived events are left out.
+    pub fn room_clashes(&self, tenant: Option<&str>) -> Vec<RoomClash> {
+        let mut clashes = Vec::new();
+        for room in self.tenant_rooms(tenant) {
+            let mut bookings: Vec<TimedBooking> = Vec::new();
+            for ev in self.events.values().filter(|ev| ev.state != EventState::Archived) {
+                for slot in &ev.slots {
+                    let Some(starts_at) = slot.starts_at else { continue; };
+                    let ends_at = slot.ends_at.filter(|end| *end > starts_at).unwrap_or(starts_at + Duration::minutes(ASSUMED_SLOT_MINUTES));
+                    for session in slot.sessions.iter().filter(|s| s.room == Some(room.uuid)) {
+                        bookings.push(TimedBooking { event_id: ev.uuid, event_name: ev.name.clone(), slot_name: slot.name.clone(), session_name: session.name.clone(), starts_at, ends_at });
+                    }
+                }
+            }
+            bookings.sort_by_key(|b| b.starts_at);
+            for (i, first) in bookings.iter().enumerate() {
+                for second in bookings[i + 1..].iter().take_while(|b| b.starts_at < first.ends_at) {
+                    clashes.push(RoomClash { room_name: room.name.clone(), first: first.clone(), second: second.clone() });
+                }
+            }
+        }
+        clashes
+    }
+
+    /// Rooms of the tenant no session uses during the slot of the event. For slots without a start
+    /// time, only the sessions of the slot itself are checked.
+    pub fn free_rooms(&self, tenant: Option<&str>, event_id: Uuid, slot_id: Uuid) -> Vec<&Room> {
+        let Some(slot) = self.events.get(&event_id).and_then(|ev| ev.slot_by_id(slot_id).ok()) else { return Vec::new(); };
+        let interval = |starts_at: DateTime<Utc>, ends_at: Option<DateTime<Utc>>| (starts_at, ends_at.filter(|end| *end > starts_at).unwrap_or(starts_at + Duration::minutes(ASSUMED_SLOT_MINUTES)));
+        let mut used: Vec<Uuid> = slot.sessions.iter().filter_map(|s| s.room).collect();
+        if let Some((starts_at, ends_at)) = slot.starts_at.map(|start| interval(start, slot.ends_at)) {
+            for other in self.events.values().filter(|ev| ev.state != EventState::Archived).flat_map(|ev| ev.slots.iter()) {
+                let Some((other_start, other_end)) = other.starts_at.map(|start| interval(start, other.ends_at)) else { continue; };
+                if other_start < ends_at && starts_at < other_end {
+                    used.extend(other.sessions.iter().filter_map(|s| s.room));
+                }
+            }
+        }
+        self.tenant_rooms(tenant).into_iter().filter(|room| !used.contains(&room.uuid)).collect()
+    }
+
+    /// Removes the room and its references from all sessions
+    pub fn remove_room(&mut self, room_id: Uuid) {
+        self.rooms.remove(&room_id);
+        for session in self.events.values_mut().flat_map(|ev| ev.slots.iter_mut()).flat_map(|slot| slot.sessions.iter_mut()) {
+            if session.room == Some(room_id) {
+                session.room = None;
+            }
+        }
+    }
+}
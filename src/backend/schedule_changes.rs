@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::backend::data::{Event, EventState, Participant};
+
+/// Net change of a participant's seat in one slot, combining all moves since the participant last
+/// dismissed the notice
+#[derive(Debug, Clone)]
+pub struct ScheduleChange {
+    pub slot_id: Uuid,
+    /// Seat the participant was told about
+    pub from_session: Uuid,
+    /// Current seat, None if the seat was taken away
+    pub to_session: Option<Uuid>,
+    /// Time of the latest move
+    pub at: DateTime<Utc>,
+}
+
+impl Event {
+    /// When the admins confirmed the distribution and participants could first see their seats
+    pub fn results_shown_at(&self) -> Option<DateTime<Utc>> {
+        self.state_history.iter().find(|change| change.to == EventState::Finished).map(|change| change.at)
+    }
+
+    /// Seat changes of the participant made after the results were shown and not dismissed yet,
+    /// one per slot. Moves that end on the original seat cancel out.
+    pub fn unseen_schedule_changes(&self, participant: &Participant) -> Vec<ScheduleChange> {
+        if !self.results_visible() {
+            return Vec::new();
+        }
+        let since = match (self.results_shown_at(), participant.schedule_seen_at) {
+            (Some(shown), Some(seen)) => Some(shown.max(seen)),
+            (shown, seen) => shown.or(seen),
+        };
+        let mut changes: Vec<ScheduleChange> = Vec::new();
+        for change in self.seat_changes.iter().filter(|c| c.participant_id == participant.uuid && since.is_none_or(|t| c.at > t)) {
+            match changes.iter_mut().find(|c| c.slot_id == change.slot_id) {
+                Some(net) => {
+                    net.to_session = change.to_session;
+                    net.at = change.at;
+                }
+                None => changes.push(ScheduleChange { slot_id: change.slot_id, from_session: change.from_session, to_session: change.to_session, at: change.at }),
+            }
+        }
+        changes.retain(|c| c.to_session != Some(c.from_session));
+        changes
+    }
+}
@@ -0,0 +1,219 @@
+use chrono::{DateTime, Local, Utc};
+use uuid::Uuid;
+use std::time::Duration;
+
+use crate::backend::config::AppConfig;
+use crate::backend::data::{EventState, Storage};
+use crate::backend::mail::{Mailer, OutgoingMail};
+use crate::backend::push::PushSender;
+use crate::backend::webhook::WebhookSender;
+use crate::backend::state::{RecoverLock, Shared};
+
+/// Recorded as initiator of automatic distribution runs
+pub const SCHEDULER_NAME: &str = "scheduler";
+
+/// Starts the background task running periodic jobs (reminders, automatic distributions, expiry of unverified registrations, mail, push and webhook delivery)
+pub fn start_scheduler(storage: Shared<Storage>, config: AppConfig, mailer: Mailer, pusher: PushSender, interval: Duration) -> tokio::task::JoinHandle<()> {
+    let webhooks = WebhookSender::default();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            // Run the storage jobs under the write lock, then drop it before any await
+            {
+                let mut guard = storage.write_unpoisoned();
+                let queued = queue_due_reminders(&mut guard, &config, Utc::now());
+                if queued > 0 {
+                    println!("Queued {} reminder(s) for incomplete registrations.", queued);
+                }
+                let notified = queue_slot_reminders(&mut guard, &config, Utc::now());
+                if notified > 0 {
+                    println!("Notified {} participant(s) about slots closing without their preferences.", notified);
+                }
+                let pushed = queue_deadline_pushes(&mut guard, Utc::now());
+                if pushed > 0 {
+                    println!("Queued {} push notification(s) about approaching deadlines.", pushed);
+                }
+                let distributed = run_due_distributions(&mut guard, Utc::now());
+                if distributed > 0 {
+                    println!("Distributed the seats of {} event(s) automatically.", distributed);
+                }
+                let published = publish_reviewed_results(&mut guard, &config, Utc::now());
+                if published > 0 {
+                    println!("Published the results of {} event(s) automatically.", published);
+                }
+                let expired = guard.expire_unverified_registrations(Utc::now());
+                if expired > 0 {
+                    println!("Removed {} unverified self-registration(s).", expired);
+                }
+            }
+            mailer.deliver_pending(&storage).await;
+            pusher.deliver_pending(&storage).await;
+            webhooks.deliver_due(&storage).await;
+        }
+    })
+}
+
+/// Queues a reminder for every participant who logged in but left slots without preferences,
+/// once the event's reminder period before the registration deadline has started.
+/// Each participant is reminded at most once. Returns the number of queued reminders.
+pub fn queue_due_reminders(storage: &mut Storage, config: &AppConfig, now: DateTime<Utc>) -> usize {
+    let mut mails: Vec<OutgoingMail> = Vec::new();
+    for ev in storage.events.values_mut() {
+        let (Some(deadline), Some(days)) = (ev.registration_deadline, ev.reminder_days_before_deadline) else { continue; };
+        if !ev.accepts_preferences(now) || now < deadline - chrono::Duration::days(days as i64) {
+            continue;
+        }
+        for inv in storage.invitations_codes.values().filter(|inv| inv.event_id == ev.uuid) {
+            let Some(pid) = inv.participant_id else { continue; };
+            let Some(participant) = ev.participants.get(&pid) else { continue; };
+            if participant.reminder_sent { continue; }
+            let missing: Vec<String> = ev.slots_without_preferences(pid).iter().map(|slot| slot.name.clone()).collect();
+            if missing.is_empty() { continue; }
+
+            let name = if participant.name.trim().is_empty() { inv.code.clone() } else { participant.name.clone() };
+            let (subject, body) = config.mail_templates.render("reminder", participant.locale.as_deref(), &[
+                ("name", &name),
+                ("event", &ev.name),
+                ("slots", &missing.iter().map(|m| format!("- {}", m)).collect::<Vec<_>>().join("\n")),
+                ("deadline", &deadline.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()),
+                ("link", &config.url(&format!("/invitation/{}", inv.code))),
+            ]);
+            mails.push(OutgoingMail::new(Some(ev.uuid), inv.email.clone(), name, subject, body));
+            if let Some(participant) = ev.participants.get_mut(&pid) {
+                participant.reminder_sent = true;
+            }
+        }
+    }
+    let count = mails.len();
+    for mail in mails {
+        storage.queue_mail(mail);
+    }
+    count
+}
+
+/// Tells participants about slots they haven't chosen preferences for once the slot's deadline is
+/// close, by mail (if the invitation has an address) and push. Each slot is only notified once per
+/// participant, also across restarts. Returns the number of notified participants.
+pub fn queue_slot_reminders(storage: &mut Storage, config: &AppConfig, now: DateTime<Utc>) -> usize {
+    let event_ids: Vec<Uuid> = storage.events.values().filter(|ev| ev.state == EventState::OpenForRegistration).map(|ev| ev.uuid).collect();
+    let mut count = 0;
+    for event_id in event_ids {
+        let Ok(states) = storage.completion_states(event_id, now) else { continue; };
+        for state in states {
+            let due: Vec<_> = state.slots.iter().filter(|slot| slot.needs_reminder(now)).collect();
+            let Some(deadline) = due.iter().filter_map(|slot| slot.deadline).min() else { continue; };
+            let slots = due.iter().map(|slot| slot.slot.clone()).collect::<Vec<_>>().join(", ");
+            let Some(ev) = storage.events.get_mut(&event_id) else { continue; };
+            let Some(participant) = ev.participants.get_mut(&state.participant_id) else { continue; };
+            participant.slot_reminders_sent.extend(due.iter().map(|slot| slot.slot_id));
+            let name = if participant.name.trim().is_empty() { state.code.clone() } else { participant.name.clone() };
+            let locale = participant.locale.clone();
+            let event_name = ev.name.clone();
+            let email = storage.invitations_codes.get(&state.code).and_then(|inv| inv.email.clone());
+            if email.is_some() {
+                let (subject, body) = config.mail_templates.render("slot_reminder", locale.as_deref(), &[
+                    ("name", &name),
+                    ("event", &event_name),
+                    ("slots", &slots),
+                    ("deadline", &deadline.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()),
+                    ("link", &config.url(&format!("/invitation/{}", state.code))),
+                ]);
+                storage.queue_mail(OutgoingMail::new(Some(event_id), email, name, subject, body));
+            }
+            storage.queue_push_for_invitation(&state.code, &event_name, &format!("You haven't chosen your preferences for {} yet.", slots));
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Notifies push subscribers once the registration deadline is less than a day away.
+/// Returns the number of queued notifications.
+pub fn queue_deadline_pushes(storage: &mut Storage, now: DateTime<Utc>) -> usize {
+    let due: Vec<(Uuid, String, DateTime<Utc>)> = storage.events.values_mut()
+        .filter(|ev| !ev.deadline_push_sent && ev.accepts_preferences(now))
+        .filter_map(|ev| {
+            let deadline = ev.registration_deadline?;
+            if now < deadline - chrono::Duration::hours(24) { return None; }
+            ev.deadline_push_sent = true;
+            Some((ev.uuid, ev.name.clone(), deadline))
+        })
+        .collect();
+    due.into_iter()
+        .map(|(event_id, name, deadline)| {
+            let body = format!("Registration closes on {}. Check your session preferences!", deadline.with_timezone(&Local).format("%Y-%m-%d %H:%M"));
+            storage.queue_push_for_event(event_id, &name, &body)
+        })
+        .sum()
+}
+
+/// Distributes the seats of events in hands-off mode whose registration deadline has passed.
+/// Returns the number of distributed events.
+pub fn run_due_distributions(storage: &mut Storage, now: DateTime<Utc>) -> usize {
+    // Participants with an individual extension keep the registration open until it ends
+    let latest_extension = |event_id: Uuid| storage.invitations_codes.values().filter(|inv| inv.event_id == event_id).filter_map(|inv| inv.deadline_extension).max();
+    let due: Vec<Uuid> = storage.events.values()
+        .filter(|ev| ev.auto_distribute && ev.state == EventState::OpenForRegistration)
+        .filter(|ev| ev.deadline_with(latest_extension(ev.uuid)).is_some_and(|deadline| now >= deadline))
+        .map(|ev| ev.uuid)
+        .collect();
+    let mut count = 0;
+    for event_id in due {
+        match storage.distribute_event(event_id, SCHEDULER_NAME) {
+            Ok(run) => {
+                println!("Automatic distribution of event {} took {} ms, {} seats assigned.", event_id, run.duration_ms, run.assigned_seats);
+                count += 1;
+            }
+            Err(e) => eprintln!("Couldn't distribute event {} automatically: {}", event_id, e),
+        }
+    }
+    count
+}
+
+/// Publishes automatically distributed results once their review window is over and mails every
+/// participant their sessions. Results an admin distributed manually are left for the admin to publish.
+/// Returns the number of published events.
+pub fn publish_reviewed_results(storage: &mut Storage, config: &AppConfig, now: DateTime<Utc>) -> usize {
+    let due: Vec<Uuid> = storage.events.values()
+        .filter(|ev| ev.auto_distribute && ev.state == EventState::AwaitingConfirmation)
+        .filter(|ev| ev.distribution_runs.last().is_some_and(|run| {
+            run.initiated_by == SCHEDULER_NAME
+                && now >= run.started_at + chrono::Duration::hours(ev.auto_publish_after_hours.unwrap_or(0) as i64)
+        }))
+        .map(|ev| ev.uuid)
+        .collect();
+
+    for event_id in &due {
+        let Some(ev) = storage.events.get_mut(event_id) else { continue; };
+        if let Err(e) = ev.try_transition(EventState::Finished).and_then(|_| ev.try_transition(EventState::Published)) {
+            eprintln!("Couldn't publish event {} automatically: {}", event_id, e);
+            continue;
+        }
+        let ev = ev.clone();
+        let mut mails: Vec<OutgoingMail> = Vec::new();
+        for inv in storage.invitations_codes.values().filter(|inv| inv.event_id == *event_id) {
+            let Some(participant) = inv.participant_id.and_then(|pid| ev.participants.get(&pid)) else { continue; };
+            if participant.awaiting_verification { continue; }
+            let assignments: Vec<String> = ev.slots.iter()
+                .filter_map(|slot| {
+                    let session = slot.sessions.iter().find(|s| s.participants.contains(&participant.uuid))?;
+                    Some(format!("- {}: {}", slot.name, session.name))
+                })
+                .collect();
+            let name = if participant.name.trim().is_empty() { inv.code.clone() } else { participant.name.clone() };
+            let template = if assignments.is_empty() { "results_no_seats" } else { "results" };
+            let (subject, body) = config.mail_templates.render(template, participant.locale.as_deref(), &[
+                ("name", &name),
+                ("event", &ev.name),
+                ("sessions", &assignments.join("\n")),
+                ("link", &config.url(&format!("/result/{}", inv.code))),
+            ]);
+            mails.push(OutgoingMail::new(Some(ev.uuid), inv.email.clone(), name, subject, body));
+        }
+        for mail in mails {
+            storage.queue_mail(mail);
+        }
+        storage.queue_state_push(*event_id);
+    }
+    due.len()
+}
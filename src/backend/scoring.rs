@@ -0,0 +1,471 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::backend::data::{ordinal, Application, ApplicationPriority, CategoryBonuses, Event, Participant, MAX_RANKED_CHOICES};
+use crate::backend::matching::AllocationMode;
+
+/// Decides in which order applications are served and what participants carry into later rounds
+pub trait ScoringStrategy {
+    /// Name recorded with every distribution run
+    fn name(&self) -> &'static str;
+
+    /// Seed of the random draw, None for deterministic strategies
+    fn seed(&self) -> Option<u64> {
+        None
+    }
+
+    /// Points of an application, the highest are served first. `bonus` is the sum of the category
+    /// and session bonuses and may be negative.
+    fn points(&self, application: &Application, participant: &Participant, bonus: i64) -> usize;
+
+    /// Points a participant carries into later rounds after getting a seat with the given priority
+    fn compensation(&self, priority: &ApplicationPriority) -> usize;
+}
+
+/// Scoring strategy selected per event
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, FromFormField)]
+pub enum ScoringMethod {
+    #[default]
+    WeightedPoints,
+    Lottery,
+    PureLottery,
+}
+
+impl ScoringMethod {
+    pub fn strategy(&self, config: AllocationConfig) -> Box<dyn ScoringStrategy> {
+        match self {
+            ScoringMethod::WeightedPoints => Box::new(WeightedPoints::new(config)),
+            ScoringMethod::Lottery => Box::new(Lottery::new(OsRng.next_u64(), config)),
+            ScoringMethod::PureLottery => Box::new(PureLottery::new(OsRng.next_u64(), config)),
+        }
+    }
+}
+
+/// Points of the preference levels and the compensation for seats, per event. The compensation
+/// is what a participant carries into later rounds: the worse the seat, the better the chances
+/// next time.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AllocationConfig {
+    pub first_points: usize,
+    pub second_points: usize,
+    pub third_points: usize,
+    pub first_compensation: usize,
+    pub second_compensation: usize,
+    pub third_compensation: usize,
+    /// Compensation for a seat the participant didn't choose
+    pub unchosen_compensation: usize,
+}
+
+impl Default for AllocationConfig {
+    fn default() -> Self {
+        AllocationConfig { first_points: 15, second_points: 10, third_points: 5, first_compensation: 0, second_compensation: 5, third_compensation: 10, unchosen_compensation: 15 }
+    }
+}
+
+impl AllocationConfig {
+    /// Points of an application with the given priority, before carry-over and bonuses. Ranks
+    /// after the third fall linearly from the third rank's points to one point at the last rank.
+    pub fn points(&self, priority: &ApplicationPriority) -> usize {
+        match priority {
+            ApplicationPriority::FirstPreference => self.first_points,
+            ApplicationPriority::SecondPreference => self.second_points,
+            ApplicationPriority::ThirdPreference => self.third_points,
+            ApplicationPriority::LowerPreference(rank) => {
+                let below_last = MAX_RANKED_CHOICES.saturating_sub(*rank as usize);
+                self.third_points.min(1) + self.third_points.saturating_sub(1) * below_last / (MAX_RANKED_CHOICES - 3)
+            }
+            ApplicationPriority::NoPreference => 0,
+        }
+    }
+
+    /// Compensation for a seat with the given priority. Ranks after the third get more the worse
+    /// they are, staying below the compensation for an unchosen seat.
+    pub fn compensation(&self, priority: &ApplicationPriority) -> usize {
+        match priority {
+            ApplicationPriority::FirstPreference => self.first_compensation,
+            ApplicationPriority::SecondPreference => self.second_compensation,
+            ApplicationPriority::ThirdPreference => self.third_compensation,
+            ApplicationPriority::LowerPreference(rank) => {
+                let after_third = (*rank as usize).saturating_sub(3);
+                self.third_compensation + self.unchosen_compensation.saturating_sub(self.third_compensation) * after_third / (MAX_RANKED_CHOICES - 2)
+            }
+            ApplicationPriority::NoPreference => self.unchosen_compensation,
+        }
+    }
+}
+
+/// How compensation accumulates: after every seat, the points carried so far shrink by
+/// `decay_percent`, the compensation for the seat is added and the sum is capped at `cap`.
+/// Without decay and cap, a participant unlucky in many slots would be unbeatable later on.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CompensationRules {
+    pub cap: usize,
+    pub decay_percent: u32,
+}
+
+impl Default for CompensationRules {
+    fn default() -> Self {
+        CompensationRules { cap: 30, decay_percent: 50 }
+    }
+}
+
+impl CompensationRules {
+    /// Points carried on after a seat earning the given compensation
+    pub fn carry(&self, points: usize, compensation: usize) -> usize {
+        let kept = points * (100 - self.decay_percent.min(100) as usize) / 100;
+        (kept + compensation).min(self.cap)
+    }
+}
+
+/// Version of the exported scoring configuration. Newer exports can't be imported.
+pub const SCORING_CONFIG_VERSION: u32 = 1;
+
+/// Fairness rules of an event in a portable form, so several events or instances can share them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoringConfig {
+    pub version: u32,
+    pub method: ScoringMethod,
+    pub category_bonuses: CategoryBonuses,
+    #[serde(default)]
+    pub compensation: CompensationRules,
+    #[serde(default)]
+    pub allocation: AllocationMode,
+    #[serde(default)]
+    pub points: AllocationConfig,
+}
+
+impl ScoringConfig {
+    pub fn from_event(event: &Event) -> Self {
+        ScoringConfig { version: SCORING_CONFIG_VERSION, method: event.scoring, category_bonuses: event.category_bonuses.clone(), compensation: event.compensation, allocation: event.allocation, points: event.allocation_config }
+    }
+
+    /// Parses an exported configuration, describing the problem if it can't be used
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let config: ScoringConfig = serde_json::from_str(json).map_err(|e| format!("This isn't a valid scoring configuration: {}", e))?;
+        if config.version > SCORING_CONFIG_VERSION {
+            return Err(format!("The configuration has version {}, this instance only understands up to version {}.", config.version, SCORING_CONFIG_VERSION));
+        }
+        Ok(config)
+    }
+
+    pub fn apply_to(&self, event: &mut Event) {
+        event.scoring = self.method;
+        event.category_bonuses = self.category_bonuses.clone();
+        event.compensation = self.compensation;
+        event.allocation = self.allocation;
+        event.allocation_config = self.points;
+    }
+}
+
+/// The ranking and compensation rules of the event in plain words, shown to participants and admins
+pub fn fairness_rules(event: &Event) -> Vec<String> {
+    let strategy = event.scoring.strategy(event.allocation_config);
+    let points = &event.allocation_config;
+    let bonuses = &event.category_bonuses;
+    let mut rules = vec![match event.scoring {
+        ScoringMethod::WeightedPoints => format!("Every application scores {}, {} or {} points for a first, second or third preference, plus the points you carry over and the bonus of your category. The highest scores are served first.", points.first_points, points.second_points, points.third_points),
+        ScoringMethod::Lottery => format!("Every participant draws a ticket from 0 to {} per distribution, plus the points they carry over and the bonus of their category. The highest tickets are served first; your preference levels decide between your own applications.", LOTTERY_TICKETS - 1),
+        ScoringMethod::PureLottery => "In every session, all first preferences are served before the second and third ones, in a random order within each level. Carried-over points and bonuses don't count.".to_string(),
+    }];
+    if let Some(ranks) = event.slots.iter().map(|slot| slot.ranked_choices).max().filter(|ranks| *ranks > 3) {
+        let mut rule = format!("Some slots ask for up to {} ranked choices. Every choice after the third counts less than the one before", ranks);
+        if event.scoring == ScoringMethod::WeightedPoints {
+            rule.push_str(&format!(", down to {} points for a {} choice", points.points(&ApplicationPriority::from_rank(ranks)), ordinal(ranks)));
+        }
+        rule.push('.');
+        rules.push(rule);
+    }
+    if event.scoring != ScoringMethod::PureLottery {
+        rules.push(format!("Category bonus: guests {:+}, members {:+}, speakers {:+}.", bonuses.guest, bonuses.member, bonuses.speaker));
+        if event.slots.iter().flat_map(|slot| &slot.sessions).any(|s| s.attraction_bonus != 0) {
+            rules.push("Some sessions add a bonus to preferences for them, to fill them evenly.".to_string());
+        }
+        rules.push("If none of your preferences has a seat left, sessions you marked as \"maybe\" are tried before the others.".to_string());
+    }
+    match event.allocation {
+        AllocationMode::Greedy => {}
+        AllocationMode::StableMatching => rules.push("Seats are matched stably: you apply to your sessions in the order of your preferences, and a session only turns you away for applicants ranked higher. Nobody ranked lower than you gets a seat in a session you preferred.".to_string()),
+        AllocationMode::OptimalAssignment => rules.push("Seats are assigned for the least disappointment overall: first as many participants as possible get a seat, then as many as possible get a better preference. Your score decides between applicants wanting the same seat.".to_string()),
+    }
+    let compensation = &event.compensation;
+    rules.push(format!(
+        "After every seat: carried-over points = min({}, points so far × {}% + compensation). The compensation is {} for a first, {} for a second and {} for a third preference, and {} for a seat you didn't choose.",
+        compensation.cap,
+        100 - compensation.decay_percent.min(100),
+        strategy.compensation(&ApplicationPriority::FirstPreference),
+        strategy.compensation(&ApplicationPriority::SecondPreference),
+        strategy.compensation(&ApplicationPriority::ThirdPreference),
+        strategy.compensation(&ApplicationPriority::NoPreference),
+    ));
+    rules
+}
+
+/// Preference points plus the points carried over from earlier rounds
+pub struct WeightedPoints {
+    config: AllocationConfig,
+}
+
+impl WeightedPoints {
+    pub fn new(config: AllocationConfig) -> Self {
+        WeightedPoints { config }
+    }
+}
+
+impl ScoringStrategy for WeightedPoints {
+    fn name(&self) -> &'static str {
+        "points-ranked-greedy"
+    }
+
+    fn points(&self, application: &Application, participant: &Participant, bonus: i64) -> usize {
+        let points = participant.points_from_previous_rounds + self.config.points(&application.priority);
+        // Apply the bonuses or penalty without dropping below zero
+        (points as i64 + bonus).max(0) as usize
+    }
+
+    fn compensation(&self, priority: &ApplicationPriority) -> usize {
+        self.config.compensation(priority)
+    }
+}
+
+/// Number of distinct tickets a participant can draw
+const LOTTERY_TICKETS: u64 = 100;
+
+/// Seeded ticket in 0..LOTTERY_TICKETS for anything hashable
+fn draw(seed: u64, key: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (seed, key).hash(&mut hasher);
+    hasher.finish() % LOTTERY_TICKETS
+}
+
+/// Higher for better preferences, 0 without preference
+fn preference_level(priority: &ApplicationPriority) -> usize {
+    priority.rank().map_or(0, |rank| MAX_RANKED_CHOICES + 1 - rank.min(MAX_RANKED_CHOICES))
+}
+
+/// Every participant draws one ticket per run, the points carried over from earlier rounds are added
+/// to it. The preference level only orders the applications of the same participant.
+/// Draws can be reproduced from the recorded seed with the same build.
+pub struct Lottery {
+    seed: u64,
+    config: AllocationConfig,
+}
+
+impl Lottery {
+    pub fn new(seed: u64, config: AllocationConfig) -> Self {
+        Lottery { seed, config }
+    }
+
+    fn ticket(&self, participant: Uuid) -> usize {
+        draw(self.seed, participant) as usize
+    }
+}
+
+impl ScoringStrategy for Lottery {
+    fn name(&self) -> &'static str {
+        "lottery-with-carryover"
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+
+    fn points(&self, application: &Application, participant: &Participant, bonus: i64) -> usize {
+        let draw = ((self.ticket(participant.uuid) + participant.points_from_previous_rounds) as i64 + bonus).max(0) as usize;
+        // One step per possible rank and one without preference per draw point keep a participant's
+        // applications below the next better draw
+        draw * (MAX_RANKED_CHOICES + 1) + preference_level(&application.priority)
+    }
+
+    fn compensation(&self, priority: &ApplicationPriority) -> usize {
+        self.config.compensation(priority)
+    }
+}
+
+/// Transparent random draw: in every session, all first preferences are served before the second
+/// ones and so on, in a seeded random order within each level. Points from earlier rounds and
+/// bonuses are ignored, but the compensation is still recorded for events using points later.
+pub struct PureLottery {
+    seed: u64,
+    config: AllocationConfig,
+}
+
+impl PureLottery {
+    pub fn new(seed: u64, config: AllocationConfig) -> Self {
+        PureLottery { seed, config }
+    }
+}
+
+impl ScoringStrategy for PureLottery {
+    fn name(&self) -> &'static str {
+        "pure-lottery"
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+
+    fn points(&self, application: &Application, participant: &Participant, _bonus: i64) -> usize {
+        let level = preference_level(&application.priority) as u64;
+        // Separate draws per session, so a lucky participant doesn't win everywhere
+        (level * LOTTERY_TICKETS + draw(self.seed, (application.session_uuid, participant.uuid))) as usize
+    }
+
+    fn compensation(&self, priority: &ApplicationPriority) -> usize {
+        self.config.compensation(priority)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::backend::data::{Invitation, InvitationCategory, Session, Slot};
+
+    const PRIORITIES: [ApplicationPriority; 4] = [ApplicationPriority::FirstPreference, ApplicationPriority::SecondPreference, ApplicationPriority::ThirdPreference, ApplicationPriority::NoPreference];
+
+    fn participant(points: usize) -> Participant {
+        let mut participant = Invitation::new("CODE".to_string(), Uuid::new_v4(), vec![], InvitationCategory::default()).new_participant();
+        participant.points_from_previous_rounds = points;
+        participant
+    }
+
+    fn application(session: &mut Session, participant: &Participant, priority: ApplicationPriority) -> Application {
+        session.insert_application(participant.uuid, priority);
+        session.applications.pop().expect("application was just inserted")
+    }
+
+    fn points_per_priority(strategy: &dyn ScoringStrategy, participant: &Participant) -> Vec<usize> {
+        let mut session = Session::new("Session".to_string(), None, 10);
+        PRIORITIES.iter().map(|priority| strategy.points(&application(&mut session, participant, priority.clone()), participant, 0)).collect()
+    }
+
+    #[test]
+    fn weighted_points_rank_preferences_and_add_carried_points() {
+        let strategy = WeightedPoints::new(AllocationConfig::default());
+        assert_eq!(points_per_priority(&strategy, &participant(0)), vec![15, 10, 5, 0]);
+        assert_eq!(points_per_priority(&strategy, &participant(7)), vec![22, 17, 12, 7]);
+        // A penalty never drops the points below zero
+        let mut session = Session::new("Session".to_string(), None, 10);
+        let p = participant(0);
+        assert_eq!(strategy.points(&application(&mut session, &p, ApplicationPriority::ThirdPreference), &p, -20), 0);
+    }
+
+    #[test]
+    fn lottery_serves_better_preferences_of_a_participant_first() {
+        for seed in 0..20 {
+            let strategy = Lottery::new(seed, AllocationConfig::default());
+            let p = participant(seed as usize % 4);
+            let points = points_per_priority(&strategy, &p);
+            assert!(points.windows(2).all(|pair| pair[0] > pair[1]), "seed {}: {:?}", seed, points);
+        }
+    }
+
+    #[test]
+    fn lottery_levels_never_outweigh_a_better_draw() {
+        let strategy = Lottery::new(42, AllocationConfig::default());
+        let mut session = Session::new("Session".to_string(), None, 10);
+        let participants: Vec<Participant> = (0..20).map(|_| participant(0)).collect();
+        for lucky in &participants {
+            for unlucky in participants.iter().filter(|p| strategy.ticket(p.uuid) < strategy.ticket(lucky.uuid)) {
+                let worst = strategy.points(&application(&mut session, lucky, ApplicationPriority::NoPreference), lucky, 0);
+                let best = strategy.points(&application(&mut session, unlucky, ApplicationPriority::FirstPreference), unlucky, 0);
+                assert!(worst > best);
+            }
+        }
+    }
+
+    #[test]
+    fn pure_lottery_serves_levels_in_order_and_repeats_with_the_seed() {
+        let participants: Vec<Participant> = (0..20).map(|i| participant(i * 5)).collect();
+        let mut session = Session::new("Session".to_string(), None, 10);
+        let strategy = PureLottery::new(7, AllocationConfig::default());
+        let scores = |strategy: &PureLottery, session: &mut Session, priority: ApplicationPriority| -> Vec<usize> {
+            participants.iter().map(|p| strategy.points(&application(session, p, priority.clone()), p, 30)).collect()
+        };
+        let first = scores(&strategy, &mut session, ApplicationPriority::FirstPreference);
+        let second = scores(&strategy, &mut session, ApplicationPriority::SecondPreference);
+        // Carried points and bonuses don't count, every first preference beats every second one
+        assert!(first.iter().min() > second.iter().max());
+        let level = preference_level(&ApplicationPriority::FirstPreference) * LOTTERY_TICKETS as usize;
+        assert!(first.iter().all(|points| (level..level + LOTTERY_TICKETS as usize).contains(points)));
+        assert_eq!(first, scores(&PureLottery::new(7, AllocationConfig::default()), &mut session, ApplicationPriority::FirstPreference));
+    }
+
+    #[test]
+    fn carry_decays_earlier_points_and_respects_the_cap() {
+        let rules = CompensationRules::default();
+        assert_eq!(rules.carry(0, 10), 10);
+        assert_eq!(rules.carry(10, 0), 5);
+        assert_eq!(rules.carry(11, 0), 5);
+        assert_eq!(rules.carry(20, 15), 25);
+        assert_eq!(rules.carry(40, 15), 30);
+        // Unlucky in every slot, the points level off at the cap
+        let points = (0..10).fold(0, |points, _| rules.carry(points, 15));
+        assert_eq!(points, 29);
+        assert_eq!((0..10).fold(0, |points, _| rules.carry(points, 40)), 30);
+        let no_decay = CompensationRules { cap: 100, decay_percent: 0 };
+        assert_eq!(no_decay.carry(40, 15), 55);
+        let full_decay = CompensationRules { cap: 100, decay_percent: 100 };
+        assert_eq!(full_decay.carry(40, 15), 15);
+    }
+
+    /// Two slots: three single-seat sessions in the first, where two participants with carried
+    /// points take the first and second choice of a third one; one session in the second, which
+    /// only the third participant wants
+    fn two_slot_event() -> (Event, Uuid) {
+        let mut ev = Event::new("Test".to_string(), None);
+        let mut morning = Slot::new("Morning".to_string(), None);
+        for name in ["A", "B", "C"] {
+            morning.sessions.push(Session::new(name.to_string(), None, 1));
+        }
+        let mut afternoon = Slot::new("Afternoon".to_string(), None);
+        afternoon.sessions.push(Session::new("D".to_string(), None, 1));
+        let morning_ids: Vec<Uuid> = morning.sessions.iter().map(|s| s.uuid).collect();
+        let afternoon_id = afternoon.sessions[0].uuid;
+        ev.slots = vec![morning, afternoon];
+        let (first, second, unlucky) = (participant(20), participant(20), participant(0));
+        ev.slots[0].insert_application(morning_ids[0], first.uuid, ApplicationPriority::FirstPreference);
+        ev.slots[0].insert_application(morning_ids[1], second.uuid, ApplicationPriority::FirstPreference);
+        for (priority, session_id) in PRIORITIES.iter().zip(&morning_ids) {
+            ev.slots[0].insert_application(*session_id, unlucky.uuid, priority.clone());
+        }
+        ev.slots[1].insert_application(afternoon_id, unlucky.uuid, ApplicationPriority::FirstPreference);
+        let unlucky_id = unlucky.uuid;
+        for p in [first, second, unlucky] {
+            ev.participants.insert(p.uuid, p);
+        }
+        (ev, unlucky_id)
+    }
+
+    #[test]
+    fn third_choice_compensation_carries_past_a_later_first_choice() {
+        let (mut ev, unlucky) = two_slot_event();
+        let third_choice = ev.slots[0].sessions[2].uuid;
+        ev.distribute("test");
+        assert!(ev.slots[0].session_by_id(third_choice).unwrap().participants.contains(&unlucky));
+        assert!(ev.slots[1].sessions[0].participants.contains(&unlucky));
+        // 10 for the third choice, halved after the first choice of the second slot
+        assert_eq!(ev.participants[&unlucky].points_from_previous_rounds, 5);
+    }
+
+    #[test]
+    fn greedy_and_stable_matching_keep_the_same_compensation() {
+        let (template, _) = two_slot_event();
+        let mut results = Vec::new();
+        for mode in [AllocationMode::Greedy, AllocationMode::StableMatching] {
+            let mut ev = template.clone();
+            ev.allocation = mode;
+            let counts = ev.distribute("test").assigned_by_priority;
+            let mut points: Vec<(Uuid, usize)> = ev.participants.values().map(|p| (p.uuid, p.points_from_previous_rounds)).collect();
+            points.sort();
+            let seats: Vec<Vec<Uuid>> = ev.slots.iter().flat_map(|slot| slot.sessions.iter()).map(|s| s.participants.clone()).collect();
+            results.push((points, seats, (counts.first, counts.second, counts.third, counts.no_preference)));
+        }
+        assert_eq!(results[0], results[1]);
+        assert_eq!(results[0].2, (3, 0, 1, 0));
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::backend::config::FormLimits;
+use crate::backend::data::Storage;
+use crate::backend::error::BackendError;
+use crate::backend::validation::{FieldError, Validator};
+
+/// New seat count of a session, e.g. a row of a room planning spreadsheet
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeatAdjustment {
+    pub session_id: Uuid,
+    pub seats: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdjustmentResult {
+    pub session_id: Uuid,
+    pub seats: usize,
+    /// Seats before the change, None for unknown sessions
+    pub previous_seats: Option<usize>,
+    /// Empty if the adjustment is valid
+    pub errors: Vec<FieldError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SeatBatchReport {
+    /// True if every adjustment was valid and applied. Otherwise nothing was changed.
+    pub applied: bool,
+    pub results: Vec<AdjustmentResult>,
+}
+
+impl Storage {
+    /// Validates every adjustment and applies all of them, or none if any is invalid
+    pub fn adjust_seats(&mut self, event_id: Uuid, adjustments: &[SeatAdjustment], limits: &FormLimits) -> Result<SeatBatchReport, BackendError> {
+        let ev = self.events.get(&event_id).ok_or(BackendError::NotFound("event"))?;
+        ev.ensure_unlocked()?;
+        let mut seen = HashSet::new();
+        let results: Vec<AdjustmentResult> = adjustments.iter()
+            .map(|adjustment| {
+                let mut v = Validator::new(limits);
+                v.seats("seats", adjustment.seats);
+                if !seen.insert(adjustment.session_id) {
+                    v.error("session_id", "The session appears more than once in the batch.");
+                }
+                let session = ev.session_by_id(adjustment.session_id).ok();
+                match session {
+                    None => v.error("session_id", "The session doesn't exist in this event."),
+                    Some(sess) => {
+                        if sess.category_seats.total() > adjustment.seats {
+                            v.error("seats", format!("{} seats are reserved for categories.", sess.category_seats.total()));
+                        }
+                        if sess.participants.len() > adjustment.seats {
+                            v.error("seats", format!("{} participants are already assigned to the session.", sess.participants.len()));
+                        }
+                        if let Some(room) = sess.room.and_then(|id| self.rooms.get(&id)) && adjustment.seats > room.capacity {
+                            v.error("seats", format!("{} only has room for {} people.", room.name, room.capacity));
+                        }
+                    }
+                }
+                AdjustmentResult { session_id: adjustment.session_id, seats: adjustment.seats, previous_seats: session.map(|s| s.seats), errors: v.into_errors() }
+            })
+            .collect();
+        let applied = results.iter().all(|r| r.errors.is_empty());
+        if applied {
+            let ev = self.events.get_mut(&event_id).ok_or(BackendError::NotFound("event"))?;
+            for adjustment in adjustments {
+                let sess = ev.session_by_id_mut(adjustment.session_id)?;
+                sess.seats = adjustment.seats;
+                sess.revision += 1;
+            }
+        }
+        Ok(SeatBatchReport { applied, results })
+    }
+}
@@ -0,0 +1,58 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::backend::data::{Event, Storage};
+
+/// Longest validity of a share link
+pub const MAX_SHARE_LINK_HOURS: i64 = 24 * 30;
+
+/// Page a share link opens
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, FromFormField)]
+pub enum ShareKind {
+    /// Registrations and preferences per session
+    Demand,
+    /// Assigned seats per session, also while the result awaits confirmation
+    Allocation,
+}
+
+/// Read-only link to figures of an event for people without an admin account, e.g. the venue
+/// manager. The pages show counts only, no names or codes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareLink {
+    pub uuid: Uuid,
+    pub token: String,
+    pub kind: ShareKind,
+    /// Who the link was made for
+    pub label: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl ShareLink {
+    pub fn new(kind: ShareKind, label: String, hours: i64, created_by: String, now: DateTime<Utc>) -> Self {
+        ShareLink {
+            uuid: Uuid::new_v4(),
+            token: format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()),
+            kind,
+            label,
+            created_by,
+            created_at: now,
+            expires_at: now + Duration::hours(hours),
+        }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+}
+
+impl Storage {
+    /// The event and the unexpired share link with the token
+    pub fn shared_event(&self, token: &str, now: DateTime<Utc>) -> Option<(&Event, &ShareLink)> {
+        self.events.values()
+            .find_map(|ev| ev.share_links.iter().find(|link| link.token == token).map(|link| (ev, link)))
+            .filter(|(_, link)| !link.is_expired(now))
+    }
+}
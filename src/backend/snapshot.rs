@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::backend::data::{Event, EventState, Storage};
+use crate::backend::state::{RecoverLock, Shared};
+
+/// Program and fill levels of a published event, served to infoscreens from a cache
+#[derive(Serialize)]
+pub struct EventSnapshot {
+    pub event_id: Uuid,
+    pub name: String,
+    pub generated_at: DateTime<Utc>,
+    pub slots: Vec<SlotSnapshot>,
+}
+
+#[derive(Serialize)]
+pub struct SlotSnapshot {
+    pub name: String,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub sessions: Vec<SessionSnapshot>,
+}
+
+#[derive(Serialize)]
+pub struct SessionSnapshot {
+    pub name: String,
+    pub room: Option<String>,
+    pub seats: usize,
+    pub taken: usize,
+    pub free: usize,
+    /// Taken seats in percent of all seats
+    pub fill_percent: usize,
+    pub online: bool,
+    pub accessible: bool,
+    pub tags: Vec<String>,
+}
+
+impl EventSnapshot {
+    fn new(storage: &Storage, ev: &Event, now: DateTime<Utc>) -> Self {
+        let slots = ev.slots.iter().map(|slot| SlotSnapshot {
+            name: slot.name.clone(),
+            starts_at: slot.starts_at,
+            ends_at: slot.ends_at,
+            sessions: slot.sessions.iter().map(|s| SessionSnapshot {
+                name: s.name.clone(),
+                room: s.room.and_then(|id| storage.rooms.get(&id)).map(|r| r.name.clone()),
+                seats: s.seats,
+                taken: s.participants.len(),
+                free: s.seats.saturating_sub(s.participants.len()),
+                fill_percent: (s.participants.len() * 100).checked_div(s.seats).unwrap_or(0),
+                online: s.online,
+                accessible: s.accessible,
+                tags: s.tags.clone(),
+            }).collect(),
+        }).collect();
+        EventSnapshot { event_id: ev.uuid, name: ev.name.clone(), generated_at: now, slots }
+    }
+}
+
+impl Storage {
+    /// Snapshots of all published events as JSON, by event
+    pub fn published_snapshots(&self, now: DateTime<Utc>) -> HashMap<Uuid, String> {
+        self.events.values()
+            .filter(|ev| ev.state == EventState::Published)
+            .map(|ev| (ev.uuid, serde_json::to_string(&EventSnapshot::new(self, ev, now)).expect("snapshot is serializable")))
+            .collect()
+    }
+}
+
+/// Regenerates the infoscreen snapshots right away and then after every interval. Requests are
+/// answered from `cache` only, so displays polling it never wait for the storage lock.
+pub fn start_snapshot_refresh(storage: Shared<Storage>, cache: Shared<HashMap<Uuid, String>>, interval: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let snapshots = storage.read_unpoisoned().published_snapshots(Utc::now());
+            *cache.write_unpoisoned() = snapshots;
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
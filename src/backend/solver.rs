@@ -0,0 +1,306 @@
+//! Exchange format for distributing the seats with an external optimizer.
+//!
+//! [`AllocationProblem`] describes everything the built-in distribution works with, as JSON:
+//!
+//! - `participants`: everyone taking part in the distribution with `id`, `category`, `tags`,
+//!   `needs_accessible_room` and the `points` carried over from earlier rounds and linked events.
+//!   Participants awaiting email verification are left out.
+//! - `slots`: the time slots with their `sessions`. A participant gets at most one seat per slot,
+//!   at most `max_participants` seats are given per slot (if set) and at most `seats` per session,
+//!   of which `reserved_seats` are kept for the given categories. Participants listed in `pinned`
+//!   must be seated in that session. Sessions sharing a `content` label are instances of the same
+//!   workshop, a participant should get at most one of them.
+//! - `preferences`: one entry per participant and session they may be seated in, with `priority`
+//!   1 to 3 for the participant's choices and `null` for sessions they didn't choose, plus the
+//!   `points` the event's scoring method gives the entry. `interested` marks sessions the participant
+//!   flagged as "maybe" without choosing them. Seats in sessions without an entry are
+//!   rejected on import.
+//! - `constraints`: `max_sessions_per_day` (sessions per participant and calendar day, `null` for
+//!   no limit) and `exclusions`, pairs of participants who must not share a session.
+//!
+//! The solver answers with an [`ExternalAssignment`]: `{"event_id": ..., "solver": "my-optimizer",
+//! "assignments": [{"participant": ..., "session": ...}]}`. The import checks all constraints and
+//! then replaces the seats like a distribution run, so the result still needs to be confirmed.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::backend::data::{ApplicationPriority, CategorySeats, DistributionRun, Event, EventState, InvitationCategory, PriorityCounts, Storage};
+use crate::backend::error::BackendError;
+use crate::backend::exclusions::excluded_from;
+use crate::backend::no_preference::NoPreferenceCounts;
+
+/// Version of the exchange format. Assignments of newer versions can't be imported.
+pub const SOLVER_FORMAT_VERSION: u32 = 1;
+
+/// Constraint violations listed in the error message, the rest are only counted
+const MAX_LISTED_PROBLEMS: usize = 10;
+
+#[derive(Debug, Serialize)]
+pub struct AllocationProblem {
+    pub version: u32,
+    pub event_id: Uuid,
+    pub exported_at: DateTime<Utc>,
+    /// Name of the built-in algorithm, for comparison
+    pub algorithm: String,
+    pub participants: Vec<ProblemParticipant>,
+    pub slots: Vec<ProblemSlot>,
+    pub preferences: Vec<ProblemPreference>,
+    pub constraints: ProblemConstraints,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProblemParticipant {
+    pub id: Uuid,
+    pub category: InvitationCategory,
+    pub tags: Vec<String>,
+    pub needs_accessible_room: bool,
+    pub points: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProblemSlot {
+    pub id: Uuid,
+    pub name: String,
+    /// Calendar day the max_sessions_per_day constraint counts the slot for
+    pub day: Option<NaiveDate>,
+    pub max_participants: Option<usize>,
+    pub sessions: Vec<ProblemSession>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProblemSession {
+    pub id: Uuid,
+    pub name: String,
+    pub seats: usize,
+    pub reserved_seats: CategorySeats,
+    /// Participants an admin reserved a seat for
+    pub pinned: Vec<Uuid>,
+    /// Label shared by sessions with the same content in other slots
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProblemPreference {
+    pub participant: Uuid,
+    pub session: Uuid,
+    /// Rank of the choice from 1, None if the participant didn't choose the session
+    pub priority: Option<u8>,
+    /// Points of the entry under the event's scoring method, higher is served first
+    pub points: usize,
+    /// The participant marked the session as "maybe" without choosing it
+    pub interested: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProblemConstraints {
+    pub max_sessions_per_day: Option<usize>,
+    pub exclusions: Vec<[Uuid; 2]>,
+}
+
+/// Seats computed by an external solver
+#[derive(Debug, Deserialize)]
+pub struct ExternalAssignment {
+    #[serde(default = "default_version")]
+    pub version: u32,
+    /// Checked against the event if given, so a result isn't imported into the wrong event
+    #[serde(default)]
+    pub event_id: Option<Uuid>,
+    /// Name of the solver, recorded with the distribution run
+    #[serde(default)]
+    pub solver: Option<String>,
+    pub assignments: Vec<AssignedSeat>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignedSeat {
+    pub participant: Uuid,
+    pub session: Uuid,
+}
+
+fn default_version() -> u32 {
+    SOLVER_FORMAT_VERSION
+}
+
+fn priority_number(priority: &ApplicationPriority) -> Option<u8> {
+    priority.rank().map(|rank| rank as u8)
+}
+
+impl ExternalAssignment {
+    /// Parses a solver result, describing the problem if it can't be used
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let assignment: ExternalAssignment = serde_json::from_str(json).map_err(|e| format!("This isn't a valid assignment: {}", e))?;
+        if assignment.version > SOLVER_FORMAT_VERSION {
+            return Err(format!("The assignment has version {}, this instance only understands up to version {}.", assignment.version, SOLVER_FORMAT_VERSION));
+        }
+        Ok(assignment)
+    }
+}
+
+impl Storage {
+    /// Describes the distribution of the event for an external solver, with the points carried
+    /// over from linked people like a distribution started now
+    pub fn allocation_problem(&self, event_id: Uuid) -> Result<AllocationProblem, BackendError> {
+        let mut ev = self.events.get(&event_id).ok_or(BackendError::NotFound("event"))?.clone();
+        self.carry_person_points(&mut ev);
+        let strategy = ev.scoring.strategy(ev.allocation_config);
+        let reference = ev.clone();
+        for sess in ev.slots.iter_mut().flat_map(|slot| slot.sessions.iter_mut()) {
+            sess.rank_applications(&reference, strategy.as_ref());
+        }
+
+        let mut participants: Vec<ProblemParticipant> = ev.participants.values()
+            .filter(|p| !p.awaiting_verification)
+            .map(|p| ProblemParticipant { id: p.uuid, category: p.category, tags: p.tags.clone(), needs_accessible_room: p.needs_accessible_room, points: p.points_from_previous_rounds })
+            .collect();
+        participants.sort_by_key(|p| p.id);
+        let slots = ev.slots.iter().map(|slot| ProblemSlot {
+            id: slot.uuid,
+            name: slot.name.clone(),
+            day: slot.day(),
+            max_participants: slot.max_participants,
+            sessions: slot.sessions.iter().map(|s| ProblemSession {
+                id: s.uuid,
+                name: s.name.clone(),
+                seats: s.seats,
+                reserved_seats: s.category_seats.clone(),
+                content: s.content.clone(),
+                pinned: s.pinned.iter().copied().filter(|pid| ev.participants.get(pid).is_some_and(|p| !p.awaiting_verification)).collect(),
+            }).collect(),
+        }).collect();
+        // Ranking already dropped applications of ineligible or unverified participants
+        let preferences = ev.slots.iter().flat_map(|slot| &slot.sessions).flat_map(|s| &s.applications)
+            .map(|a| ProblemPreference { participant: a.participant, session: a.session_uuid, priority: priority_number(&a.priority), points: a.calculated_points.unwrap_or(0), interested: a.interested })
+            .collect();
+        let constraints = ProblemConstraints {
+            max_sessions_per_day: ev.max_sessions_per_day,
+            exclusions: ev.exclusions.iter().map(|e| [e.first, e.second]).collect(),
+        };
+        Ok(AllocationProblem { version: SOLVER_FORMAT_VERSION, event_id, exported_at: Utc::now(), algorithm: strategy.name().to_string(), participants, slots, preferences, constraints })
+    }
+
+    /// Closes the registration of the event and stores the externally computed seats as its
+    /// distribution. If the assignment violates a constraint, the event is reopened unchanged.
+    pub fn import_assignment(&mut self, event_id: Uuid, assignment: &ExternalAssignment, initiated_by: &str) -> Result<DistributionRun, BackendError> {
+        if assignment.event_id.is_some_and(|id| id != event_id) {
+            return Err(BackendError::bad_request("The assignment was computed for another event."));
+        }
+        let state = self.events.get(&event_id).ok_or(BackendError::NotFound("event"))?.state;
+        if state != EventState::OpenForRegistration {
+            return Err(BackendError::conflict(format!("Assignments can only be imported while the event is open for registration, it's {}.", state)));
+        }
+        let mut ev = self.begin_distribution(event_id)?;
+        match ev.apply_external_assignment(assignment, initiated_by) {
+            Ok(run) => {
+                let run = run.clone();
+                self.finish_distribution(ev)?;
+                Ok(run)
+            }
+            Err(e) => {
+                self.abort_distribution(event_id);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Event {
+    /// Checks the assignment against the constraints of the exchange format and replaces all seats
+    /// with it. Compensation points are carried like in the built-in distribution.
+    fn apply_external_assignment(&mut self, assignment: &ExternalAssignment, initiated_by: &str) -> Result<&DistributionRun, BackendError> {
+        let started_at = Utc::now();
+        let start = std::time::Instant::now();
+        let mut problems = Vec::new();
+        let mut seats: Vec<Vec<Vec<Uuid>>> = self.slots.iter().map(|slot| vec![Vec::new(); slot.sessions.len()]).collect();
+        let mut per_day: HashMap<(Uuid, NaiveDate), usize> = HashMap::new();
+        let mut seated: HashSet<(Uuid, usize)> = HashSet::new();
+        for seat in &assignment.assignments {
+            let Some(participant) = self.participants.get(&seat.participant).filter(|p| !p.awaiting_verification) else {
+                problems.push(format!("Participant {} doesn't take part in the distribution.", seat.participant));
+                continue;
+            };
+            let Some((slot_index, session_index)) = self.slots.iter().enumerate()
+                .find_map(|(i, slot)| slot.sessions.iter().position(|s| s.uuid == seat.session).map(|j| (i, j))) else {
+                problems.push(format!("Session {} doesn't exist.", seat.session));
+                continue;
+            };
+            let slot = &self.slots[slot_index];
+            let session = &slot.sessions[session_index];
+            if !seated.insert((participant.uuid, slot_index)) {
+                problems.push(format!("Participant {} has more than one seat in slot {}.", participant.uuid, slot.name));
+                continue;
+            }
+            let pinned = session.is_pinned(participant.uuid);
+            if !pinned && (!session.is_eligible(participant) || !session.applications.iter().any(|a| a.participant == participant.uuid)) {
+                problems.push(format!("Participant {} can't be seated in {}.", participant.uuid, session.name));
+            }
+            if excluded_from(&self.exclusions, participant.uuid, &seats[slot_index][session_index]) {
+                problems.push(format!("Participant {} is kept apart from someone in {}.", participant.uuid, session.name));
+            }
+            if let (Some(max), Some(day)) = (self.max_sessions_per_day, slot.day()) {
+                let count = per_day.entry((participant.uuid, day)).or_default();
+                *count += 1;
+                if *count == max + 1 {
+                    problems.push(format!("Participant {} has more than {} session(s) on {}.", participant.uuid, max, day));
+                }
+            }
+            seats[slot_index][session_index].push(participant.uuid);
+        }
+        for (slot, slot_seats) in self.slots.iter().zip(&seats) {
+            for (session, session_seats) in slot.sessions.iter().zip(slot_seats) {
+                for pid in session.pinned.iter().filter(|pid| !session_seats.contains(pid) && self.participants.get(pid).is_some_and(|p| !p.awaiting_verification)) {
+                    problems.push(format!("Participant {} is pinned to {} but not seated there.", pid, session.name));
+                }
+                if session_seats.len() > session.seats {
+                    problems.push(format!("{} has {} seats, the assignment fills {}.", session.name, session.seats, session_seats.len()));
+                }
+            }
+            let total: usize = slot_seats.iter().map(Vec::len).sum();
+            if let Some(max) = slot.max_participants.filter(|max| total > *max) {
+                problems.push(format!("Slot {} allows {} participants, the assignment seats {}.", slot.name, max, total));
+            }
+        }
+        if !problems.is_empty() {
+            let more = problems.len().saturating_sub(MAX_LISTED_PROBLEMS);
+            problems.truncate(MAX_LISTED_PROBLEMS);
+            if more > 0 {
+                problems.push(format!("… and {} more.", more));
+            }
+            return Err(BackendError::bad_request(format!("The assignment wasn't imported: {}", problems.join(" "))));
+        }
+
+        let strategy = self.scoring.strategy(self.allocation_config);
+        let compensation = self.compensation;
+        let mut assigned_by_priority = PriorityCounts::default();
+        for (slot, slot_seats) in self.slots.iter_mut().zip(seats) {
+            slot.waiting_list.clear();
+            for (session, session_seats) in slot.sessions.iter_mut().zip(slot_seats) {
+                for pid in &session_seats {
+                    let priority = session.applications.iter().find(|a| a.participant == *pid).map(|a| a.priority.clone()).unwrap_or(ApplicationPriority::NoPreference);
+                    assigned_by_priority.add(&priority);
+                    if let Some(participant) = self.participants.get_mut(pid) {
+                        participant.points_from_previous_rounds = compensation.carry(participant.points_from_previous_rounds, strategy.compensation(&priority));
+                    }
+                }
+                session.participants = session_seats;
+            }
+        }
+        let solver = assignment.solver.as_deref().map(str::trim).filter(|s| !s.is_empty()).unwrap_or("unnamed solver");
+        self.distribution_runs.push(DistributionRun {
+            started_at,
+            duration_ms: start.elapsed().as_millis() as u64,
+            algorithm: format!("external: {}", solver),
+            seed: None,
+            participants: self.participants.len(),
+            applications: self.slots.iter().flat_map(|slot| &slot.sessions).map(|s| s.applications.len()).sum(),
+            assigned_seats: assignment.assignments.len(),
+            assigned_by_priority,
+            without_preferences: NoPreferenceCounts::default(),
+            initiated_by: initiated_by.to_string(),
+        });
+        Ok(self.distribution_runs.last().expect("run was just recorded"))
+    }
+}
@@ -0,0 +1,154 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::backend::data::{ApplicationPriority, Event, PriorityCounts, Session, MAX_RANKED_CHOICES};
+use crate::backend::error::BackendError;
+use crate::backend::rooms::Room;
+
+/// Sessions with this many first preferences per seat are offered to be split
+pub const OVERSUBSCRIBED_DEMAND: f64 = 1.5;
+
+/// How a session would be split: a copy in the target slot and the applicants moving to it
+#[derive(Debug, Clone, Serialize)]
+pub struct SplitPlan {
+    pub slot_id: Uuid,
+    pub session_id: Uuid,
+    pub target_slot_id: Uuid,
+    /// Name of the copy
+    pub name: String,
+    /// Applicants moving to the copy, every other applicant in order of their preference
+    pub moving: Vec<Uuid>,
+    pub moving_counts: PriorityCounts,
+    pub staying_counts: PriorityCounts,
+    /// Applicants who already chose another session with the same preference in the target slot
+    pub blocked: usize,
+}
+
+impl Session {
+    /// First preferences per seat, above 1.0 not everyone choosing the session first gets a seat
+    pub fn demand(&self) -> f64 {
+        let first = self.applications.iter().filter(|a| matches!(a.priority, ApplicationPriority::FirstPreference)).count();
+        if self.seats == 0 { first as f64 } else { first as f64 / self.seats as f64 }
+    }
+
+    /// Copy of the session without room, seats and applications
+    fn duplicate(&self, name: String) -> Session {
+        let mut copy = Session::new(name, self.description.clone(), self.seats);
+        copy.eligibility_tags = self.eligibility_tags.clone();
+        copy.tags = self.tags.clone();
+        copy.content = self.content.clone();
+        copy.online = self.online;
+        copy.join_url = self.join_url.clone();
+        copy.attraction_bonus = self.attraction_bonus;
+        copy.accessible = self.accessible;
+        copy.category_seats = self.category_seats.clone();
+        copy.acknowledgment = self.acknowledgment.clone();
+        copy.acknowledgments = self.acknowledgments.clone();
+        copy.avoided = self.avoided.clone();
+        copy
+    }
+}
+
+/// Rank of a preference, lower is better
+fn rank(priority: &ApplicationPriority) -> usize {
+    priority.rank().map_or(MAX_RANKED_CHOICES, |rank| rank - 1)
+}
+
+impl Event {
+    /// Returns true if the session is offered to be split: preferences are still open and far more
+    /// participants chose it first than it has seats
+    pub fn is_oversubscribed(&self, session: &Session) -> bool {
+        !self.is_distributed() && session.demand() >= OVERSUBSCRIBED_DEMAND
+    }
+
+    /// Plans a copy of the session in the target slot (which may be its own slot) taking over every
+    /// other applicant. Applicants keep their preference for the copy. Nothing is changed.
+    pub fn plan_split(&self, slot_id: Uuid, session_id: Uuid, target_slot_id: Uuid) -> Result<SplitPlan, BackendError> {
+        if self.is_distributed() {
+            return Err(BackendError::conflict("Sessions can only be split before the seats are distributed."));
+        }
+        let slot = self.slot_by_id(slot_id)?;
+        let session = slot.session_by_id(session_id).ok_or(BackendError::NotFound("session"))?;
+        let target = self.slot_by_id(target_slot_id)?;
+
+        let mut applications: Vec<_> = session.applications.iter().filter(|a| !matches!(a.priority, ApplicationPriority::NoPreference)).collect();
+        applications.sort_by_key(|a| (rank(&a.priority), a.participant));
+        // In another slot, the copy can't take the place of a session the applicant chose with the same preference
+        let (movable, blocked): (Vec<_>, Vec<_>) = applications.into_iter().partition(|a| {
+            target_slot_id == slot_id || !target.sessions.iter().flat_map(|s| &s.applications).any(|other| other.participant == a.participant && rank(&other.priority) == rank(&a.priority))
+        });
+
+        let mut plan = SplitPlan {
+            slot_id,
+            session_id,
+            target_slot_id,
+            name: copy_name(&session.name, self.slots.iter().flat_map(|slot| &slot.sessions).map(|s| s.name.as_str()).collect()),
+            moving: Vec::new(),
+            moving_counts: PriorityCounts::default(),
+            staying_counts: PriorityCounts::default(),
+            blocked: blocked.len(),
+        };
+        for application in &blocked {
+            plan.staying_counts.add(&application.priority);
+        }
+        for (i, application) in movable.into_iter().enumerate() {
+            if i % 2 == 1 {
+                plan.moving.push(application.participant);
+                plan.moving_counts.add(&application.priority);
+            } else {
+                plan.staying_counts.add(&application.priority);
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Creates the copy planned and moves the applicants, optionally into the given room.
+    /// Returns the copy's id.
+    pub fn apply_split(&mut self, plan: &SplitPlan, room: Option<&Room>) -> Result<Uuid, BackendError> {
+        let source = self.slot_by_id(plan.slot_id)?.session_by_id(plan.session_id).ok_or(BackendError::NotFound("session"))?;
+        let mut copy = source.duplicate(plan.name.clone());
+        if let Some(room) = room {
+            copy.room = Some(room.uuid);
+            copy.accessible = room.accessible;
+            copy.seats = copy.seats.min(room.capacity);
+        }
+        let moved: Vec<(Uuid, ApplicationPriority)> = source.applications.iter()
+            .filter(|a| plan.moving.contains(&a.participant))
+            .map(|a| (a.participant, a.priority.clone()))
+            .collect();
+        let copy_id = copy.uuid;
+        let same_slot = plan.slot_id == plan.target_slot_id;
+        // Everyone else applying in the slot may still get a seat in the copy, like in any other session of the slot
+        let others: Vec<Uuid> = if same_slot {
+            let mut others: Vec<Uuid> = self.slot_by_id(plan.slot_id)?.sessions.iter().flat_map(|s| &s.applications).map(|a| a.participant)
+                .filter(|pid| !plan.moving.contains(pid))
+                .collect();
+            others.sort();
+            others.dedup();
+            others
+        } else { Vec::new() };
+
+        self.slot_by_id_mut(plan.target_slot_id)?.sessions.push(copy);
+        self.reindex();
+        for (participant, priority) in &moved {
+            let slot = self.slot_by_id_mut(plan.slot_id)?;
+            if same_slot {
+                // The original stays open to the applicant as a fallback
+                slot.insert_application(plan.session_id, *participant, ApplicationPriority::NoPreference);
+            } else if let Some(session) = slot.session_by_id_mut(plan.session_id) {
+                session.applications.retain(|a| a.participant != *participant);
+            }
+            self.slot_by_id_mut(plan.target_slot_id)?.insert_application(copy_id, *participant, priority.clone());
+        }
+        let target = self.slot_by_id_mut(plan.target_slot_id)?;
+        for participant in others {
+            target.insert_application(copy_id, participant, ApplicationPriority::NoPreference);
+        }
+        Ok(copy_id)
+    }
+}
+
+/// The session name with the lowest free number, e.g. "Workshop (2)"
+fn copy_name(name: &str, taken: Vec<&str>) -> String {
+    (2..).map(|n| format!("{} ({})", name, n)).find(|candidate| !taken.contains(&candidate.as_str())).expect("some number is free")
+}
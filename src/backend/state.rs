@@ -2,21 +2,68 @@ use std::collections::HashMap;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::time::Duration;
 use uuid::Uuid;
 
 use tokio::fs as tfs;
 use tokio::io::AsyncWriteExt;
 
-use crate::backend::auth::Session;
-use crate::backend::data::Storage;
+use crate::backend::auth::{LoginToken, Session, SessionUserType};
+use crate::backend::confirmation::ConfirmationToken;
+use crate::backend::data::{EventState, Storage};
+use crate::backend::progress::DistributionProgress;
+use crate::backend::rate_limit::RateLimiter;
+use crate::backend::waiting_room::WaitingRoom;
 
 pub type Shared<T> = Arc<RwLock<T>>;
 
+/// Lock access that survives a panic in another request. A panic while holding a lock poisons
+/// it; instead of failing every later request, the poison is cleared and the data is used as the
+/// panicking request left it. Handlers validate before they change anything, so that's the state
+/// before or after a complete change in almost all cases.
+pub trait RecoverLock<T> {
+    fn read_unpoisoned(&self) -> RwLockReadGuard<'_, T>;
+    fn write_unpoisoned(&self) -> RwLockWriteGuard<'_, T>;
+}
+
+impl<T> RecoverLock<T> for RwLock<T> {
+    fn read_unpoisoned(&self) -> RwLockReadGuard<'_, T> {
+        self.read().unwrap_or_else(|poisoned| {
+            eprintln!("Recovering a lock poisoned by an earlier panic.");
+            self.clear_poison();
+            poisoned.into_inner()
+        })
+    }
+
+    fn write_unpoisoned(&self) -> RwLockWriteGuard<'_, T> {
+        self.write().unwrap_or_else(|poisoned| {
+            eprintln!("Recovering a lock poisoned by an earlier panic.");
+            self.clear_poison();
+            poisoned.into_inner()
+        })
+    }
+}
+
 pub struct AppState {
     pub storage: Shared<Storage>,
     pub sessions: Shared<HashMap<Uuid, Session>>,
+    /// Limits self-service registrations per client IP
+    pub registration_limiter: RateLimiter,
+    /// Unused tokens of login links sent by email
+    pub login_tokens: Shared<HashMap<String, LoginToken>>,
+    /// Limits login link requests per email address
+    pub login_link_limiter: RateLimiter,
+    /// Limits API requests per token
+    pub api_limiter: RateLimiter,
+    /// Unused tokens of confirmation pages for destructive admin actions
+    pub confirmation_tokens: Shared<HashMap<String, ConfirmationToken>>,
+    /// Progress of distributions started from the admin interface, by event
+    pub distribution_progress: Shared<HashMap<Uuid, DistributionProgress>>,
+    /// JSON snapshots of published events for infoscreens, regenerated periodically
+    pub infoscreen_snapshots: Shared<HashMap<Uuid, String>>,
+    /// Queue for participant requests while the server is under load
+    pub waiting_room: WaitingRoom,
 }
 
 impl Default for AppState {
@@ -32,6 +79,14 @@ impl AppState {
         AppState {
             storage: Arc::new(RwLock::new(storage)),
             sessions: Arc::new(RwLock::new(HashMap::new())),
+            registration_limiter: RateLimiter::new(Duration::from_secs(60 * 60)),
+            login_tokens: Arc::new(RwLock::new(HashMap::new())),
+            login_link_limiter: RateLimiter::new(Duration::from_secs(60 * 60)),
+            api_limiter: RateLimiter::new(Duration::from_secs(60)),
+            confirmation_tokens: Arc::new(RwLock::new(HashMap::new())),
+            distribution_progress: Arc::new(RwLock::new(HashMap::new())),
+            infoscreen_snapshots: Arc::new(RwLock::new(HashMap::new())),
+            waiting_room: WaitingRoom::default(),
         }
     }
 
@@ -39,9 +94,23 @@ impl AppState {
         AppState {
             storage: Arc::new(RwLock::new(storage)),
             sessions: Arc::new(RwLock::new(HashMap::new())),
+            registration_limiter: RateLimiter::new(Duration::from_secs(60 * 60)),
+            login_tokens: Arc::new(RwLock::new(HashMap::new())),
+            login_link_limiter: RateLimiter::new(Duration::from_secs(60 * 60)),
+            api_limiter: RateLimiter::new(Duration::from_secs(60)),
+            confirmation_tokens: Arc::new(RwLock::new(HashMap::new())),
+            distribution_progress: Arc::new(RwLock::new(HashMap::new())),
+            infoscreen_snapshots: Arc::new(RwLock::new(HashMap::new())),
+            waiting_room: WaitingRoom::default(),
         }
     }
 
+    /// Logs out all sessions of the given user, e.g. after their invitation code changed
+    pub fn end_sessions_of(&self, user_type: &SessionUserType) {
+        let mut sessions = self.sessions.write_unpoisoned();
+        sessions.retain(|_, sess| sess.user_type != *user_type);
+    }
+
     /// Load state from a JSON file or create a new one if not present.
     /// If there are no admin accounts yet, a secure initial admin password
     /// is generated and written to a local file.
@@ -60,6 +129,19 @@ impl AppState {
             Storage::new()
         };
 
+        storage.ensure_vapid_key();
+        storage.ensure_link_key();
+        storage.reindex_events();
+        // Distributions run in the background and don't survive a restart
+        let interrupted: Vec<Uuid> = storage.events.values().filter(|ev| ev.state == EventState::AssigningSeats).map(|ev| ev.uuid).collect();
+        for event_id in interrupted {
+            eprintln!("Distribution of event {} was interrupted, reopening its registration.", event_id);
+            storage.abort_distribution(event_id);
+        }
+        for problem in storage.check_integrity() {
+            eprintln!("Integrity check: {}", problem);
+        }
+
         // If this is the first startup (no admins exist), generate secure credentials.
         if storage.admins.is_empty() {
             if let Err(e) = Self::generate_initial_admin(&mut storage, path) {
@@ -75,7 +157,7 @@ impl AppState {
         if let Some(parent) = path.parent() { tfs::create_dir_all(parent).await?; }
         // Build JSON while holding read lock, then drop it before any await
         let json = {
-            let storage = self.storage.read().expect("storage poisoned");
+            let storage = self.storage.read_unpoisoned();
             serde_json::to_string_pretty(&*storage)?
         };
         // write atomically
@@ -96,13 +178,7 @@ impl AppState {
             loop {
                 tokio::time::sleep(interval).await;
                 // Serialize under read lock, then drop guard before any await.
-                let json_opt = {
-                    if let Ok(guard) = storage.read() {
-                        serde_json::to_string_pretty(&*guard).ok()
-                    } else {
-                        None
-                    }
-                };
+                let json_opt = serde_json::to_string_pretty(&*storage.read_unpoisoned()).ok();
                 if let Some(json) = json_opt {
                     let tmp_path = path.with_extension("json.tmp");
                     if let Some(parent) = path.parent() { let _ = tfs::create_dir_all(parent).await; }
@@ -127,7 +203,7 @@ impl AppState {
 
         // Insert admin account with hashed password
         // Ignore existing admin silently (race-safe if called once at startup)
-        let _ = state.add_admin(username, &password);
+        let _ = state.add_admin(username, &password, None);
 
         // Serialize storage now; we will write it synchronously (no Tokio runtime involved)
         let json = serde_json::to_string_pretty(&state)
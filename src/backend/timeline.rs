@@ -0,0 +1,133 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::data::{EventState, Storage};
+use crate::backend::error::BackendError;
+
+/// Something the holder of an invitation did, kept on the invitation for support requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvitationActivity {
+    pub at: DateTime<Utc>,
+    pub kind: ActivityKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ActivityKind {
+    Created,
+    /// Only the first login is kept
+    FirstLogin,
+    EmailVerified,
+    NameSet { name: String },
+    /// One line per slot, e.g. "Morning: 1. Workshop A, 2. Talk B"
+    PreferencesSaved { choices: Vec<String> },
+    CodeRotated,
+}
+
+/// One line of a participant's timeline
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineEntry {
+    pub at: DateTime<Utc>,
+    /// Who caused the entry: participant, admin, event or mail
+    pub source: &'static str,
+    pub title: String,
+    pub details: Vec<String>,
+}
+
+impl TimelineEntry {
+    fn new(at: DateTime<Utc>, source: &'static str, title: impl Into<String>) -> Self {
+        TimelineEntry { at, source, title: title.into(), details: vec![] }
+    }
+
+    fn with_details(mut self, details: Vec<String>) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+impl Storage {
+    /// Adds an entry to the activity of the invitation, if it still exists
+    pub fn record_activity(&mut self, code: &str, kind: ActivityKind) {
+        if let Some(inv) = self.invitations_codes.get_mut(code) {
+            inv.activity.push(InvitationActivity { at: Utc::now(), kind });
+        }
+    }
+
+    pub fn record_first_login(&mut self, code: &str) {
+        let logged_in_before = self.invitations_codes.get(code).is_none_or(|inv| inv.activity.iter().any(|a| matches!(a.kind, ActivityKind::FirstLogin)));
+        if !logged_in_before {
+            self.record_activity(code, ActivityKind::FirstLogin);
+        }
+    }
+
+    /// Everything that happened to the holder of the invitation code, oldest first: their own
+    /// activity, the event's state changes and distributions, seat changes by admins, feedback and mails
+    pub fn participant_timeline(&self, code: &str) -> Result<Vec<TimelineEntry>, BackendError> {
+        let inv = self.invitations_codes.get(code).ok_or(BackendError::NotFound("invitation"))?;
+        let ev = self.events.get(&inv.event_id).ok_or(BackendError::NotFound("event"))?;
+        let pid = inv.participant_id;
+        let mut entries: Vec<TimelineEntry> = inv.activity.iter().map(|a| match &a.kind {
+            ActivityKind::Created => TimelineEntry::new(a.at, "admin", "Invitation created"),
+            ActivityKind::FirstLogin => TimelineEntry::new(a.at, "participant", "First login"),
+            ActivityKind::EmailVerified => TimelineEntry::new(a.at, "participant", "Email address verified"),
+            ActivityKind::NameSet { name } => TimelineEntry::new(a.at, "participant", format!("Name set to {}", name)),
+            ActivityKind::PreferencesSaved { choices } => TimelineEntry::new(a.at, "participant", "Preferences saved").with_details(choices.clone()),
+            ActivityKind::CodeRotated => TimelineEntry::new(a.at, "admin", "Invitation code replaced"),
+        }).collect();
+
+        if let Some(entry) = ev.waiting_list.iter().find(|entry| entry.code == code) {
+            entries.push(TimelineEntry::new(entry.joined_at, "event", "Put on the waiting list, the event was full"));
+        }
+        for change in &ev.state_history {
+            let title = match change.to {
+                EventState::OpenForRegistration => "Registration opened",
+                EventState::AssigningSeats => "Registration closed",
+                EventState::Finished if change.from == EventState::AwaitingConfirmation => "Distribution result confirmed",
+                EventState::Published => "Results published",
+                _ => continue,
+            };
+            entries.push(TimelineEntry::new(change.at, "event", title));
+        }
+        if let Some(pid) = pid {
+            let session_name = |id| ev.session_by_id(id).map(|s| s.name.clone()).unwrap_or_default();
+            if let Some(run) = ev.distribution_runs.last() {
+                // Seats of earlier runs were replaced, so only the current result is shown
+                let result: Vec<String> = ev.slots.iter().map(|slot| {
+                    let session = slot.sessions.iter().find(|s| s.participants.contains(&pid)).map(|s| s.name.as_str());
+                    match session {
+                        Some(name) => format!("{}: {}", slot.name, name),
+                        None if slot.waiting_list.contains(&pid) => format!("{}: waiting list", slot.name),
+                        None => format!("{}: no seat", slot.name),
+                    }
+                }).collect();
+                entries.push(TimelineEntry::new(run.started_at, "event", format!("Seats distributed (started by {})", run.initiated_by)).with_details(result));
+            }
+            for change in ev.seat_changes.iter().filter(|change| change.participant_id == pid) {
+                let slot = ev.slot_by_id(change.slot_id).map(|s| s.name.clone()).unwrap_or_default();
+                let text = match change.to_session {
+                    Some(to) => format!("Moved from {} to {} in {} by {}", session_name(change.from_session), session_name(to), slot, change.changed_by),
+                    None => format!("Seat in {} in {} removed by {}", session_name(change.from_session), slot, change.changed_by),
+                };
+                entries.push(TimelineEntry::new(change.at, "admin", text));
+            }
+            for feedback in ev.feedback.iter().filter(|f| f.participant_id == pid) {
+                let mut details = vec![format!("{}/5", feedback.rating)];
+                details.extend(feedback.comment.clone());
+                entries.push(TimelineEntry::new(feedback.submitted_at, "participant", format!("Feedback on {}", session_name(feedback.session_id))).with_details(details));
+            }
+        }
+        if let Some(email) = &inv.email {
+            for mail in self.outbox.iter().filter(|m| m.event_id == Some(ev.uuid) && m.recipient.as_deref().is_some_and(|r| r.eq_ignore_ascii_case(email))) {
+                let status = match (mail.sent_at, &mail.last_error) {
+                    (Some(sent_at), _) => format!("Sent {}", sent_at.to_rfc3339()),
+                    (None, Some(error)) if mail.dead_lettered_at.is_some() => format!("Gave up after {} attempts: {}", mail.attempts, error),
+                    (None, Some(error)) => format!("Not sent yet: {}", error),
+                    (None, None) => "Not sent yet".to_string(),
+                };
+                entries.push(TimelineEntry::new(mail.created_at, "mail", format!("Mail: {}", mail.subject)).with_details(vec![status]));
+            }
+        }
+        entries.sort_by_key(|entry| entry.at);
+        Ok(entries)
+    }
+}
@@ -0,0 +1,81 @@
+use serde::Serialize;
+
+use crate::backend::config::FormLimits;
+use crate::backend::error::BackendError;
+
+/// A rejected form field or JSON member
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Collects all problems of one submission so they can be reported together
+pub struct Validator<'a> {
+    limits: &'a FormLimits,
+    errors: Vec<FieldError>,
+}
+
+impl<'a> Validator<'a> {
+    pub fn new(limits: &'a FormLimits) -> Self {
+        Validator { limits, errors: Vec::new() }
+    }
+
+    pub fn error(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError { field: field.to_string(), message: message.into() });
+    }
+
+    /// Checks a required name that is already trimmed
+    pub fn name(&mut self, field: &str, value: &str) {
+        if value.is_empty() {
+            self.error(field, "Please enter a name.");
+        } else if value.chars().count() > self.limits.max_name_length {
+            self.error(field, format!("Names can have at most {} characters.", self.limits.max_name_length));
+        }
+    }
+
+    /// Checks the length of an optional free text (description, note, comment)
+    pub fn text(&mut self, field: &str, value: Option<&str>) {
+        if value.is_some_and(|v| v.chars().count() > self.limits.max_text_length) {
+            self.error(field, format!("Texts can have at most {} characters.", self.limits.max_text_length));
+        }
+    }
+
+    pub fn seats(&mut self, field: &str, seats: usize) {
+        if seats < 1 || seats > self.limits.max_seats {
+            self.error(field, format!("Please enter between 1 and {} seats.", self.limits.max_seats));
+        }
+    }
+
+    pub fn code(&mut self, field: &str, code: &str) {
+        if code.is_empty() || code.chars().count() > self.limits.max_code_length {
+            self.error(field, format!("Invitation codes need between 1 and {} characters.", self.limits.max_code_length));
+        }
+    }
+
+    /// Checks an optional link, which has to be an absolute http(s) URL
+    pub fn url(&mut self, field: &str, value: Option<&str>) {
+        let Some(url) = value else { return; };
+        if !(url.starts_with("https://") || url.starts_with("http://")) || url.contains(char::is_whitespace) {
+            self.error(field, "Please enter a link starting with https://.");
+        } else if url.chars().count() > self.limits.max_text_length {
+            self.error(field, format!("Links can have at most {} characters.", self.limits.max_text_length));
+        }
+    }
+
+    pub fn password(&mut self, field: &str, password: &str) {
+        if password.chars().count() < self.limits.min_password_length {
+            self.error(field, format!("Passwords need at least {} characters.", self.limits.min_password_length));
+        }
+    }
+
+    /// Returns the collected problems, for reports listing them per item
+    pub fn into_errors(self) -> Vec<FieldError> {
+        self.errors
+    }
+
+    /// Returns all collected problems as one validation error
+    pub fn finish(self) -> Result<(), BackendError> {
+        if self.errors.is_empty() { Ok(()) } else { Err(BackendError::Invalid(self.errors)) }
+    }
+}
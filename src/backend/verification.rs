@@ -0,0 +1,99 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hkdf::hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::backend::data::{EmailVerification, Storage};
+
+impl Storage {
+    /// Generates the key verification links are signed with, if there is none yet
+    pub fn ensure_link_key(&mut self) {
+        if self.link_key.is_none() {
+            self.link_key = Some(format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()));
+        }
+    }
+
+    fn link_mac(&self) -> Option<Hmac<Sha256>> {
+        Some(Hmac::<Sha256>::new_from_slice(self.link_key.as_ref()?.as_bytes()).expect("HMAC takes keys of any length"))
+    }
+
+    /// Copy of the verification to put into the link: the token is followed by the expiry and the HMAC of both
+    pub fn signed_verification(&self, verification: &EmailVerification) -> EmailVerification {
+        let mut token = format!("{}.{}", verification.token, verification.expires_at.timestamp());
+        if let Some(mut mac) = self.link_mac() {
+            mac.update(token.as_bytes());
+            token = format!("{}.{}", token, URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes()));
+        }
+        EmailVerification { token, expires_at: verification.expires_at }
+    }
+
+    /// Checks the signature of a verification link token and returns the stored token it carries
+    pub fn verified_token<'a>(&self, signed: &'a str) -> Option<&'a str> {
+        let (payload, signature) = signed.rsplit_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        let mut mac = self.link_mac()?;
+        mac.update(payload.as_bytes());
+        mac.verify_slice(&signature).ok()?;
+        payload.split_once('.').map(|(token, _)| token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::*;
+    use crate::backend::data::{Event, Invitation, InvitationCategory};
+
+    /// Storage with an event of one place and a pending self-registration for each code
+    fn storage_with_registrations(codes: &[&str]) -> (Storage, Uuid, Vec<EmailVerification>) {
+        let mut storage = Storage::new();
+        let mut ev = Event::new("Test".to_string(), None);
+        ev.max_participants = Some(1);
+        let event_id = ev.uuid;
+        storage.events.insert(event_id, ev);
+        let verifications = codes.iter().map(|code| {
+            let verification = EmailVerification::new(Duration::hours(48));
+            let mut inv = Invitation::new(code.to_string(), event_id, vec![], InvitationCategory::Guest);
+            inv.pending_verification = Some(verification.clone());
+            storage.invitations_codes.insert(code.to_string(), inv);
+            verification
+        }).collect();
+        (storage, event_id, verifications)
+    }
+
+    #[test]
+    fn only_signed_links_verify() {
+        let (mut storage, _, verifications) = storage_with_registrations(&["a"]);
+        let now = Utc::now();
+        assert_eq!(storage.verify_email(&verifications[0].token, now), None);
+        let signed = storage.signed_verification(&verifications[0]).token;
+        let tampered = format!("{}x{}", &signed[..1], &signed[2..]);
+        assert_eq!(storage.verify_email(&tampered, now), None);
+
+        let mut other = Storage::new();
+        other.link_key = Some("another key".to_string());
+        assert_eq!(storage.verify_email(&other.signed_verification(&verifications[0]).token, now), None);
+
+        assert_eq!(storage.verify_email(&signed, now).as_deref(), Some("a"));
+        assert_eq!(storage.verify_email(&signed, now), None);
+    }
+
+    #[test]
+    fn registrations_take_a_place_once_verified() {
+        let (mut storage, event_id, verifications) = storage_with_registrations(&["a", "b"]);
+        assert_eq!(storage.events[&event_id].free_places(), Some(1));
+
+        let now = Utc::now();
+        let second = storage.signed_verification(&verifications[1]).token;
+        let first = storage.signed_verification(&verifications[0]).token;
+        storage.verify_email(&second, now);
+        storage.verify_email(&first, now);
+        let ev = &storage.events[&event_id];
+        assert_eq!(ev.free_places(), Some(0));
+        assert_eq!(ev.participants.len(), 1);
+        assert!(storage.invitations_codes["b"].participant_id.is_some());
+        assert_eq!(ev.waiting_list.iter().map(|entry| entry.code.as_str()).collect::<Vec<_>>(), ["a"]);
+    }
+}
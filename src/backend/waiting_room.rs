@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use rocket::http::{Cookie, Header, SameSite, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::State;
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::backend::config::AppConfig;
+use crate::backend::state::AppState;
+
+/// Cookie holding the queue ticket of a waiting participant
+const QUEUE_COOKIE: &str = "queue";
+/// Tickets not retried within this time are given up, so abandoned tabs don't block the queue
+const TICKET_TTL: Duration = Duration::from_secs(60);
+/// Upper bound of the suggested wait between retries
+const MAX_RETRY_AFTER_SECONDS: usize = 30;
+
+struct Ticket {
+    number: u64,
+    last_seen: Instant,
+}
+
+#[derive(Default)]
+struct Queue {
+    next_number: u64,
+    tickets: HashMap<String, Ticket>,
+}
+
+/// Limits the participant requests handled at the same time. When registration opens, everyone
+/// else gets a queue ticket and is asked to retry; tickets are admitted in the order they were
+/// issued as requests finish, instead of all of them piling up on the storage lock.
+#[derive(Default)]
+pub struct WaitingRoom {
+    in_flight: AtomicUsize,
+    queue: Mutex<Queue>,
+}
+
+/// Place of a participant turned away by the [`WaitingRoom`]
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuePosition {
+    /// 1-based position among the waiting participants
+    pub position: usize,
+    pub retry_after: usize,
+}
+
+impl WaitingRoom {
+    /// Admits the request if fewer than `capacity` requests are in flight and no earlier ticket
+    /// waits for the free places. Otherwise returns the ticket to retry with and the position.
+    fn admit(&self, ticket: Option<&str>, capacity: usize) -> Result<(), (String, QueuePosition)> {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        queue.tickets.retain(|_, t| now.duration_since(t.last_seen) < TICKET_TTL);
+        let number = ticket.and_then(|token| queue.tickets.get(token)).map(|t| t.number);
+        let ahead = queue.tickets.values().filter(|t| number.is_none_or(|n| t.number < n)).count();
+        let free = capacity.saturating_sub(self.in_flight.load(Ordering::SeqCst));
+        if ahead < free {
+            if let Some(token) = ticket {
+                queue.tickets.remove(token);
+            }
+            self.in_flight.fetch_add(1, Ordering::SeqCst);
+            return Ok(());
+        }
+        let token = match ticket.filter(|_| number.is_some()) {
+            Some(token) => token.to_string(),
+            None => {
+                let token = Uuid::new_v4().simple().to_string();
+                let number = queue.next_number;
+                queue.next_number += 1;
+                queue.tickets.insert(token.clone(), Ticket { number, last_seen: now });
+                token
+            }
+        };
+        if let Some(t) = queue.tickets.get_mut(&token) {
+            t.last_seen = now;
+        }
+        let retry_after = (2 + ahead / capacity.max(1)).min(MAX_RETRY_AFTER_SECONDS);
+        Err((token, QueuePosition { position: ahead + 1, retry_after }))
+    }
+
+    fn leave(&self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Place in the [`WaitingRoom`] for the duration of a participant request. Take it as the first
+/// guard so queued requests are turned away before anything touches the storage.
+pub struct Admission<'r> {
+    room: Option<&'r WaitingRoom>,
+}
+
+impl Drop for Admission<'_> {
+    fn drop(&mut self) {
+        if let Some(room) = self.room {
+            room.leave();
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admission<'r> {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let (Outcome::Success(state), Outcome::Success(config)) = (req.guard::<&State<AppState>>().await, req.guard::<&State<AppConfig>>().await) else {
+            return Outcome::Error((Status::InternalServerError, ()));
+        };
+        let room = &state.waiting_room;
+        if config.concurrent_user_requests == 0 {
+            return Outcome::Success(Admission { room: None });
+        }
+        let ticket = req.cookies().get(QUEUE_COOKIE).map(|c| c.value().to_string());
+        match room.admit(ticket.as_deref(), config.concurrent_user_requests) {
+            Ok(()) => {
+                if ticket.is_some() {
+                    req.cookies().remove(Cookie::from(QUEUE_COOKIE));
+                }
+                Outcome::Success(Admission { room: Some(room) })
+            }
+            Err((token, position)) => {
+                req.cookies().add(Cookie::build((QUEUE_COOKIE, token)).http_only(true).same_site(SameSite::Lax));
+                req.local_cache(|| Some(position));
+                Outcome::Error((Status::ServiceUnavailable, ()))
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct QueueContext {
+    position: usize,
+    retry_after: usize,
+    /// Page reloaded once the wait is over
+    retry_url: String,
+    /// True if a form submission was turned away and has to be sent again
+    was_submission: bool,
+}
+
+#[derive(Responder)]
+#[response(status = 503)]
+pub struct QueuePage(Template, Header<'static>);
+
+/// Page shown to participants waiting for a place. It reloads itself after the suggested wait.
+#[catch(503)]
+pub fn queue_page(req: &Request<'_>) -> Result<QueuePage, Status> {
+    let position = req.local_cache(|| None::<QueuePosition>).clone().ok_or(Status::ServiceUnavailable)?;
+    let was_submission = req.method() != rocket::http::Method::Get;
+    let retry_url = if was_submission { "/event".to_string() } else { req.uri().to_string() };
+    let ctx = QueueContext { position: position.position, retry_after: position.retry_after, retry_url, was_submission };
+    Ok(QueuePage(Template::render("queue", &ctx), Header::new("Retry-After", position.retry_after.to_string())))
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::backend::data::{Application, ApplicationPriority, Event, EventState, InvitationCategory, SeatChange, Slot};
+use crate::backend::error::BackendError;
+use crate::backend::exclusions::excluded_from;
+use crate::backend::matching::{dissatisfaction, UNRANKED_DISSATISFACTION};
+
+/// Participant who got a seat from a session's waitlist
+#[derive(Debug, Clone)]
+pub struct Promotion {
+    pub participant_id: Uuid,
+    pub session_id: Uuid,
+    /// Session the participant moved away from, None if they had no seat in the slot before
+    pub from_session: Option<Uuid>,
+}
+
+impl Slot {
+    /// Puts applicants who didn't get a seat in a session they preferred over their assigned seat
+    /// on the session's waitlist, best ranked first. `ranked` holds the ranked applications per
+    /// session from before the allocation, which consumes them. Only real preferences are kept.
+    pub fn build_waitlists(&mut self, ranked: &[(Uuid, Vec<Application>)]) {
+        // How far each seated participant is from their first preference
+        let mut seated: HashMap<Uuid, u8> = HashMap::new();
+        for session in &self.sessions {
+            let applications = ranked.iter().find(|(id, _)| *id == session.uuid).map(|(_, apps)| apps.as_slice()).unwrap_or_default();
+            for pid in &session.participants {
+                let level = applications.iter().find(|a| a.participant == *pid).map_or(UNRANKED_DISSATISFACTION, dissatisfaction);
+                seated.insert(*pid, level);
+            }
+        }
+        for session in self.sessions.iter_mut() {
+            let applications = ranked.iter().find(|(id, _)| *id == session.uuid).map(|(_, apps)| apps.as_slice()).unwrap_or_default();
+            session.waitlist = applications.iter()
+                .filter(|a| !matches!(a.priority, ApplicationPriority::NoPreference) && !a.repeat)
+                .filter(|a| !session.participants.contains(&a.participant))
+                .filter(|a| seated.get(&a.participant).is_none_or(|level| dissatisfaction(a) < *level))
+                .cloned()
+                .collect();
+        }
+    }
+}
+
+impl Event {
+    /// Takes the participant's seat in the slot away and fills the free seat from the session's
+    /// waitlist. A participant moving up from another session frees a seat there, which is filled
+    /// the same way. The removal and every move are recorded as seat changes.
+    pub fn remove_seat(&mut self, slot_id: Uuid, participant_id: Uuid, changed_by: &str) -> Result<Vec<Promotion>, BackendError> {
+        if !matches!(self.state, EventState::AwaitingConfirmation | EventState::Finished | EventState::Published) {
+            return Err(BackendError::conflict("Seats can only be removed after the distribution and before the event is archived."));
+        }
+        let slot = self.slot_by_id_mut(slot_id)?;
+        let session = slot.sessions.iter_mut().find(|s| s.participants.contains(&participant_id))
+            .ok_or_else(|| BackendError::bad_request("The participant has no seat in this slot."))?;
+        session.participants.retain(|p| *p != participant_id);
+        let freed = session.uuid;
+        for session in slot.sessions.iter_mut() {
+            session.waitlist.retain(|a| a.participant != participant_id);
+        }
+        self.seat_changes.push(SeatChange { participant_id, slot_id, from_session: freed, to_session: None, changed_by: changed_by.to_string(), at: Utc::now() });
+        Ok(self.promote_from_waitlists(slot_id, freed, changed_by))
+    }
+
+    fn promote_from_waitlists(&mut self, slot_id: Uuid, freed: Uuid, changed_by: &str) -> Vec<Promotion> {
+        let Some(index) = self.slots.iter().position(|s| s.uuid == slot_id) else { return Vec::new(); };
+        let at_daily_limit = self.participants_at_daily_limit(index);
+        let categories: HashMap<Uuid, InvitationCategory> = self.participants.values().map(|p| (p.uuid, p.category)).collect();
+        let exclusions = &self.exclusions;
+        let slot = &mut self.slots[index];
+        let mut promotions = Vec::new();
+        let mut free_sessions = vec![freed];
+        while let Some(session_id) = free_sessions.pop() {
+            let Some(session) = slot.session_by_id(session_id) else { continue; };
+            let slot_full = slot.is_full();
+            let candidate = session.waitlist.iter().find(|a| {
+                let current = slot.sessions.iter().find(|s| s.participants.contains(&a.participant));
+                let category = categories.get(&a.participant).copied().unwrap_or_default();
+                categories.contains_key(&a.participant)
+                    && session.has_free_seat() && session.has_seat_for(category, &categories)
+                    && !excluded_from(exclusions, a.participant, &session.participants)
+                    // Participants without a seat in the slot take up a new one
+                    && (current.is_some() || (!slot_full && !at_daily_limit.contains(&a.participant)))
+            }).cloned();
+            let Some(application) = candidate else { continue; };
+            let pid = application.participant;
+            let from_session = slot.sessions.iter().find(|s| s.participants.contains(&pid)).map(|s| s.uuid);
+            for session in slot.sessions.iter_mut() {
+                session.participants.retain(|p| *p != pid);
+                // Waitlists of sessions the participant doesn't prefer over the new seat
+                session.waitlist.retain(|a| a.participant != pid || dissatisfaction(a) < dissatisfaction(&application));
+            }
+            if let Some(session) = slot.session_by_id_mut(session_id) {
+                session.assign_participant(pid);
+            }
+            if let Some(from) = from_session {
+                self.seat_changes.push(SeatChange { participant_id: pid, slot_id, from_session: from, to_session: Some(session_id), changed_by: changed_by.to_string(), at: Utc::now() });
+                free_sessions.push(from);
+            }
+            // The session may have more free seats
+            free_sessions.push(session_id);
+            promotions.push(Promotion { participant_id: pid, session_id, from_session });
+        }
+        promotions
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use hkdf::hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::backend::data::{ApplicationPriority, Event, Storage};
+use crate::backend::delivery::{retry_delay, DeadWebhook, MAX_DELIVERY_ATTEMPTS};
+use crate::backend::state::{RecoverLock, Shared};
+
+/// Submissions of the same participant within this time are sent as one call
+pub const WEBHOOK_DEBOUNCE_SECONDS: i64 = 30;
+
+/// Endpoint an event's organizers receive preference submissions at, e.g. to sync a spreadsheet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    /// Key of the `X-FESD-Signature` HMAC and of the participant pseudonyms
+    pub secret: String,
+}
+
+impl Webhook {
+    pub fn new(url: String) -> Self {
+        Webhook { url, secret: format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()) }
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_from_slice(self.secret.as_bytes()).expect("HMAC takes keys of any length")
+    }
+
+    /// Stable per participant and webhook, but doesn't reveal who the participant is
+    pub fn pseudonym(&self, participant: Uuid) -> String {
+        let mut mac = self.mac();
+        mac.update(participant.as_bytes());
+        hex(&mac.finalize().into_bytes()[..8])
+    }
+
+    /// Hex encoded HMAC-SHA256 of the body, sent as `X-FESD-Signature: sha256=<hex>`
+    pub fn signature(&self, body: &[u8]) -> String {
+        let mut mac = self.mac();
+        mac.update(body);
+        hex(&mac.finalize().into_bytes())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Call waiting for its debounce time to pass
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingWebhook {
+    pub event_id: Uuid,
+    pub participant_id: Uuid,
+    pub due_at: DateTime<Utc>,
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+#[derive(Serialize)]
+struct PreferencePayload {
+    event_id: Uuid,
+    participant: String,
+    sent_at: DateTime<Utc>,
+    /// True once the participant chose a preference in every slot
+    complete: bool,
+    slots: Vec<SlotStatus>,
+}
+
+#[derive(Serialize)]
+struct SlotStatus {
+    slot_id: Uuid,
+    slot: String,
+    /// Number of preferences chosen (first to third)
+    preferences: usize,
+    complete: bool,
+}
+
+impl Event {
+    fn preference_payload(&self, webhook: &Webhook, participant_id: Uuid, now: DateTime<Utc>) -> PreferencePayload {
+        let slots: Vec<SlotStatus> = self.slots.iter().filter(|slot| !slot.sessions.is_empty()).map(|slot| {
+            let preferences = slot.sessions.iter().flat_map(|s| &s.applications)
+                .filter(|a| a.participant == participant_id && !matches!(a.priority, ApplicationPriority::NoPreference))
+                .count();
+            SlotStatus { slot_id: slot.uuid, slot: slot.name.clone(), preferences, complete: preferences > 0 }
+        }).collect();
+        PreferencePayload { event_id: self.uuid, participant: webhook.pseudonym(participant_id), sent_at: now, complete: slots.iter().all(|s| s.complete), slots }
+    }
+}
+
+impl Storage {
+    /// Schedules a webhook call for the participant's preferences, if the event has a webhook.
+    /// Another submission within the debounce time postpones the call, so only the latest state is sent.
+    /// A given-up call of the participant is outdated by the new one.
+    pub fn queue_preference_webhook(&mut self, event_id: Uuid, participant_id: Uuid, now: DateTime<Utc>) {
+        if self.events.get(&event_id).is_none_or(|ev| ev.webhook.is_none()) {
+            return;
+        }
+        self.dead_webhooks.retain(|d| d.participant_id != participant_id);
+        let due_at = now + chrono::Duration::seconds(WEBHOOK_DEBOUNCE_SECONDS);
+        self.pending_webhooks.insert(participant_id, PendingWebhook { event_id, participant_id, due_at, attempts: 0 });
+    }
+
+    /// Takes the calls whose debounce time has passed, with their URL, body and signature
+    fn take_due_webhooks(&mut self, now: DateTime<Utc>) -> Vec<(PendingWebhook, String, Vec<u8>, String)> {
+        let due: Vec<Uuid> = self.pending_webhooks.values().filter(|p| p.due_at <= now).map(|p| p.participant_id).collect();
+        due.into_iter().filter_map(|pid| {
+            let pending = self.pending_webhooks.remove(&pid)?;
+            let ev = self.events.get(&pending.event_id)?;
+            let webhook = ev.webhook.as_ref()?;
+            let body = serde_json::to_vec(&ev.preference_payload(webhook, pid, now)).ok()?;
+            let signature = webhook.signature(&body);
+            Some((pending, webhook.url.clone(), body, signature))
+        }).collect()
+    }
+}
+
+/// Calls the webhooks of preference submissions once their debounce time has passed
+#[derive(Clone)]
+pub struct WebhookSender {
+    client: reqwest::Client,
+}
+
+impl Default for WebhookSender {
+    fn default() -> Self {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build().expect("failed to build HTTP client");
+        WebhookSender { client }
+    }
+}
+
+impl WebhookSender {
+    async fn send(&self, url: &str, body: Vec<u8>, signature: &str) -> Result<(), String> {
+        let response = self.client.post(url)
+            .header("Content-Type", "application/json")
+            .header("X-FESD-Signature", format!("sha256={}", signature))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        if status.is_success() { Ok(()) } else { Err(status.to_string()) }
+    }
+
+    /// Sends all due calls. Failed calls are retried with growing delays unless the participant
+    /// submitted again in the meantime, which queues a fresh call anyway. Calls failing the last
+    /// attempt are kept as dead letters for the admins.
+    pub async fn deliver_due(&self, storage: &Shared<Storage>) {
+        let due = storage.write_unpoisoned().take_due_webhooks(Utc::now());
+        let mut retries: HashMap<Uuid, PendingWebhook> = HashMap::new();
+        let mut dead: Vec<DeadWebhook> = Vec::new();
+        for (mut pending, url, body, signature) in due {
+            if let Err(e) = self.send(&url, body, &signature).await {
+                pending.attempts += 1;
+                if pending.attempts >= MAX_DELIVERY_ATTEMPTS {
+                    eprintln!("Giving up on the webhook of event {} after {} attempts: {}", pending.event_id, pending.attempts, e);
+                    dead.push(DeadWebhook { uuid: Uuid::new_v4(), event_id: pending.event_id, participant_id: pending.participant_id, attempts: pending.attempts, last_error: e, failed_at: Utc::now() });
+                    continue;
+                }
+                eprintln!("Webhook of event {} failed, retrying later: {}", pending.event_id, e);
+                pending.due_at = Utc::now() + retry_delay(pending.attempts);
+                retries.insert(pending.participant_id, pending);
+            }
+        }
+        if retries.is_empty() && dead.is_empty() {
+            return;
+        }
+        let mut guard = storage.write_unpoisoned();
+        for (pid, pending) in retries {
+            guard.pending_webhooks.entry(pid).or_insert(pending);
+        }
+        for letter in dead {
+            // A newer submission is already on its way, the given-up call is outdated
+            if !guard.pending_webhooks.contains_key(&letter.participant_id) {
+                guard.dead_webhooks.retain(|d| d.participant_id != letter.participant_id);
+                guard.dead_webhooks.push(letter);
+            }
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Simulates participants against a running instance to measure how it behaves under load.
+//!
+//! Creates invitations for an event as admin, then lets every simulated participant log in with
+//! its code, open the event page, save a name and submit random preferences. Reports latencies
+//! per step and how often participants were queued.
+//!
+//! ```text
+//! LOADTEST_ADMIN_PASSWORD=... LOADTEST_API_TOKEN=... \
+//!   cargo run --release --features loadtest --bin loadtest -- \
+//!   --url http://localhost:8000 --event <event uuid> --participants 500
+//! ```
+//!
+//! The API token needs the read-only scope; it's used to look up the event's slots and sessions.
+//! The invitations are tagged `loadtest`, so they can be found and deleted afterwards. At most
+//! `max_bulk_invites` (see the form limits) participants can be simulated per run.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use rand_core::{OsRng, RngCore};
+use reqwest::header::{COOKIE, LOCATION, RETRY_AFTER, SET_COOKIE};
+use reqwest::redirect::Policy;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// Give up on a request after being queued this often
+const MAX_QUEUE_RETRIES: usize = 100;
+
+struct Options {
+    url: String,
+    event: Uuid,
+    participants: usize,
+    /// Participants start evenly spread over this time
+    ramp_up: Duration,
+    admin_user: String,
+    admin_password: String,
+    api_token: String,
+}
+
+impl Options {
+    fn parse() -> Result<Self, String> {
+        let mut args: HashMap<String, String> = HashMap::new();
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            let key = arg.strip_prefix("--").ok_or_else(|| format!("unexpected argument {}", arg))?.to_string();
+            let value = iter.next().ok_or_else(|| format!("missing value for --{}", key))?;
+            args.insert(key, value);
+        }
+        let env = |name: &str| std::env::var(name).map_err(|_| format!("{} is not set", name));
+        Ok(Options {
+            url: args.remove("url").unwrap_or_else(|| "http://localhost:8000".to_string()).trim_end_matches('/').to_string(),
+            event: args.remove("event").ok_or("--event is required")?.parse().map_err(|e| format!("invalid --event: {}", e))?,
+            participants: args.remove("participants").map_or(Ok(100), |n| n.parse()).map_err(|e| format!("invalid --participants: {}", e))?,
+            ramp_up: Duration::from_secs(args.remove("ramp-up").map_or(Ok(0), |n| n.parse()).map_err(|e| format!("invalid --ramp-up: {}", e))?),
+            admin_user: args.remove("admin").unwrap_or_else(|| "admin".to_string()),
+            admin_password: env("LOADTEST_ADMIN_PASSWORD")?,
+            api_token: env("LOADTEST_API_TOKEN")?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct EventStats {
+    sessions: Vec<SessionStats>,
+}
+
+#[derive(Deserialize)]
+struct SessionStats {
+    slot_id: Uuid,
+    session_id: Uuid,
+}
+
+/// Outcome of one request of a simulated participant
+struct Sample {
+    step: &'static str,
+    status: Option<StatusCode>,
+    latency: Duration,
+    /// How often the request was queued before it was handled
+    queued: usize,
+}
+
+/// Cookies of one simulated browser
+#[derive(Default)]
+struct Browser {
+    cookies: HashMap<String, String>,
+}
+
+impl Browser {
+    /// Sends the request with the stored cookies, retrying as long as the instance queues it
+    async fn send(&mut self, step: &'static str, build: impl Fn() -> RequestBuilder) -> Sample {
+        let started = Instant::now();
+        let mut queued = 0;
+        loop {
+            let cookie = self.cookies.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("; ");
+            let response = match build().header(COOKIE, cookie).send().await {
+                Ok(response) => response,
+                Err(e) => {
+                    eprintln!("{} failed: {}", step, e);
+                    return Sample { step, status: None, latency: started.elapsed(), queued };
+                }
+            };
+            for value in response.headers().get_all(SET_COOKIE).iter().filter_map(|v| v.to_str().ok()) {
+                let Some((name, rest)) = value.split_once('=') else { continue; };
+                let value = rest.split(';').next().unwrap_or_default();
+                if value.is_empty() || rest.contains("Max-Age=0") {
+                    self.cookies.remove(name);
+                } else {
+                    self.cookies.insert(name.to_string(), value.to_string());
+                }
+            }
+            let status = response.status();
+            if status != StatusCode::SERVICE_UNAVAILABLE || queued >= MAX_QUEUE_RETRIES {
+                return Sample { step, status: Some(status), latency: started.elapsed(), queued };
+            }
+            queued += 1;
+            let retry_after = response.headers().get(RETRY_AFTER).and_then(|v| v.to_str().ok()?.parse().ok()).unwrap_or(2);
+            tokio::time::sleep(Duration::from_secs(retry_after)).await;
+        }
+    }
+}
+
+fn random_below(n: usize) -> usize {
+    (OsRng.next_u64() % n as u64) as usize
+}
+
+/// Up to three distinct random sessions per slot as first, second and third preference
+fn random_preferences(program: &HashMap<Uuid, Vec<Uuid>>) -> Vec<(String, String)> {
+    let mut form = Vec::new();
+    for (slot, sessions) in program {
+        let mut choices = sessions.clone();
+        for priority in ["first", "second", "third"] {
+            if choices.is_empty() { break; }
+            let session = choices.swap_remove(random_below(choices.len()));
+            form.push((format!("{}[{}]", priority, slot), session.to_string()));
+        }
+    }
+    form
+}
+
+async fn simulate(client: Client, url: String, code: String, program: HashMap<Uuid, Vec<Uuid>>, delay: Duration) -> Vec<Sample> {
+    tokio::time::sleep(delay).await;
+    let mut browser = Browser::default();
+    let mut samples = Vec::new();
+    let login = browser.send("login", || client.post(format!("{}/login", url)).form(&[("code", code.as_str())])).await;
+    let logged_in = login.status == Some(StatusCode::SEE_OTHER) && browser.cookies.contains_key("sid");
+    samples.push(login);
+    if !logged_in { return samples; }
+    samples.push(browser.send("event page", || client.get(format!("{}/event", url))).await);
+    let name = format!("Load test {}", code);
+    samples.push(browser.send("name", || client.post(format!("{}/event/name", url)).form(&[("name", name.as_str())])).await);
+    let preferences = random_preferences(&program);
+    samples.push(browser.send("preferences", || client.post(format!("{}/event/preferences", url)).form(&preferences)).await);
+    samples
+}
+
+/// Logs in as admin and creates one invitation per simulated participant
+async fn create_invitations(client: &Client, options: &Options) -> Result<Vec<String>, String> {
+    let mut admin = Browser::default();
+    let login = admin.send("admin login", || client.post(format!("{}/login/admin", options.url))
+        .form(&[("username", options.admin_user.as_str()), ("password", options.admin_password.as_str())])).await;
+    if !admin.cookies.contains_key("sid") {
+        return Err(format!("admin login failed with {:?}", login.status));
+    }
+    let run = Uuid::new_v4().simple().to_string();
+    let codes: Vec<String> = (0..options.participants).map(|i| format!("lt-{}-{}", &run[..8], i)).collect();
+    let body = codes.join("\n");
+    let response = client.post(format!("{}/admin/events/{}/invites/bulk", options.url, options.event))
+        .header(COOKIE, format!("sid={}", admin.cookies["sid"]))
+        .form(&[("codes", body.as_str()), ("tags", "loadtest"), ("category", "guest")])
+        .send().await.map_err(|e| e.to_string())?;
+    let target = response.headers().get(LOCATION).and_then(|v| v.to_str().ok()).unwrap_or_default();
+    if response.status() != StatusCode::SEE_OTHER || !target.starts_with("/admin/events/") {
+        return Err(format!("creating invitations failed with {}", response.status()));
+    }
+    Ok(codes)
+}
+
+/// Sessions by slot of the event
+async fn load_program(client: &Client, options: &Options) -> Result<HashMap<Uuid, Vec<Uuid>>, String> {
+    let response = client.get(format!("{}/api/v1/events/{}/stats", options.url, options.event))
+        .bearer_auth(&options.api_token)
+        .send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("loading the event failed with {}", response.status()));
+    }
+    let text = response.text().await.map_err(|e| e.to_string())?;
+    let stats: EventStats = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    let mut program: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    for sess in stats.sessions {
+        program.entry(sess.slot_id).or_default().push(sess.session_id);
+    }
+    Ok(program)
+}
+
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    sorted.get((sorted.len() * p / 100).min(sorted.len().saturating_sub(1))).copied().unwrap_or_default()
+}
+
+fn report(samples: &[Sample], total: Duration) {
+    println!("{} requests in {:.1} s", samples.len(), total.as_secs_f64());
+    for step in ["login", "event page", "name", "preferences"] {
+        let step_samples: Vec<&Sample> = samples.iter().filter(|s| s.step == step).collect();
+        let mut latencies: Vec<Duration> = step_samples.iter().map(|s| s.latency).collect();
+        latencies.sort();
+        let failed = step_samples.iter().filter(|s| !s.status.is_some_and(|st| st.is_success() || st.is_redirection())).count();
+        let queued = step_samples.iter().filter(|s| s.queued > 0).count();
+        println!(
+            "{:<12} n={:<6} failed={:<5} queued={:<5} p50={:>6} ms p95={:>6} ms p99={:>6} ms max={:>6} ms",
+            step, step_samples.len(), failed, queued,
+            percentile(&latencies, 50).as_millis(), percentile(&latencies, 95).as_millis(),
+            percentile(&latencies, 99).as_millis(), latencies.last().copied().unwrap_or_default().as_millis(),
+        );
+    }
+    let mut statuses: HashMap<String, usize> = HashMap::new();
+    for sample in samples {
+        *statuses.entry(sample.status.map_or("error".to_string(), |s| s.as_u16().to_string())).or_default() += 1;
+    }
+    let mut statuses: Vec<_> = statuses.into_iter().collect();
+    statuses.sort();
+    println!("Responses: {}", statuses.iter().map(|(s, n)| format!("{} × {}", n, s)).collect::<Vec<_>>().join(", "));
+}
+
+async fn run(options: Options) -> Result<(), String> {
+    let client = Client::builder().redirect(Policy::none()).timeout(Duration::from_secs(60)).build().map_err(|e| e.to_string())?;
+    let program = load_program(&client, &options).await?;
+    if program.is_empty() {
+        return Err("the event has no sessions".to_string());
+    }
+    let codes = create_invitations(&client, &options).await?;
+    println!("Created {} invitations, starting the participants.", codes.len());
+    let started = Instant::now();
+    let handles: Vec<_> = codes.into_iter().enumerate().map(|(i, code)| {
+        let delay = options.ramp_up.mul_f64(i as f64 / options.participants as f64);
+        tokio::spawn(simulate(client.clone(), options.url.clone(), code, program.clone(), delay))
+    }).collect();
+    let mut samples = Vec::new();
+    for handle in handles {
+        samples.extend(handle.await.map_err(|e| e.to_string())?);
+    }
+    report(&samples, started.elapsed());
+    Ok(())
+}
+
+fn main() {
+    let options = match Options::parse() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("Usage: loadtest --event <uuid> [--url <base url>] [--participants <n>] [--ramp-up <seconds>] [--admin <username>]");
+            std::process::exit(2);
+        }
+    };
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build().expect("tokio runtime");
+    if let Err(e) = runtime.block_on(run(options)) {
+        eprintln!("Load test failed: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -1,390 +1,2059 @@
 use rocket::form::{Form, FromForm};
-use rocket::http::Status;
-use rocket::response::Redirect;
+use rocket::request::FlashMessage;
+use rocket::response::stream::{Event as StreamEvent, EventStream};
+use rocket::response::{Flash, Redirect};
+use rocket::serde::json::Json;
 use rocket::State;
 use rocket_dyn_templates::Template;
 use serde::Serialize;
 
-use crate::backend::auth::{Session, SessionUserType};
-use crate::backend::data::{Event, EventState, Slot, Session as EventSession, Invitation, ApplicationPriority};
-use crate::backend::state::AppState;
+use crate::backend::auth::{AdminSession, SessionUserType};
+use crate::backend::body_limit::BulkForm;
+use crate::backend::concurrency::{check_revision, form_value, EditConflict};
+use crate::backend::confirmation::CONFIRMATION_TTL;
+use crate::backend::config::AppConfig;
+use crate::backend::error::{BackendError, FlashView};
+use crate::backend::identity::Participation;
+use crate::backend::directory;
+use crate::backend::duplicates::DuplicateGroup;
+use crate::backend::event_template::EventTemplate;
+use crate::backend::data::{CategorySeats, Event, EventNote, EventState, Slot, Storage, Session as EventSession, Invitation, InvitationCategory, InviteBundle, ApplicationPriority, PriorityCounts, RegistrationMode, parse_tags, ordinal, MAX_RANKED_CHOICES};
+use crate::backend::mail::{MailAudience, OutgoingMail};
+use crate::backend::matching::AllocationMode;
+use crate::backend::no_preference::{NoPreferenceCounts, NoPreferencePolicy};
+use crate::backend::progress::{self, DistributionProgress};
+use crate::backend::quality::DistributionQuality;
+use crate::backend::rooms::Room;
+use crate::backend::solver::ExternalAssignment;
+use crate::backend::splitting::SplitPlan;
+use crate::backend::share::{ShareKind, ShareLink, MAX_SHARE_LINK_HOURS};
+use crate::backend::co_admins::{CoAdminInvite, CO_ADMIN_INVITE_DAYS};
+use crate::backend::scoring::{fairness_rules, AllocationConfig, CompensationRules, ScoringConfig, ScoringMethod};
+use crate::backend::state::{AppState, RecoverLock};
+use crate::backend::validation::Validator;
+use crate::backend::webhook::Webhook;
+use crate::gui::datetime::{format_datetime_local, format_display, parse_datetime_local};
 use uuid::Uuid;
 
 #[derive(Serialize)]
 struct AdminIndexContext {
-    events: Vec<Event>,
+    events: Vec<AdminViewEventSummary>,
+    templates: Vec<AdminViewTemplate>,
+    rooms: Vec<AdminViewRoom>,
+    /// Rooms used by two sessions at the same time
+    room_clashes: Vec<String>,
+    /// The admin only manages the events they were invited to as co-organizer
+    co_organizer: bool,
+    flash: Option<FlashView>,
+}
+
+#[derive(Serialize)]
+struct AdminViewRoom {
+    uuid: Uuid,
+    name: String,
+    capacity: usize,
+    accessible: bool,
+    equipment: String,
+    /// Sessions of all events held in the room
+    sessions: usize,
+}
+
+#[derive(Serialize)]
+struct AdminViewTemplate {
+    uuid: Uuid,
+    name: String,
+    slots: usize,
+    sessions: usize,
+    created_by: String,
+}
+
+/// Row of the event list. The full events (participants, applications, codes) stay out of the index.
+#[derive(Serialize)]
+struct AdminViewEventSummary {
+    uuid: Uuid,
+    name: String,
+    description: Option<String>,
+    state: EventState,
 }
 
 #[derive(FromForm)]
 pub struct CreateEventForm {
     pub name: String,
     pub description: Option<String>,
+    /// Template to copy the slots and sessions from
+    pub template: Option<Uuid>,
 }
 
+#[derive(FromForm)]
+pub struct SaveTemplateForm { pub name: String }
+
+#[derive(FromForm)]
+pub struct SwapSeatsForm { pub first: Uuid, pub second: Uuid }
+
+#[derive(FromForm)]
+pub struct RemoveSeatForm { pub participant: Uuid }
+
+#[derive(FromForm)]
+pub struct PinSeatForm { pub participant: Uuid, pub session: Uuid }
+
+#[derive(FromForm)]
+pub struct MergeParticipantsForm { pub keep: Uuid, pub duplicate: Uuid }
+
+#[derive(FromForm)]
+pub struct ExclusionForm { pub first: Uuid, pub second: Uuid }
+
+#[derive(FromForm)]
+pub struct ParticipantMailForm { pub audience: String, pub subject: String, pub body: String }
+
 #[derive(Serialize, Clone)]
 struct AdminViewSession {
     uuid: Uuid,
+    /// Revision the edit form is based on
+    revision: u64,
     name: String,
     description: Option<String>,
     seats: usize,
+    eligibility_tags: String,
+    tags: String,
+    /// Label shared with the instances of the session in other slots
+    content: Option<String>,
+    online: bool,
+    join_url: Option<String>,
+    attraction_bonus: usize,
+    accessible: bool,
+    category_seats: CategorySeats,
+    room: Option<Uuid>,
+    room_name: Option<String>,
     assigned_names: Vec<String>,
     first_pref_count: usize,
     second_pref_count: usize,
     third_pref_count: usize,
+    /// Fourth and later choices
+    later_pref_count: usize,
+    /// Participants who marked the session as "maybe"
+    interest_count: usize,
+    /// Participants who can't attend the session
+    avoid_count: usize,
+    acknowledgment: Option<String>,
+    acknowledgment_count: usize,
+    feedback_responses: usize,
+    /// Average rating with one decimal, None without feedback
+    feedback_average: Option<String>,
+    /// Far more first preferences than seats, the split assistant is offered
+    oversubscribed: bool,
+    /// "Name (1st)" of the applicants waiting for a seat, best ranked first
+    waitlist: Vec<String>,
 }
 
 #[derive(Serialize, Clone)]
 struct AdminViewSlot {
     uuid: Uuid,
+    revision: u64,
     name: String,
     description: Option<String>,
+    max_participants: Option<usize>,
+    /// Slot deadline formatted for the datetime-local input
+    preference_deadline_local: Option<String>,
+    starts_at_local: Option<String>,
+    ends_at_local: Option<String>,
+    ranked_choices: usize,
+    allocated_count: usize,
+    /// Names of participants who didn't fit under the slot's participant cap
+    waiting_names: Vec<String>,
+    /// Assigned seats of the slot for the swap tool, empty before the distribution
+    seats: Vec<AdminViewSeat>,
+    /// Seats reserved for participants ahead of the distribution
+    pins: Vec<AdminViewSeat>,
     sessions: Vec<AdminViewSession>,
 }
 
+#[derive(Serialize, Clone)]
+struct AdminViewSeat {
+    participant_id: Uuid,
+    name: String,
+    session: String,
+}
+
+#[derive(Serialize)]
+struct AdminViewSeatChange {
+    at: String,
+    participant: String,
+    slot: String,
+    from: String,
+    to: String,
+    changed_by: String,
+}
+
+#[derive(Serialize)]
+struct AdminViewInvite {
+    code: String,
+    tags: String,
+    category: InvitationCategory,
+    email: Option<String>,
+    /// Name suggested to the participant, e.g. from the member directory
+    name: Option<String>,
+    /// Individual registration deadline, formatted for display and for the datetime-local input
+    deadline_extension: Option<String>,
+    deadline_extension_local: Option<String>,
+    needs_accessible_room: bool,
+    /// Group leader who created the code from a bundle
+    bundle_leader: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AdminViewShareLink {
+    uuid: Uuid,
+    kind: ShareKind,
+    label: String,
+    link: String,
+    created_by: String,
+    expires_at: String,
+    expired: bool,
+}
+
+#[derive(Serialize)]
+struct AdminViewCoAdminInvite {
+    uuid: Uuid,
+    email: String,
+    invited_by: String,
+    expires_at: String,
+    expired: bool,
+}
+
+#[derive(Serialize)]
+struct AdminViewBundle {
+    uuid: Uuid,
+    leader: String,
+    /// Link for the group leader, None once revoked
+    link: Option<String>,
+    size: usize,
+    /// Codes created so far and how many of them were used to register
+    created: usize,
+    registered: usize,
+    tags: String,
+    category: InvitationCategory,
+    created_by: String,
+    created_at: String,
+}
+
+#[derive(Serialize)]
+struct AdminViewParticipant {
+    uuid: Uuid,
+    name: String,
+    code: String,
+    category: InvitationCategory,
+    tags: String,
+    points_from_previous_rounds: usize,
+    needs_accessible_room: bool,
+    /// Confirmed address if the participant linked their registrations across events
+    linked_email: Option<String>,
+    /// Other events of this tenant the linked person took part in
+    history: Vec<Participation>,
+}
+
+#[derive(Serialize)]
+struct AdminViewDistributionRun {
+    started_at: String,
+    duration_ms: u64,
+    algorithm: String,
+    seed: Option<u64>,
+    participants: usize,
+    applications: usize,
+    assigned_seats: usize,
+    assigned_by_priority: PriorityCounts,
+    without_preferences: NoPreferenceCounts,
+    initiated_by: String,
+}
+
+#[derive(Serialize)]
+struct AdminViewStateChange {
+    from: EventState,
+    to: EventState,
+    at: String,
+}
+
+#[derive(Serialize)]
+struct AdminViewExclusion {
+    uuid: Uuid,
+    first: String,
+    second: String,
+    created_by: String,
+    created_at: String,
+}
+
+#[derive(Serialize)]
+struct AdminViewNote {
+    uuid: Uuid,
+    author: String,
+    text: String,
+    created_at: String,
+    /// Only the author may delete a note
+    can_delete: bool,
+}
+
+#[derive(Serialize)]
+struct AdminViewTimelineEntry {
+    at: String,
+    source: &'static str,
+    badge_class: &'static str,
+    title: String,
+    details: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct AdminTimelineContext {
+    event_uuid: Uuid,
+    event_name: String,
+    code: String,
+    participant_name: Option<String>,
+    email: Option<String>,
+    entries: Vec<AdminViewTimelineEntry>,
+}
+
+#[derive(Serialize)]
+struct ConfirmDeleteContext {
+    flash: Option<FlashView>,
+    event_uuid: Uuid,
+    event_name: String,
+    token: String,
+    valid_minutes: u64,
+}
+
 #[derive(Serialize)]
 struct AdminEventContext {
+    flash: Option<FlashView>,
     event: Event,
-    invite_codes: Vec<String>,
+    invites: Vec<AdminViewInvite>,
+    bundles: Vec<AdminViewBundle>,
+    share_links: Vec<AdminViewShareLink>,
+    co_admin_invites: Vec<AdminViewCoAdminInvite>,
+    /// Usernames of the co-organizers limited to this event
+    co_admins: Vec<String>,
+    participants: Vec<AdminViewParticipant>,
     view_slots: Vec<AdminViewSlot>,
     can_close_and_distribute: bool,
-    is_finished: bool,
+    /// True once seats have been assigned (including results awaiting confirmation)
+    is_distributed: bool,
+    can_publish: bool,
+    /// States the admin can switch to manually
+    next_states: Vec<EventState>,
+    state_history: Vec<AdminViewStateChange>,
+    distribution_runs: Vec<AdminViewDistributionRun>,
+    seat_changes: Vec<AdminViewSeatChange>,
+    /// Codes of participants who logged in once but never entered anything
+    unused_participants: Vec<String>,
+    /// Participants who probably registered twice, only checked before the distribution
+    duplicates: Vec<DuplicateGroup>,
+    notes: Vec<AdminViewNote>,
+    /// Participants kept apart, confidential to admins
+    exclusions: Vec<AdminViewExclusion>,
+    /// Excluded participants who currently share a session
+    exclusion_violations: Vec<String>,
+    /// Sessions that look like the same workshop in several slots but lack a shared content label
+    content_hints: Vec<String>,
+    /// Deadline formatted for the datetime-local input
+    registration_deadline_local: Option<String>,
+    waiting_list: Vec<AdminViewWaitingEntry>,
+    /// Whether a member directory is configured for imports
+    directory_enabled: bool,
+    /// Running distribution, or the error of the last one that failed
+    distribution_progress: Option<DistributionProgress>,
+    /// Rating of the assignment, shown until the results are published
+    quality: Option<DistributionQuality>,
+    /// Rooms sessions can be placed in
+    rooms: Vec<AdminViewRoom>,
+    /// Ranking and compensation rules as participants see them
+    fairness_rules: Vec<String>,
+    /// Events of the tenant with published results to import fairness points from
+    carryover_sources: Vec<AdminViewOption>,
+}
+
+#[derive(Serialize)]
+struct AdminViewWaitingEntry {
+    position: usize,
+    code: String,
+    joined_at: String,
+}
+
+#[derive(Serialize)]
+struct AdminViewMail {
+    uuid: Uuid,
+    recipient: Option<String>,
+    recipient_name: String,
+    subject: String,
+    body: String,
+    created_at: String,
+    last_error: Option<String>,
+    attempts: u32,
+    /// Next automatic attempt after a failure
+    next_attempt_at: Option<String>,
 }
 
+impl From<&OutgoingMail> for AdminViewMail {
+    fn from(m: &OutgoingMail) -> Self {
+        AdminViewMail {
+            uuid: m.uuid,
+            recipient: m.recipient.clone(),
+            recipient_name: m.recipient_name.clone(),
+            subject: m.subject.clone(),
+            body: m.body.clone(),
+            created_at: format_display(&m.created_at),
+            last_error: m.last_error.clone(),
+            attempts: m.attempts,
+            next_attempt_at: m.next_attempt_at.as_ref().map(format_display),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct AdminViewDeadWebhook {
+    uuid: Uuid,
+    event_name: String,
+    attempts: u32,
+    last_error: String,
+    failed_at: String,
+}
+
+/// Preview of a session split, applied with the same target slot and room
+#[derive(Serialize)]
+struct SplitPreviewContext {
+    event_uuid: Uuid,
+    event_name: String,
+    slot_name: String,
+    session_name: String,
+    seats: usize,
+    /// First preferences per seat with one decimal
+    demand: String,
+    target_slot_name: String,
+    plan: SplitPlan,
+    /// Slots the copy can be placed in, the session's own slot first
+    slots: Vec<AdminViewOption>,
+    rooms: Vec<AdminViewOption>,
+    room: Option<Uuid>,
+    /// Seats of the copy, fewer than the original if the chosen room is smaller
+    copy_seats: usize,
+    moving_count: usize,
+}
+
+/// Printable sign-in sheets, one page per session
+#[derive(Serialize)]
+struct SignInSheetsContext {
+    event_uuid: Uuid,
+    event_name: String,
+    sheets: Vec<SignInSheet>,
+}
+
+#[derive(Serialize)]
+struct SignInSheet {
+    slot_name: String,
+    session_name: String,
+    /// Start and end of the slot, if set
+    time: Option<String>,
+    room_name: Option<String>,
+    seats: usize,
+    /// Assigned attendees first, then blank lines for walk-ins up to the seats
+    rows: Vec<SignInRow>,
+}
+
+#[derive(Serialize)]
+struct SignInRow {
+    number: usize,
+    name: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AdminViewOption {
+    uuid: Uuid,
+    label: String,
+    selected: bool,
+}
+
+/// Mail to participants as it will be sent, shown before anything is queued
+#[derive(Serialize)]
+struct AdminMailPreviewContext {
+    event_uuid: Uuid,
+    event_name: String,
+    /// Submitted form values, sent again on confirmation
+    audience: String,
+    subject: String,
+    body: String,
+    audience_label: String,
+    /// Rendered mails of the first recipients
+    samples: Vec<AdminViewMail>,
+    recipient_names: Vec<String>,
+    recipient_count: usize,
+    /// Recipients without email address, their mails have to be forwarded from the outbox
+    without_address: usize,
+}
+
+#[derive(Serialize)]
+struct AdminOutboxContext {
+    flash: Option<FlashView>,
+    pending: Vec<AdminViewMail>,
+    /// Mails given up after the last delivery attempt
+    dead_mails: Vec<AdminViewMail>,
+    dead_webhooks: Vec<AdminViewDeadWebhook>,
+    sent_count: usize,
+}
+
+#[derive(FromForm)]
+pub struct SetStateForm { pub state: EventState }
+
+#[derive(FromForm)]
+pub struct PublishResultsForm { pub published: bool }
+
+/// State change applied to several events at once from the admin index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub enum BulkAction { Open, Close, Distribute }
+
+#[derive(FromForm)]
+pub struct BulkStateForm { pub events: Vec<Uuid>, pub action: BulkAction }
+
+#[derive(FromForm)]
+pub struct KioskModeForm { pub enabled: bool }
+
+#[derive(FromForm)]
+pub struct CreateBundleForm { pub leader: String, pub size: usize, pub tags: Option<String>, pub category: InvitationCategory }
+
 #[derive(FromForm)]
-pub struct SetStateForm { pub state: String }
+pub struct ShareLinkForm { pub kind: ShareKind, pub label: String, pub hours: i64 }
 
 #[derive(FromForm)]
-pub struct CreateSlotForm { pub name: String, pub description: Option<String> }
+pub struct CoAdminInviteForm { pub email: String }
 
 #[derive(FromForm)]
-pub struct EditSlotForm { pub name: String, pub description: Option<String> }
+pub struct WebhookForm { pub url: Option<String> }
 
 #[derive(FromForm)]
-pub struct CreateSessionForm { pub name: String, pub description: Option<String>, pub seats: usize }
+pub struct NoteForm { pub text: String }
 
 #[derive(FromForm)]
-pub struct EditSessionForm { pub name: String, pub description: Option<String>, pub seats: usize }
+pub struct CreateSlotForm { pub name: String, pub description: Option<String>, pub max_participants: Option<usize>, pub starts_at: Option<String> }
 
 #[derive(FromForm)]
-pub struct BulkInvitesForm { pub codes: String }
+pub struct EditSlotForm { pub name: String, pub description: Option<String>, pub max_participants: Option<usize>, pub preference_deadline: Option<String>, pub starts_at: Option<String>, pub ends_at: Option<String>, pub ranked_choices: usize, pub revision: u64 }
+
+#[derive(FromForm)]
+pub struct CreateSessionForm { pub name: String, pub description: Option<String>, pub seats: usize, pub eligibility_tags: Option<String>, pub tags: Option<String>, pub content: Option<String>, pub online: bool, pub join_url: Option<String>, pub attraction_bonus: Option<usize>, pub accessible: bool, pub acknowledgment: Option<String> }
+
+#[derive(FromForm)]
+pub struct EditSessionForm { pub name: String, pub description: Option<String>, pub seats: usize, pub eligibility_tags: Option<String>, pub tags: Option<String>, pub content: Option<String>, pub online: bool, pub join_url: Option<String>, pub attraction_bonus: Option<usize>, pub accessible: bool, pub guest_seats: Option<usize>, pub member_seats: Option<usize>, pub speaker_seats: Option<usize>, pub room: Option<Uuid>, pub acknowledgment: Option<String>, pub revision: u64 }
+
+#[derive(FromForm)]
+pub struct BulkInvitesForm { pub codes: String, pub tags: Option<String>, pub category: InvitationCategory }
+
+#[derive(FromForm)]
+pub struct EditInviteForm { pub tags: Option<String>, pub category: InvitationCategory, pub email: Option<String>, pub deadline_extension: Option<String>, pub needs_accessible_room: bool }
+
+#[derive(FromForm)]
+pub struct EventSettingsForm {
+    pub registration_deadline: Option<String>,
+    pub reminder_days_before_deadline: Option<u32>,
+    pub max_participants: Option<usize>,
+    pub registration_mode: RegistrationMode,
+    pub feedback_open: bool,
+    pub show_peer_names: bool,
+    pub anonymous_rosters: bool,
+    pub max_sessions_per_day: Option<usize>,
+    pub auto_distribute: bool,
+    pub auto_publish_after_hours: Option<u32>,
+    pub scoring: ScoringMethod,
+    pub allocation: AllocationMode,
+    pub no_preference_policy: NoPreferencePolicy,
+    pub revision: u64,
+}
+
+#[derive(FromForm)]
+pub struct DirectoryImportForm { pub group: Option<String>, pub tags: Option<String>, pub category: InvitationCategory }
+
+#[derive(FromForm)]
+pub struct CategoryBonusesForm { pub guest: i64, pub member: i64, pub speaker: i64 }
+
+#[derive(FromForm)]
+pub struct CompensationForm { pub cap: usize, pub decay_percent: u32 }
+
+#[derive(FromForm)]
+pub struct CarryoverImportForm { pub source: Uuid }
+
+#[derive(FromForm)]
+pub struct AllocationConfigForm {
+    pub first_points: usize,
+    pub second_points: usize,
+    pub third_points: usize,
+    pub first_compensation: usize,
+    pub second_compensation: usize,
+    pub third_compensation: usize,
+    pub unchosen_compensation: usize,
+}
+
+#[derive(FromForm)]
+pub struct ScoringImportForm { pub config: String }
+
+#[derive(FromForm)]
+pub struct AssignmentImportForm { pub assignment: String }
+
+#[derive(FromForm)]
+pub struct SplitSessionForm { pub target: Uuid, pub room: Option<Uuid> }
+
+#[derive(FromForm)]
+pub struct WelcomeTextForm { pub text: String, pub revision: u64 }
+
+#[derive(FromForm)]
+pub struct DeleteEventForm { pub token: String, pub name: String }
+
+#[derive(FromForm)]
+pub struct RoomForm { pub name: String, pub capacity: usize, pub accessible: bool, pub equipment: Option<String> }
+
+fn preference_label(priority: &ApplicationPriority) -> String {
+    priority.rank().map_or_else(|| "no preference".to_string(), ordinal)
+}
+
+fn slot_conflict(event_id: Uuid, slot: &Slot, form: &EditSlotForm) -> EditConflict {
+    EditConflict::new("slot", &slot.name, format!("/admin/events/{}/slots/{}/edit", event_id, slot.uuid), slot.revision, format!("/admin/events/{}#slot-{}", event_id, slot.uuid))
+        .field("name", "Name", &form.name, &slot.name)
+        .field("description", "Description", form_value(&form.description), form_value(&slot.description))
+        .field("max_participants", "Participant cap", form_value(&form.max_participants), form_value(&slot.max_participants))
+        .field("preference_deadline", "Preference deadline", form_value(&form.preference_deadline), form_value(&slot.preference_deadline.as_ref().map(format_datetime_local)))
+        .field("starts_at", "Start", form_value(&form.starts_at), form_value(&slot.starts_at.as_ref().map(format_datetime_local)))
+        .field("ranked_choices", "Ranked choices", form.ranked_choices, slot.ranked_choices)
+        .field("ends_at", "End", form_value(&form.ends_at), form_value(&slot.ends_at.as_ref().map(format_datetime_local)))
+}
+
+fn session_conflict(event_id: Uuid, slot_id: Uuid, sess: &EventSession, form: &EditSessionForm) -> EditConflict {
+    EditConflict::new("session", &sess.name, format!("/admin/events/{}/slots/{}/sessions/{}/edit", event_id, slot_id, sess.uuid), sess.revision, format!("/admin/events/{}#slot-{}", event_id, slot_id))
+        .field("name", "Name", &form.name, &sess.name)
+        .field("description", "Description", form_value(&form.description), form_value(&sess.description))
+        .field("seats", "Seats", form.seats, sess.seats)
+        .field("guest_seats", "Reserved for guests", form_value(&form.guest_seats), form_value(&sess.category_seats.guest))
+        .field("member_seats", "Reserved for members", form_value(&form.member_seats), form_value(&sess.category_seats.member))
+        .field("speaker_seats", "Reserved for speakers", form_value(&form.speaker_seats), form_value(&sess.category_seats.speaker))
+        .field("eligibility_tags", "Eligibility tags", form_value(&form.eligibility_tags), sess.eligibility_tags.join(", "))
+        .field("tags", "Tags", form_value(&form.tags), sess.tags.join(", "))
+        .field("content", "Content label", form_value(&form.content), form_value(&sess.content))
+        .field("online", "Online", form.online, sess.online)
+        .field("join_url", "Join URL", form_value(&form.join_url), form_value(&sess.join_url))
+        .field("attraction_bonus", "Attraction bonus", form.attraction_bonus.unwrap_or(0), sess.attraction_bonus)
+        .field("accessible", "Accessible", form.accessible, sess.accessible)
+        .field("room", "Room", form_value(&form.room), form_value(&sess.room))
+        .field("acknowledgment", "Conditions to accept", form_value(&form.acknowledgment), form_value(&sess.acknowledgment))
+}
+
+fn settings_conflict(ev: &Event, form: &EventSettingsForm) -> EditConflict {
+    EditConflict::new("event settings", &ev.name, format!("/admin/events/{}/settings", ev.uuid), ev.revision, format!("/admin/events/{}", ev.uuid))
+        .field("registration_deadline", "Registration deadline", form_value(&form.registration_deadline), form_value(&ev.registration_deadline.as_ref().map(format_datetime_local)))
+        .field("reminder_days_before_deadline", "Reminder days before the deadline", form_value(&form.reminder_days_before_deadline), form_value(&ev.reminder_days_before_deadline))
+        .field("max_participants", "Registration cap", form_value(&form.max_participants), form_value(&ev.max_participants))
+        .field("registration_mode", "Registration", format!("{:?}", form.registration_mode), format!("{:?}", ev.registration_mode))
+        .field("feedback_open", "Feedback open", form.feedback_open, ev.feedback_open)
+        .field("show_peer_names", "Show peer names", form.show_peer_names, ev.show_peer_names)
+        .field("anonymous_rosters", "Anonymous rosters", form.anonymous_rosters, ev.anonymous_rosters)
+        .field("max_sessions_per_day", "Sessions per day", form_value(&form.max_sessions_per_day), form_value(&ev.max_sessions_per_day))
+        .field("auto_distribute", "Distribute automatically", form.auto_distribute, ev.auto_distribute)
+        .field("auto_publish_after_hours", "Publish after hours", form_value(&form.auto_publish_after_hours), form_value(&ev.auto_publish_after_hours))
+        .field("scoring", "Scoring", format!("{:?}", form.scoring), format!("{:?}", ev.scoring))
+        .field("allocation", "Allocation", format!("{:?}", form.allocation), format!("{:?}", ev.allocation))
+        .field("no_preference_policy", "Participants without preferences", format!("{:?}", form.no_preference_policy), format!("{:?}", ev.no_preference_policy))
+}
+
+fn room_views(storage: &Storage, tenant: Option<&str>) -> Vec<AdminViewRoom> {
+    storage.tenant_rooms(tenant).into_iter()
+        .map(|r| AdminViewRoom { uuid: r.uuid, name: r.name.clone(), capacity: r.capacity, accessible: r.accessible, equipment: r.equipment.join(", "), sessions: storage.room_bookings(r.uuid).len() })
+        .collect()
+}
 
 #[get("/admin")]
-pub fn admin_index(session: Session, state: &State<AppState>) -> Result<Template, Status> {
-    match session.user_type {
-        SessionUserType::Admin => {
-            let storage = state.storage.read().expect("storage poisoned");
-            let events : Vec<Event> = storage.events.values().cloned().collect();
-            let ctx = AdminIndexContext { events };
-            Ok(Template::render("admin/index", &ctx))
-        }
-        _ => Err(Status::Forbidden),
-    }
+pub fn admin_index(admin: AdminSession, state: &State<AppState>, flash: Option<FlashMessage<'_>>) -> Result<Template, BackendError> {
+    let storage = state.storage.read_unpoisoned();
+    let events: Vec<AdminViewEventSummary> = storage.events.values()
+        .filter(|ev| ev.tenant == admin.tenant && admin.events.as_ref().is_none_or(|events| events.contains(&ev.uuid)))
+        .map(|ev| AdminViewEventSummary { uuid: ev.uuid, name: ev.name.clone(), description: ev.description.clone(), state: ev.state })
+        .collect();
+    // Templates, rooms and clashes span the tenant's other events, co-organizers don't see them
+    let co_organizer = admin.events.is_some();
+    let mut templates: Vec<AdminViewTemplate> = storage.event_templates.values()
+        .filter(|t| t.tenant == admin.tenant && !co_organizer)
+        .map(|t| AdminViewTemplate { uuid: t.uuid, name: t.name.clone(), slots: t.slots.len(), sessions: t.session_count(), created_by: t.created_by.clone() })
+        .collect();
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    let rooms = if co_organizer { Vec::new() } else { room_views(&storage, admin.tenant.as_deref()) };
+    let clashes = if co_organizer { Vec::new() } else { storage.room_clashes(admin.tenant.as_deref()) };
+    let room_clashes = clashes.into_iter()
+        .map(|clash| format!(
+            "{}: {} / {} ({}) overlaps {} / {} ({})",
+            clash.room_name,
+            clash.first.event_name, clash.first.session_name, format_display(&clash.first.starts_at),
+            clash.second.event_name, clash.second.session_name, format_display(&clash.second.starts_at),
+        ))
+        .collect();
+    let ctx = AdminIndexContext { events, templates, rooms, room_clashes, co_organizer, flash: FlashView::from_flash(flash) };
+    Ok(Template::render("admin/index", &ctx))
 }
 
 #[get("/admin/events/<event_id>")]
-pub fn event_view(session: Session, state: &State<AppState>, event_id: Uuid) -> Result<Template, Status> {
-    match session.user_type {
-        SessionUserType::Admin => {
-            let storage = state.storage.read().expect("storage poisoned");
-            match storage.events.get(&event_id) {
-                Some(ev) => {
-                    let invite_codes: Vec<String> = storage
-                        .invitations_codes
-                        .iter()
-                        .filter_map(|(code, inv)| if inv.event_id == event_id { Some(code.clone()) } else { None })
-                        .collect();
-                    // Build view model with assigned names (only non-empty after Finished)
-                    let mut view_slots: Vec<AdminViewSlot> = Vec::new();
-                    // We need access to participants map for name lookup
-                    let participants = &ev.participants;
-                    for slot in &ev.slots {
-                        let mut v_sessions: Vec<AdminViewSession> = Vec::new();
-                        for sess in &slot.sessions {
-                            let assigned_names: Vec<String> = if matches!(ev.state, EventState::Finished) {
-                                sess.participants.iter()
-                                    .filter_map(|pid| participants.get(pid).map(|p| p.name.clone()))
-                                    .collect()
-                            } else { Vec::new() };
-                            let mut first_pref_count = 0usize;
-                            let mut second_pref_count = 0usize;
-                            let mut third_pref_count = 0usize;
-                            for app in &sess.applications {
-                                match app.priority {
-                                    ApplicationPriority::FirstPreference => first_pref_count += 1,
-                                    ApplicationPriority::SecondPreference => second_pref_count += 1,
-                                    ApplicationPriority::ThirdPreference => third_pref_count += 1,
-                                    ApplicationPriority::NoPreference => {}
-                                }
-                            }
-                            v_sessions.push(AdminViewSession {
-                                uuid: sess.uuid,
-                                name: sess.name.clone(),
-                                description: sess.description.clone(),
-                                seats: sess.seats,
-                                assigned_names,
-                                first_pref_count,
-                                second_pref_count,
-                                third_pref_count,
-                            });
-                        }
-                        view_slots.push(AdminViewSlot {
-                            uuid: slot.uuid,
-                            name: slot.name.clone(),
-                            description: slot.description.clone(),
-                            sessions: v_sessions,
-                        })
-                    }
-                    let can_close_and_distribute = matches!(ev.state, EventState::OpenForRegistration);
-                    let is_finished = matches!(ev.state, EventState::Finished);
-                    let ctx = AdminEventContext { event: ev.clone(), invite_codes, view_slots, can_close_and_distribute, is_finished };
-                    Ok(Template::render("admin/event", &ctx))
+pub fn event_view(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, flash: Option<FlashMessage<'_>>) -> Result<Template, BackendError> {
+    let storage = state.storage.read_unpoisoned();
+    let ev = storage.tenant_event(admin.tenant.as_deref(), event_id)?;
+    let mut invites: Vec<AdminViewInvite> = storage
+        .invitations_codes
+        .iter()
+        .filter(|(_, inv)| inv.event_id == event_id)
+        .map(|(code, inv)| AdminViewInvite {
+            code: code.clone(),
+            tags: inv.tags.join(", "),
+            category: inv.category,
+            email: inv.email.clone(),
+            name: inv.name.clone(),
+            deadline_extension: inv.deadline_extension.as_ref().map(format_display),
+            deadline_extension_local: inv.deadline_extension.as_ref().map(format_datetime_local),
+            needs_accessible_room: inv.needs_accessible_room,
+            bundle_leader: inv.bundle.and_then(|id| ev.bundles.iter().find(|b| b.uuid == id)).map(|b| b.leader.clone()),
+        })
+        .collect();
+    invites.sort_by(|a, b| a.code.cmp(&b.code));
+    let bundles = ev.bundles.iter()
+        .map(|bundle| {
+            let codes = storage.bundle_invitations(bundle.uuid);
+            AdminViewBundle {
+                uuid: bundle.uuid,
+                leader: bundle.leader.clone(),
+                link: bundle.token.as_ref().map(|token| config.url(&format!("/bundles/{}", token))),
+                size: bundle.size,
+                created: codes.len(),
+                registered: codes.iter().filter(|inv| inv.participant_id.is_some()).count(),
+                tags: bundle.tags.join(", "),
+                category: bundle.category,
+                created_by: bundle.created_by.clone(),
+                created_at: format_display(&bundle.created_at),
+            }
+        })
+        .collect();
+    let now = chrono::Utc::now();
+    let share_links = ev.share_links.iter()
+        .map(|link| AdminViewShareLink {
+            uuid: link.uuid,
+            kind: link.kind,
+            label: link.label.clone(),
+            link: config.url(&format!("/share/{}", link.token)),
+            created_by: link.created_by.clone(),
+            expires_at: format_display(&link.expires_at),
+            expired: link.is_expired(now),
+        })
+        .collect();
+    let co_admin_invites = ev.co_admin_invites.iter()
+        .map(|invite| AdminViewCoAdminInvite {
+            uuid: invite.uuid,
+            email: invite.email.clone(),
+            invited_by: invite.invited_by.clone(),
+            expires_at: format_display(&invite.expires_at),
+            expired: invite.is_expired(now),
+        })
+        .collect();
+    let co_admins = storage.co_admins(ev.uuid).into_iter().map(|acc| acc.username.clone()).collect();
+    let mut view_participants: Vec<AdminViewParticipant> = storage
+        .invitations_codes
+        .values()
+        .filter(|inv| inv.event_id == event_id)
+        .filter_map(|inv| {
+            let p = ev.participants.get(&inv.participant_id?)?;
+            Some(AdminViewParticipant {
+                uuid: p.uuid,
+                name: p.name.clone(),
+                code: inv.code.clone(),
+                category: p.category,
+                tags: p.tags.join(", "),
+                points_from_previous_rounds: p.points_from_previous_rounds,
+                needs_accessible_room: p.needs_accessible_room,
+                linked_email: inv.person_id.and_then(|id| storage.people.get(&id)).map(|person| person.email.clone()),
+                history: inv.person_id.map(|id| storage.participation_history(id, admin.tenant.as_deref(), event_id)).unwrap_or_default(),
+            })
+        })
+        .collect();
+    view_participants.sort_by(|a, b| a.name.cmp(&b.name));
+    // Build view model with assigned names (only non-empty after the distribution)
+    let mut view_slots: Vec<AdminViewSlot> = Vec::new();
+    // We need access to participants map for name lookup
+    let participants = &ev.participants;
+    for slot in &ev.slots {
+        let mut v_sessions: Vec<AdminViewSession> = Vec::new();
+        let mut seats: Vec<AdminViewSeat> = Vec::new();
+        let mut pins: Vec<AdminViewSeat> = Vec::new();
+        for sess in &slot.sessions {
+            pins.extend(sess.pinned.iter().filter_map(|pid| participants.get(pid))
+                .map(|p| AdminViewSeat { participant_id: p.uuid, name: p.name.clone(), session: sess.name.clone() }));
+            if ev.is_distributed() {
+                seats.extend(sess.participants.iter().filter_map(|pid| participants.get(pid))
+                    .map(|p| AdminViewSeat { participant_id: p.uuid, name: p.name.clone(), session: sess.name.clone() }));
+            }
+            let assigned_names: Vec<String> = if ev.is_distributed() {
+                sess.participants.iter()
+                    .filter_map(|pid| participants.get(pid).map(|p| ev.roster_label(p)))
+                    .collect()
+            } else { Vec::new() };
+            let mut first_pref_count = 0usize;
+            let mut second_pref_count = 0usize;
+            let mut third_pref_count = 0usize;
+            let mut later_pref_count = 0usize;
+            for app in &sess.applications {
+                match app.priority {
+                    ApplicationPriority::FirstPreference => first_pref_count += 1,
+                    ApplicationPriority::SecondPreference => second_pref_count += 1,
+                    ApplicationPriority::ThirdPreference => third_pref_count += 1,
+                    ApplicationPriority::LowerPreference(_) => later_pref_count += 1,
+                    ApplicationPriority::NoPreference => {}
                 }
-                None => Err(Status::NotFound)
             }
+            let feedback = ev.feedback_summary(sess.uuid);
+            v_sessions.push(AdminViewSession {
+                uuid: sess.uuid,
+                revision: sess.revision,
+                name: sess.name.clone(),
+                description: sess.description.clone(),
+                seats: sess.seats,
+                eligibility_tags: sess.eligibility_tags.join(", "),
+                tags: sess.tags.join(", "),
+                content: sess.content.clone(),
+                online: sess.online,
+                join_url: sess.join_url.clone(),
+                attraction_bonus: sess.attraction_bonus,
+                accessible: sess.accessible,
+                category_seats: sess.category_seats.clone(),
+                room: sess.room,
+                room_name: sess.room.and_then(|id| storage.rooms.get(&id)).map(|r| format!("{} ({} seats)", r.name, r.capacity)),
+                assigned_names,
+                first_pref_count,
+                second_pref_count,
+                third_pref_count,
+                later_pref_count,
+                interest_count: sess.interested.len(),
+                avoid_count: sess.avoided.len(),
+                acknowledgment: sess.acknowledgment.clone(),
+                acknowledgment_count: sess.acknowledgments.len(),
+                feedback_responses: feedback.responses,
+                feedback_average: feedback.average().map(|avg| format!("{:.1}", avg)),
+                oversubscribed: ev.is_oversubscribed(sess),
+                waitlist: sess.waitlist.iter()
+                    .filter_map(|a| participants.get(&a.participant).map(|p| format!("{} ({})", p.name, preference_label(&a.priority))))
+                    .collect(),
+            });
         }
-        _ => Err(Status::Forbidden),
+        view_slots.push(AdminViewSlot {
+            uuid: slot.uuid,
+            revision: slot.revision,
+            name: slot.name.clone(),
+            description: slot.description.clone(),
+            max_participants: slot.max_participants,
+            preference_deadline_local: slot.preference_deadline.as_ref().map(format_datetime_local),
+            starts_at_local: slot.starts_at.as_ref().map(format_datetime_local),
+            ends_at_local: slot.ends_at.as_ref().map(format_datetime_local),
+            ranked_choices: slot.ranked_choices,
+            allocated_count: slot.allocated_count(),
+            waiting_names: slot.waiting_list.iter().filter_map(|pid| participants.get(pid).map(|p| p.name.clone())).collect(),
+            seats,
+            pins,
+            sessions: v_sessions,
+        })
+    }
+    for slot in view_slots.iter_mut() {
+        slot.seats.sort_by(|a, b| a.name.cmp(&b.name));
+        slot.pins.sort_by(|a, b| a.name.cmp(&b.name));
     }
+    let can_close_and_distribute = ev.state.successors().contains(&EventState::AssigningSeats);
+    let distribution_progress = state.distribution_progress.read_unpoisoned().get(&event_id)
+        .filter(|p| !p.finished || p.error.is_some())
+        .cloned();
+    let is_distributed = ev.is_distributed();
+    let quality = matches!(ev.state, EventState::AwaitingConfirmation | EventState::Finished).then(|| ev.distribution_quality());
+    let can_publish = matches!(ev.state, EventState::Finished | EventState::Published);
+    let next_states: Vec<EventState> = ev.state.successors().iter().copied().filter(|s| *s != EventState::AssigningSeats).collect();
+    let notes = ev.notes.iter()
+        .map(|note| AdminViewNote { uuid: note.uuid, author: note.author.clone(), text: note.text.clone(), created_at: format_display(&note.created_at), can_delete: note.author == admin.username })
+        .collect();
+    let participant_name = |id: Uuid| ev.participants.get(&id).map(|p| p.name.clone()).unwrap_or_default();
+    let exclusions = ev.exclusions.iter()
+        .map(|e| AdminViewExclusion { uuid: e.uuid, first: participant_name(e.first), second: participant_name(e.second), created_by: e.created_by.clone(), created_at: format_display(&e.created_at) })
+        .collect();
+    let exclusion_violations = ev.exclusion_violations();
+    let state_history = ev.state_history.iter().rev()
+        .map(|change| AdminViewStateChange { from: change.from, to: change.to, at: format_display(&change.at) })
+        .collect();
+    let distribution_runs = ev.distribution_runs.iter().rev()
+        .map(|run| AdminViewDistributionRun {
+            started_at: format_display(&run.started_at),
+            duration_ms: run.duration_ms,
+            algorithm: run.algorithm.clone(),
+            seed: run.seed,
+            participants: run.participants,
+            applications: run.applications,
+            assigned_seats: run.assigned_seats,
+            assigned_by_priority: run.assigned_by_priority,
+            without_preferences: run.without_preferences,
+            initiated_by: run.initiated_by.clone(),
+        })
+        .collect();
+    let session_name = |id: Uuid| ev.session_by_id(id).map(|s| s.name.clone()).unwrap_or_default();
+    let seat_changes = ev.seat_changes.iter().rev()
+        .map(|change| AdminViewSeatChange {
+            at: format_display(&change.at),
+            participant: participants.get(&change.participant_id).map(|p| p.name.clone()).unwrap_or_default(),
+            slot: ev.slot_by_id(change.slot_id).map(|s| s.name.clone()).unwrap_or_default(),
+            from: session_name(change.from_session),
+            to: change.to_session.map_or_else(|| "no seat".to_string(), session_name),
+            changed_by: change.changed_by.clone(),
+        })
+        .collect();
+    let unused_participants = storage.unused_participants(event_id).into_iter().map(|inv| inv.code.clone()).collect();
+    let duplicates = if is_distributed { Vec::new() } else { storage.likely_duplicates(event_id) };
+    let mut carryover_sources: Vec<AdminViewOption> = if is_distributed { Vec::new() } else {
+        storage.events.values()
+            .filter(|other| other.uuid != event_id && other.tenant == ev.tenant && other.results_visible())
+            .map(|other| AdminViewOption { uuid: other.uuid, label: other.name.clone(), selected: false })
+            .collect()
+    };
+    carryover_sources.sort_by(|a, b| a.label.cmp(&b.label));
+    let registration_deadline_local = ev.registration_deadline.as_ref().map(format_datetime_local);
+    let waiting_list = ev.waiting_list.iter().enumerate()
+        .map(|(i, entry)| AdminViewWaitingEntry { position: i + 1, code: entry.code.clone(), joined_at: format_display(&entry.joined_at) })
+        .collect();
+    let ctx = AdminEventContext { flash: FlashView::from_flash(flash), event: ev.clone(), invites, bundles, share_links, co_admin_invites, co_admins, participants: view_participants, view_slots, can_close_and_distribute, is_distributed, can_publish, next_states, state_history, distribution_runs, seat_changes, unused_participants, duplicates, notes, exclusions, exclusion_violations, content_hints: ev.unlabelled_repeats(), registration_deadline_local, waiting_list, directory_enabled: config.directory.is_some(), distribution_progress, quality, rooms: room_views(&storage, admin.tenant.as_deref()), fairness_rules: fairness_rules(ev), carryover_sources };
+    Ok(Template::render("admin/event", &ctx))
 }
 
 #[post("/admin/events", data = "<form>")]
-pub fn create_event(session: Session, state: &State<AppState>, form: Form<CreateEventForm>) -> Result<Redirect, Status> {
-    match session.user_type {
-        SessionUserType::Admin => {
-            let form = form.into_inner();
-            let mut storage = state.storage.write().expect("storage poisoned");
-            let name = form.name.trim().to_string();
-            if name.is_empty() { return Err(Status::BadRequest); }
-            let event = Event::new(name, form.description.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()));
-            let id = event.uuid;
-            storage.events.insert(id, event);
-            Ok(Redirect::to("/admin"))
-        }
-        _ => Err(Status::Forbidden),
+pub fn create_event(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, form: Form<CreateEventForm>) -> Result<Redirect, BackendError> {
+    let form = form.into_inner();
+    let name = form.name.trim().to_string();
+    let description = form.description.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let mut v = Validator::new(&config.form_limits);
+    v.name("name", &name);
+    v.text("description", description.as_deref());
+    v.finish()?;
+    let mut storage = state.storage.write_unpoisoned();
+    let mut event = match form.template {
+        Some(template_id) => storage.tenant_template(admin.tenant.as_deref(), template_id)?.instantiate(name, description),
+        None => Event::new(name, description),
+    };
+    event.tenant = admin.tenant;
+    let id = event.uuid;
+    storage.events.insert(id, event);
+    Ok(Redirect::to("/admin"))
+}
+
+/// Confirmation page of the event deletion, issuing the token the deletion requires
+#[get("/admin/events/<event_id>/delete")]
+pub fn confirm_delete_event(admin: AdminSession, state: &State<AppState>, event_id: Uuid, flash: Option<FlashMessage<'_>>) -> Result<Template, BackendError> {
+    let event_name = state.storage.read_unpoisoned().tenant_event(admin.tenant.as_deref(), event_id)?.name.clone();
+    let token = state.issue_confirmation(&admin.username, &delete_event_action(event_id));
+    let ctx = ConfirmDeleteContext { flash: FlashView::from_flash(flash), event_uuid: event_id, event_name, token, valid_minutes: CONFIRMATION_TTL.as_secs() / 60 };
+    Ok(Template::render("admin/confirm_delete", &ctx))
+}
+
+fn delete_event_action(event_id: Uuid) -> String {
+    format!("delete-event:{}", event_id)
+}
+
+/// Deletes the event. Needs a token from the confirmation page and the typed event name.
+#[post("/admin/events/<event_id>/delete", data = "<form>")]
+pub fn delete_event(admin: AdminSession, state: &State<AppState>, event_id: Uuid, form: Form<DeleteEventForm>) -> Result<Flash<Redirect>, BackendError> {
+    let DeleteEventForm { token, name } = form.into_inner();
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_for_update(admin.tenant.as_deref(), event_id)?;
+    if name.trim() != ev.name.trim() {
+        return Err(BackendError::bad_request("The typed name doesn't match the event name."));
+    }
+    // Checked last, so a typo doesn't use up the token
+    if !state.consume_confirmation(&token, &admin.username, &delete_event_action(event_id)) {
+        return Err(BackendError::bad_request("The confirmation has expired. Please confirm the deletion again."));
     }
+    let event_name = ev.name.clone();
+    storage.events.remove(&event_id);
+    Ok(Flash::success(Redirect::to("/admin"), format!("Deleted event {}.", event_name)))
+}
+
+/// Saves the slot/session structure of the event as a template for new events
+#[post("/admin/events/<event_id>/template", data = "<form>")]
+pub fn save_event_template(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, form: Form<SaveTemplateForm>) -> Result<Flash<Redirect>, BackendError> {
+    let name = form.into_inner().name.trim().to_string();
+    let mut v = Validator::new(&config.form_limits);
+    v.name("name", &name);
+    v.finish()?;
+    let mut storage = state.storage.write_unpoisoned();
+    let template = EventTemplate::from_event(name, storage.tenant_event(admin.tenant.as_deref(), event_id)?, admin.username);
+    let message = format!("Saved template \"{}\" with {} slots and {} sessions.", template.name, template.slots.len(), template.session_count());
+    storage.event_templates.insert(template.uuid, template);
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}", event_id)), message))
+}
+
+#[post("/admin/templates/<template_id>/delete")]
+pub fn delete_event_template(admin: AdminSession, state: &State<AppState>, template_id: Uuid) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    storage.tenant_template(admin.tenant.as_deref(), template_id)?;
+    storage.event_templates.remove(&template_id);
+    Ok(Redirect::to("/admin"))
+}
+
+fn room_from_form(form: RoomForm, config: &AppConfig) -> Result<(String, usize, bool, Vec<String>), BackendError> {
+    let name = form.name.trim().to_string();
+    let mut v = Validator::new(&config.form_limits);
+    v.name("name", &name);
+    v.seats("capacity", form.capacity);
+    v.finish()?;
+    Ok((name, form.capacity, form.accessible, form.equipment.as_deref().map(parse_tags).unwrap_or_default()))
+}
+
+#[post("/admin/rooms", data = "<form>")]
+pub fn create_room(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, form: Form<RoomForm>) -> Result<Flash<Redirect>, BackendError> {
+    let (name, capacity, accessible, equipment) = room_from_form(form.into_inner(), config)?;
+    let mut room = Room::new(name, admin.tenant, capacity);
+    room.accessible = accessible;
+    room.equipment = equipment;
+    let message = format!("Added room {}.", room.name);
+    state.storage.write_unpoisoned().rooms.insert(room.uuid, room);
+    Ok(Flash::success(Redirect::to("/admin#rooms"), message))
 }
 
-#[post("/admin/events/<event_id>/delete")]
-pub fn delete_event(session: Session, state: &State<AppState>, event_id: Uuid) -> Result<Redirect, Status> {
-    match session.user_type {
-        SessionUserType::Admin => {
-            let mut storage = state.storage.write().expect("storage poisoned");
-            storage.events.remove(&event_id);
-            Ok(Redirect::to("/admin"))
+/// Updates the room. The capacity can't drop below the seats of a session held in it.
+#[post("/admin/rooms/<room_id>/edit", data = "<form>")]
+pub fn edit_room(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, room_id: Uuid, form: Form<RoomForm>) -> Result<Flash<Redirect>, BackendError> {
+    let (name, capacity, accessible, equipment) = room_from_form(form.into_inner(), config)?;
+    let mut storage = state.storage.write_unpoisoned();
+    storage.tenant_room(admin.tenant.as_deref(), room_id)?;
+    let too_large: Vec<String> = storage.room_bookings(room_id).into_iter()
+        .filter(|(_, _, _, seats)| *seats > capacity)
+        .map(|(event, slot, session, seats)| format!("{} / {} / {} ({} seats)", event, slot, session, seats))
+        .collect();
+    if !too_large.is_empty() {
+        return Err(BackendError::bad_request(format!("These sessions have more seats than {} people: {}", capacity, too_large.join(", "))));
+    }
+    for session in storage.events.values_mut().flat_map(|ev| ev.slots.iter_mut()).flat_map(|slot| slot.sessions.iter_mut()) {
+        if session.room == Some(room_id) {
+            session.accessible = accessible;
         }
-        _ => Err(Status::Forbidden),
     }
+    let Some(room) = storage.rooms.get_mut(&room_id) else { return Err(BackendError::NotFound("room")); };
+    room.name = name;
+    room.capacity = capacity;
+    room.accessible = accessible;
+    room.equipment = equipment;
+    Ok(Flash::success(Redirect::to("/admin#rooms"), format!("Saved room {}.", room.name)))
+}
+
+/// Deletes the room. Sessions held in it keep their seats but lose the room.
+#[post("/admin/rooms/<room_id>/delete")]
+pub fn delete_room(admin: AdminSession, state: &State<AppState>, room_id: Uuid) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    storage.tenant_room(admin.tenant.as_deref(), room_id)?;
+    storage.remove_room(room_id);
+    Ok(Redirect::to("/admin#rooms"))
+}
+
+/// Swaps the seats of two participants between sessions of the same slot
+#[post("/admin/events/<event_id>/slots/<slot_id>/swap", data = "<form>")]
+pub fn swap_seats(admin: AdminSession, state: &State<AppState>, event_id: Uuid, slot_id: Uuid, form: Form<SwapSeatsForm>) -> Result<Flash<Redirect>, BackendError> {
+    let SwapSeatsForm { first, second } = form.into_inner();
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    ev.swap_seats(slot_id, first, second, &admin.username)?;
+    let name = |id: Uuid| ev.participants.get(&id).map(|p| p.name.clone()).unwrap_or_default();
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}#slot-{}", event_id, slot_id)), format!("Swapped the seats of {} and {}.", name(first), name(second))))
+}
+
+/// Removes a participant's seat, e.g. after a cancellation. The seat goes to the session's waitlist.
+#[post("/admin/events/<event_id>/slots/<slot_id>/remove_seat", data = "<form>")]
+pub fn remove_seat(admin: AdminSession, state: &State<AppState>, event_id: Uuid, slot_id: Uuid, form: Form<RemoveSeatForm>) -> Result<Flash<Redirect>, BackendError> {
+    let participant = form.into_inner().participant;
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    let promotions = ev.remove_seat(slot_id, participant, &admin.username)?;
+    let name = |id: Uuid| ev.participants.get(&id).map(|p| p.name.clone()).unwrap_or_default();
+    let session_name = |id: Uuid| ev.session_by_id(id).map(|s| s.name.clone()).unwrap_or_default();
+    let mut message = format!("Removed the seat of {}.", name(participant));
+    if !promotions.is_empty() {
+        let moved: Vec<String> = promotions.iter().map(|p| format!("{} to {}", name(p.participant_id), session_name(p.session_id))).collect();
+        message.push_str(&format!(" Moved up from the waitlist: {}.", moved.join(", ")));
+    }
+    println!("{} removed the seat of participant {} in event {}, {} promoted from waitlists.", admin.username, participant, event_id, promotions.len());
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}#slot-{}", event_id, slot_id)), message))
+}
+
+/// Reserves a seat in a session for a participant before the distribution, e.g. for a speaker
+#[post("/admin/events/<event_id>/slots/<slot_id>/pins", data = "<form>")]
+pub fn pin_seat(admin: AdminSession, state: &State<AppState>, event_id: Uuid, slot_id: Uuid, form: Form<PinSeatForm>) -> Result<Flash<Redirect>, BackendError> {
+    let PinSeatForm { participant, session } = form.into_inner();
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    ev.pin_participant(slot_id, session, participant)?;
+    let name = ev.participants.get(&participant).map(|p| p.name.clone()).unwrap_or_default();
+    let session_name = ev.session_by_id(session).map(|s| s.name.clone()).unwrap_or_default();
+    println!("{} pinned participant {} to session {} in event {}", admin.username, participant, session, event_id);
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}#slot-{}", event_id, slot_id)), format!("{} will be seated in {}.", name, session_name)))
+}
+
+#[post("/admin/events/<event_id>/slots/<slot_id>/pins/<participant_id>/remove")]
+pub fn unpin_seat(admin: AdminSession, state: &State<AppState>, event_id: Uuid, slot_id: Uuid, participant_id: Uuid) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    ev.unpin_participant(slot_id, participant_id)?;
+    Ok(Redirect::to(format!("/admin/events/{}#slot-{}", event_id, slot_id)))
 }
 
+/// Closes the registration and distributes the seats in the background. The event page shows the progress.
 #[post("/admin/events/<event_id>/close_and_distribute")]
-pub fn close_and_distribute(session: Session, state: &State<AppState>, event_id: Uuid) -> Result<Redirect, Status> {
-    match session.user_type {
-        SessionUserType::Admin => {
-            let mut storage = state.storage.write().expect("storage poisoned");
-            let Some(ev) = storage.events.get_mut(&event_id) else { return Err(Status::NotFound); };
-            // Only allow when open for registration
-            if !matches!(ev.state, EventState::OpenForRegistration) {
-                return Err(Status::BadRequest);
-            }
-            // Move to assigning
-            ev.state = EventState::AssigningSeats;
-            // Rank all applications first
-            let ev_clone_for_ref = ev.clone();
-            for slot in ev.slots.iter_mut() {
-                for sess in slot.sessions.iter_mut() {
-                    sess.rank_applications(&ev_clone_for_ref);
-                }
+pub fn close_and_distribute(admin: AdminSession, state: &State<AppState>, event_id: Uuid) -> Result<Redirect, BackendError> {
+    let ev = {
+        let mut storage = state.storage.write_unpoisoned();
+        storage.tenant_event_for_update(admin.tenant.as_deref(), event_id)?;
+        // Only allowed when open for registration. The result has to be confirmed by an admin before participants can see it.
+        storage.begin_distribution(event_id)?
+    };
+    progress::spawn_distribution(state.storage.clone(), state.distribution_progress.clone(), ev, admin.username);
+    Ok(Redirect::to(format!("/admin/events/{}", event_id)))
+}
+
+/// Current progress of the distribution started for the event
+#[get("/admin/events/<event_id>/distribution/progress")]
+pub fn distribution_progress(admin: AdminSession, state: &State<AppState>, event_id: Uuid) -> Result<Json<DistributionProgress>, BackendError> {
+    state.storage.read_unpoisoned().tenant_event(admin.tenant.as_deref(), event_id)?;
+    let progress = state.distribution_progress.read_unpoisoned().get(&event_id).cloned();
+    progress.map(Json).ok_or(BackendError::NotFound("distribution"))
+}
+
+/// Server-sent events with the progress of the distribution, ending once it's finished
+#[get("/admin/events/<event_id>/distribution/progress/stream")]
+pub fn distribution_progress_stream(admin: AdminSession, state: &State<AppState>, event_id: Uuid) -> Result<EventStream![], BackendError> {
+    state.storage.read_unpoisoned().tenant_event(admin.tenant.as_deref(), event_id)?;
+    let progress = state.distribution_progress.clone();
+    Ok(EventStream! {
+        let mut interval = rocket::tokio::time::interval(std::time::Duration::from_millis(500));
+        loop {
+            interval.tick().await;
+            let current = progress.read_unpoisoned().get(&event_id).cloned();
+            let Some(current) = current else { break; };
+            yield StreamEvent::json(&current);
+            if current.finished { break; }
+        }
+    })
+}
+
+/// Applies one action to every selected event. Each event either completes the whole action or
+/// stays untouched; the flash message lists what happened per event.
+#[post("/admin/events/bulk", data = "<form>")]
+pub fn bulk_event_action(admin: AdminSession, state: &State<AppState>, form: Form<BulkStateForm>) -> Flash<Redirect> {
+    let BulkStateForm { events, action } = form.into_inner();
+    if events.is_empty() {
+        return Flash::error(Redirect::to("/admin"), "Please select at least one event.");
+    }
+    let mut storage = state.storage.write_unpoisoned();
+    let mut done = Vec::new();
+    let mut failed = Vec::new();
+    for event_id in events {
+        let name = match storage.tenant_event(admin.tenant.as_deref(), event_id) {
+            Ok(ev) => ev.name.clone(),
+            Err(e) => { failed.push(format!("{}: {}", event_id, e)); continue; }
+        };
+        // Each step fails before touching the event, so a failed event stays as it was
+        let result = match action {
+            BulkAction::Open => storage.tenant_event_mut(admin.tenant.as_deref(), event_id).and_then(|ev| ev.try_transition(EventState::OpenForRegistration)),
+            BulkAction::Close => storage.tenant_event_mut(admin.tenant.as_deref(), event_id).and_then(|ev| ev.try_transition(EventState::NotOpenedYet)),
+            BulkAction::Distribute => storage.distribute_event(event_id, &admin.username).map(|run| {
+                println!("Distribution of event {} by {} took {} ms, {} seats assigned.", event_id, admin.username, run.duration_ms, run.assigned_seats);
+            }),
+        };
+        match result {
+            Ok(()) => {
+                done.push(name);
+                storage.queue_state_push(event_id);
             }
-            // Allocate
-            ev.allocate_participants();
-            // Finish
-            ev.state = EventState::Finished;
-            Ok(Redirect::to(format!("/admin/events/{}", event_id)))
+            Err(e) => failed.push(format!("{}: {}", name, e)),
         }
-        _ => Err(Status::Forbidden),
     }
+    let verb = match action { BulkAction::Open => "Opened", BulkAction::Close => "Closed", BulkAction::Distribute => "Distributed" };
+    let mut summary = format!("{} {} event(s){}", verb, done.len(), if done.is_empty() { ".".to_string() } else { format!(": {}.", done.join(", ")) });
+    if failed.is_empty() {
+        return Flash::success(Redirect::to("/admin"), summary);
+    }
+    summary.push_str(&format!(" Not changed: {}", failed.join(" ")));
+    Flash::warning(Redirect::to("/admin"), summary)
+}
+
+#[post("/admin/events/<event_id>/results/publish", data = "<form>")]
+pub fn publish_results(admin: AdminSession, state: &State<AppState>, event_id: Uuid, form: Form<PublishResultsForm>) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    let target = if form.into_inner().published { EventState::Published } else { EventState::Finished };
+    ev.try_transition(target)?;
+    storage.queue_state_push(event_id);
+    Ok(Redirect::to(format!("/admin/events/{}", event_id)))
 }
 
 #[post("/admin/events/<event_id>/state", data = "<form>")]
-pub fn set_event_state(session: Session, state: &State<AppState>, event_id: Uuid, form: Form<SetStateForm>) -> Result<Redirect, Status> {
-    match session.user_type {
-        SessionUserType::Admin => {
-            let desired = form.into_inner().state;
-            let mut storage = state.storage.write().expect("storage poisoned");
-            let Some(ev) = storage.events.get_mut(&event_id) else { return Err(Status::NotFound); };
-            let target = match desired.as_str() {
-                "NotOpenedYet" => EventState::NotOpenedYet,
-                "OpenForRegistration" => EventState::OpenForRegistration,
-                _ => return Err(Status::BadRequest),
-            };
-            // Allow transitions only between these two states or no-op
-            let allowed_transition = matches!((ev.state.clone(), target.clone()),
-                (EventState::NotOpenedYet, EventState::OpenForRegistration) |
-                (EventState::OpenForRegistration, EventState::NotOpenedYet)
-            ) || std::mem::discriminant(&ev.state) == std::mem::discriminant(&target);
-
-            if allowed_transition {
-                ev.state = target;
-                Ok(Redirect::to(format!("/admin/events/{}", event_id)))
-            } else {
-                Err(Status::BadRequest)
-            }
-        }
-        _ => Err(Status::Forbidden),
+pub fn set_event_state(admin: AdminSession, state: &State<AppState>, event_id: Uuid, form: Form<SetStateForm>) -> Result<Redirect, BackendError> {
+    let desired = form.into_inner().state;
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    // Seats are only assigned through close_and_distribute, which runs the allocation
+    if desired == EventState::AssigningSeats {
+        return Err(BackendError::conflict("Use \"Close registrations & distribute seats\" to start the seat distribution."));
+    }
+    if desired != ev.state {
+        ev.try_transition(desired)?;
+        storage.queue_state_push(event_id);
     }
+    Ok(Redirect::to(format!("/admin/events/{}", event_id)))
 }
 
 #[post("/admin/events/<event_id>/slots", data = "<form>")]
-pub fn create_slot(session: Session, state: &State<AppState>, event_id: Uuid, form: Form<CreateSlotForm>) -> Result<Redirect, Status> {
-    match session.user_type {
-        SessionUserType::Admin => {
-            let mut storage = state.storage.write().expect("storage poisoned");
-            let Some(ev) = storage.events.get_mut(&event_id) else { return Err(Status::NotFound); };
-            let form = form.into_inner();
-            let name = form.name.trim().to_string();
-            if name.is_empty() { return Err(Status::BadRequest); }
-            let mut slot = Slot::new(name, form.description.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()));
-            let slot_uuid = slot.uuid;
-            // slot.sessions already empty
-            ev.slots.push(slot);
-            Ok(Redirect::to(format!("/admin/events/{}#slot-{}", event_id, slot_uuid)))
-        }
-        _ => Err(Status::Forbidden),
-    }
+pub fn create_slot(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, form: Form<CreateSlotForm>) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    let form = form.into_inner();
+    let name = form.name.trim().to_string();
+    let description = form.description.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let mut v = Validator::new(&config.form_limits);
+    v.name("name", &name);
+    v.text("description", description.as_deref());
+    v.finish()?;
+    let starts_at = parse_datetime_local(form.starts_at.as_deref().unwrap_or("")).map_err(BackendError::BadRequest)?;
+    let mut slot = Slot::new(name, description);
+    slot.max_participants = form.max_participants;
+    slot.starts_at = starts_at;
+    let slot_uuid = slot.uuid;
+    // slot.sessions already empty
+    ev.slots.push(slot);
+    ev.reindex();
+    Ok(Redirect::to(format!("/admin/events/{}#slot-{}", event_id, slot_uuid)))
 }
 
 #[post("/admin/events/<event_id>/slots/<slot_id>/edit", data = "<form>")]
-pub fn edit_slot(session: Session, state: &State<AppState>, event_id: Uuid, slot_id: Uuid, form: Form<EditSlotForm>) -> Result<Redirect, Status> {
-    match session.user_type {
-        SessionUserType::Admin => {
-            let mut storage = state.storage.write().expect("storage poisoned");
-            let Some(ev) = storage.events.get_mut(&event_id) else { return Err(Status::NotFound); };
-            let Some(slot) = ev.slots.iter_mut().find(|s| s.uuid == slot_id) else { return Err(Status::NotFound); };
-            let form = form.into_inner();
-            let name = form.name.trim().to_string();
-            if name.is_empty() { return Err(Status::BadRequest); }
-            slot.name = name;
-            slot.description = form.description.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
-            Ok(Redirect::to(format!("/admin/events/{}#slot-{}", event_id, slot_id)))
+pub fn edit_slot(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, slot_id: Uuid, form: Form<EditSlotForm>) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    let slot = ev.slot_by_id_mut(slot_id)?;
+    let form = form.into_inner();
+    check_revision(form.revision, slot.revision, || slot_conflict(event_id, slot, &form))?;
+    let name = form.name.trim().to_string();
+    let description = form.description.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let mut v = Validator::new(&config.form_limits);
+    v.name("name", &name);
+    v.text("description", description.as_deref());
+    if !(1..=MAX_RANKED_CHOICES).contains(&form.ranked_choices) {
+        v.error("ranked_choices", format!("Participants can rank between 1 and {} sessions.", MAX_RANKED_CHOICES));
+    }
+    // Lowering the number would leave saved choices participants can't see anymore
+    let ranked = slot.sessions.iter().flat_map(|s| &s.applications).filter_map(|a| a.priority.rank()).max().unwrap_or(0);
+    if form.ranked_choices < ranked {
+        v.error("ranked_choices", format!("Participants already ranked {} sessions in this slot.", ranked));
+    }
+    v.finish()?;
+    let preference_deadline = parse_datetime_local(form.preference_deadline.as_deref().unwrap_or("")).map_err(BackendError::BadRequest)?;
+    let starts_at = parse_datetime_local(form.starts_at.as_deref().unwrap_or("")).map_err(BackendError::BadRequest)?;
+    let ends_at = parse_datetime_local(form.ends_at.as_deref().unwrap_or("")).map_err(BackendError::BadRequest)?;
+    if let (Some(start), Some(end)) = (starts_at, ends_at) && end <= start {
+        return Err(BackendError::bad_request("The slot has to end after it starts."));
+    }
+    let deadline_changed = slot.preference_deadline != preference_deadline;
+    slot.name = name;
+    slot.preference_deadline = preference_deadline;
+    slot.starts_at = starts_at;
+    slot.ends_at = ends_at;
+    slot.description = description;
+    slot.max_participants = form.max_participants;
+    slot.ranked_choices = form.ranked_choices;
+    slot.revision += 1;
+    if deadline_changed {
+        // A new deadline is worth another notification
+        for p in ev.participants.values_mut() {
+            p.slot_reminders_sent.retain(|id| *id != slot_id);
         }
-        _ => Err(Status::Forbidden),
     }
+    Ok(Redirect::to(format!("/admin/events/{}#slot-{}", event_id, slot_id)))
 }
 
 #[post("/admin/events/<event_id>/slots/<slot_id>/delete")]
-pub fn delete_slot(session: Session, state: &State<AppState>, event_id: Uuid, slot_id: Uuid) -> Result<Redirect, Status> {
-    match session.user_type {
-        SessionUserType::Admin => {
-            let mut storage = state.storage.write().expect("storage poisoned");
-            let Some(ev) = storage.events.get_mut(&event_id) else { return Err(Status::NotFound); };
-            ev.slots.retain(|s| s.uuid != slot_id);
-            Ok(Redirect::to(format!("/admin/events/{}", event_id)))
-        }
-        _ => Err(Status::Forbidden),
-    }
+pub fn delete_slot(admin: AdminSession, state: &State<AppState>, event_id: Uuid, slot_id: Uuid) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    ev.slots.retain(|s| s.uuid != slot_id);
+    ev.reindex();
+    Ok(Redirect::to(format!("/admin/events/{}", event_id)))
 }
 
 #[post("/admin/events/<event_id>/slots/<slot_id>/sessions", data = "<form>")]
-pub fn create_session(session: Session, state: &State<AppState>, event_id: Uuid, slot_id: Uuid, form: Form<CreateSessionForm>) -> Result<Redirect, Status> {
-    match session.user_type {
-        SessionUserType::Admin => {
-            let mut storage = state.storage.write().expect("storage poisoned");
-            let Some(ev) = storage.events.get_mut(&event_id) else { return Err(Status::NotFound); };
-            let Some(slot) = ev.slots.iter_mut().find(|s| s.uuid == slot_id) else { return Err(Status::NotFound); };
-            let form = form.into_inner();
-            let name = form.name.trim().to_string();
-            if name.is_empty() || form.seats < 1 || form.seats > 10000 { return Err(Status::BadRequest); }
-            let sess = EventSession::new(name, form.description.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()), form.seats);
-            slot.sessions.push(sess);
-            Ok(Redirect::to(format!("/admin/events/{}#slot-{}", event_id, slot_id)))
-        }
-        _ => Err(Status::Forbidden),
-    }
+pub fn create_session(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, slot_id: Uuid, form: Form<CreateSessionForm>) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    let slot = ev.slot_by_id_mut(slot_id)?;
+    let form = form.into_inner();
+    let name = form.name.trim().to_string();
+    let description = form.description.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let mut v = Validator::new(&config.form_limits);
+    v.name("name", &name);
+    v.text("description", description.as_deref());
+    v.seats("seats", form.seats);
+    let join_url = form.join_url.map(|u| u.trim().to_string()).filter(|u| !u.is_empty());
+    v.url("join_url", join_url.as_deref());
+    let acknowledgment = form.acknowledgment.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    v.text("acknowledgment", acknowledgment.as_deref());
+    let content = form.content.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    v.text("content", content.as_deref());
+    v.finish()?;
+    let mut sess = EventSession::new(name, description, form.seats);
+    sess.eligibility_tags = form.eligibility_tags.as_deref().map(parse_tags).unwrap_or_default();
+    sess.tags = form.tags.as_deref().map(parse_tags).unwrap_or_default();
+    sess.content = content;
+    sess.online = form.online;
+    sess.join_url = join_url;
+    sess.attraction_bonus = form.attraction_bonus.unwrap_or(0);
+    sess.accessible = form.accessible;
+    sess.acknowledgment = acknowledgment;
+    slot.sessions.push(sess);
+    ev.reindex();
+    Ok(Redirect::to(format!("/admin/events/{}#slot-{}", event_id, slot_id)))
 }
 
 #[post("/admin/events/<event_id>/slots/<slot_id>/sessions/<session_id>/edit", data = "<form>")]
-pub fn edit_session(session: Session, state: &State<AppState>, event_id: Uuid, slot_id: Uuid, session_id: Uuid, form: Form<EditSessionForm>) -> Result<Redirect, Status> {
-    match session.user_type {
-        SessionUserType::Admin => {
-            let mut storage = state.storage.write().expect("storage poisoned");
-            let Some(ev) = storage.events.get_mut(&event_id) else { return Err(Status::NotFound); };
-            let Some(slot) = ev.slots.iter_mut().find(|s| s.uuid == slot_id) else { return Err(Status::NotFound); };
-            let Some(sess) = slot.sessions.iter_mut().find(|s| s.uuid == session_id) else { return Err(Status::NotFound); };
-            let form = form.into_inner();
-            let name = form.name.trim().to_string();
-            if name.is_empty() || form.seats < 1 || form.seats > 10000 { return Err(Status::BadRequest); }
-            sess.name = name;
-            sess.description = form.description.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
-            sess.seats = form.seats;
-            Ok(Redirect::to(format!("/admin/events/{}#slot-{}", event_id, slot_id)))
-        }
-        _ => Err(Status::Forbidden),
+pub fn edit_session(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, slot_id: Uuid, session_id: Uuid, form: Form<EditSessionForm>) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let form = form.into_inner();
+    let sess = storage.tenant_event(admin.tenant.as_deref(), event_id)?.slot_by_id(slot_id)?.session_by_id(session_id).ok_or(BackendError::NotFound("session"))?;
+    check_revision(form.revision, sess.revision, || session_conflict(event_id, slot_id, sess, &form))?;
+    let name = form.name.trim().to_string();
+    let description = form.description.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let mut v = Validator::new(&config.form_limits);
+    v.name("name", &name);
+    v.text("description", description.as_deref());
+    v.seats("seats", form.seats);
+    let join_url = form.join_url.map(|u| u.trim().to_string()).filter(|u| !u.is_empty());
+    v.url("join_url", join_url.as_deref());
+    let acknowledgment = form.acknowledgment.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    v.text("acknowledgment", acknowledgment.as_deref());
+    let content = form.content.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    v.text("content", content.as_deref());
+    let category_seats = CategorySeats { guest: form.guest_seats, member: form.member_seats, speaker: form.speaker_seats };
+    if category_seats.total() > form.seats {
+        v.error("seats", "More seats are reserved for categories than the session has.");
+    }
+    let room = form.room.map(|room_id| storage.tenant_room(admin.tenant.as_deref(), room_id).cloned()).transpose()?;
+    if let Some(room) = &room && form.seats > room.capacity {
+        v.error("seats", format!("{} only has room for {} people.", room.name, room.capacity));
     }
+    v.finish()?;
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    let slot = ev.slot_by_id_mut(slot_id)?;
+    let Some(sess) = slot.session_by_id_mut(session_id) else { return Err(BackendError::NotFound("session")); };
+    sess.name = name;
+    sess.description = description;
+    sess.seats = form.seats;
+    sess.eligibility_tags = form.eligibility_tags.as_deref().map(parse_tags).unwrap_or_default();
+    sess.tags = form.tags.as_deref().map(parse_tags).unwrap_or_default();
+    sess.content = content;
+    sess.online = form.online;
+    sess.join_url = join_url;
+    sess.attraction_bonus = form.attraction_bonus.unwrap_or(0);
+    // The room decides about accessibility
+    sess.accessible = room.as_ref().map_or(form.accessible, |r| r.accessible);
+    sess.category_seats = category_seats;
+    sess.room = room.map(|r| r.uuid);
+    // Participants accepted the previous wording, changed conditions have to be accepted again
+    if sess.acknowledgment != acknowledgment {
+        sess.acknowledgments.clear();
+    }
+    sess.acknowledgment = acknowledgment;
+    sess.revision += 1;
+    Ok(Redirect::to(format!("/admin/events/{}#slot-{}", event_id, slot_id)))
+}
+
+/// Preview of copying an oversubscribed session into the same or another slot and moving every
+/// other applicant to the copy. Only free rooms of the venue are offered for the copy.
+#[get("/admin/events/<event_id>/slots/<slot_id>/sessions/<session_id>/split?<target>&<room>")]
+pub fn preview_session_split(admin: AdminSession, state: &State<AppState>, event_id: Uuid, slot_id: Uuid, session_id: Uuid, target: Option<Uuid>, room: Option<Uuid>) -> Result<Template, BackendError> {
+    let storage = state.storage.read_unpoisoned();
+    let ev = storage.tenant_event(admin.tenant.as_deref(), event_id)?;
+    let target = target.unwrap_or(slot_id);
+    let plan = ev.plan_split(slot_id, session_id, target)?;
+    let slot = ev.slot_by_id(slot_id)?;
+    let session = slot.session_by_id(session_id).ok_or(BackendError::NotFound("session"))?;
+    let free_rooms = storage.free_rooms(admin.tenant.as_deref(), event_id, target);
+    let room = room.filter(|id| free_rooms.iter().any(|r| r.uuid == *id));
+    let copy_seats = room.and_then(|id| free_rooms.iter().find(|r| r.uuid == id)).map_or(session.seats, |r| session.seats.min(r.capacity));
+    let mut slots: Vec<&Slot> = ev.slots.iter().filter(|s| s.uuid != slot_id).collect();
+    slots.insert(0, slot);
+    let ctx = SplitPreviewContext {
+        event_uuid: event_id,
+        event_name: ev.name.clone(),
+        slot_name: slot.name.clone(),
+        session_name: session.name.clone(),
+        seats: session.seats,
+        demand: format!("{:.1}", session.demand()),
+        target_slot_name: ev.slot_by_id(target)?.name.clone(),
+        slots: slots.into_iter().map(|s| AdminViewOption { uuid: s.uuid, label: if s.uuid == slot_id { format!("{} (same slot)", s.name) } else { s.name.clone() }, selected: s.uuid == target }).collect(),
+        rooms: free_rooms.into_iter().map(|r| AdminViewOption { uuid: r.uuid, label: format!("{} ({} seats)", r.name, r.capacity), selected: Some(r.uuid) == room }).collect(),
+        room,
+        copy_seats,
+        moving_count: plan.moving.len(),
+        plan,
+    };
+    Ok(Template::render("admin/split_session", &ctx))
+}
+
+/// Splits the session as previewed
+#[post("/admin/events/<event_id>/slots/<slot_id>/sessions/<session_id>/split", data = "<form>")]
+pub fn split_session(admin: AdminSession, state: &State<AppState>, event_id: Uuid, slot_id: Uuid, session_id: Uuid, form: Form<SplitSessionForm>) -> Result<Flash<Redirect>, BackendError> {
+    let SplitSessionForm { target, room } = form.into_inner();
+    let mut storage = state.storage.write_unpoisoned();
+    let room = match room {
+        Some(id) => Some(storage.free_rooms(admin.tenant.as_deref(), event_id, target).into_iter().find(|r| r.uuid == id)
+            .ok_or_else(|| BackendError::conflict("The room is no longer free at that time."))?.clone()),
+        None => None,
+    };
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    let plan = ev.plan_split(slot_id, session_id, target)?;
+    ev.apply_split(&plan, room.as_ref())?;
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}#slot-{}", event_id, target)), format!("Added {} and moved {} applicants to it.", plan.name, plan.moving.len())))
 }
 
 #[post("/admin/events/<event_id>/slots/<slot_id>/sessions/<session_id>/delete")]
-pub fn delete_session(session: Session, state: &State<AppState>, event_id: Uuid, slot_id: Uuid, session_id: Uuid) -> Result<Redirect, Status> {
-    match session.user_type {
-        SessionUserType::Admin => {
-            let mut storage = state.storage.write().expect("storage poisoned");
-            let Some(ev) = storage.events.get_mut(&event_id) else { return Err(Status::NotFound); };
-            let Some(slot) = ev.slots.iter_mut().find(|s| s.uuid == slot_id) else { return Err(Status::NotFound); };
-            slot.sessions.retain(|s| s.uuid != session_id);
-            Ok(Redirect::to(format!("/admin/events/{}#slot-{}", event_id, slot_id)))
-        }
-        _ => Err(Status::Forbidden),
-    }
+pub fn delete_session(admin: AdminSession, state: &State<AppState>, event_id: Uuid, slot_id: Uuid, session_id: Uuid) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    let slot = ev.slot_by_id_mut(slot_id)?;
+    slot.sessions.retain(|s| s.uuid != session_id);
+    ev.reindex();
+    Ok(Redirect::to(format!("/admin/events/{}#slot-{}", event_id, slot_id)))
 }
 
 #[post("/admin/events/<event_id>/invites/bulk", data = "<form>")]
-pub fn add_invites_bulk(session: Session, state: &State<AppState>, event_id: Uuid, form: Form<BulkInvitesForm>) -> Result<Redirect, Status> {
-    match session.user_type {
-        SessionUserType::Admin => {
-            let BulkInvitesForm { codes } = form.into_inner();
-            let mut storage = state.storage.write().expect("storage poisoned");
-            if !storage.events.contains_key(&event_id) { return Err(Status::NotFound); }
-            for line in codes.lines() {
-                let code = line.trim();
-                if code.is_empty() { continue; }
-                if storage.invitations_codes.contains_key(code) { continue; }
-                let inv = Invitation { code: code.to_string(), event_id, participant_id: None };
-                storage.invitations_codes.insert(code.to_string(), inv);
-            }
-            Ok(Redirect::to(format!("/admin/events/{}", event_id)))
-        }
-        _ => Err(Status::Forbidden),
+pub fn add_invites_bulk(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, form: Result<BulkForm<BulkInvitesForm>, BackendError>) -> Result<Flash<Redirect>, BackendError> {
+    let BulkInvitesForm { codes, tags, category } = form?.0;
+    let tags = tags.as_deref().map(parse_tags).unwrap_or_default();
+    let codes: Vec<&str> = codes.lines().map(str::trim).filter(|c| !c.is_empty()).collect();
+    let mut v = Validator::new(&config.form_limits);
+    if codes.len() > config.form_limits.max_bulk_invites {
+        v.error("codes", format!("At most {} codes can be imported at once.", config.form_limits.max_bulk_invites));
+    }
+    for code in &codes {
+        v.code("codes", code);
+    }
+    v.finish()?;
+    let mut storage = state.storage.write_unpoisoned();
+    storage.tenant_event_for_update(admin.tenant.as_deref(), event_id)?;
+    let mut added = 0;
+    for code in codes {
+        if storage.invitations_codes.contains_key(code) { continue; }
+        let inv = Invitation::new(code.to_string(), event_id, tags.clone(), category);
+        storage.invitations_codes.insert(code.to_string(), inv);
+        added += 1;
     }
+    let back = Redirect::to(format!("/admin/events/{}", event_id));
+    Ok(match storage.capacity_warning(event_id) {
+        Some(warning) => Flash::warning(back, format!("Added {} invitations. {}", added, warning)),
+        None => Flash::success(back, format!("Added {} invitations.", added)),
+    })
+}
+
+/// Creates invitations for the members of the configured directory. Re-imports only add members
+/// who don't have an invitation for the event yet.
+#[post("/admin/events/<event_id>/invites/directory", data = "<form>")]
+pub async fn import_directory_members(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, form: Form<DirectoryImportForm>) -> Result<Flash<Redirect>, BackendError> {
+    let DirectoryImportForm { group, tags, category } = form.into_inner();
+    let tags = tags.as_deref().map(parse_tags).unwrap_or_default();
+    let group = group.map(|g| g.trim().to_string()).filter(|g| !g.is_empty());
+    let Some(directory_config) = &config.directory else { return Err(BackendError::NotFound("member directory")); };
+    state.storage.read_unpoisoned().tenant_event_for_update(admin.tenant.as_deref(), event_id)?;
+
+    let back = Redirect::to(format!("/admin/events/{}", event_id));
+    let members = match directory::from_config(directory_config).members(group.as_deref()).await {
+        Ok(members) => members,
+        Err(e) => return Ok(Flash::error(back, format!("Couldn't read the member directory: {}", e))),
+    };
+    if members.len() > config.form_limits.max_bulk_invites {
+        return Ok(Flash::error(back, format!("The directory lists {} members, at most {} can be imported at once.", members.len(), config.form_limits.max_bulk_invites)));
+    }
+    let mut storage = state.storage.write_unpoisoned();
+    // The event may have been deleted while waiting for the directory
+    storage.tenant_event_for_update(admin.tenant.as_deref(), event_id)?;
+    let summary = storage.import_members(event_id, members, &tags, category);
+    let message = format!("Imported {} new members, {} already had an invitation.", summary.added, summary.skipped);
+    Ok(match storage.capacity_warning(event_id) {
+        Some(warning) => Flash::warning(back, format!("{} {}", message, warning)),
+        None => Flash::success(back, message),
+    })
 }
 
 #[post("/admin/events/<event_id>/invites/<code>/delete")]
-pub fn delete_invite(session: Session, state: &State<AppState>, event_id: Uuid, code: &str) -> Result<Redirect, Status> {
-    match session.user_type {
-        SessionUserType::Admin => {
-            let mut storage = state.storage.write().expect("storage poisoned");
-            // Look up the invite first to validate event and capture participant id
-            if let Some(inv) = storage.invitations_codes.get(code).cloned() {
-                if inv.event_id == event_id {
-                    // If a participant was registered via this invite, remove them and their data from the event
-                    if let Some(participant_id) = inv.participant_id {
-                        if let Some(ev) = storage.events.get_mut(&event_id) {
-                            // Remove from event participants map
-                            ev.participants.remove(&participant_id);
-                            // Remove from all sessions: assigned seats and applications
-                            for slot in ev.slots.iter_mut() {
-                                for sess in slot.sessions.iter_mut() {
-                                    // remove from assigned participants
-                                    sess.participants.retain(|p| *p != participant_id);
-                                    // remove any applications by this participant
-                                    sess.applications.retain(|a| a.participant != participant_id);
-                                }
-                            }
-                        }
-                    }
-                    // Finally remove the invite code itself
-                    storage.invitations_codes.remove(code);
-                }
-            }
-            Ok(Redirect::to(format!("/admin/events/{}", event_id)))
+pub fn delete_invite(admin: AdminSession, state: &State<AppState>, event_id: Uuid, code: &str) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    storage.tenant_event_for_update(admin.tenant.as_deref(), event_id)?;
+    // Look up the invite first to validate the event
+    if storage.invitations_codes.get(code).is_some_and(|inv| inv.event_id == event_id) {
+        storage.remove_invitation(code);
+    }
+    Ok(Redirect::to(format!("/admin/events/{}", event_id)))
+}
+
+
+#[post("/admin/events/<event_id>/invites/<code>/edit", data = "<form>")]
+pub fn edit_invite(admin: AdminSession, state: &State<AppState>, event_id: Uuid, code: &str, form: Form<EditInviteForm>) -> Result<Redirect, BackendError> {
+    let EditInviteForm { tags, category, email, deadline_extension, needs_accessible_room } = form.into_inner();
+    let deadline_extension = parse_datetime_local(deadline_extension.as_deref().unwrap_or("")).map_err(BackendError::BadRequest)?;
+    let mut storage = state.storage.write_unpoisoned();
+    storage.tenant_event_for_update(admin.tenant.as_deref(), event_id)?;
+    let Some(inv) = storage.invitations_codes.get_mut(code) else { return Err(BackendError::NotFound("invitation")); };
+    if inv.event_id != event_id { return Err(BackendError::NotFound("invitation")); }
+    inv.tags = tags.as_deref().map(parse_tags).unwrap_or_default();
+    inv.category = category;
+    inv.email = email.map(|e| e.trim().to_string()).filter(|e| !e.is_empty());
+    inv.deadline_extension = deadline_extension;
+    inv.needs_accessible_room = needs_accessible_room;
+    let inv = inv.clone();
+    // Keep an already registered participant in sync with the invitation
+    if let Some(p) = inv.participant_id.and_then(|pid| storage.events.get_mut(&event_id)?.participants.get_mut(&pid)) {
+        inv.apply_to(p);
+    }
+    Ok(Redirect::to(format!("/admin/events/{}", event_id)))
+}
+
+/// Issues a new code for a leaked invitation. The participant and their preferences are kept.
+#[post("/admin/events/<event_id>/invites/<code>/rotate")]
+pub fn rotate_invite(admin: AdminSession, state: &State<AppState>, event_id: Uuid, code: &str) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    storage.tenant_event_for_update(admin.tenant.as_deref(), event_id)?;
+    if storage.invitations_codes.get(code).is_none_or(|inv| inv.event_id != event_id) {
+        return Err(BackendError::NotFound("invitation"));
+    }
+    storage.rotate_invitation_code(code).ok_or(BackendError::NotFound("invitation"))?;
+    drop(storage);
+    state.end_sessions_of(&SessionUserType::User { code: code.to_string() });
+    Ok(Redirect::to(format!("/admin/events/{}", event_id)))
+}
+
+/// Sign-in sheets to print (or save as PDF) for the sessions of the event, a slot or a single session.
+/// Lists the assigned attendees once the seats are distributed, by badge number for anonymous rosters.
+#[get("/admin/events/<event_id>/sign-in-sheets?<slot>&<session>")]
+pub fn sign_in_sheets(admin: AdminSession, state: &State<AppState>, event_id: Uuid, slot: Option<Uuid>, session: Option<Uuid>) -> Result<Template, BackendError> {
+    let storage = state.storage.read_unpoisoned();
+    let ev = storage.tenant_event(admin.tenant.as_deref(), event_id)?;
+    let slots: Vec<&Slot> = match slot {
+        Some(slot_id) => vec![ev.slot_by_id(slot_id)?],
+        None => ev.slots.iter().collect(),
+    };
+    let mut sheets = Vec::new();
+    for slot in slots {
+        let time = match (slot.starts_at, slot.ends_at) {
+            (Some(start), Some(end)) => Some(format!("{} – {}", format_display(&start), format_display(&end))),
+            (Some(start), None) => Some(format_display(&start)),
+            _ => None,
+        };
+        for sess in slot.sessions.iter().filter(|s| session.is_none_or(|id| s.uuid == id)) {
+            let mut names: Vec<String> = if ev.is_distributed() {
+                sess.participants.iter().filter_map(|pid| ev.participants.get(pid)).map(|p| ev.roster_label(p)).collect()
+            } else { Vec::new() };
+            names.sort();
+            let blank = sess.seats.saturating_sub(names.len());
+            let rows = names.into_iter().map(Some).chain(std::iter::repeat_n(None, blank))
+                .enumerate()
+                .map(|(i, name)| SignInRow { number: i + 1, name })
+                .collect();
+            sheets.push(SignInSheet {
+                slot_name: slot.name.clone(),
+                session_name: sess.name.clone(),
+                time: time.clone(),
+                room_name: sess.room.and_then(|id| storage.rooms.get(&id)).map(|r| r.name.clone()),
+                seats: sess.seats,
+                rows,
+            });
+        }
+    }
+    if sheets.is_empty() {
+        return Err(BackendError::NotFound("session"));
+    }
+    let ctx = SignInSheetsContext { event_uuid: event_id, event_name: ev.name.clone(), sheets };
+    Ok(Template::render("admin/sign_in_sheets", &ctx))
+}
+
+/// Everything that happened to one invitation and its participant, for answering support requests
+#[get("/admin/events/<event_id>/invites/<code>/timeline")]
+pub fn participant_timeline(admin: AdminSession, state: &State<AppState>, event_id: Uuid, code: &str) -> Result<Template, BackendError> {
+    let storage = state.storage.read_unpoisoned();
+    let ev = storage.tenant_event(admin.tenant.as_deref(), event_id)?;
+    let Some(inv) = storage.invitations_codes.get(code).filter(|inv| inv.event_id == event_id) else { return Err(BackendError::NotFound("invitation")); };
+    let participant_name = inv.participant_id.and_then(|pid| ev.participants.get(&pid)).map(|p| p.name.clone()).filter(|n| !n.is_empty());
+    let entries = storage.participant_timeline(code)?.into_iter()
+        .map(|entry| AdminViewTimelineEntry {
+            at: format_display(&entry.at),
+            source: entry.source,
+            badge_class: match entry.source {
+                "participant" => "bg-primary",
+                "admin" => "bg-warning text-dark",
+                "mail" => "bg-info text-dark",
+                _ => "bg-secondary",
+            },
+            title: entry.title,
+            details: entry.details,
+        })
+        .collect();
+    let ctx = AdminTimelineContext { event_uuid: event_id, event_name: ev.name.clone(), code: code.to_string(), participant_name, email: inv.email.clone(), entries };
+    Ok(Template::render("admin/timeline", &ctx))
+}
+
+#[post("/admin/events/<event_id>/notes", data = "<form>")]
+pub fn add_note(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, form: Form<NoteForm>) -> Result<Redirect, BackendError> {
+    let text = form.into_inner().text.trim().to_string();
+    if text.is_empty() { return Err(BackendError::bad_request("Please enter a note.")); }
+    let mut v = Validator::new(&config.form_limits);
+    v.text("text", Some(&text));
+    v.finish()?;
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    ev.notes.push(EventNote { uuid: Uuid::new_v4(), author: admin.username, text, created_at: chrono::Utc::now() });
+    Ok(Redirect::to(format!("/admin/events/{}#notes", event_id)))
+}
+
+#[post("/admin/events/<event_id>/notes/<note_id>/delete")]
+pub fn delete_note(admin: AdminSession, state: &State<AppState>, event_id: Uuid, note_id: Uuid) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    let Some(index) = ev.notes.iter().position(|note| note.uuid == note_id) else { return Err(BackendError::NotFound("note")); };
+    if ev.notes[index].author != admin.username {
+        return Err(BackendError::conflict("Only the author can delete a note."));
+    }
+    ev.notes.remove(index);
+    Ok(Redirect::to(format!("/admin/events/{}#notes", event_id)))
+}
+
+/// Keeps two participants out of the same session in future distributions. Seats already
+/// assigned stay as they are; the event page lists pairs who currently share a session.
+#[post("/admin/events/<event_id>/exclusions", data = "<form>")]
+pub fn add_exclusion(admin: AdminSession, state: &State<AppState>, event_id: Uuid, form: Form<ExclusionForm>) -> Result<Flash<Redirect>, BackendError> {
+    let ExclusionForm { first, second } = form.into_inner();
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    ev.add_exclusion(first, second, &admin.username)?;
+    let name = |id: Uuid| ev.participants.get(&id).map(|p| p.name.clone()).unwrap_or_default();
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}#exclusions", event_id)), format!("{} and {} will be kept apart.", name(first), name(second))))
+}
+
+#[post("/admin/events/<event_id>/exclusions/<exclusion_id>/delete")]
+pub fn delete_exclusion(admin: AdminSession, state: &State<AppState>, event_id: Uuid, exclusion_id: Uuid) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    ev.remove_exclusion(exclusion_id)?;
+    Ok(Redirect::to(format!("/admin/events/{}#exclusions", event_id)))
+}
+
+#[post("/admin/events/<event_id>/category_bonuses", data = "<form>")]
+pub fn set_category_bonuses(admin: AdminSession, state: &State<AppState>, event_id: Uuid, form: Form<CategoryBonusesForm>) -> Result<Redirect, BackendError> {
+    let CategoryBonusesForm { guest, member, speaker } = form.into_inner();
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    ev.category_bonuses.guest = guest;
+    ev.category_bonuses.member = member;
+    ev.category_bonuses.speaker = speaker;
+    Ok(Redirect::to(format!("/admin/events/{}", event_id)))
+}
+
+/// Sets how compensation points accumulate from slot to slot
+#[post("/admin/events/<event_id>/compensation", data = "<form>")]
+pub fn set_compensation(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, form: Form<CompensationForm>) -> Result<Redirect, BackendError> {
+    let CompensationForm { cap, decay_percent } = form.into_inner();
+    let mut v = Validator::new(&config.form_limits);
+    if decay_percent > 100 {
+        v.error("decay_percent", "The decay can be at most 100%.");
+    }
+    v.finish()?;
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    ev.compensation = CompensationRules { cap, decay_percent };
+    Ok(Redirect::to(format!("/admin/events/{}#fairness", event_id)))
+}
+
+/// Gives the invitees the fairness points they ended an earlier event of the tenant with
+#[post("/admin/events/<event_id>/carryover", data = "<form>")]
+pub fn import_carryover(admin: AdminSession, state: &State<AppState>, event_id: Uuid, form: Form<CarryoverImportForm>) -> Result<Flash<Redirect>, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    storage.tenant_event_for_update(admin.tenant.as_deref(), event_id)?;
+    let source_name = storage.tenant_event(admin.tenant.as_deref(), form.source)?.name.clone();
+    let report = storage.import_carryover_points(form.source, event_id)?;
+    println!("{} imported the points of {} invitees from event {} into event {}.", admin.username, report.matched, form.source, event_id);
+    let mut message = format!("Imported points from {} for {} invitations.", source_name, report.matched);
+    if report.unmatched > 0 {
+        message.push_str(&format!(" {} participants with points have no invitation in this event.", report.unmatched));
+    }
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}#fairness", event_id)), message))
+}
+
+/// Sets the points of the preference levels and the compensation for seats
+#[post("/admin/events/<event_id>/allocation_config", data = "<form>")]
+pub fn set_allocation_config(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, form: Form<AllocationConfigForm>) -> Result<Redirect, BackendError> {
+    let AllocationConfigForm { first_points, second_points, third_points, first_compensation, second_compensation, third_compensation, unchosen_compensation } = form.into_inner();
+    let mut v = Validator::new(&config.form_limits);
+    if first_points < second_points || second_points < third_points {
+        v.error("first_points", "A better preference can't score fewer points than a worse one.");
+    }
+    v.finish()?;
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    ev.allocation_config = AllocationConfig { first_points, second_points, third_points, first_compensation, second_compensation, third_compensation, unchosen_compensation };
+    Ok(Redirect::to(format!("/admin/events/{}#fairness", event_id)))
+}
+
+/// Sets the Markdown welcome text participants see on the event page, an empty text removes it
+#[post("/admin/events/<event_id>/welcome", data = "<form>")]
+pub fn set_welcome_text(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, form: Form<WelcomeTextForm>) -> Result<Flash<Redirect>, BackendError> {
+    let WelcomeTextForm { text, revision } = form.into_inner();
+    let text = text.trim().to_string();
+    let mut v = Validator::new(&config.form_limits);
+    v.text("text", Some(&text));
+    v.finish()?;
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    check_revision(revision, ev.revision, || {
+        EditConflict::new("welcome text", &ev.name, format!("/admin/events/{}/welcome", event_id), ev.revision, format!("/admin/events/{}#welcome-text", event_id))
+            .field("text", "Welcome text", &text, form_value(&ev.welcome_text))
+    })?;
+    ev.welcome_text = Some(text).filter(|t| !t.is_empty());
+    ev.revision += 1;
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}#welcome-text", event_id)), "Saved the welcome text."))
+}
+
+/// Replaces the scoring method, bonuses and compensation rules of the event with an exported configuration
+#[post("/admin/events/<event_id>/scoring/import", data = "<form>")]
+pub fn import_scoring(admin: AdminSession, state: &State<AppState>, event_id: Uuid, form: Form<ScoringImportForm>) -> Result<Flash<Redirect>, BackendError> {
+    let config = ScoringConfig::parse(&form.into_inner().config).map_err(BackendError::bad_request)?;
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    config.apply_to(ev);
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}", event_id)), "Imported the scoring configuration."))
+}
+
+/// Closes the registration and takes the seats from an external solver's result instead of running the distribution
+#[post("/admin/events/<event_id>/assignment/import", data = "<form>")]
+pub fn import_assignment(admin: AdminSession, state: &State<AppState>, event_id: Uuid, form: Result<BulkForm<AssignmentImportForm>, BackendError>) -> Result<Flash<Redirect>, BackendError> {
+    let assignment = ExternalAssignment::parse(&form?.0.assignment).map_err(BackendError::bad_request)?;
+    let mut storage = state.storage.write_unpoisoned();
+    storage.tenant_event_for_update(admin.tenant.as_deref(), event_id)?;
+    let run = storage.import_assignment(event_id, &assignment, &admin.username)?;
+    storage.queue_state_push(event_id);
+    println!("Imported an external assignment for event {} by {}, {} seats assigned.", event_id, admin.username, run.assigned_seats);
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}", event_id)), format!("Imported {} seats. Review them before confirming the result.", run.assigned_seats)))
+}
+
+/// Turns the walk-in kiosk on (with a new secret URL) or off
+#[post("/admin/events/<event_id>/kiosk", data = "<form>")]
+pub fn set_kiosk_mode(admin: AdminSession, state: &State<AppState>, event_id: Uuid, form: Form<KioskModeForm>) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    ev.kiosk_token = form.enabled.then(|| format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()));
+    Ok(Redirect::to(format!("/admin/events/{}#kiosk", event_id)))
+}
+
+/// Hands a number of invitations to a group leader, who gets a secret link to create the codes
+#[post("/admin/events/<event_id>/bundles", data = "<form>")]
+pub fn create_bundle(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, form: Form<CreateBundleForm>) -> Result<Flash<Redirect>, BackendError> {
+    let CreateBundleForm { leader, size, tags, category } = form.into_inner();
+    let leader = leader.trim().to_string();
+    let mut v = Validator::new(&config.form_limits);
+    v.name("leader", &leader);
+    if size == 0 || size > config.form_limits.max_bulk_invites {
+        v.error("size", format!("A bundle holds between 1 and {} invitations.", config.form_limits.max_bulk_invites));
+    }
+    v.finish()?;
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    ev.bundles.push(InviteBundle {
+        uuid: Uuid::new_v4(),
+        leader: leader.clone(),
+        token: Some(format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())),
+        size,
+        tags: tags.as_deref().map(parse_tags).unwrap_or_default(),
+        category,
+        created_by: admin.username,
+        created_at: chrono::Utc::now(),
+    });
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}#bundles", event_id)), format!("Created a bundle of {} invitations for {}. Send them the link.", size, leader)))
+}
+
+/// Disables the link of a bundle. Codes created from it keep working.
+#[post("/admin/events/<event_id>/bundles/<bundle_id>/revoke")]
+pub fn revoke_bundle(admin: AdminSession, state: &State<AppState>, event_id: Uuid, bundle_id: Uuid) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    let bundle = ev.bundles.iter_mut().find(|b| b.uuid == bundle_id).ok_or(BackendError::NotFound("bundle"))?;
+    bundle.token = None;
+    Ok(Redirect::to(format!("/admin/events/{}#bundles", event_id)))
+}
+
+/// Creates an expiring read-only link to the demand or the allocation of the event
+#[post("/admin/events/<event_id>/share_links", data = "<form>")]
+pub fn create_share_link(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, form: Form<ShareLinkForm>) -> Result<Flash<Redirect>, BackendError> {
+    let ShareLinkForm { kind, label, hours } = form.into_inner();
+    let label = label.trim().to_string();
+    let mut v = Validator::new(&config.form_limits);
+    v.name("label", &label);
+    if !(1..=MAX_SHARE_LINK_HOURS).contains(&hours) {
+        v.error("hours", format!("A share link is valid for 1 to {} hours.", MAX_SHARE_LINK_HOURS));
+    }
+    v.finish()?;
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    ev.share_links.push(ShareLink::new(kind, label.clone(), hours, admin.username, chrono::Utc::now()));
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}#share-links", event_id)), format!("Created a share link for {}.", label)))
+}
+
+/// Removes a share link, it stops working right away
+#[post("/admin/events/<event_id>/share_links/<link_id>/revoke")]
+pub fn revoke_share_link(admin: AdminSession, state: &State<AppState>, event_id: Uuid, link_id: Uuid) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    let before = ev.share_links.len();
+    ev.share_links.retain(|link| link.uuid != link_id);
+    if ev.share_links.len() == before {
+        return Err(BackendError::NotFound("share link"));
+    }
+    Ok(Redirect::to(format!("/admin/events/{}#share-links", event_id)))
+}
+
+/// Mails an onboarding link to a co-organizer, who gets an admin account limited to this event
+#[post("/admin/events/<event_id>/co_admins", data = "<form>")]
+pub fn invite_co_admin(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, form: Form<CoAdminInviteForm>) -> Result<Flash<Redirect>, BackendError> {
+    let email = form.into_inner().email.trim().to_lowercase();
+    if !email.contains('@') {
+        return Err(BackendError::bad_request("Please enter a valid email address."));
+    }
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    let invite = CoAdminInvite::new(email.clone(), admin.username.clone(), chrono::Utc::now());
+    let (subject, body) = config.mail_templates.render("co_admin_invite", None, &[
+        ("event", &ev.name),
+        ("inviter", &admin.username),
+        ("days", &CO_ADMIN_INVITE_DAYS.to_string()),
+        ("link", &config.url(&format!("/onboarding/{}", invite.token))),
+    ]);
+    ev.co_admin_invites.push(invite);
+    storage.queue_mail(OutgoingMail::new(Some(event_id), Some(email.clone()), email.clone(), subject, body));
+    println!("{} invited {} as co-organizer of event {}", admin.username, email, event_id);
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}#co-admins", event_id)), format!("Sent an invitation to {}.", email)))
+}
+
+/// Withdraws a pending co-organizer invitation, its link stops working right away
+#[post("/admin/events/<event_id>/co_admins/invites/<invite_id>/revoke")]
+pub fn revoke_co_admin_invite(admin: AdminSession, state: &State<AppState>, event_id: Uuid, invite_id: Uuid) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    let before = ev.co_admin_invites.len();
+    ev.co_admin_invites.retain(|invite| invite.uuid != invite_id);
+    if ev.co_admin_invites.len() == before {
+        return Err(BackendError::NotFound("invitation"));
+    }
+    Ok(Redirect::to(format!("/admin/events/{}#co-admins", event_id)))
+}
+
+/// Takes the event away from a co-organizer, their open sessions lose access with the next request
+#[post("/admin/events/<event_id>/co_admins/<username>/remove")]
+pub fn remove_co_admin(admin: AdminSession, state: &State<AppState>, event_id: Uuid, username: &str) -> Result<Flash<Redirect>, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    storage.tenant_event(admin.tenant.as_deref(), event_id)?;
+    storage.remove_co_admin(event_id, username)?;
+    println!("{} removed co-organizer {} from event {}", admin.username, username, event_id);
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}#co-admins", event_id)), format!("{} no longer organizes this event.", username)))
+}
+
+/// Sets the URL called after preference submissions, an empty URL turns the webhook off.
+/// The signing secret is kept when only the URL changes.
+#[post("/admin/events/<event_id>/webhook", data = "<form>")]
+pub fn set_webhook(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, form: Form<WebhookForm>) -> Result<Flash<Redirect>, BackendError> {
+    let url = form.into_inner().url.map(|u| u.trim().to_string()).filter(|u| !u.is_empty());
+    let mut v = Validator::new(&config.form_limits);
+    v.url("url", url.as_deref());
+    v.finish()?;
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    let back = Redirect::to(format!("/admin/events/{}#webhook", event_id));
+    let Some(url) = url else {
+        ev.webhook = None;
+        return Ok(Flash::success(back, "Turned the webhook off."));
+    };
+    match &mut ev.webhook {
+        Some(webhook) => webhook.url = url,
+        None => ev.webhook = Some(Webhook::new(url)),
+    }
+    Ok(Flash::success(back, "Saved the webhook."))
+}
+
+#[post("/admin/events/<event_id>/settings", data = "<form>")]
+pub fn update_event_settings(admin: AdminSession, state: &State<AppState>, event_id: Uuid, form: Form<EventSettingsForm>) -> Result<Redirect, BackendError> {
+    let form = form.into_inner();
+    let registration_deadline = parse_datetime_local(form.registration_deadline.as_deref().unwrap_or("")).map_err(BackendError::BadRequest)?;
+    let mut storage = state.storage.write_unpoisoned();
+    if form.auto_distribute && registration_deadline.is_none() {
+        return Err(BackendError::bad_request("Automatic distribution needs a registration deadline."));
+    }
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    check_revision(form.revision, ev.revision, || settings_conflict(ev, &form))?;
+    if ev.registration_deadline != registration_deadline || ev.reminder_days_before_deadline != form.reminder_days_before_deadline {
+        // A new deadline or reminder period starts a new reminder round
+        for p in ev.participants.values_mut() {
+            p.reminder_sent = false;
+            p.slot_reminders_sent.clear();
         }
-        _ => Err(Status::Forbidden),
+        ev.deadline_push_sent = false;
+    }
+    ev.registration_deadline = registration_deadline;
+    ev.reminder_days_before_deadline = form.reminder_days_before_deadline;
+    ev.max_participants = form.max_participants;
+    ev.registration_mode = form.registration_mode;
+    ev.feedback_open = form.feedback_open;
+    ev.show_peer_names = form.show_peer_names;
+    ev.anonymous_rosters = form.anonymous_rosters;
+    if ev.anonymous_rosters {
+        ev.assign_badges();
+    }
+    ev.max_sessions_per_day = form.max_sessions_per_day.filter(|max| *max > 0);
+    ev.auto_distribute = form.auto_distribute;
+    ev.auto_publish_after_hours = form.auto_publish_after_hours;
+    ev.scoring = form.scoring;
+    ev.allocation = form.allocation;
+    ev.no_preference_policy = form.no_preference_policy;
+    ev.revision += 1;
+    Ok(Redirect::to(format!("/admin/events/{}", event_id)))
+}
+
+#[get("/admin/outbox")]
+pub fn outbox_view(admin: AdminSession, state: &State<AppState>, flash: Option<FlashMessage<'_>>) -> Result<Template, BackendError> {
+    let storage = state.storage.read_unpoisoned();
+    let visible: Vec<&OutgoingMail> = storage.outbox.iter().filter(|m| storage.mail_visible_to(m, admin.tenant.as_deref())).collect();
+    let pending = visible.iter().filter(|m| m.is_pending() && m.dead_lettered_at.is_none()).map(|m| AdminViewMail::from(*m)).collect();
+    let dead_mails = visible.iter().filter(|m| m.is_pending() && m.dead_lettered_at.is_some()).map(|m| AdminViewMail::from(*m)).collect();
+    let dead_webhooks = storage.dead_webhooks.iter()
+        .filter_map(|dead| {
+            let ev = storage.tenant_event(admin.tenant.as_deref(), dead.event_id).ok()?;
+            Some(AdminViewDeadWebhook { uuid: dead.uuid, event_name: ev.name.clone(), attempts: dead.attempts, last_error: dead.last_error.clone(), failed_at: format_display(&dead.failed_at) })
+        })
+        .collect();
+    let sent_count = visible.iter().filter(|m| !m.is_pending()).count();
+    Ok(Template::render("admin/outbox", &AdminOutboxContext { flash: FlashView::from_flash(flash), pending, dead_mails, dead_webhooks, sent_count }))
+}
+
+/// Validates a mail to participants and renders it for every recipient
+fn compose_participant_mails(admin: &AdminSession, storage: &Storage, config: &AppConfig, event_id: Uuid, form: &ParticipantMailForm) -> Result<(MailAudience, Vec<OutgoingMail>), BackendError> {
+    storage.tenant_event(admin.tenant.as_deref(), event_id)?;
+    let mut v = Validator::new(&config.form_limits);
+    let audience = MailAudience::parse(&form.audience);
+    if audience.is_none() {
+        v.error("audience", "Choose who should receive the mail.");
     }
+    v.name("subject", form.subject.trim());
+    if form.body.trim().is_empty() {
+        v.error("body", "The message must not be empty.");
+    }
+    v.text("body", Some(&form.body));
+    v.finish()?;
+    let audience = audience.expect("validated");
+    let mails = storage.compose_participant_mails(event_id, audience, form.subject.trim(), form.body.trim_end(), config)?;
+    Ok((audience, mails))
+}
+
+/// Shows the mail as rendered for the first recipients before it is sent
+#[post("/admin/events/<event_id>/mail/preview", data = "<form>")]
+pub fn preview_participant_mail(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, form: Form<ParticipantMailForm>) -> Result<Template, BackendError> {
+    let form = form.into_inner();
+    let storage = state.storage.read_unpoisoned();
+    let (audience, mails) = compose_participant_mails(&admin, &storage, config, event_id, &form)?;
+    let ev = storage.tenant_event(admin.tenant.as_deref(), event_id)?;
+    let audience_label = match audience {
+        MailAudience::Registered => "all registered participants".to_string(),
+        MailAudience::Unassigned => "participants without a seat".to_string(),
+        MailAudience::Session(session_id) => format!("attendees of {}", ev.session_by_id(session_id)?.name),
+    };
+    let ctx = AdminMailPreviewContext {
+        event_uuid: event_id,
+        event_name: ev.name.clone(),
+        audience: form.audience,
+        subject: form.subject,
+        body: form.body,
+        audience_label,
+        samples: mails.iter().take(3).map(AdminViewMail::from).collect(),
+        recipient_names: mails.iter().map(|m| m.recipient_name.clone()).collect(),
+        recipient_count: mails.len(),
+        without_address: mails.iter().filter(|m| m.recipient.is_none()).count(),
+    };
+    Ok(Template::render("admin/mail_preview", &ctx))
+}
+
+/// Queues the confirmed mail for every recipient
+#[post("/admin/events/<event_id>/mail/send", data = "<form>")]
+pub fn send_participant_mail(admin: AdminSession, state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid, form: Form<ParticipantMailForm>) -> Result<Flash<Redirect>, BackendError> {
+    let form = form.into_inner();
+    let mut storage = state.storage.write_unpoisoned();
+    let (_, mails) = compose_participant_mails(&admin, &storage, config, event_id, &form)?;
+    let back = Redirect::to(format!("/admin/events/{}", event_id));
+    if mails.is_empty() {
+        return Ok(Flash::warning(back, "Nobody matches the chosen recipients, no mail was queued."));
+    }
+    let count = mails.len();
+    for mail in mails {
+        storage.queue_mail(mail);
+    }
+    Ok(Flash::success(back, format!("Queued {} mails. Mails without address are waiting in the outbox.", count)))
+}
+
+/// Marks a queued mail as delivered, e.g. after an admin forwarded it manually
+#[post("/admin/outbox/<mail_id>/done")]
+pub fn outbox_mark_done(admin: AdminSession, state: &State<AppState>, mail_id: Uuid) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let Some(index) = storage.outbox.iter().position(|m| m.uuid == mail_id && storage.mail_visible_to(m, admin.tenant.as_deref())) else { return Err(BackendError::NotFound("mail")); };
+    let mail = &mut storage.outbox[index];
+    mail.sent_at = Some(chrono::Utc::now());
+    Ok(Redirect::to("/admin/outbox"))
+}
+
+/// Gives a mail that failed every delivery attempt a fresh set of attempts, starting right away
+#[post("/admin/outbox/<mail_id>/resend")]
+pub fn outbox_resend_mail(admin: AdminSession, state: &State<AppState>, mail_id: Uuid) -> Result<Flash<Redirect>, BackendError> {
+    state.storage.write_unpoisoned().resend_mail(mail_id, admin.tenant.as_deref())?;
+    Ok(Flash::success(Redirect::to("/admin/outbox"), "The mail is queued again."))
+}
+
+/// Calls a webhook again that failed every attempt, with the participant's current preferences
+#[post("/admin/outbox/webhooks/<dead_id>/resend")]
+pub fn outbox_resend_webhook(admin: AdminSession, state: &State<AppState>, dead_id: Uuid) -> Result<Flash<Redirect>, BackendError> {
+    state.storage.write_unpoisoned().resend_webhook(dead_id, admin.tenant.as_deref(), chrono::Utc::now())?;
+    Ok(Flash::success(Redirect::to("/admin/outbox"), "The webhook call is queued again."))
+}
+
+/// Registers a waiting invitation holder right away, even if this exceeds the registration cap
+#[post("/admin/events/<event_id>/waiting_list/<code>/admit")]
+pub fn admit_waiting(admin: AdminSession, state: &State<AppState>, event_id: Uuid, code: &str) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_for_update(admin.tenant.as_deref(), event_id)?;
+    if !ev.waiting_list.iter().any(|entry| entry.code == code) { return Err(BackendError::NotFound("waiting list entry")); }
+    storage.register_participant(code).ok_or(BackendError::NotFound("invitation"))?;
+    Ok(Redirect::to(format!("/admin/events/{}", event_id)))
+}
+
+/// Removes participants who logged in but never entered a name or preferences. Their codes stay valid.
+#[post("/admin/events/<event_id>/participants/cleanup")]
+pub fn remove_unused_participants(admin: AdminSession, state: &State<AppState>, event_id: Uuid) -> Result<Flash<Redirect>, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    storage.tenant_event_for_update(admin.tenant.as_deref(), event_id)?;
+    let removed = storage.remove_unused_participants(event_id);
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}", event_id)), format!("Removed {} unused participant entries.", removed)))
+}
+
+/// Merges a participant who registered twice into their other entry, see Storage::merge_participants
+#[post("/admin/events/<event_id>/participants/merge", data = "<form>")]
+pub fn merge_participants(admin: AdminSession, state: &State<AppState>, event_id: Uuid, form: Form<MergeParticipantsForm>) -> Result<Flash<Redirect>, BackendError> {
+    let MergeParticipantsForm { keep, duplicate } = form.into_inner();
+    let mut storage = state.storage.write_unpoisoned();
+    storage.tenant_event_for_update(admin.tenant.as_deref(), event_id)?;
+    storage.merge_participants(event_id, keep, duplicate)?;
+    println!("{} merged participant {} into {} in event {}.", admin.username, duplicate, keep, event_id);
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}#duplicates", event_id)), "Merged the participants. Both codes now log in to the kept entry."))
+}
+
+#[post("/admin/events/<event_id>/waiting_list/<code>/remove")]
+pub fn remove_waiting(admin: AdminSession, state: &State<AppState>, event_id: Uuid, code: &str) -> Result<Redirect, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = storage.tenant_event_mut(admin.tenant.as_deref(), event_id)?;
+    ev.waiting_list.retain(|entry| entry.code != code);
+    Ok(Redirect::to(format!("/admin/events/{}", event_id)))
 }
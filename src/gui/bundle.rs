@@ -0,0 +1,100 @@
+use rocket::form::{Form, FromForm};
+use rocket::http::Status;
+use rocket::State;
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::backend::config::AppConfig;
+use crate::backend::data::{Event, Invitation, InviteBundle, Storage};
+use crate::backend::state::{AppState, RecoverLock};
+use crate::backend::validation::Validator;
+
+#[derive(Serialize)]
+struct BundleContext {
+    token: String,
+    event_name: String,
+    leader: String,
+    size: usize,
+    remaining: usize,
+    members: Vec<BundleMember>,
+    error: Option<String>,
+    /// Number of codes created by the submission just handled
+    created: usize,
+}
+
+#[derive(Serialize)]
+struct BundleMember {
+    code: String,
+    name: Option<String>,
+    login_url: String,
+    /// The member logged in with the code
+    registered: bool,
+}
+
+#[derive(FromForm)]
+pub struct BundleForm {
+    /// One member name per line
+    pub names: String,
+}
+
+fn bundle_event<'a>(events: &'a HashMap<Uuid, Event>, token: &str) -> Option<(&'a Event, &'a InviteBundle)> {
+    events.values().find_map(|ev| ev.bundles.iter().find(|b| b.token.as_deref() == Some(token)).map(|b| (ev, b)))
+}
+
+fn render_page(storage: &Storage, config: &AppConfig, token: &str, error: Option<String>, created: usize) -> Result<Template, Status> {
+    let (ev, bundle) = bundle_event(&storage.events, token).ok_or(Status::NotFound)?;
+    let members: Vec<BundleMember> = storage.bundle_invitations(bundle.uuid).into_iter()
+        .map(|inv| BundleMember { code: inv.code.clone(), name: inv.name.clone(), login_url: config.url(&format!("/invitation/{}", inv.code)), registered: inv.participant_id.is_some() })
+        .collect();
+    let ctx = BundleContext {
+        token: token.to_string(),
+        event_name: ev.name.clone(),
+        leader: bundle.leader.clone(),
+        size: bundle.size,
+        remaining: bundle.size.saturating_sub(members.len()),
+        members,
+        error,
+        created,
+    };
+    Ok(Template::render("bundle", &ctx))
+}
+
+/// Self-service page of a group leader listing the codes created from the bundle so far
+#[get("/bundles/<token>")]
+pub fn bundle_page(state: &State<AppState>, config: &State<AppConfig>, token: &str) -> Result<Template, Status> {
+    render_page(&state.storage.read_unpoisoned(), config, token, None, 0)
+}
+
+/// Creates a code for each listed member, as long as the bundle has invitations left
+#[post("/bundles/<token>", data = "<form>")]
+pub fn bundle_create_codes(state: &State<AppState>, config: &State<AppConfig>, token: &str, form: Form<BundleForm>) -> Result<Template, Status> {
+    let names: Vec<String> = form.names.lines().map(str::trim).filter(|n| !n.is_empty()).map(str::to_string).collect();
+    let mut storage = state.storage.write_unpoisoned();
+    let (ev, bundle) = bundle_event(&storage.events, token).ok_or(Status::NotFound)?;
+    if let Err(e) = ev.ensure_unlocked() {
+        return render_page(&storage, config, token, Some(e.message()), 0);
+    }
+    let mut v = Validator::new(&config.form_limits);
+    for name in &names {
+        v.name("names", name);
+    }
+    let remaining = bundle.size.saturating_sub(storage.bundle_invitations(bundle.uuid).len());
+    if names.len() > remaining {
+        v.error("names", format!("Only {} invitations are left in this bundle.", remaining));
+    }
+    if let Err(e) = v.finish() {
+        return render_page(&storage, config, token, Some(e.message()), 0);
+    }
+
+    let (event_id, bundle_id, tags, category) = (ev.uuid, bundle.uuid, bundle.tags.clone(), bundle.category);
+    for name in &names {
+        let code = storage.generate_invitation_code();
+        let mut inv = Invitation::new(code.clone(), event_id, tags.clone(), category);
+        inv.name = Some(name.clone());
+        inv.bundle = Some(bundle_id);
+        storage.invitations_codes.insert(code, inv);
+    }
+    render_page(&storage, config, token, None, names.len())
+}
@@ -0,0 +1,24 @@
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+
+/// Format used by `<input type="datetime-local">`
+const DATETIME_LOCAL_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+/// Parses the value of a datetime-local input, interpreted in the server's local time zone.
+/// Empty input yields `Ok(None)`.
+pub fn parse_datetime_local(input: &str) -> Result<Option<DateTime<Utc>>, String> {
+    let input = input.trim();
+    if input.is_empty() { return Ok(None); }
+    let naive = NaiveDateTime::parse_from_str(input, DATETIME_LOCAL_FORMAT).map_err(|e| format!("invalid date '{}': {}", input, e))?;
+    let local = Local.from_local_datetime(&naive).earliest().ok_or_else(|| format!("'{}' doesn't exist in the local time zone", input))?;
+    Ok(Some(local.with_timezone(&Utc)))
+}
+
+/// Formats a timestamp as value for a datetime-local input
+pub fn format_datetime_local(value: &DateTime<Utc>) -> String {
+    value.with_timezone(&Local).format(DATETIME_LOCAL_FORMAT).to_string()
+}
+
+/// Formats a timestamp for display in the server's local time zone
+pub fn format_display(value: &DateTime<Utc>) -> String {
+    value.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+}
@@ -0,0 +1,163 @@
+use rocket::http::ContentType;
+use rocket::State;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::backend::auth::AdminSession;
+use crate::backend::error::BackendError;
+use crate::backend::scoring::ScoringConfig;
+use crate::backend::state::{AppState, RecoverLock};
+
+/// Quotes a value for use in a CSV file if needed
+pub fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') || value.contains('\r') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Joins the given values into one CSV line (including the line break)
+pub fn csv_line<S: AsRef<str>>(values: &[S]) -> String {
+    let fields: Vec<String> = values.iter().map(|v| csv_field(v.as_ref())).collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+/// Scoring method and bonuses of the event, for importing into other events or instances
+#[get("/admin/events/<event_id>/export/scoring.json")]
+pub fn export_scoring(admin: AdminSession, state: &State<AppState>, event_id: Uuid) -> Result<(ContentType, String), BackendError> {
+    let storage = state.storage.read_unpoisoned();
+    let ev = storage.tenant_event(admin.tenant.as_deref(), event_id)?;
+    let json = serde_json::to_string_pretty(&ScoringConfig::from_event(ev)).expect("scoring configuration is serializable");
+    Ok((ContentType::JSON, json))
+}
+
+#[get("/admin/events/<event_id>/export/participants.csv")]
+pub fn export_participants(admin: AdminSession, state: &State<AppState>, event_id: Uuid) -> Result<(ContentType, String), BackendError> {
+    let storage = state.storage.read_unpoisoned();
+    let ev = storage.tenant_event(admin.tenant.as_deref(), event_id)?;
+    let mut invites: Vec<_> = storage.invitations_codes.values().filter(|inv| inv.event_id == event_id).collect();
+    invites.sort_by(|a, b| a.code.cmp(&b.code));
+
+    let mut csv = csv_line(&["code", "name", "category", "tags", "points_from_previous_rounds"]);
+    for inv in invites {
+        let participant = inv.participant_id.and_then(|pid| ev.participants.get(&pid));
+        csv.push_str(&csv_line(&[
+            inv.code.clone(),
+            participant.map(|p| p.name.clone()).unwrap_or_default(),
+            inv.category.as_tag().to_string(),
+            inv.tags.join(" "),
+            participant.map(|p| p.points_from_previous_rounds.to_string()).unwrap_or_default(),
+        ]));
+    }
+    Ok((ContentType::CSV, csv))
+}
+
+/// Aggregated session feedback, one line per session. Ratings are anonymous.
+#[get("/admin/events/<event_id>/export/feedback.csv")]
+pub fn export_feedback(admin: AdminSession, state: &State<AppState>, event_id: Uuid) -> Result<(ContentType, String), BackendError> {
+    let storage = state.storage.read_unpoisoned();
+    let ev = storage.tenant_event(admin.tenant.as_deref(), event_id)?;
+
+    let mut csv = csv_line(&["slot", "session", "responses", "average_rating", "rating_1", "rating_2", "rating_3", "rating_4", "rating_5", "comments"]);
+    for slot in &ev.slots {
+        for sess in &slot.sessions {
+            let summary = ev.feedback_summary(sess.uuid);
+            let mut line = vec![
+                slot.name.clone(),
+                sess.name.clone(),
+                summary.responses.to_string(),
+                summary.average().map(|avg| format!("{:.2}", avg)).unwrap_or_default(),
+            ];
+            line.extend(summary.counts.iter().map(|c| c.to_string()));
+            line.push(summary.comments.join("\n"));
+            csv.push_str(&csv_line(&line));
+        }
+    }
+    Ok((ContentType::CSV, csv))
+}
+
+/// Attendees per session as handed to session hosts, by badge number if the event uses anonymous rosters
+#[get("/admin/events/<event_id>/export/rosters.csv")]
+pub fn export_rosters(admin: AdminSession, state: &State<AppState>, event_id: Uuid) -> Result<(ContentType, String), BackendError> {
+    let storage = state.storage.read_unpoisoned();
+    let ev = storage.tenant_event(admin.tenant.as_deref(), event_id)?;
+
+    let mut csv = csv_line(&["slot", "session", "attendee", "conditions_accepted_at"]);
+    for slot in &ev.slots {
+        for sess in &slot.sessions {
+            let mut attendees: Vec<(String, String)> = sess.participants.iter().filter_map(|pid| ev.participants.get(pid))
+                .map(|p| (ev.roster_label(p), sess.acknowledgments.get(&p.uuid).map(|at| at.to_rfc3339()).unwrap_or_default()))
+                .collect();
+            attendees.sort();
+            for (attendee, accepted_at) in attendees {
+                csv.push_str(&csv_line(&[slot.name.as_str(), sess.name.as_str(), attendee.as_str(), accepted_at.as_str()]));
+            }
+        }
+    }
+    Ok((ContentType::CSV, csv))
+}
+
+/// Maps badge numbers to participants. Admins only, never hand this to session hosts.
+#[get("/admin/events/<event_id>/export/badges.csv")]
+pub fn export_badges(admin: AdminSession, state: &State<AppState>, event_id: Uuid) -> Result<(ContentType, String), BackendError> {
+    let storage = state.storage.read_unpoisoned();
+    let ev = storage.tenant_event(admin.tenant.as_deref(), event_id)?;
+    let codes: HashMap<Uuid, &str> = storage.invitations_codes.values()
+        .filter(|inv| inv.event_id == event_id)
+        .filter_map(|inv| Some((inv.participant_id?, inv.code.as_str())))
+        .collect();
+    let mut participants: Vec<_> = ev.participants.values().collect();
+    participants.sort_by_key(|p| p.badge);
+
+    let mut csv = csv_line(&["badge", "name", "code"]);
+    for p in participants {
+        csv.push_str(&csv_line(&[
+            p.badge.map(|b| b.to_string()).unwrap_or_default(),
+            p.name.clone(),
+            codes.get(&p.uuid).map(|c| c.to_string()).unwrap_or_default(),
+        ]));
+    }
+    Ok((ContentType::CSV, csv))
+}
+
+/// The distribution as a problem for an external solver, see [`crate::backend::solver`]
+#[get("/admin/events/<event_id>/export/problem.json")]
+pub fn export_problem(admin: AdminSession, state: &State<AppState>, event_id: Uuid) -> Result<(ContentType, String), BackendError> {
+    let storage = state.storage.read_unpoisoned();
+    storage.tenant_event(admin.tenant.as_deref(), event_id)?;
+    let json = serde_json::to_string_pretty(&storage.allocation_problem(event_id)?).expect("allocation problem is serializable");
+    Ok((ContentType::JSON, json))
+}
+
+/// Hypothetical assignment if the seats were distributed now. Nothing is stored.
+#[get("/admin/events/<event_id>/export/dry_run.csv")]
+pub fn export_dry_run(admin: AdminSession, state: &State<AppState>, event_id: Uuid) -> Result<(ContentType, String), BackendError> {
+    let storage = state.storage.read_unpoisoned();
+    let mut ev = storage.tenant_event(admin.tenant.as_deref(), event_id)?.clone();
+    if ev.is_distributed() { return Err(BackendError::conflict("The seats of this event have already been distributed.")); }
+    storage.carry_person_points(&mut ev);
+    ev.distribute(&admin.username);
+
+    let codes: HashMap<Uuid, &str> = storage.invitations_codes.values()
+        .filter(|inv| inv.event_id == event_id)
+        .filter_map(|inv| Some((inv.participant_id?, inv.code.as_str())))
+        .collect();
+    let row = |slot: &str, session: &str, pid: &Uuid| {
+        let name = ev.participants.get(pid).map(|p| p.name.clone()).unwrap_or_default();
+        csv_line(&[slot.to_string(), session.to_string(), codes.get(pid).copied().unwrap_or_default().to_string(), name])
+    };
+
+    let mut csv = csv_line(&["slot", "session", "code", "name"]);
+    for slot in &ev.slots {
+        for sess in &slot.sessions {
+            for pid in &sess.participants {
+                csv.push_str(&row(&slot.name, &sess.name, pid));
+            }
+        }
+        for pid in &slot.waiting_list {
+            csv.push_str(&row(&slot.name, "(waiting list)", pid));
+        }
+    }
+    Ok((ContentType::CSV, csv))
+}
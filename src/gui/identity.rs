@@ -0,0 +1,50 @@
+use chrono::Utc;
+use rocket::form::{Form, FromForm};
+use rocket::response::{Flash, Redirect};
+use rocket::State;
+
+use crate::backend::auth::ParticipantSession;
+use crate::backend::config::AppConfig;
+use crate::backend::error::BackendError;
+use crate::backend::identity::LINK_CONFIRMATION_HOURS;
+use crate::backend::mail::OutgoingMail;
+use crate::backend::state::{AppState, RecoverLock};
+
+#[derive(FromForm)]
+pub struct LinkForm { pub email: String }
+
+/// Sends a confirmation link to the given address. The registration is only linked once the
+/// participant opens it, so nobody can take over the points of someone else's address.
+#[post("/event/link", data = "<form>")]
+pub fn request_link(user: ParticipantSession, state: &State<AppState>, config: &State<AppConfig>, form: Form<LinkForm>) -> Result<Flash<Redirect>, BackendError> {
+    let email = form.into_inner().email.trim().to_lowercase();
+    if !email.contains('@') { return Err(BackendError::bad_request("Please enter a valid email address.")); }
+    if !state.login_link_limiter.check(&email, 3) {
+        return Err(BackendError::bad_request("Too many requests for this address. Please try again later."));
+    }
+    let mut storage = state.storage.write_unpoisoned();
+    let Some(token) = storage.request_person_link(&user.code, &email, Utc::now()) else { return Err(BackendError::NotFound("invitation")) };
+    let event_name = storage.events.get(&user.invitation.event_id).map(|ev| ev.name.clone()).unwrap_or_default();
+    let (subject, body) = config.mail_templates.render("link_confirmation", storage.locale_of(&user.invitation).as_deref(), &[
+        ("event", &event_name),
+        ("link", &config.url(&format!("/link/{}", token))),
+        ("hours", &LINK_CONFIRMATION_HOURS.to_string()),
+    ]);
+    storage.queue_mail(OutgoingMail::new(Some(user.invitation.event_id), Some(email.clone()), email.clone(), subject, body));
+    Ok(Flash::success(Redirect::to("/event#identity"), format!("We've sent a confirmation link to {}.", email)))
+}
+
+#[post("/event/unlink")]
+pub fn remove_link(user: ParticipantSession, state: &State<AppState>) -> Flash<Redirect> {
+    state.storage.write_unpoisoned().unlink_person(&user.code);
+    Flash::success(Redirect::to("/event#identity"), "The link to your other registrations was removed.")
+}
+
+/// Confirmation link from the mail, works without being logged in
+#[get("/link/<token>")]
+pub fn confirm_link(state: &State<AppState>, token: &str) -> Flash<Redirect> {
+    match state.storage.write_unpoisoned().confirm_person_link(token, Utc::now()) {
+        Ok(_) => Flash::success(Redirect::to("/"), "Your address is confirmed and your registrations are linked."),
+        Err(e) => Flash::error(Redirect::to("/"), e.message()),
+    }
+}
@@ -0,0 +1,19 @@
+use rocket::http::{Header, Status};
+use rocket::State;
+use uuid::Uuid;
+
+use crate::backend::config::AppConfig;
+use crate::backend::state::{AppState, RecoverLock};
+
+#[derive(Responder)]
+#[response(content_type = "json")]
+pub struct CachedJson(String, Header<'static>);
+
+/// Program and fill levels of a published event for displays at the venue. Served from the
+/// snapshot cache without login; the data is up to `infoscreen_refresh_seconds` old.
+#[get("/events/<event_id>/infoscreen.json")]
+pub fn infoscreen_snapshot(state: &State<AppState>, config: &State<AppConfig>, event_id: Uuid) -> Result<CachedJson, Status> {
+    let snapshot = state.infoscreen_snapshots.read_unpoisoned().get(&event_id).cloned().ok_or(Status::NotFound)?;
+    let cache_control = format!("public, max-age={}", config.infoscreen_refresh_seconds);
+    Ok(CachedJson(snapshot, Header::new("Cache-Control", cache_control)))
+}
@@ -0,0 +1,175 @@
+use rocket::form::{Form, FromForm};
+use rocket::http::Status;
+use rocket::State;
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::backend::config::AppConfig;
+use crate::backend::data::{ApplicationPriority, Event, Invitation, InvitationCategory, Participant};
+use crate::backend::state::{AppState, RecoverLock};
+use crate::backend::validation::Validator;
+use crate::gui::datetime::format_display;
+
+#[derive(Serialize)]
+struct KioskContext {
+    token: String,
+    event_name: String,
+    /// True if the seats are already distributed and walk-ins get free seats right away
+    distributed: bool,
+    slots: Vec<KioskSlot>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct KioskSlot {
+    uuid: Uuid,
+    name: String,
+    /// Choices asked for at the kiosk, at most three to keep the form short
+    ranked_choices: usize,
+    starts_at: Option<String>,
+    sessions: Vec<KioskSession>,
+}
+
+#[derive(Serialize)]
+struct KioskSession {
+    uuid: Uuid,
+    name: String,
+    free_seats: usize,
+}
+
+#[derive(Serialize)]
+struct KioskDoneContext {
+    token: String,
+    event_name: String,
+    code: String,
+    login_url: String,
+    /// "Slot: Session" lines of the seats assigned right away
+    assignments: Vec<String>,
+    distributed: bool,
+}
+
+#[derive(FromForm)]
+pub struct KioskForm {
+    pub name: String,
+    // Keys are slot UUID strings, values the chosen session UUID strings (may be empty)
+    pub first: HashMap<String, String>,
+    pub second: HashMap<String, String>,
+    pub third: HashMap<String, String>,
+}
+
+/// Walk-ins register as guests without tags
+fn walk_in(event_id: Uuid) -> Participant {
+    Invitation::new(String::new(), event_id, vec![], InvitationCategory::Guest).new_participant()
+}
+
+/// Slots that haven't started yet and still have sessions with free seats open to walk-ins
+fn kiosk_slots(ev: &Event, now: DateTime<Utc>) -> Vec<KioskSlot> {
+    let guest = walk_in(ev.uuid);
+    ev.slots.iter()
+        .filter(|slot| slot.starts_at.is_none_or(|t| now < t) && !slot.is_full())
+        .filter(|slot| ev.is_distributed() || slot.accepts_preferences(now))
+        .map(|slot| KioskSlot {
+            uuid: slot.uuid,
+            name: slot.name.clone(),
+            ranked_choices: slot.ranked_choices.min(3),
+            starts_at: slot.starts_at.as_ref().map(format_display),
+            // Conditions to accept (NDA, safety briefing) aren't collected at the kiosk
+            sessions: slot.sessions.iter()
+                .filter(|s| s.has_free_seat() && s.is_eligible(&guest) && s.acknowledgment.is_none())
+                .map(|s| KioskSession { uuid: s.uuid, name: s.name.clone(), free_seats: s.seats - s.participants.len() })
+                .collect(),
+        })
+        .filter(|slot| !slot.sessions.is_empty())
+        .collect()
+}
+
+fn kiosk_event<'a>(events: &'a HashMap<Uuid, Event>, token: &str) -> Option<&'a Event> {
+    events.values().find(|ev| ev.kiosk_token.as_deref() == Some(token))
+}
+
+fn render_form(ev: &Event, token: &str, error: Option<String>) -> Template {
+    let ctx = KioskContext { token: token.to_string(), event_name: ev.name.clone(), distributed: ev.is_distributed(), slots: kiosk_slots(ev, Utc::now()), error };
+    Template::render("kiosk", &ctx)
+}
+
+/// Simplified registration form for a tablet at the venue entrance
+#[get("/kiosk/<token>")]
+pub fn kiosk_page(state: &State<AppState>, token: &str) -> Result<Template, Status> {
+    let storage = state.storage.read_unpoisoned();
+    let ev = kiosk_event(&storage.events, token).ok_or(Status::NotFound)?;
+    Ok(render_form(ev, token, None))
+}
+
+/// Registers a walk-in with a generated code. Before the distribution the choices are stored as
+/// preferences, afterwards the walk-in gets the first choice with a free seat right away.
+#[post("/kiosk/<token>", data = "<form>")]
+pub fn kiosk_register(state: &State<AppState>, config: &State<AppConfig>, token: &str, form: Form<KioskForm>) -> Result<Template, Status> {
+    let KioskForm { name, mut first, mut second, mut third } = form.into_inner();
+    let name = name.trim().to_string();
+    let now = Utc::now();
+
+    let mut storage = state.storage.write_unpoisoned();
+    let ev = kiosk_event(&storage.events, token).ok_or(Status::NotFound)?;
+    if let Err(e) = ev.ensure_unlocked() {
+        return Ok(render_form(ev, token, Some(e.message())));
+    }
+    let mut v = Validator::new(&config.form_limits);
+    v.name("name", &name);
+    if let Err(e) = v.finish() {
+        return Ok(render_form(ev, token, Some(e.message())));
+    }
+    let event_id = ev.uuid;
+    let distributed = ev.is_distributed();
+    let accepts_preferences = ev.accepts_preferences(now);
+    // Only slots offered on the form can be chosen
+    let offered: Vec<(Uuid, usize, Vec<Uuid>)> = kiosk_slots(ev, now).into_iter()
+        .map(|slot| (slot.uuid, slot.ranked_choices, slot.sessions.iter().map(|s| s.uuid).collect()))
+        .collect();
+
+    let code = storage.generate_invitation_code();
+    storage.invitations_codes.insert(code.clone(), Invitation::new(code.clone(), event_id, vec![], InvitationCategory::Guest));
+    let pid = storage.register_participant(&code).ok_or(Status::InternalServerError)?;
+    let ev = storage.events.get_mut(&event_id).ok_or(Status::NotFound)?;
+    if let Some(p) = ev.participants.get_mut(&pid) {
+        p.name = name;
+    }
+
+    let mut assignments = Vec::new();
+    for (slot_id, ranked_choices, session_ids) in offered {
+        let key = slot_id.to_string();
+        let mut picks: Vec<Uuid> = Vec::new();
+        for choices in [&mut first, &mut second, &mut third] {
+            let pick = choices.remove(&key).and_then(|v| Uuid::parse_str(v.trim()).ok()).filter(|id| session_ids.contains(id));
+            if let Some(id) = pick.filter(|id| !picks.contains(id)) {
+                picks.push(id);
+            }
+        }
+        picks.truncate(ranked_choices);
+        let Ok(slot) = ev.slot_by_id_mut(slot_id) else { continue; };
+        if distributed {
+            // First come, first served
+            let Some(session_id) = picks.iter().copied().find(|id| slot.session_by_id(*id).is_some_and(|s| s.has_free_seat())) else { continue; };
+            let slot_name = slot.name.clone();
+            let Some(session) = slot.session_by_id_mut(session_id) else { continue; };
+            session.assign_participant(pid);
+            assignments.push(format!("{}: {}", slot_name, session.name));
+        } else if accepts_preferences {
+            for (i, session_id) in picks.iter().enumerate() {
+                slot.insert_application(*session_id, pid, ApplicationPriority::from_rank(i + 1));
+            }
+            for session_id in session_ids.iter().filter(|id| !picks.contains(id)) {
+                slot.insert_application(*session_id, pid, ApplicationPriority::NoPreference);
+            }
+        }
+    }
+    let event_name = ev.name.clone();
+    if accepts_preferences && !distributed {
+        storage.queue_preference_webhook(event_id, pid, now);
+    }
+
+    let ctx = KioskDoneContext { token: token.to_string(), event_name, login_url: config.url(&format!("/invitation/{}", code)), code, assignments, distributed };
+    Ok(Template::render("kiosk_done", &ctx))
+}
@@ -1,11 +1,14 @@
-use rocket_dyn_templates::Template;
+use rocket::request::FlashMessage;
+use rocket_dyn_templates::{context, Template};
+
+use crate::backend::error::FlashView;
 
 #[get("/login/admin")]
-pub fn admin_login_page() -> Template {
-    Template::render("admin/login", ())
+pub fn admin_login_page(flash: Option<FlashMessage<'_>>) -> Template {
+    Template::render("admin/login", context! { flash: FlashView::from_flash(flash) })
 }
 
 #[get("/")]
-pub fn start_page() -> Template {
-    Template::render("index", ())
+pub fn start_page(flash: Option<FlashMessage<'_>>) -> Template {
+    Template::render("index", context! { flash: FlashView::from_flash(flash) })
 }
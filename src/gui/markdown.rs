@@ -0,0 +1,99 @@
+/// Escapes text for use in HTML content and quoted attributes
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Renders the Markdown subset admins can use in texts shown to participants: paragraphs,
+/// `#` headings, `-` lists, `**bold**`, `*italic*`, `` `code` `` and `[links](https://...)`.
+/// Raw HTML is escaped, links only accept http(s) and mailto URLs.
+pub fn render_markdown(text: &str) -> String {
+    let mut html = String::new();
+    for block in text.replace("\r\n", "\n").split("\n\n") {
+        let lines: Vec<&str> = block.lines().map(str::trim_end).filter(|l| !l.trim().is_empty()).collect();
+        if lines.is_empty() {
+            continue;
+        }
+        let mut paragraph: Vec<String> = Vec::new();
+        let mut list: Vec<String> = Vec::new();
+        for line in lines {
+            let trimmed = line.trim_start();
+            if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+                flush_paragraph(&mut html, &mut paragraph);
+                list.push(inline(item.trim()));
+            } else if let Some((level, heading)) = heading(trimmed) {
+                flush_paragraph(&mut html, &mut paragraph);
+                flush_list(&mut html, &mut list);
+                html.push_str(&format!("<h{0} class=\"h{1}\">{2}</h{0}>", level + 2, (level + 4).min(6), inline(heading)));
+            } else {
+                flush_list(&mut html, &mut list);
+                paragraph.push(inline(trimmed));
+            }
+        }
+        flush_paragraph(&mut html, &mut paragraph);
+        flush_list(&mut html, &mut list);
+    }
+    html
+}
+
+/// Level (1 to 3) and text of a `#` heading
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=3).contains(&level).then_some((level, text.trim()))
+}
+
+fn flush_paragraph(html: &mut String, lines: &mut Vec<String>) {
+    if !lines.is_empty() {
+        html.push_str(&format!("<p>{}</p>", lines.join("<br/>")));
+        lines.clear();
+    }
+}
+
+fn flush_list(html: &mut String, items: &mut Vec<String>) {
+    if !items.is_empty() {
+        html.push_str("<ul>");
+        for item in items.drain(..) {
+            html.push_str(&format!("<li>{}</li>", item));
+        }
+        html.push_str("</ul>");
+    }
+}
+
+fn inline(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if let Some(inner) = rest.strip_prefix("**") && let Some(end) = inner.find("**").filter(|end| *end > 0) {
+            out.push_str(&format!("<strong>{}</strong>", inline(&inner[..end])));
+            rest = &inner[end + 2..];
+            continue;
+        }
+        if (c == '*' || c == '`') && let Some(end) = rest[1..].find(c).filter(|end| *end > 0) {
+            let inner = &rest[1..end + 1];
+            if c == '*' {
+                out.push_str(&format!("<em>{}</em>", inline(inner)));
+            } else {
+                out.push_str(&format!("<code>{}</code>", escape_html(inner)));
+            }
+            rest = &rest[end + 2..];
+            continue;
+        }
+        if c == '[' && let Some((label, url, remaining)) = link(rest) {
+            out.push_str(&format!("<a href=\"{}\" rel=\"noopener\">{}</a>", escape_html(url), inline(label)));
+            rest = remaining;
+            continue;
+        }
+        out.push_str(&escape_html(&rest[..c.len_utf8()]));
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// Splits `[label](url)...` into label, URL and the remaining text if the URL is allowed
+fn link(text: &str) -> Option<(&str, &str, &str)> {
+    let (label, after) = text[1..].split_once("](")?;
+    let (url, remaining) = after.split_once(')')?;
+    let url = url.trim();
+    let allowed = url.starts_with("https://") || url.starts_with("http://") || url.starts_with("mailto:");
+    (allowed && !label.is_empty() && !url.contains(char::is_whitespace)).then_some((label, url, remaining))
+}
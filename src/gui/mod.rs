@@ -1,3 +1,16 @@
 pub mod admin;
 pub mod user;
-pub mod login;
\ No newline at end of file
+pub mod login;
+pub mod export;
+pub mod datetime;
+pub mod register;
+pub mod result;
+pub mod push;
+pub mod kiosk;
+pub mod bundle;
+pub mod share;
+pub mod onboarding;
+pub mod identity;
+pub mod templates;
+pub mod markdown;
+pub mod infoscreen;
\ No newline at end of file
@@ -0,0 +1,72 @@
+use chrono::Utc;
+use rocket::form::Form;
+use rocket::http::{CookieJar, Status};
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::State;
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+use crate::backend::auth::{start_session, SessionUserType};
+use crate::backend::config::AppConfig;
+use crate::backend::error::{BackendError, FlashView};
+use crate::backend::state::{AppState, RecoverLock};
+use crate::backend::validation::Validator;
+use crate::gui::datetime::format_display;
+
+#[derive(Serialize)]
+struct OnboardingContext {
+    token: String,
+    event_name: String,
+    email: String,
+    invited_by: String,
+    expires_at: String,
+    flash: Option<FlashView>,
+}
+
+#[derive(FromForm)]
+pub struct OnboardingForm {
+    pub username: String,
+    pub password: String,
+    /// Repetition of the password, only checked for new accounts
+    pub password_confirm: String,
+}
+
+/// Page of an onboarding link mailed to a co-organizer
+#[get("/onboarding/<token>")]
+pub fn onboarding_page(state: &State<AppState>, token: &str, flash: Option<FlashMessage<'_>>) -> Result<Template, Status> {
+    let storage = state.storage.read_unpoisoned();
+    let (ev, invite) = storage.co_admin_invite(token, Utc::now()).ok_or(Status::NotFound)?;
+    let ctx = OnboardingContext {
+        token: token.to_string(),
+        event_name: ev.name.clone(),
+        email: invite.email.clone(),
+        invited_by: invite.invited_by.clone(),
+        expires_at: format_display(&invite.expires_at),
+        flash: FlashView::from_flash(flash),
+    };
+    Ok(Template::render("admin/onboarding", &ctx))
+}
+
+/// Creates the co-organizer's account or adds the event to an existing one, then logs them in
+#[post("/onboarding/<token>", data = "<form>")]
+pub fn accept_onboarding(state: &State<AppState>, config: &State<AppConfig>, jar: &CookieJar, token: &str, form: Form<OnboardingForm>) -> Result<Flash<Redirect>, BackendError> {
+    let OnboardingForm { username, password, password_confirm } = form.into_inner();
+    let username = username.trim().to_string();
+    let (event_id, tenant) = {
+        let mut storage = state.storage.write_unpoisoned();
+        if !storage.admins.contains_key(&username) {
+            let mut v = Validator::new(&config.form_limits);
+            v.name("username", &username);
+            v.password("password", &password);
+            if password != password_confirm {
+                v.error("password_confirm", "The passwords don't match.");
+            }
+            v.finish()?;
+        }
+        storage.accept_co_admin_invite(token, &username, &password, Utc::now())?
+    };
+    println!("{} joined event {} as co-organizer", username, event_id);
+    start_session(jar, state, SessionUserType::Admin { username, tenant });
+    Ok(Flash::success(Redirect::to(format!("/admin/events/{}", event_id)), "Welcome! You can now organize this event."))
+}
@@ -0,0 +1,43 @@
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use serde::Deserialize;
+
+use crate::backend::auth::ParticipantSession;
+use crate::backend::error::BackendError;
+use crate::backend::push::PushSubscription;
+use crate::backend::state::{AppState, RecoverLock};
+
+#[derive(Deserialize)]
+pub struct SubscriptionKeys {
+    pub p256dh: String,
+    pub auth: String,
+}
+
+/// Body as produced by `PushSubscription.toJSON()` in the browser
+#[derive(Deserialize)]
+pub struct SubscriptionRequest {
+    pub endpoint: String,
+    pub keys: SubscriptionKeys,
+}
+
+#[post("/event/push/subscribe", data = "<request>")]
+pub fn push_subscribe(user: ParticipantSession, state: &State<AppState>, request: Json<SubscriptionRequest>) -> Result<Status, BackendError> {
+    let SubscriptionRequest { endpoint, keys } = request.into_inner();
+    if !endpoint.starts_with("https://") {
+        return Err(BackendError::bad_request("Push endpoints must use https."));
+    }
+    let mut storage = state.storage.write_unpoisoned();
+    if !storage.add_push_subscription(&user.code, PushSubscription { endpoint, p256dh: keys.p256dh, auth: keys.auth }) {
+        return Err(BackendError::NotFound("invitation"));
+    }
+    Ok(Status::NoContent)
+}
+
+#[post("/event/push/unsubscribe", data = "<request>")]
+pub fn push_unsubscribe(user: ParticipantSession, state: &State<AppState>, request: Json<SubscriptionRequest>) -> Result<Status, BackendError> {
+    let mut storage = state.storage.write_unpoisoned();
+    let Some(inv) = storage.invitations_codes.get_mut(&user.code) else { return Err(BackendError::NotFound("invitation")); };
+    inv.push_subscriptions.retain(|s| s.endpoint != request.endpoint);
+    Ok(Status::NoContent)
+}
@@ -0,0 +1,150 @@
+use rocket::form::{Form, FromForm};
+use rocket::http::{CookieJar, Status};
+use rocket::response::Redirect;
+use rocket::State;
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::backend::auth::{start_session, SessionUserType};
+use crate::backend::client_ip::ClientIp;
+use crate::backend::config::AppConfig;
+use crate::backend::data::{EmailVerification, Invitation, InvitationCategory};
+use crate::backend::mail::OutgoingMail;
+use crate::backend::mail_templates::AcceptLanguage;
+use crate::backend::state::{AppState, RecoverLock};
+use crate::backend::validation::Validator;
+use crate::gui::datetime::format_display;
+
+#[derive(Serialize)]
+struct RegisterContext {
+    event_uuid: Uuid,
+    event_name: String,
+    event_description: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RegisterDoneContext {
+    event_name: String,
+}
+
+#[derive(FromForm)]
+pub struct RegisterForm {
+    pub name: String,
+    pub email: String,
+    /// Honeypot field, hidden from humans. Bots filling it in are silently ignored.
+    pub website: Option<String>,
+}
+
+fn access_link_mail(config: &AppConfig, locale: Option<&str>, event_id: Uuid, event_name: &str, name: &str, email: &str, code: &str) -> OutgoingMail {
+    let (subject, body) = config.mail_templates.render("registration_access", locale, &[
+        ("name", name),
+        ("event", event_name),
+        ("link", &config.url(&format!("/invitation/{}", code))),
+        ("code", code),
+    ]);
+    OutgoingMail::new(Some(event_id), Some(email.to_string()), name.to_string(), subject, body)
+}
+
+fn verification_mail(config: &AppConfig, locale: Option<&str>, event_id: Uuid, event_name: &str, name: &str, email: &str, verification: &EmailVerification) -> OutgoingMail {
+    let (subject, body) = config.mail_templates.render("registration_verification", locale, &[
+        ("name", name),
+        ("event", event_name),
+        ("expires", &format_display(&verification.expires_at)),
+        ("link", &config.url(&format!("/register/verify/{}", verification.token))),
+    ]);
+    OutgoingMail::new(Some(event_id), Some(email.to_string()), name.to_string(), subject, body)
+}
+
+#[get("/events/<event_id>/register")]
+pub fn register_page(state: &State<AppState>, event_id: Uuid) -> Result<Template, Status> {
+    let storage = state.storage.read_unpoisoned();
+    let Some(ev) = storage.events.get(&event_id) else { return Err(Status::NotFound); };
+    if !ev.allows_self_registration() { return Err(Status::NotFound); }
+    let ctx = RegisterContext { event_uuid: ev.uuid, event_name: ev.name.clone(), event_description: ev.description.clone(), error: None };
+    Ok(Template::render("register", &ctx))
+}
+
+#[post("/events/<event_id>/register", data = "<form>")]
+pub fn register(state: &State<AppState>, config: &State<AppConfig>, client_ip: Option<ClientIp>, accept_language: AcceptLanguage, event_id: Uuid, form: Form<RegisterForm>) -> Result<Result<Redirect, Template>, Status> {
+    let RegisterForm { name, email, website } = form.into_inner();
+    let name = name.trim().to_string();
+    let email = email.trim().to_string();
+    let locale = accept_language.0.as_deref().and_then(|header| config.mail_templates.negotiate(header));
+
+    let mut storage = state.storage.write_unpoisoned();
+    let Some(ev) = storage.events.get(&event_id) else { return Err(Status::NotFound); };
+    if !ev.allows_self_registration() { return Err(Status::NotFound); }
+    let event_name = ev.name.clone();
+
+    let render_error = |error: &str| {
+        let ctx = RegisterContext { event_uuid: ev.uuid, event_name: ev.name.clone(), event_description: ev.description.clone(), error: Some(error.to_string()) };
+        Ok(Err(Template::render("register", &ctx)))
+    };
+    if website.is_some_and(|w| !w.trim().is_empty()) {
+        return Ok(Err(Template::render("register_done", &RegisterDoneContext { event_name })));
+    }
+    let ip_key = client_ip.map(|ip| ip.0.to_string()).unwrap_or_default();
+    if !state.registration_limiter.check(&ip_key, config.registrations_per_hour) {
+        return render_error("Too many registrations from your network. Please try again later.");
+    }
+    if name.is_empty() || !email.contains('@') {
+        return render_error("Please enter your name and a valid email address.");
+    }
+    let mut v = Validator::new(&config.form_limits);
+    v.name("name", &name);
+    if let Err(e) = v.finish() {
+        return render_error(&e.message());
+    }
+
+    // Don't hand out access to an existing registration, send its link to the owner instead
+    let existing = storage.invitations_codes.values()
+        .find(|inv| inv.event_id == event_id && inv.email.as_deref().is_some_and(|e| e.eq_ignore_ascii_case(&email)))
+        .cloned();
+    if let Some(inv) = existing {
+        let locale = storage.locale_of(&inv).or(locale);
+        let mail = match &inv.pending_verification {
+            Some(verification) => verification_mail(config, locale.as_deref(), event_id, &event_name, &name, &email, &storage.signed_verification(verification)),
+            None => access_link_mail(config, locale.as_deref(), event_id, &event_name, &name, &email, &inv.code),
+        };
+        storage.queue_mail(mail);
+        return Ok(Err(Template::render("register_done", &RegisterDoneContext { event_name })));
+    }
+
+    let code = storage.generate_invitation_code();
+    let mut inv = Invitation::new(code.clone(), event_id, vec![], InvitationCategory::Guest);
+    inv.email = Some(email.clone());
+    inv.name = Some(name.clone());
+    let verification = EmailVerification::new(chrono::Duration::hours(config.verification_hours as i64));
+    inv.pending_verification = Some(verification.clone());
+    storage.invitations_codes.insert(code.clone(), inv);
+    // The participant is only created once the address is verified
+    let signed = storage.signed_verification(&verification);
+    storage.queue_mail(verification_mail(config, locale.as_deref(), event_id, &event_name, &name, &email, &signed));
+    Ok(Err(Template::render("register_done", &RegisterDoneContext { event_name })))
+}
+
+/// Confirms the email address of a self-registration and logs the participant in
+#[get("/register/verify/<token>")]
+pub fn verify_registration(state: &State<AppState>, config: &State<AppConfig>, jar: &CookieJar, accept_language: AcceptLanguage, token: &str) -> Result<Redirect, Status> {
+    let mut storage = state.storage.write_unpoisoned();
+    // If the event filled up in the meantime, the registration lands on the waiting list instead
+    let Some(code) = storage.verify_email(token, Utc::now()) else { return Err(Status::NotFound); };
+    let inv = storage.invitations_codes.get(&code).cloned().ok_or(Status::NotFound)?;
+    let Some(ev) = storage.events.get_mut(&inv.event_id) else { return Err(Status::NotFound); };
+    if let Some(p) = inv.participant_id.and_then(|pid| ev.participants.get_mut(&pid)) {
+        p.locale = accept_language.0.as_deref().and_then(|header| config.mail_templates.negotiate(header));
+    }
+    let name = inv.participant_id.and_then(|pid| ev.participants.get(&pid)).map(|p| p.name.clone()).unwrap_or_else(|| code.clone());
+    let event_name = ev.name.clone();
+    if let Some(email) = &inv.email {
+        let locale = storage.locale_of(&inv);
+        storage.queue_mail(access_link_mail(config, locale.as_deref(), inv.event_id, &event_name, &name, email, &code));
+    }
+    drop(storage);
+
+    start_session(jar, state, SessionUserType::User { code });
+    Ok(Redirect::to("/event"))
+}
@@ -0,0 +1,119 @@
+use chrono::{DateTime, Utc};
+use rocket::http::Status;
+use rocket::State;
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::backend::data::{Event, Participant, Session, JOIN_URL_LEAD_MINUTES};
+use crate::backend::state::{AppState, RecoverLock};
+use crate::gui::datetime::format_display;
+
+#[derive(Serialize)]
+struct ResultSession {
+    name: String,
+    online: bool,
+    /// Only set once the join link is revealed
+    join_url: Option<String>,
+    /// First names (or badge numbers for anonymous rosters) of the other attendees, empty unless the event shows them
+    peers: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ResultSlot {
+    name: String,
+    description: Option<String>,
+    starts_at: Option<String>,
+    /// Sessions the participant was assigned to in this slot
+    sessions: Vec<ResultSession>,
+}
+
+#[derive(Serialize)]
+struct ResultContext {
+    event_name: String,
+    participant_name: String,
+    /// Badge number to show at the sessions if the event uses anonymous rosters
+    badge: Option<String>,
+    slots: Vec<ResultSlot>,
+    has_any_assignment: bool,
+    join_url_lead_minutes: i64,
+}
+
+/// Sorted first names (badge numbers for anonymous rosters) of everyone assigned to the session except the participant
+fn peer_names(ev: &Event, session: &Session, participant_id: Uuid) -> Vec<String> {
+    let mut names: Vec<String> = session.participants.iter()
+        .filter(|pid| **pid != participant_id)
+        .filter_map(|pid| {
+            let peer = ev.participants.get(pid)?;
+            if ev.anonymous_rosters { Some(ev.roster_label(peer)) } else { peer.first_name().map(str::to_string) }
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+/// Read-only result lookup by invitation code, doesn't create a session
+#[get("/result/<code>")]
+pub fn result_view(state: &State<AppState>, code: &str) -> Result<Template, Status> {
+    let storage = state.storage.read_unpoisoned();
+    let Some(inv) = storage.invitations_codes.get(code) else { return Err(Status::NotFound); };
+    let Some(ev) = storage.events.get(&inv.event_id) else { return Err(Status::NotFound); };
+    // Unknown codes and unpublished events look the same from outside
+    if !ev.has_public_results() { return Err(Status::NotFound); }
+    let Some(participant) = inv.participant_id.and_then(|pid| ev.participants.get(&pid)) else { return Err(Status::NotFound); };
+    Ok(Template::render("result", result_context(ev, participant, code, Utc::now())))
+}
+
+/// The participant's own seats, with the other attendees only as first names or badge numbers
+fn result_context(ev: &Event, participant: &Participant, code: &str, now: DateTime<Utc>) -> ResultContext {
+    let slots: Vec<ResultSlot> = ev.slots.iter().map(|slot| ResultSlot {
+        name: slot.name.clone(),
+        description: slot.description.clone(),
+        starts_at: slot.starts_at.as_ref().map(format_display),
+        sessions: slot.sessions.iter().filter(|s| s.participants.contains(&participant.uuid)).map(|s| ResultSession {
+            name: s.name.clone(),
+            online: s.online,
+            join_url: s.join_url.clone().filter(|_| s.online && slot.reveals_join_urls(now)),
+            peers: if ev.show_peer_names { peer_names(ev, s, participant.uuid) } else { vec![] },
+        }).collect(),
+    }).collect();
+    let has_any_assignment = slots.iter().any(|slot| !slot.sessions.is_empty());
+    let participant_name = if participant.name.trim().is_empty() { code.to_string() } else { participant.name.clone() };
+    let badge = ev.anonymous_rosters.then(|| ev.roster_label(participant));
+    ResultContext { event_name: ev.name.clone(), participant_name, badge, slots, has_any_assignment, join_url_lead_minutes: JOIN_URL_LEAD_MINUTES }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::backend::data::{Event, EventState, Invitation, InvitationCategory, Session, Slot};
+
+    use super::result_context;
+
+    #[test]
+    fn result_context_shows_peers_only_by_first_name() {
+        let mut ev = Event::new("Test".to_string(), None);
+        let mut slot = Slot::new("Morning".to_string(), None);
+        slot.sessions.push(Session::new("Workshop".to_string(), None, 2));
+        ev.slots.push(slot);
+        ev.state = EventState::Published;
+        ev.show_peer_names = true;
+        let own = Invitation::new("OWNCODE".to_string(), ev.uuid, vec![], InvitationCategory::default());
+        let other = Invitation::new("OTHERCODE".to_string(), ev.uuid, vec![], InvitationCategory::default());
+        let mut participant = own.new_participant();
+        participant.name = "Alice Owner".to_string();
+        let mut peer = other.new_participant();
+        peer.name = "Bob Otherton".to_string();
+        ev.slots[0].sessions[0].participants = vec![participant.uuid, peer.uuid];
+        let peer_id = peer.uuid;
+        ev.participants.insert(peer.uuid, peer);
+
+        let json = serde_json::to_string(&result_context(&ev, &participant, &own.code, Utc::now())).unwrap();
+
+        assert!(json.contains("\"Bob\""));
+        assert!(!json.contains(&peer_id.to_string()));
+        assert!(!json.contains("OTHERCODE"));
+        assert!(!json.contains("Otherton"));
+    }
+}
@@ -0,0 +1,109 @@
+use chrono::Utc;
+use rocket::http::Status;
+use rocket::State;
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+use crate::api::stats::{collect_stats, RegistrationStats};
+use crate::backend::data::{ApplicationPriority, EventState};
+use crate::backend::share::ShareKind;
+use crate::backend::state::{AppState, RecoverLock};
+use crate::gui::datetime::format_display;
+
+#[derive(Serialize)]
+struct ShareContext {
+    event_name: String,
+    state: EventState,
+    kind: ShareKind,
+    label: String,
+    expires_at: String,
+    /// The seats are assigned, possibly still awaiting the admins' confirmation
+    distributed: bool,
+    /// The assignment hasn't been confirmed yet and may still change
+    draft: bool,
+    registrations: RegistrationStats,
+    slots: Vec<ShareSlot>,
+    /// Share of assigned seats that were a first choice or one of the three choices, in percent
+    first_choice_percent: Option<u32>,
+    top_three_percent: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct ShareSlot {
+    name: String,
+    sessions: Vec<ShareSession>,
+}
+
+#[derive(Serialize)]
+struct ShareSession {
+    name: String,
+    seats: usize,
+    first_preferences: usize,
+    second_preferences: usize,
+    third_preferences: usize,
+    /// First preferences per seat
+    demand: String,
+    assigned: usize,
+    fill_percent: u32,
+    /// Assigned participants by the preference their seat was
+    assigned_first: usize,
+    assigned_second: usize,
+    assigned_third: usize,
+    assigned_other: usize,
+}
+
+fn percent(rate: f64) -> u32 {
+    (rate * 100.0).round() as u32
+}
+
+/// Read-only figures of an event for stakeholders holding a share link, until the link expires
+#[get("/share/<token>")]
+pub fn share_page(state: &State<AppState>, token: &str) -> Result<Template, Status> {
+    let storage = state.storage.read_unpoisoned();
+    let (ev, link) = storage.shared_event(token, Utc::now()).ok_or(Status::NotFound)?;
+    let stats = collect_stats(&storage, ev);
+    let slots = ev.slots.iter()
+        .map(|slot| ShareSlot {
+            name: slot.name.clone(),
+            sessions: slot.sessions.iter()
+                .map(|session| {
+                    let stat = stats.sessions.iter().find(|s| s.session_id == session.uuid);
+                    let assigned_with = |priority: fn(&ApplicationPriority) -> bool| session.participants.iter()
+                        .filter(|pid| session.applications.iter().any(|a| a.participant == **pid && priority(&a.priority)))
+                        .count();
+                    let assigned_first = assigned_with(|p| matches!(p, ApplicationPriority::FirstPreference));
+                    let assigned_second = assigned_with(|p| matches!(p, ApplicationPriority::SecondPreference));
+                    let assigned_third = assigned_with(|p| matches!(p, ApplicationPriority::ThirdPreference));
+                    ShareSession {
+                        name: session.name.clone(),
+                        seats: session.seats,
+                        first_preferences: stat.map_or(0, |s| s.first_preferences),
+                        second_preferences: stat.map_or(0, |s| s.second_preferences),
+                        third_preferences: stat.map_or(0, |s| s.third_preferences),
+                        demand: format!("{:.1}", stat.map_or(0.0, |s| s.demand)),
+                        assigned: session.participants.len(),
+                        fill_percent: percent(stat.map_or(0.0, |s| s.fill_rate)),
+                        assigned_first,
+                        assigned_second,
+                        assigned_third,
+                        assigned_other: session.participants.len() - assigned_first - assigned_second - assigned_third,
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+    let ctx = ShareContext {
+        event_name: ev.name.clone(),
+        state: ev.state,
+        kind: link.kind,
+        label: link.label.clone(),
+        expires_at: format_display(&link.expires_at),
+        distributed: ev.is_distributed(),
+        draft: ev.state == EventState::AwaitingConfirmation,
+        registrations: stats.registrations,
+        slots,
+        first_choice_percent: stats.fairness.as_ref().map(|f| percent(f.first_choice_rate)),
+        top_three_percent: stats.fairness.as_ref().map(|f| percent(f.top_three_rate)),
+    };
+    Ok(Template::render("share", &ctx))
+}
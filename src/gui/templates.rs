@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+
+use rocket::fairing::AdHoc;
+use rocket::request::Request;
+use rocket::response::content::RawHtml;
+use rocket::Config;
+
+use crate::gui::markdown::escape_html;
+
+/// Templates rendered by the routes and the partials they include, without the `.html.hbs` extension
+pub const TEMPLATES: &[&str] = &[
+    "admin/confirm_delete",
+    "admin/edit_conflict",
+    "admin/event",
+    "admin/index",
+    "admin/login",
+    "admin/mail_preview",
+    "admin/onboarding",
+    "admin/outbox",
+    "admin/sign_in_sheets",
+    "admin/split_session",
+    "admin/timeline",
+    "bundle",
+    "flash",
+    "index",
+    "kiosk",
+    "kiosk_done",
+    "queue",
+    "read_only",
+    "register",
+    "register_done",
+    "result",
+    "share",
+    "user/event",
+    "user/waiting",
+];
+
+/// Template directory from the config, `templates` by default
+pub fn template_dir(config: &rocket::figment::Figment) -> PathBuf {
+    config.extract_inner::<PathBuf>("template_dir").unwrap_or_else(|_| PathBuf::from("templates"))
+}
+
+/// Templates of [`TEMPLATES`] without a file in the given directory
+pub fn missing_templates(dir: &Path) -> Vec<&'static str> {
+    TEMPLATES.iter().copied().filter(|name| !dir.join(format!("{}.html.hbs", name)).is_file()).collect()
+}
+
+/// Refuses to launch if a template is missing, instead of failing the first request that needs it
+pub fn check_templates() -> AdHoc {
+    AdHoc::try_on_ignite("template check", |rocket| async move {
+        let dir = template_dir(rocket.figment());
+        let missing = missing_templates(&dir);
+        if missing.is_empty() {
+            return Ok(rocket);
+        }
+        eprintln!("Missing templates in {}: {}", dir.display(), missing.join(", "));
+        Err(rocket)
+    })
+}
+
+/// Error page that doesn't depend on templates, as a broken template may be the cause.
+/// Debug builds add the request and any templates missing since launch.
+#[catch(500)]
+pub fn internal_error(req: &Request<'_>) -> RawHtml<String> {
+    let mut details = String::new();
+    if req.rocket().config().profile == Config::DEBUG_PROFILE {
+        details.push_str(&format!("<p>Request: <code>{} {}</code></p>", req.method(), escape_html(&req.uri().to_string())));
+        let missing = missing_templates(&template_dir(req.rocket().figment()));
+        if !missing.is_empty() {
+            details.push_str(&format!("<p>Missing templates: <code>{}</code></p>", escape_html(&missing.join(", "))));
+        }
+        details.push_str("<p>See the server log for details.</p>");
+    }
+    RawHtml(format!(
+        "<!doctype html><html lang=\"en\"><head><meta charset=\"utf-8\"><title>Something went wrong</title></head>\
+         <body><h1>Something went wrong</h1><p>The page couldn't be shown. Please try again later.</p>{}</body></html>",
+        details
+    ))
+}
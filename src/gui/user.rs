@@ -1,51 +1,117 @@
 use rocket::form::{Form, FromForm};
-use rocket::http::Status;
-use rocket::response::Redirect;
+use rocket::http::ContentType;
+use rocket::request::FlashMessage;
+use crate::backend::error::{BackendError, FlashView};
+use rocket::response::{Flash, Redirect};
 use rocket::State;
 use rocket_dyn_templates::Template;
 use serde::Serialize;
 use uuid::Uuid;
 use std::collections::HashMap;
 
-use crate::backend::auth::{Session, SessionUserType};
-use crate::backend::data::{Application, ApplicationPriority, Event, EventState, Invitation, Participant, Slot};
-use crate::backend::state::AppState;
-
+use crate::backend::auth::ParticipantSession;
+use crate::backend::config::AppConfig;
+use crate::backend::mail_templates::normalize_locale;
+use crate::backend::data::{ordinal, ApplicationPriority, EventState, Invitation, Registration, Slot, Storage};
+use crate::backend::state::{AppState, RecoverLock};
+use crate::backend::scoring::fairness_rules;
+use crate::backend::timeline::ActivityKind;
+use crate::backend::validation::Validator;
+use crate::backend::waiting_room::Admission;
+use crate::gui::datetime::format_display;
+use crate::gui::markdown::render_markdown;
+use chrono::Utc;
+
+/// Only what the participant templates need. Other participants' applications and assignments
+/// never reach the rendering layer.
 #[derive(Serialize, Clone)]
 pub struct UserEventContext {
-    pub event: Event,
-    pub participant: Participant,
+    pub flash: Option<FlashView>,
+    pub event: ViewEvent,
+    pub participant: ViewParticipant,
+    /// Contact address stored on the invitation
+    pub email: Option<String>,
+    /// Languages mails can be sent in
+    pub mail_locales: Vec<String>,
+    /// Registration deadline formatted in local time, if set
+    pub registration_deadline: Option<String>,
     pub is_open: bool,
     pub is_finished: bool,
+    /// True if assigned sessions can be rated
+    pub accepts_feedback: bool,
+    /// Bookmarkable result page, once the admins published the results
+    pub result_path: Option<String>,
+    /// Public VAPID key for subscribing to push notifications
+    pub push_public_key: Option<String>,
+    /// Confirmed address linking this registration to the participant's other events
+    pub linked_email: Option<String>,
+    /// Address of a link request still waiting for confirmation
+    pub pending_link_email: Option<String>,
+    /// Ranking and compensation rules of the event in plain words
+    pub fairness_rules: Vec<String>,
+    /// Earlier linked event whose preferences can be imported
+    pub import_from: Option<String>,
+    /// Shown next to the name field if preferences were rejected because the name is missing
+    pub name_error: Option<String>,
+    /// All session tags of the event, for filtering the program
+    pub session_tags: Vec<String>,
     /// True if the user has any assignment in any slot (only meaningful when finished)
     pub has_any_assignment: bool,
-    /// True if the user has chosen any preference in any slot (first/second/third)
+    /// True if the user has ranked any session in any slot
     pub has_any_selection: bool,
-    /// per-slot selections list (optional)
-    pub selections: Vec<SlotSelection>,
-    /// map slot_id (string) -> selection for easier templating
-    pub selections_map: std::collections::HashMap<String, SlotSelectionStr>,
     /// View-friendly slots including sessions and the user's selection per slot
     pub view_slots: Vec<ViewSlot>,
+    /// Seats the admins changed since the results were shown, until the participant dismisses them
+    pub schedule_changes: Vec<ViewScheduleChange>,
 }
 
 #[derive(Serialize, Clone)]
-pub struct SlotSelection {
-    pub slot_id: Uuid,
-    pub first: Option<Uuid>,
-    pub second: Option<Uuid>,
-    pub third: Option<Uuid>,
+pub struct ViewScheduleChange {
+    pub slot: String,
+    pub from: String,
+    /// None if the seat was taken away
+    pub to: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ViewEvent {
+    pub name: String,
+    pub description: Option<String>,
+    pub state: EventState,
+    /// Rendered welcome text of the event
+    pub welcome_html: Option<String>,
 }
 
+#[derive(Serialize, Clone)]
+pub struct ViewParticipant {
+    pub name: String,
+    pub needs_accessible_room: bool,
+    pub locale: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct UserWaitingContext {
+    pub event_name: String,
+    /// 1-based position on the event's waiting list
+    pub position: usize,
+}
+
+/// The participant's ranked choices in a slot, one entry per rank the slot asks for
 #[derive(Serialize, Clone, Default)]
 pub struct SlotSelectionStr {
-    pub first: Option<String>,
-    pub second: Option<String>,
-    pub third: Option<String>,
-    // Resolved human-friendly names for the selected sessions (if any)
-    pub first_name: Option<String>,
-    pub second_name: Option<String>,
-    pub third_name: Option<String>,
+    pub choices: Vec<ViewChoice>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ViewChoice {
+    /// Counting from 1, also the key of the form field
+    pub rank: usize,
+    /// "1st", "2nd", …
+    pub label: String,
+    /// UUID string of the chosen session
+    pub session: Option<String>,
+    // Resolved human-friendly name of the chosen session
+    pub name: Option<String>,
 }
 
 #[derive(Serialize, Clone)]
@@ -55,6 +121,17 @@ pub struct ViewSession {
     pub description: Option<String>,
     pub seats: usize,
     pub assigned_to_me: bool,
+    pub tags: Vec<String>,
+    /// Marked as "maybe" by the participant
+    pub interested: bool,
+    /// Marked by the participant as a session they can't attend
+    pub avoided: bool,
+    /// Conditions to accept before choosing the session
+    pub acknowledgment: Option<String>,
+    pub acknowledged: bool,
+    /// Own rating and comment if feedback was given
+    pub my_rating: Option<u8>,
+    pub my_comment: Option<String>,
 }
 
 #[derive(Serialize, Clone)]
@@ -64,10 +141,35 @@ pub struct ViewSlot {
     pub description: Option<String>,
     pub sessions: Vec<ViewSession>,
     pub selection: SlotSelectionStr,
+    /// True if the participant didn't get a seat because the slot's participant cap was reached
+    pub on_waiting_list: bool,
+    /// False once the slot's own preference deadline has passed
+    pub accepts_preferences: bool,
+    /// Preference deadline of this slot formatted in local time, if set
+    pub deadline: Option<String>,
+    /// Seconds until the preference deadline, for countdowns
+    pub deadline_remaining_secs: Option<i64>,
+    /// Start of the slot formatted in local time, if set
+    pub starts_at: Option<String>,
+    /// Problems with the submitted preferences of this slot
+    pub errors: Vec<String>,
+    /// True if the shown selection was submitted or imported, but isn't saved
+    pub unsaved: bool,
+}
+
+/// Preferences shown on the event page without being saved: submissions that failed validation,
+/// with the problems next to the affected slots, or preferences imported from an earlier event.
+#[derive(Default)]
+struct UnsavedPreferences {
+    /// Attempted sessions per slot id, by rank
+    selections: HashMap<String, Vec<Option<String>>>,
+    /// Problems per slot id
+    errors: HashMap<String, Vec<String>>,
+    name_error: Option<String>,
 }
 
 #[derive(FromForm)]
-pub struct SaveNameForm { pub name: String }
+pub struct SaveNameForm { pub name: String, pub email: Option<String>, pub needs_accessible_room: bool, pub locale: Option<String> }
 
 #[derive(FromForm)]
 pub struct PreferencesForm {
@@ -76,124 +178,119 @@ pub struct PreferencesForm {
     pub third: Option<Uuid>,
 }
 
+#[derive(FromForm)]
+pub struct FeedbackForm { pub rating: u8, pub comment: Option<String> }
+
 #[derive(FromForm, Default)]
 pub struct AllPreferencesForm {
-    // Keys are slot UUID strings; values are selected session UUID strings (may be empty)
-    pub first: HashMap<String, String>,
-    pub second: HashMap<String, String>,
-    pub third: HashMap<String, String>,
+    // Keys are slot UUID strings and ranks from 1; values are selected session UUID strings (may be empty)
+    pub rank: HashMap<String, HashMap<usize, String>>,
+    /// Sessions marked as "maybe", by UUID string
+    pub interested: Vec<String>,
+    /// Sessions the participant can't attend, by UUID string
+    pub avoided: Vec<String>,
+    /// Sessions whose conditions the participant accepted, by UUID string
+    pub acknowledged: Vec<String>,
 }
 
 #[get("/event")]
-pub fn event_view(session: Session, state: &State<AppState>) -> Result<Template, Status> {
-    let code = match &session.user_type {
-        SessionUserType::User { code } => code.clone(),
-        _ => return Err(Status::Forbidden),
-    };
+pub fn event_view(_admission: Admission<'_>, user: ParticipantSession, state: &State<AppState>, config: &State<AppConfig>, flash: Option<FlashMessage<'_>>) -> Result<Template, BackendError> {
+    let ParticipantSession { code, invitation: inv } = user;
 
     // Acquire write lock because we may create a participant the first time
-    let mut storage = state.storage.write().map_err(|_| Status::InternalServerError)?;
-    let inv = match storage.invitations_codes.get(&code).cloned() {
-        Some(inv) => inv,
-        None => return Err(Status::Unauthorized),
-    };
-
-    let ev = match storage.events.get(&inv.event_id).cloned() {
-        Some(ev) => ev,
-        None => return Err(Status::NotFound),
-    };
+    let mut storage = state.storage.write_unpoisoned();
+    render_event(&mut storage, config, &code, &inv, FlashView::from_flash(flash), None)
+}
 
-    // Ensure participant exists for this invitation, without overlapping borrows
-    let participant = {
-        let mut new_pid: Option<Uuid> = None;
-        let pid = if let Some(pid) = inv.participant_id { pid } else {
-            let p = Participant { uuid: Uuid::new_v4(), name: String::new(), points_from_previous_rounds: 0 };
-            if let Some(ev_mut) = storage.events.get_mut(&inv.event_id) {
-                ev_mut.participants.insert(p.uuid, p.clone());
-            }
-            new_pid = Some(p.uuid);
-            p.uuid
-        };
-        // Now update invitation outside of the event mutable borrow
-        if let Some(new_pid) = new_pid {
-            let mut inv_new = inv.clone();
-            inv_new.participant_id = Some(new_pid);
-            storage.invitations_codes.insert(inv_new.code.clone(), inv_new);
-        }
-        // Return participant (fetch from storage)
-        if let Some(ev_ro) = storage.events.get(&inv.event_id) {
-            if let Some(p) = ev_ro.participants.get(&pid) {
-                p.clone()
-            } else {
-                // Should not happen, but create a default fallback
-                Participant { uuid: pid, name: String::new(), points_from_previous_rounds: 0 }
-            }
-        } else {
-            return Err(Status::NotFound);
+/// Renders the event page, or the waiting list page while the participant has no place at the event
+fn render_event(storage: &mut Storage, config: &AppConfig, code: &str, inv: &Invitation, flash: Option<FlashView>, unsaved: Option<&UnsavedPreferences>) -> Result<Template, BackendError> {
+    let Some(ev) = storage.events.get(&inv.event_id) else { return Err(BackendError::NotFound("event")); };
+    // Viewing is fine during the distribution, joining the event isn't
+    if inv.participant_id.is_none() { ev.ensure_unlocked()?; }
+
+    // Ensure participant exists for this invitation, unless the event is full
+    let registration = storage.ensure_participant(code, Utc::now());
+    // Everything below only reads, so the event doesn't need to be copied
+    let storage: &Storage = storage;
+    let Some(ev) = storage.events.get(&inv.event_id) else { return Err(BackendError::NotFound("event")) };
+    let pid = match registration {
+        Some(Registration::Registered(pid)) => pid,
+        Some(Registration::Waiting(position)) => {
+            let ctx = UserWaitingContext { event_name: ev.name.clone(), position };
+            return Ok(Template::render("user/waiting", &ctx));
         }
+        None => return Err(BackendError::NotFound("participant")),
     };
+    let ctx = event_context(storage, config, code, inv, pid, flash, unsaved)?;
+    Ok(Template::render("user/event", &ctx))
+}
+
+/// Context of the event page for a participant who has a place at the event
+fn event_context(storage: &Storage, config: &AppConfig, code: &str, inv: &Invitation, pid: Uuid, flash: Option<FlashView>, unsaved: Option<&UnsavedPreferences>) -> Result<UserEventContext, BackendError> {
+    let Some(ev) = storage.events.get(&inv.event_id) else { return Err(BackendError::NotFound("event")) };
+    let Some(participant) = ev.participants.get(&pid) else { return Err(BackendError::NotFound("participant")) };
 
-    // Build selections per slot from applications and collect session names for display
-    let mut selections: Vec<SlotSelection> = Vec::new();
+    // Collect the chosen sessions per slot by rank and the session names for display
+    let mut picks_by_slot: HashMap<String, Vec<Option<String>>> = HashMap::new();
     let mut session_name_map: HashMap<Uuid, String> = HashMap::new();
-    if let Some(ev_mut) = storage.events.get(&inv.event_id) {
-        for slot in &ev_mut.slots {
-            let mut sel = SlotSelection { slot_id: slot.uuid, first: None, second: None, third: None };
-            for sess in &slot.sessions {
-                // cache names
-                session_name_map.insert(sess.uuid, sess.name.clone());
-                for app in &sess.applications {
-                    if app.participant == participant.uuid {
-                        match app.priority {
-                            ApplicationPriority::FirstPreference => sel.first = Some(sess.uuid),
-                            ApplicationPriority::SecondPreference => sel.second = Some(sess.uuid),
-                            ApplicationPriority::ThirdPreference => sel.third = Some(sess.uuid),
-                            ApplicationPriority::NoPreference => {}
-                        }
-                    }
-                }
+    for slot in &ev.slots {
+        let mut picks: Vec<Option<String>> = vec![None; slot.ranked_choices];
+        for sess in &slot.sessions {
+            session_name_map.insert(sess.uuid, sess.name.clone());
+            for rank in sess.applications.iter().filter(|a| a.participant == participant.uuid).filter_map(|a| a.priority.rank()) {
+                if picks.len() < rank { picks.resize(rank, None); }
+                picks[rank - 1] = Some(sess.uuid.to_string());
             }
-            selections.push(sel);
         }
+        picks_by_slot.insert(slot.uuid.to_string(), picks);
     }
 
     // Whether user has made any explicit preference selections
-    let has_any_selection = selections.iter().any(|s| s.first.is_some() || s.second.is_some() || s.third.is_some());
-
-    // Build selections_map as strings for template convenience (also resolve names)
-    let mut selections_map: HashMap<String, SlotSelectionStr> = HashMap::new();
-    for sel in &selections {
-        let first_str = sel.first.map(|u| u.to_string());
-        let second_str = sel.second.map(|u| u.to_string());
-        let third_str = sel.third.map(|u| u.to_string());
-        selections_map.insert(
-            sel.slot_id.to_string(),
-            SlotSelectionStr {
-                first: first_str,
-                second: second_str,
-                third: third_str,
-                first_name: sel.first.and_then(|u| session_name_map.get(&u).cloned()),
-                second_name: sel.second.and_then(|u| session_name_map.get(&u).cloned()),
-                third_name: sel.third.and_then(|u| session_name_map.get(&u).cloned()),
-            },
-        );
+    let has_any_selection = picks_by_slot.values().flatten().any(Option::is_some);
+
+    // Show unsaved preferences as submitted or imported, so nothing has to be chosen again
+    if let Some(unsaved) = unsaved {
+        for (slot_key, attempted) in &unsaved.selections {
+            picks_by_slot.insert(slot_key.clone(), attempted.clone());
+        }
     }
-    let is_open = matches!(ev.state, EventState::OpenForRegistration);
-    let is_finished = matches!(ev.state, EventState::Finished);
+    let selection_of = |slot: &Slot| {
+        let picks = picks_by_slot.get(&slot.uuid.to_string()).map(Vec::as_slice).unwrap_or_default();
+        let choices = (0..slot.ranked_choices.max(picks.len()))
+            .map(|i| {
+                let session = picks.get(i).cloned().flatten();
+                let name = session.as_deref().and_then(|id| Uuid::parse_str(id).ok()).and_then(|id| session_name_map.get(&id).cloned());
+                ViewChoice { rank: i + 1, label: ordinal(i + 1), session, name }
+            })
+            .collect();
+        SlotSelectionStr { choices }
+    };
+    let now = Utc::now();
+    let is_open = ev.accepts_preferences_with(inv.deadline_extension, now);
+    let is_finished = ev.results_visible();
 
     // Build view-friendly slots to avoid template helpers like `lookup`
     let mut view_slots: Vec<ViewSlot> = Vec::new();
     let mut has_any_assignment = false;
     if let Some(ev_ro) = storage.events.get(&inv.event_id) {
         for slot in &ev_ro.slots {
-            let iter = slot.sessions.iter().map(|s| {
+            // Restricted sessions are hidden from participants lacking a matching tag
+            let iter = slot.sessions.iter().filter(|s| s.is_eligible(participant)).map(|s| {
                 let assigned = if is_finished { s.participants.iter().any(|p| *p == participant.uuid) } else { false };
+                let feedback = ev_ro.feedback_of(participant.uuid, s.uuid);
                 ViewSession {
                     uuid: s.uuid,
                     name: s.name.clone(),
                     description: s.description.clone(),
                     seats: s.seats,
                     assigned_to_me: assigned,
+                    tags: s.tags.clone(),
+                    interested: s.interested.contains(&participant.uuid),
+                    avoided: s.is_avoided_by(participant.uuid),
+                    acknowledgment: s.acknowledgment.clone(),
+                    acknowledged: s.acknowledgments.contains_key(&participant.uuid),
+                    my_rating: feedback.map(|f| f.rating),
+                    my_comment: feedback.and_then(|f| f.comment.clone()),
                 }
             });
             let mut sessions: Vec<ViewSession> = if is_finished {
@@ -207,150 +304,351 @@ pub fn event_view(session: Session, state: &State<AppState>) -> Result<Template,
             if !is_finished {
                 // do nothing
             }
-            let selection = selections_map
-                .get(&slot.uuid.to_string())
-                .cloned()
-                .unwrap_or_default();
+            let selection = selection_of(slot);
             view_slots.push(ViewSlot {
                 uuid: slot.uuid,
                 name: slot.name.clone(),
                 description: slot.description.clone(),
                 sessions,
                 selection,
+                on_waiting_list: is_finished && slot.waiting_list.contains(&participant.uuid),
+                accepts_preferences: slot.accepts_preferences(now),
+                deadline: slot.preference_deadline.as_ref().map(format_display),
+                deadline_remaining_secs: slot.preference_deadline.map(|d| (d - now).num_seconds().max(0)),
+                starts_at: slot.starts_at.as_ref().map(format_display),
+                errors: unsaved.and_then(|r| r.errors.get(&slot.uuid.to_string())).cloned().unwrap_or_default(),
+                unsaved: unsaved.is_some_and(|r| r.selections.contains_key(&slot.uuid.to_string())),
             });
         }
     }
 
-    let ctx = UserEventContext { event: ev, participant, is_open, is_finished, has_any_assignment, has_any_selection, selections, selections_map, view_slots };
-    Ok(Template::render("user/event", &ctx))
+    let registration_deadline = ev.deadline_with(inv.deadline_extension).as_ref().map(format_display);
+    let email = storage.invitations_codes.get(code).and_then(|i| i.email.clone());
+    let result_path = ev.has_public_results().then(|| format!("/result/{}", code));
+    let push_public_key = storage.vapid_public_key();
+    let invitation = storage.invitations_codes.get(code);
+    let linked_email = invitation.and_then(|i| i.person_id).and_then(|id| storage.people.get(&id)).map(|p| p.email.clone());
+    let pending_link_email = invitation.and_then(|i| i.pending_link.as_ref()).map(|l| l.email.clone());
+    let session_tags = ev.session_tags();
+    let accepts_feedback = ev.accepts_feedback();
+    let name_error = unsaved.and_then(|r| r.name_error.clone());
+    let fairness_rules = fairness_rules(ev);
+    let import_from = storage.previous_linked_event(code).map(|(previous, _)| previous.name.clone());
+    let event = ViewEvent { name: ev.name.clone(), description: ev.description.clone(), state: ev.state, welcome_html: ev.welcome_text.as_deref().map(render_markdown) };
+    let session_name = |id: Uuid| session_name_map.get(&id).cloned().unwrap_or_default();
+    let schedule_changes = ev.unseen_schedule_changes(participant).into_iter()
+        .map(|change| ViewScheduleChange {
+            slot: ev.slot_by_id(change.slot_id).map(|s| s.name.clone()).unwrap_or_default(),
+            from: session_name(change.from_session),
+            to: change.to_session.map(session_name),
+        })
+        .collect();
+    let participant = ViewParticipant { name: participant.name.clone(), needs_accessible_room: participant.needs_accessible_room, locale: participant.locale.clone() };
+    let mail_locales = config.mail_templates.locales();
+    Ok(UserEventContext { flash, event, participant, email, mail_locales, registration_deadline, is_open, is_finished, accepts_feedback, result_path, push_public_key, linked_email, pending_link_email, fairness_rules, import_from, name_error, session_tags, has_any_assignment, has_any_selection, view_slots, schedule_changes })
+}
+
+/// Everything stored about the participant, as JSON or CSV
+#[get("/event/my_data?<format>")]
+pub fn download_my_data(_admission: Admission<'_>, user: ParticipantSession, state: &State<AppState>, format: Option<&str>) -> Result<(ContentType, String), BackendError> {
+    let storage = state.storage.read_unpoisoned();
+    let data = storage.personal_data(&user.code)?;
+    match format.unwrap_or("json") {
+        "json" => Ok((ContentType::JSON, serde_json::to_string_pretty(&data).expect("personal data is serializable"))),
+        "csv" => Ok((ContentType::CSV, data.to_csv())),
+        other => Err(BackendError::bad_request(format!("Unknown format {}, use json or csv.", other))),
+    }
 }
 
 #[post("/event/name", data = "<form>")]
-pub fn save_name(session: Session, state: &State<AppState>, form: Form<SaveNameForm>) -> Result<Redirect, Status> {
-    let code = match &session.user_type {
-        SessionUserType::User { code } => code.clone(),
-        _ => return Err(Status::Forbidden),
-    };
-    let SaveNameForm { name } = form.into_inner();
-    let mut storage = state.storage.write().map_err(|_| Status::InternalServerError)?;
-    let inv = match storage.invitations_codes.get(&code).cloned() { Some(i) => i, None => return Err(Status::Unauthorized) };
+pub fn save_name(_admission: Admission<'_>, user: ParticipantSession, state: &State<AppState>, config: &State<AppConfig>, form: Form<SaveNameForm>) -> Result<Redirect, BackendError> {
+    let ParticipantSession { code, invitation: inv } = user;
+    let SaveNameForm { name, email, needs_accessible_room, locale } = form.into_inner();
+    // Unknown locales fall back to the default, like unset ones
+    let locale = locale.filter(|l| config.mail_templates.supports(l)).map(|l| normalize_locale(&l));
+    let name = name.trim().to_string();
+    // The name may still be left empty here, it's only required for choosing preferences
+    if !name.is_empty() {
+        let mut v = Validator::new(&config.form_limits);
+        v.name("name", &name);
+        v.finish()?;
+    }
+    let mut storage = state.storage.write_unpoisoned();
     let event_id = inv.event_id;
-    let mut new_pid: Option<Uuid> = None;
-    let pid: Uuid;
-    // Scope the event mutable borrow
-    {
-        let Some(ev_mut) = storage.events.get_mut(&event_id) else { return Err(Status::NotFound) };
-        pid = if let Some(existing) = inv.participant_id { existing } else {
-            let p = Participant { uuid: Uuid::new_v4(), name: String::new(), points_from_previous_rounds: 0 };
-            ev_mut.participants.insert(p.uuid, p.clone());
-            new_pid = Some(p.uuid);
-            p.uuid
-        };
-        if let Some(p) = ev_mut.participants.get_mut(&pid) { p.name = name.trim().to_string(); }
+    storage.events.get(&event_id).ok_or(BackendError::NotFound("event"))?.ensure_unlocked()?;
+    let pid = match storage.ensure_participant(&code, Utc::now()) {
+        Some(Registration::Registered(pid)) => pid,
+        // Still on the waiting list, nothing to save yet
+        Some(Registration::Waiting(_)) => return Ok(Redirect::to("/event")),
+        None => return Err(BackendError::NotFound("participant")),
+    };
+    let Some(ev_mut) = storage.events.get_mut(&event_id) else { return Err(BackendError::NotFound("event")) };
+    let mut name_changed = false;
+    if let Some(p) = ev_mut.participants.get_mut(&pid) {
+        name_changed = !name.is_empty() && p.name != name;
+        p.name = name.clone();
+        p.needs_accessible_room = needs_accessible_room;
+        p.locale = locale;
+    }
+    if name_changed {
+        storage.record_activity(&code, ActivityKind::NameSet { name });
     }
-    // Update invitation mapping after releasing event borrow
-    if let Some(npid) = new_pid {
-        let mut inv_new = inv.clone();
-        inv_new.participant_id = Some(npid);
-        storage.invitations_codes.insert(inv_new.code.clone(), inv_new);
+    // Update the contact address after releasing the event borrow. The accessibility need is kept
+    // on the invitation too, so admins editing the invitation don't reset it.
+    if let Some(inv_mut) = storage.invitations_codes.get_mut(&code) {
+        inv_mut.email = email.map(|e| e.trim().to_string()).filter(|e| !e.is_empty());
+        inv_mut.needs_accessible_room = needs_accessible_room;
     }
     Ok(Redirect::to("/event"))
 }
 
+/// Hides the notice about changed seats until the admins change them again
+#[post("/event/schedule_changes/seen")]
+pub fn dismiss_schedule_changes(_admission: Admission<'_>, user: ParticipantSession, state: &State<AppState>) -> Result<Redirect, BackendError> {
+    let inv = user.invitation;
+    let mut storage = state.storage.write_unpoisoned();
+    let Some(pid) = inv.participant_id else { return Err(BackendError::NotFound("participant")) };
+    let Some(participant) = storage.events.get_mut(&inv.event_id).and_then(|ev| ev.participants.get_mut(&pid)) else { return Err(BackendError::NotFound("participant")) };
+    participant.schedule_seen_at = Some(Utc::now());
+    Ok(Redirect::to("/event"))
+}
+
+#[post("/event/sessions/<session_id>/feedback", data = "<form>")]
+pub fn save_feedback(_admission: Admission<'_>, user: ParticipantSession, state: &State<AppState>, config: &State<AppConfig>, session_id: Uuid, form: Form<FeedbackForm>) -> Result<Flash<Redirect>, BackendError> {
+    let inv = user.invitation;
+    let FeedbackForm { rating, comment } = form.into_inner();
+    let comment = comment.map(|c| c.trim().to_string()).filter(|c| !c.is_empty());
+    let mut v = Validator::new(&config.form_limits);
+    v.text("comment", comment.as_deref());
+    v.finish()?;
+    let mut storage = state.storage.write_unpoisoned();
+    let Some(pid) = inv.participant_id else { return Err(BackendError::NotFound("participant")) };
+    let Some(ev) = storage.events.get_mut(&inv.event_id) else { return Err(BackendError::NotFound("event")) };
+    ev.ensure_unlocked()?;
+    ev.submit_feedback(pid, session_id, rating, comment)?;
+    Ok(Flash::success(Redirect::to("/event"), "Thank you for your feedback!"))
+}
+
 #[post("/event/slots/<slot_id>/preferences", data = "<form>")]
-pub fn save_preferences(session: Session, state: &State<AppState>, slot_id: Uuid, form: Form<PreferencesForm>) -> Result<Redirect, Status> {
+pub fn save_preferences(admission: Admission<'_>, user: ParticipantSession, state: &State<AppState>, config: &State<AppConfig>, slot_id: Uuid, form: Form<PreferencesForm>) -> Result<Result<Redirect, Template>, BackendError> {
     // Backward-compatible endpoint (no longer used by template). We delegate to the same logic by
     // constructing an AllPreferencesForm with only this slot filled.
-    let mut first = HashMap::new();
-    let mut second = HashMap::new();
-    let mut third = HashMap::new();
-    let PreferencesForm { first: f, second: s, third: t } = form.into_inner();
-    if let Some(v) = f { first.insert(slot_id.to_string(), v.to_string()); }
-    if let Some(v) = s { second.insert(slot_id.to_string(), v.to_string()); }
-    if let Some(v) = t { third.insert(slot_id.to_string(), v.to_string()); }
-    let all = AllPreferencesForm { first, second, third };
-    save_all_preferences(session, state, Form::from(all))
+    let PreferencesForm { first, second, third } = form.into_inner();
+    let ranks: HashMap<usize, String> = [first, second, third].into_iter().enumerate()
+        .filter_map(|(i, pick)| Some((i + 1, pick?.to_string())))
+        .collect();
+    // Sessions marked as impossible to attend stay marked, this form has no field for them
+    let avoided = {
+        let storage = state.storage.read_unpoisoned();
+        let slot = storage.events.get(&user.invitation.event_id).and_then(|ev| ev.slot_by_id(slot_id).ok());
+        slot.zip(user.invitation.participant_id)
+            .map(|(slot, pid)| slot.sessions.iter().filter(|s| s.is_avoided_by(pid)).map(|s| s.uuid.to_string()).collect())
+            .unwrap_or_default()
+    };
+    let all = AllPreferencesForm { rank: HashMap::from([(slot_id.to_string(), ranks)]), interested: vec![], avoided, acknowledged: vec![] };
+    save_all_preferences(admission, user, state, config, Form::from(all))
 }
 
-#[post("/event/preferences", data = "<form>")]
-pub fn save_all_preferences(session: Session, state: &State<AppState>, form: Form<AllPreferencesForm>) -> Result<Redirect, Status> {
-    let code = match &session.user_type {
-        SessionUserType::User { code } => code.clone(),
-        _ => return Err(Status::Forbidden),
+/// Shows the event page with the preferences of the previous linked event preselected, matched by
+/// session name. Nothing is saved until the participant saves the preferences.
+#[post("/event/preferences/import")]
+pub fn import_preferences(_admission: Admission<'_>, user: ParticipantSession, state: &State<AppState>, config: &State<AppConfig>) -> Result<Result<Template, Flash<Redirect>>, BackendError> {
+    let ParticipantSession { code, invitation: inv } = user;
+    let mut storage = state.storage.write_unpoisoned();
+    let Some(ev) = storage.events.get(&inv.event_id) else { return Err(BackendError::NotFound("event")) };
+    if !ev.accepts_preferences_with(inv.deadline_extension, Utc::now()) { return Err(BackendError::conflict("The registration for this event is closed.")); }
+    let Some(imported) = storage.import_preferences(&code, Utc::now()) else {
+        return Ok(Err(Flash::error(Redirect::to("/event"), "There are no preferences from an earlier linked event to import.")));
+    };
+    let mut unsaved = UnsavedPreferences::default();
+    for (slot_id, picks) in &imported.picks {
+        unsaved.selections.insert(slot_id.to_string(), picks.iter().map(|id| Some(id.to_string())).collect());
+    }
+    let skipped = match imported.unmatched {
+        0 => String::new(),
+        n => format!(" {} earlier choice(s) have no matching session and were left out.", n),
     };
+    let flash = FlashView {
+        class: "info".to_string(),
+        message: format!("Your preferences from {} are preselected below.{} Please check them and press \"Save all preferences\".", imported.event_name, skipped),
+    };
+    render_event(&mut storage, config, &code, &inv, Some(flash), Some(&unsaved)).map(Ok)
+}
+
+/// Saves the preferences of all slots. Invalid choices are answered with the event page showing
+/// the attempted selection and the problems per slot; nothing is saved in that case.
+#[post("/event/preferences", data = "<form>")]
+pub fn save_all_preferences(_admission: Admission<'_>, user: ParticipantSession, state: &State<AppState>, config: &State<AppConfig>, form: Form<AllPreferencesForm>) -> Result<Result<Redirect, Template>, BackendError> {
+    let ParticipantSession { code, invitation: inv } = user;
 
-    let AllPreferencesForm { mut first, mut second, mut third } = form.into_inner();
+    let AllPreferencesForm { rank, interested, avoided, acknowledged } = form.into_inner();
+    let interested: Vec<Uuid> = interested.iter().filter_map(|v| Uuid::parse_str(v.trim()).ok()).collect();
+    let avoided: Vec<Uuid> = avoided.iter().filter_map(|v| Uuid::parse_str(v.trim()).ok()).collect();
+    let acknowledged: Vec<Uuid> = acknowledged.iter().filter_map(|v| Uuid::parse_str(v.trim()).ok()).collect();
 
-    let mut storage = state.storage.write().map_err(|_| Status::InternalServerError)?;
-    let inv = match storage.invitations_codes.get(&code).cloned() { Some(i) => i, None => return Err(Status::Unauthorized) };
+    let mut storage = state.storage.write_unpoisoned();
     let event_id = inv.event_id;
 
-    // Participant must already exist and have a non-empty name
-    let pid = match inv.participant_id { Some(pid) => pid, None => return Err(Status::BadRequest) };
-
-    let Some(ev_mut) = storage.events.get_mut(&event_id) else { return Err(Status::NotFound) };
+    // Participant must already exist
+    let pid = match inv.participant_id { Some(pid) => pid, None => return Err(BackendError::bad_request("Please open the event page before saving preferences.")) };
 
-    // Verify participant exists in event and has a name
-    let participant_has_name = ev_mut
-        .participants
-        .get(&pid)
-        .map(|p| !p.name.trim().is_empty())
-        .unwrap_or(false);
-    if !participant_has_name { return Err(Status::BadRequest); }
+    let Some(ev) = storage.events.get(&event_id) else { return Err(BackendError::NotFound("event")) };
+    ev.ensure_unlocked()?;
+    let now = Utc::now();
+    if !ev.accepts_preferences_with(inv.deadline_extension, now) { return Err(BackendError::conflict("The registration for this event is closed.")); }
+    let Some(participant) = ev.participants.get(&pid) else { return Err(BackendError::NotFound("participant")) };
 
-    for slot in ev_mut.slots.iter_mut() {
+    // Check every slot before changing anything, so all problems can be shown at once
+    let mut rejected = UnsavedPreferences::default();
+    if participant.name.trim().is_empty() {
+        rejected.name_error = Some("Please enter your name before choosing preferences.".to_string());
+    }
+    let mut accepted: Vec<(Uuid, Vec<Option<Uuid>>, Vec<Uuid>)> = Vec::new();
+    for slot in &ev.slots {
         let slot_key = slot.uuid.to_string();
-        // Read selections as Option<Uuid> per slot
-        let parse_opt = |m: &mut HashMap<String, String>| -> Option<Uuid> {
-            if let Some(val) = m.remove(&slot_key) {
-                let trimmed = val.trim().to_string();
-                if trimmed.is_empty() { None } else { Uuid::parse_str(&trimmed).ok() }
-            } else { None }
-        };
-        let f = parse_opt(&mut first);
-        let s = parse_opt(&mut second);
-        let t = parse_opt(&mut third);
-
-        // Validate distinctness
-        let mut picks: Vec<Uuid> = Vec::new();
-        for opt in [f, s, t] { if let Some(id) = opt { picks.push(id); } }
-        for i in 0..picks.len() { for j in (i+1)..picks.len() { if picks[i] == picks[j] { return Err(Status::BadRequest); } } }
-
-        // Validate that chosen sessions belong to this slot
-        let valid_session_ids: Vec<Uuid> = slot.sessions.iter().map(|s| s.uuid).collect();
-        for id in &picks { if !valid_session_ids.contains(id) { return Err(Status::BadRequest); } }
-
-        // Remove previous applications by this participant in this slot
-        for sess in slot.sessions.iter_mut() {
-            sess.applications.retain(|a| a.participant != pid);
+        let ranks = rank.get(&slot_key);
+        let submitted: Vec<Option<String>> = (1..=slot.ranked_choices)
+            .map(|r| ranks.and_then(|m| m.get(&r)).map(|v| v.trim().to_string()).filter(|v| !v.is_empty()))
+            .collect();
+        let mut errors = Vec::new();
+        if ranks.is_some_and(|m| m.iter().any(|(r, v)| (*r == 0 || *r > slot.ranked_choices) && !v.trim().is_empty())) {
+            errors.push(format!("You can rank at most {} sessions in {}.", slot.ranked_choices, slot.name));
         }
-
-        // Insert new applications with priorities
-        let mut maybe_push = |sess_id_opt: Option<Uuid>, prio: ApplicationPriority| {
-            if let Some(sess_id) = sess_id_opt {
-                if let Some(target) = slot.sessions.iter_mut().find(|s| s.uuid == sess_id) {
-                    target.applications.push(Application { uuid: Uuid::new_v4(), session_uuid: sess_id, participant: pid, priority: prio, calculated_points: None });
+        // Slots past their own deadline keep the preferences saved before
+        if !slot.accepts_preferences(now) {
+            if submitted.iter().any(Option::is_some) {
+                errors.push(format!("The preference deadline for {} has passed.", slot.name));
+            }
+        } else {
+            // Only sessions of this slot that are open to the participant can be chosen
+            let eligible: Vec<Uuid> = slot.sessions.iter().filter(|s| s.is_eligible(participant)).map(|s| s.uuid).collect();
+            let mut picks: Vec<Option<Uuid>> = vec![None; slot.ranked_choices];
+            for (i, value) in submitted.iter().enumerate() {
+                let Some(value) = value else { continue; };
+                let label = ordinal(i + 1);
+                match Uuid::parse_str(value).ok().filter(|id| eligible.contains(id)) {
+                    Some(id) if picks.contains(&Some(id)) => errors.push(format!("Your {} choice repeats an earlier one. Please choose a different session for each preference.", label)),
+                    Some(id) if avoided.contains(&id) => errors.push(format!("You marked the session of your {} choice as one you can't attend.", label)),
+                    Some(id) => {
+                        picks[i] = Some(id);
+                        if let Some(session) = slot.session_by_id(id) && !acknowledged.contains(&id) && !session.is_acknowledged_by(pid) {
+                            errors.push(format!("Please accept the conditions of {} to choose it.", session.name));
+                        }
+                    }
+                    None => errors.push(format!("The session of your {} choice isn't available to you.", label)),
                 }
             }
-        };
-        maybe_push(f, ApplicationPriority::FirstPreference);
-        maybe_push(s, ApplicationPriority::SecondPreference);
-        maybe_push(t, ApplicationPriority::ThirdPreference);
+            if errors.is_empty() { accepted.push((slot.uuid, picks, eligible)); }
+        }
+        rejected.selections.insert(slot_key.clone(), submitted);
+        if !errors.is_empty() { rejected.errors.insert(slot_key, errors); }
+    }
+    if rejected.name_error.is_some() || !rejected.errors.is_empty() {
+        let flash = FlashView { class: "danger".to_string(), message: "Your preferences weren't saved. Please check the marked fields.".to_string() };
+        return render_event(&mut storage, config, &code, &inv, Some(flash), Some(&rejected)).map(Err);
+    }
 
-        // Add NoPreference for others
-        let chosen: Vec<Uuid> = [f, s, t].into_iter().flatten().collect();
+    let Some(ev_mut) = storage.events.get_mut(&event_id) else { return Err(BackendError::NotFound("event")) };
+    let mut choices = Vec::new();
+    for (slot_id, picks, eligible) in accepted {
+        let slot = ev_mut.slot_by_id_mut(slot_id)?;
+        // Accepted conditions are a record and stay, even if the session isn't chosen anymore
+        for sess in slot.sessions.iter_mut().filter(|s| s.acknowledgment.is_some() && acknowledged.contains(&s.uuid)) {
+            sess.acknowledgments.entry(pid).or_insert(now);
+        }
+        // Replace previous applications by this participant in this slot
+        slot.remove_applications(pid);
+        for (i, sess_id) in picks.iter().enumerate() {
+            if let Some(sess_id) = sess_id {
+                slot.insert_application(*sess_id, pid, ApplicationPriority::from_rank(i + 1));
+            }
+        }
+        // Add NoPreference for other sessions the participant is eligible for, unless they have conditions the participant
+        // didn't accept or the participant can't attend them
+        let unacknowledged: Vec<Uuid> = slot.sessions.iter().filter(|s| !s.is_acknowledged_by(pid)).map(|s| s.uuid).collect();
+        for sess_id in eligible.iter().filter(|id| !picks.contains(&Some(**id)) && !unacknowledged.contains(id) && !avoided.contains(id)) {
+            slot.insert_application(*sess_id, pid, ApplicationPriority::NoPreference);
+        }
+        // "Maybe" only counts for sessions that aren't among the preferences and can be attended
         for sess in slot.sessions.iter_mut() {
-            if !chosen.contains(&sess.uuid) {
-                sess.applications.push(Application {
-                    uuid: Uuid::new_v4(),
-                    session_uuid: sess.uuid,
-                    participant: pid,
-                    priority: ApplicationPriority::NoPreference,
-                    calculated_points: None,
-                });
+            sess.interested.retain(|p| *p != pid);
+            sess.avoided.retain(|p| *p != pid);
+            if avoided.contains(&sess.uuid) && eligible.contains(&sess.uuid) {
+                sess.avoided.push(pid);
+            } else if interested.contains(&sess.uuid) && eligible.contains(&sess.uuid) && !picks.contains(&Some(sess.uuid)) {
+                sess.interested.push(pid);
             }
         }
+        let picked: Vec<String> = picks.iter().enumerate()
+            .filter_map(|(i, pick)| Some(format!("{}. {}", i + 1, slot.session_by_id((*pick)?)?.name)))
+            .collect();
+        if !picked.is_empty() {
+            choices.push(format!("{}: {}", slot.name, picked.join(", ")));
+        }
     }
+    storage.record_activity(&code, ActivityKind::PreferencesSaved { choices });
+    storage.queue_preference_webhook(event_id, pid, now);
 
-    Ok(Redirect::to("/event"))
+    Ok(Ok(Redirect::to("/event")))
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::backend::config::AppConfig;
+    use crate::backend::data::{ApplicationPriority, Event, EventState, Invitation, InvitationCategory, Session, Slot, Storage};
+
+    use super::event_context;
+
+    /// Two participants who applied for and got a seat in the same session, each with a name and an email
+    fn shared_session(state: EventState) -> (Storage, Uuid, Uuid) {
+        let mut storage = Storage::new();
+        let mut ev = Event::new("Test".to_string(), None);
+        let mut slot = Slot::new("Morning".to_string(), None);
+        slot.sessions.push(Session::new("Workshop".to_string(), None, 2));
+        let session_id = slot.sessions[0].uuid;
+        ev.slots.push(slot);
+        ev.state = state;
+        let event_id = ev.uuid;
+        storage.events.insert(event_id, ev);
+        let mut pids = Vec::new();
+        for (code, name) in [("OWNCODE", "Alice Owner"), ("OTHERCODE", "Bob Otherton")] {
+            let mut inv = Invitation::new(code.to_string(), event_id, vec![], InvitationCategory::default());
+            inv.email = Some(format!("{}@example.org", code.to_lowercase()));
+            storage.invitations_codes.insert(code.to_string(), inv);
+            let pid = storage.register_participant(code).unwrap();
+            let ev = storage.events.get_mut(&event_id).unwrap();
+            ev.participants.get_mut(&pid).unwrap().name = name.to_string();
+            ev.slots[0].insert_application(session_id, pid, ApplicationPriority::FirstPreference);
+            ev.slots[0].sessions[0].participants.push(pid);
+            pids.push(pid);
+        }
+        (storage, pids[0], pids[1])
+    }
+
+    fn assert_only_own_data(state: EventState) {
+        let (storage, own, other) = shared_session(state);
+        let config: AppConfig = serde_json::from_str("{}").unwrap();
+        let inv = &storage.invitations_codes["OWNCODE"];
+
+        let ctx = event_context(&storage, &config, "OWNCODE", inv, own, None, None).unwrap();
+        let json = serde_json::to_string(&ctx).unwrap();
+
+        assert!(json.contains("Alice Owner"));
+        assert!(!json.contains(&other.to_string()));
+        assert!(!json.contains("OTHERCODE"));
+        assert!(!json.contains("othercode@example.org"));
+        assert!(!json.contains("Otherton"));
+    }
+
+    #[test]
+    fn event_page_while_open_carries_no_foreign_participant_data() {
+        assert_only_own_data(EventState::OpenForRegistration);
+    }
+
+    #[test]
+    fn event_page_with_results_carries_no_foreign_participant_data() {
+        assert_only_own_data(EventState::Published);
+    }
 }
@@ -2,44 +2,125 @@
 
 pub mod gui;
 pub mod backend;
+pub mod api;
 
-use crate::gui::user::{event_view as user_event_view, save_name, save_preferences, save_all_preferences};
-use crate::gui::admin::{admin_index, create_event, event_view, delete_event, set_event_state, create_slot, edit_slot, delete_slot, create_session, edit_session, delete_session, add_invites_bulk, delete_invite, close_and_distribute};
+use crate::gui::user::{event_view as user_event_view, save_name, save_preferences, save_all_preferences, import_preferences, save_feedback, dismiss_schedule_changes, download_my_data};
+use crate::gui::admin::{admin_index, create_event, event_view, confirm_delete_event, delete_event, save_event_template, delete_event_template, create_room, edit_room, delete_room, set_event_state, publish_results, create_slot, edit_slot, delete_slot, create_session, edit_session, preview_session_split, split_session, delete_session, add_invites_bulk, import_directory_members, delete_invite, edit_invite, rotate_invite, participant_timeline, sign_in_sheets, add_note, delete_note, add_exclusion, delete_exclusion, set_category_bonuses, set_compensation, set_allocation_config, import_carryover, import_scoring, import_assignment, set_welcome_text, update_event_settings, set_kiosk_mode, create_bundle, revoke_bundle, create_share_link, revoke_share_link, invite_co_admin, revoke_co_admin_invite, remove_co_admin, set_webhook, outbox_view, outbox_mark_done, outbox_resend_mail, outbox_resend_webhook, admit_waiting, remove_waiting, close_and_distribute, distribution_progress, distribution_progress_stream, bulk_event_action, swap_seats, remove_seat, pin_seat, unpin_seat, remove_unused_participants, merge_participants, preview_participant_mail, send_participant_mail};
 use crate::gui::login::{admin_login_page, start_page};
-use backend::auth::{logout, login_admin, login_user, invitation_login};
+use crate::gui::export::{export_participants, export_feedback, export_dry_run, export_problem, export_scoring, export_rosters, export_badges};
+use crate::gui::register::{register_page, register, verify_registration};
+use crate::gui::result::result_view;
+use crate::gui::push::{push_subscribe, push_unsubscribe};
+use crate::gui::kiosk::{kiosk_page, kiosk_register};
+use crate::gui::bundle::{bundle_page, bundle_create_codes};
+use crate::gui::share::share_page;
+use crate::gui::onboarding::{onboarding_page, accept_onboarding};
+use crate::gui::infoscreen::infoscreen_snapshot;
+use crate::gui::identity::{request_link, remove_link, confirm_link};
+use backend::auth::{logout, login_admin, login_user, invitation_login, request_login_link, login_link};
+use api::api_catcher;
+use crate::gui::templates::{check_templates, internal_error, template_dir};
+use backend::error::payload_too_large;
+use backend::waiting_room::queue_page;
+use api::state::{export_state, import_state};
+use api::research::export_research_dataset;
+use api::admins::{create_admin, list_admins};
+use api::stats::event_stats;
+use api::completion::event_completion;
+use api::solver::{export_allocation_problem, import_external_assignment};
+use api::seats::adjust_seats;
+use backend::config::AppConfig;
+use backend::diagnostics;
+use backend::mail::Mailer;
+use backend::mail_templates::MailTemplates;
+use backend::push::PushSender;
+use backend::read_only::{ReadOnlyMode, read_only_page, api_read_only};
+use backend::scheduler::start_scheduler;
+use backend::snapshot::start_snapshot_refresh;
 use backend::state::AppState;
 use rocket::fairing::AdHoc;
 use rocket::fs::FileServer;
 use rocket_dyn_templates::Template;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 #[launch]
 fn rocket() -> _ {
     let state_path = PathBuf::from("data/state.json");
-    let app_state = AppState::load_or_new(&state_path).unwrap_or_else(|_| AppState::new());
+    let rocket = rocket::build();
+    let mut config: AppConfig = rocket.figment().extract().unwrap_or_else(|e| {
+        eprintln!("Failed to read application config: {}. Using defaults.", e);
+        AppConfig::default()
+    });
+    // Deployment pipelines check an instance before switching traffic to it
+    if std::env::args().any(|arg| arg == "--check") {
+        let report = diagnostics::run(&state_path, &template_dir(rocket.figment()), Path::new("static"), &config);
+        println!("{}", serde_json::to_string_pretty(&report).expect("report serializes"));
+        std::process::exit(if report.ok { 0 } else { 1 });
+    }
 
+    let app_state = AppState::load_or_new(&state_path).unwrap_or_else(|_| AppState::new());
     let state_path_for_liftoff = state_path.clone();
     let state_path_for_shutdown = state_path.clone();
+    config.mail_templates = MailTemplates::load(&config.mail);
+    let pusher = PushSender::new(config.push_contact.clone());
+    let mailer = Mailer::from_config(&config.mail);
+    if !mailer.is_configured() {
+        println!("No SMTP server configured, outgoing mails will be queued for the admins.");
+    }
+    let rocket = if config.read_only {
+        println!("Running read-only: changes are rejected and the state file is never written.");
+        rocket.attach(ReadOnlyMode)
+    } else {
+        rocket
+    };
 
-    rocket::build()
+    rocket
+        .attach(check_templates())
         .attach(Template::fairing())
         .manage(app_state)
+        .manage(config)
+        .manage(mailer)
         .mount("/static", FileServer::from("static"))
         .attach(AdHoc::on_liftoff("autosave", move |rocket| {
             let state_path = state_path_for_liftoff.clone();
             Box::pin(async move {
-                if let Some(state) = rocket.state::<AppState>() {
+                if let (Some(state), Some(config)) = (rocket.state::<AppState>(), rocket.state::<AppConfig>()) {
+                    if config.read_only {
+                        return;
+                    }
                     // Start async autosave every 30 seconds within Tokio runtime
                     let _handle = state.start_autosave_async(state_path.clone(), Duration::from_secs(30));
                     let _ = _handle; // detached
                 }
             })
         }))
+        .attach(AdHoc::on_liftoff("scheduler", move |rocket| {
+            let pusher = pusher.clone();
+            Box::pin(async move {
+                if let (Some(state), Some(config), Some(mailer)) = (rocket.state::<AppState>(), rocket.state::<AppConfig>(), rocket.state::<Mailer>()) {
+                    if config.read_only {
+                        return;
+                    }
+                    // Check for due reminders and deliver queued mails and push notifications every minute
+                    let _handle = start_scheduler(state.storage.clone(), config.clone(), mailer.clone(), pusher, Duration::from_secs(60));
+                }
+            })
+        }))
+        .attach(AdHoc::on_liftoff("infoscreen snapshots", |rocket| {
+            Box::pin(async move {
+                if let (Some(state), Some(config)) = (rocket.state::<AppState>(), rocket.state::<AppConfig>()) {
+                    let _handle = start_snapshot_refresh(state.storage.clone(), state.infoscreen_snapshots.clone(), Duration::from_secs(config.infoscreen_refresh_seconds.max(1)));
+                }
+            })
+        }))
         .attach(AdHoc::on_shutdown("save_state", move |rocket| {
             let state_path = state_path_for_shutdown.clone();
             Box::pin(async move {
-                if let Some(state) = rocket.state::<AppState>() {
+                if let (Some(state), Some(config)) = (rocket.state::<AppState>(), rocket.state::<AppConfig>()) {
+                    if config.read_only {
+                        return;
+                    }
                     let _ = state.save_to_async(&state_path).await;
                     println!("Successfully saved state to file");
                 }
@@ -50,25 +131,111 @@ fn rocket() -> _ {
                     save_name,
                     save_preferences,
                     save_all_preferences,
+                    import_preferences,
+                    save_feedback,
+                    dismiss_schedule_changes,
+                    download_my_data,
+                    push_subscribe,
+                    push_unsubscribe,
                     start_page,
                     admin_index,
                     create_event,
                     event_view,
+                    confirm_delete_event,
                     delete_event,
+                    save_event_template,
+                    delete_event_template,
+                    create_room,
+                    edit_room,
+                    delete_room,
                     set_event_state,
+                    publish_results,
                     create_slot,
                     edit_slot,
                     delete_slot,
                     create_session,
                     edit_session,
+                    preview_session_split,
+                    split_session,
                     delete_session,
                     add_invites_bulk,
+                    import_directory_members,
                     delete_invite,
+                    edit_invite,
+                    rotate_invite,
+                    participant_timeline,
+                    sign_in_sheets,
+                    add_note,
+                    delete_note,
+                    add_exclusion,
+                    delete_exclusion,
+                    set_category_bonuses,
+                    set_compensation,
+                    set_allocation_config,
+                    import_carryover,
+                    import_scoring,
+                    import_assignment,
+                    set_welcome_text,
+                    update_event_settings,
+                    set_kiosk_mode,
+                    create_bundle,
+                    revoke_bundle,
+                    create_share_link,
+                    revoke_share_link,
+                    invite_co_admin,
+                    revoke_co_admin_invite,
+                    remove_co_admin,
+                    set_webhook,
+                    outbox_view,
+                    outbox_mark_done,
+                    outbox_resend_mail,
+                    outbox_resend_webhook,
+                    admit_waiting,
+                    remove_waiting,
                     close_and_distribute,
+                    distribution_progress,
+                    distribution_progress_stream,
+                    bulk_event_action,
+                    swap_seats,
+                    remove_seat,
+                    pin_seat,
+                    unpin_seat,
+                    remove_unused_participants,
+                    merge_participants,
+                    preview_participant_mail,
+                    send_participant_mail,
+                    export_participants,
+                    export_feedback,
+                    export_dry_run,
+                    export_problem,
+                    export_scoring,
+                    export_rosters,
+                    export_badges,
+                    register_page,
+                    register,
+                    verify_registration,
+                    result_view,
+                    kiosk_page,
+                    infoscreen_snapshot,
+                    kiosk_register,
+                    bundle_page,
+                    bundle_create_codes,
+                    share_page,
+                    onboarding_page,
+                    accept_onboarding,
+                    request_link,
+                    remove_link,
+                    confirm_link,
                     admin_login_page,
                     login_admin,
                     login_user,
                     logout,
-                    invitation_login
+                    invitation_login,
+                    request_login_link,
+                    login_link,
+                    read_only_page
                 ])
+        .mount("/api", routes![export_state, import_state, list_admins, create_admin, event_stats, event_completion, export_research_dataset, export_allocation_problem, import_external_assignment, adjust_seats, api_read_only])
+        .register("/", catchers![payload_too_large, queue_page, internal_error])
+        .register("/api", catchers![api_catcher])
 }
@@ -0,0 +1,14 @@
+// Service worker showing push notifications sent by FESD
+self.addEventListener('push', function(event) {
+  var data = {};
+  try { data = event.data ? event.data.json() : {}; } catch (_) {}
+  event.waitUntil(self.registration.showNotification(data.title || 'FESD', {
+    body: data.body || '',
+    data: { url: data.url || '/event' }
+  }));
+});
+
+self.addEventListener('notificationclick', function(event) {
+  event.notification.close();
+  event.waitUntil(clients.openWindow(event.notification.data.url));
+});